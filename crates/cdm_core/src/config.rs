@@ -7,6 +7,11 @@ use log::{debug, info};
 
 use serde::{Deserialize, Serialize};
 
+use pdf_helper::{
+    outline::DocumentInfo,
+    template::{PageTemplate, Revision, TitleBlock},
+};
+
 /// `Config` represents configuration options for the various cdm binary programs
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Config {
@@ -15,6 +20,132 @@ pub struct Config {
     pub library_files: Vec<PathBuf>,
     /// `no_default_libraries` prevents loading of default libraries provided with the application
     pub no_default_libraries: bool,
+    /// `drawing_template` is the border and title block drawn on every page of a generated
+    /// drawing set. Pages only have a footer if it is not set.
+    #[serde(default)]
+    pub drawing_template: Option<DrawingTemplate>,
+    /// `document_metadata` is the title, author and other metadata of generated PDF documents
+    #[serde(default)]
+    pub document_metadata: DocumentMetadata,
+}
+
+/// `DocumentMetadata` is the configuration of the metadata of generated PDF documents, shown
+/// in the document properties of PDF readers
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DocumentMetadata {
+    /// title of the documents
+    pub title: String,
+    /// who created the documents
+    pub author: String,
+    /// what the documents are about
+    pub subject: String,
+    /// words the documents can be searched for by
+    pub keywords: Vec<String>,
+    /// date the documents were created, such as 2024-05-01 or 2024-05-01T13:45. Documents have
+    /// no creation date if it is not set.
+    pub creation_date: Option<String>,
+}
+
+/// `DrawingTemplate` is the configuration of the page template applied to generated drawings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DrawingTemplate {
+    /// number of zones along the width of the page
+    pub horizontal_zones: u8,
+    /// number of zones along the height of the page
+    pub vertical_zones: u8,
+    /// name of the project
+    pub project_name: String,
+    /// drawing number of the drawing set
+    pub drawing_number: String,
+    /// current revision of the drawing set
+    pub revision: String,
+    /// date of the current revision
+    pub date: String,
+    /// who drew the drawing set
+    pub drawn_by: String,
+    /// who checked the drawing set
+    pub checked_by: String,
+    /// revision history, oldest first
+    pub revisions: Vec<DrawingRevision>,
+}
+
+/// `DrawingRevision` is one entry in the revision history of a [`DrawingTemplate`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DrawingRevision {
+    /// revision identifier
+    pub revision: String,
+    /// date of the revision
+    pub date: String,
+    /// description of what changed
+    pub description: String,
+    /// who made the revision
+    pub by: String,
+}
+
+impl Default for DrawingTemplate {
+    fn default() -> Self {
+        let template = PageTemplate::default();
+        Self {
+            horizontal_zones: template.horizontal_zones,
+            vertical_zones: template.vertical_zones,
+            project_name: String::new(),
+            drawing_number: String::new(),
+            revision: String::new(),
+            date: String::new(),
+            drawn_by: String::new(),
+            checked_by: String::new(),
+            revisions: Vec::new(),
+        }
+    }
+}
+
+impl From<&DrawingTemplate> for PageTemplate {
+    fn from(template: &DrawingTemplate) -> Self {
+        Self {
+            horizontal_zones: template.horizontal_zones,
+            vertical_zones: template.vertical_zones,
+            title_block: TitleBlock {
+                project_name: template.project_name.clone(),
+                drawing_number: template.drawing_number.clone(),
+                revision: template.revision.clone(),
+                date: template.date.clone(),
+                drawn_by: template.drawn_by.clone(),
+                checked_by: template.checked_by.clone(),
+                revisions: template
+                    .revisions
+                    .iter()
+                    .map(|revision| Revision {
+                        revision: revision.revision.clone(),
+                        date: revision.date.clone(),
+                        description: revision.description.clone(),
+                        by: revision.by.clone(),
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl TryFrom<&DocumentMetadata> for DocumentInfo {
+    type Error = pdf_helper::Error;
+
+    fn try_from(metadata: &DocumentMetadata) -> Result<Self, Self::Error> {
+        Ok(Self {
+            title: metadata.title.clone(),
+            author: metadata.author.clone(),
+            subject: metadata.subject.clone(),
+            keywords: metadata.keywords.clone(),
+            creator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            creation_date: metadata
+                .creation_date
+                .as_deref()
+                .map(str::parse)
+                .transpose()?,
+        })
+    }
 }
 
 /// `read_config_file` reads an indivdiual configuration yaml file into a [`fs::File`]
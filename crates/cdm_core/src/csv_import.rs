@@ -0,0 +1,662 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use csv::{ReaderBuilder, StringRecord, Trim};
+
+use crate::datatypes::file_types::{
+    cable::{Cable, Connector as CableConnector},
+    equipment::Equipment,
+    location::Location,
+    wire::{Connector as WireConnector, Wire},
+    DataFile,
+};
+use crate::datatypes::internal_types::Library;
+
+/// `RecordType` selects which `DataFile` structure each row of a CSV file is read into
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecordType {
+    /// each row is a `Wire`
+    Wire,
+    /// each row is a `Cable`
+    Cable,
+    /// each row is an `Equipment` instance
+    Equipment,
+    /// each row is a `Location`
+    Location,
+}
+
+impl RecordType {
+    /// returns the field names of this record type, and whether each one is required
+    fn fields(self) -> &'static [(&'static str, bool)] {
+        match self {
+            RecordType::Wire => &[
+                ("id", true),
+                ("type", true),
+                ("identifier", false),
+                ("description", false),
+                ("length", true),
+                ("pathway", false),
+                ("end1", true),
+                ("end2", true),
+            ],
+            RecordType::Cable => &[
+                ("id", true),
+                ("type", true),
+                ("identifier", false),
+                ("description", false),
+                ("length", true),
+                ("pathway", false),
+                ("end1", false),
+                ("end2", false),
+            ],
+            RecordType::Equipment => &[
+                ("id", true),
+                ("type", true),
+                ("identifier", false),
+                ("mounting_type", false),
+                ("location", true),
+                ("sub_location", true),
+                ("description", false),
+            ],
+            RecordType::Location => &[
+                ("id", true),
+                ("type", true),
+                ("identifier", false),
+                ("description", false),
+                ("physical_location", false),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecordType::Wire => write!(f, "Wire"),
+            RecordType::Cable => write!(f, "Cable"),
+            RecordType::Equipment => write!(f, "Equipment"),
+            RecordType::Location => write!(f, "Location"),
+        }
+    }
+}
+
+/// `HeaderMapping` maps field names to the CSV column headers they are read from.
+///
+/// Fields that are not mapped are read from a column whose header is the field name. Headers are
+/// matched ignoring case.
+pub type HeaderMapping = HashMap<String, String>;
+
+/// `Row` is one record of a CSV file, with the columns each field is read from
+struct Row<'a> {
+    /// contents of row
+    record: &'a StringRecord,
+    /// line number of row in file, starting at 1 for the header
+    line: u64,
+    /// field name to (column index, column header)
+    columns: &'a HashMap<&'static str, (usize, String)>,
+}
+
+impl Row<'_> {
+    /// returns the contents of `field`, or `None` if its column is missing or the cell is empty
+    fn get(&self, field: &str) -> Option<String> {
+        let (index, _) = self.columns.get(field)?;
+        self.record
+            .get(*index)
+            .filter(|cell| !cell.is_empty())
+            .map(ToString::to_string)
+    }
+
+    /// creates a `RowError` pointing at the column of `field` in this row
+    fn error(&self, field: &str, message: String) -> RowError {
+        let column = self.columns.get(field);
+        RowError {
+            row: self.line,
+            column: column.and_then(|(index, _)| index.checked_add(1)),
+            header: column.map(|(_, header)| header.clone()),
+            message,
+        }
+    }
+
+    /// returns the contents of `field`, adding an error to `errors` if it is empty
+    fn required(&self, field: &str, errors: &mut Vec<RowError>) -> Option<String> {
+        let contents = self.get(field);
+        if contents.is_none() {
+            errors.push(self.error(field, format!("{field} is required")));
+        }
+        contents
+    }
+
+    /// returns the contents of `field` as a number, adding an error to `errors` if it is empty or
+    /// not a number
+    fn number(&self, field: &str, errors: &mut Vec<RowError>) -> Option<f64> {
+        let contents = self.required(field, errors)?;
+        match contents.parse() {
+            Ok(number) => Some(number),
+            Err(e) => {
+                errors.push(self.error(field, format!("{contents} is not a number: {e}")));
+                None
+            }
+        }
+    }
+
+    /// adds an error to `errors` if `id`, read from `field`, is not found in `library`
+    fn check_library<T>(
+        &self,
+        field: &str,
+        id: &str,
+        datatype: &str,
+        library: &HashMap<String, T>,
+        errors: &mut Vec<RowError>,
+    ) {
+        if !library.contains_key(id) {
+            errors.push(self.error(field, format!("{datatype}: {id} not found in library")));
+        }
+    }
+
+    /// reads `field` as a list of `ConnectorType` ids separated by `;`, adding an error to
+    /// `errors` for each one not found in `library`
+    fn connectors(
+        &self,
+        field: &str,
+        library: &Library,
+        errors: &mut Vec<RowError>,
+    ) -> Vec<String> {
+        let connector_types = self
+            .get(field)
+            .map(|contents| {
+                contents
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|connector_type| !connector_type.is_empty())
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for connector_type in &connector_types {
+            self.check_library(
+                field,
+                connector_type,
+                "ConnectorType",
+                &library.connector_types,
+                errors,
+            );
+        }
+        connector_types
+    }
+}
+
+/// `import_csv` reads a CSV file, where each row is one `RecordType`, into a `DataFile`
+///
+/// The first row must be a header row. Columns are matched to fields using `mapping`, and any
+/// columns that are not needed are ignored. The ends of a `Cable` may list several connector
+/// types, separated by `;`. Every type and connector type referenced is checked against
+/// `library`, and all rows are validated before returning, so that every error in the file can
+/// be reported at once.
+///
+/// # Errors
+///
+/// Will error if:
+/// - the file can't be read or is not valid CSV
+/// - `mapping` contains a field that does not exist in `record_type`, or a required column is
+///   missing from the header row
+/// - any row is missing a required value, has a value that can't be parsed, references a type
+///   not found in `library` or repeats an id. The returned `RowError`s contain the row and column
+///   of each problem.
+pub fn import_csv<R: io::Read>(
+    reader: R,
+    record_type: RecordType,
+    mapping: &HeaderMapping,
+    library: &Library,
+) -> Result<DataFile, Error> {
+    let mut csv_reader = ReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+    let headers = csv_reader.headers()?.clone();
+    let columns = column_indices(record_type, mapping, &headers)?;
+
+    let mut wires = HashMap::new();
+    let mut cables = HashMap::new();
+    let mut equipment = HashMap::new();
+    let mut locations = HashMap::new();
+    let mut errors = Vec::new();
+    // id to the row it was first defined on
+    let mut ids = HashMap::new();
+    for result in csv_reader.records() {
+        let record = result?;
+        let row = Row {
+            record: &record,
+            line: record.position().map_or(0, csv::Position::line),
+            columns: &columns,
+        };
+        if let Some(id) = row.get("id") {
+            if let Some(first_row) = ids.get(&id) {
+                errors.push(row.error("id", format!("id {id} already defined on row {first_row}")));
+            } else {
+                ids.insert(id, row.line);
+            }
+        }
+        match record_type {
+            RecordType::Wire => add_wire(&row, library, &mut wires, &mut errors),
+            RecordType::Cable => add_cable(&row, library, &mut cables, &mut errors),
+            RecordType::Equipment => add_equipment(&row, library, &mut equipment, &mut errors),
+            RecordType::Location => add_location(&row, library, &mut locations, &mut errors),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(Error::RowErrors(errors));
+    }
+    Ok(DataFile {
+        wires: (!wires.is_empty()).then_some(wires),
+        cables: (!cables.is_empty()).then_some(cables),
+        equipment: (!equipment.is_empty()).then_some(equipment),
+        locations: (!locations.is_empty()).then_some(locations),
+        ..Default::default()
+    })
+}
+
+/// `write_datafile` writes `datafile` to `path` as YAML that can be placed in the `src` directory
+/// of a project
+///
+/// # Errors
+///
+/// Will error if the file can't be created or `datafile` fails to serialize
+pub fn write_datafile(datafile: &DataFile, path: &Path) -> Result<(), Error> {
+    let file = File::create(path)?;
+    serde_yaml::to_writer(file, datafile)?;
+    Ok(())
+}
+
+/// finds the column index and header of each field of `record_type` in `headers`
+fn column_indices(
+    record_type: RecordType,
+    mapping: &HeaderMapping,
+    headers: &StringRecord,
+) -> Result<HashMap<&'static str, (usize, String)>, Error> {
+    if let Some(field) = mapping
+        .keys()
+        .find(|field| !record_type.fields().iter().any(|(name, _)| name == field))
+    {
+        return Err(Error::HeaderError(format!(
+            "{field} is not a field of {record_type}"
+        )));
+    }
+    let mut columns = HashMap::new();
+    for (field, required) in record_type.fields() {
+        let header = mapping.get(*field).map_or(*field, String::as_str);
+        match headers
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(header.trim()))
+        {
+            Some(index) => {
+                columns.insert(*field, (index, headers[index].to_string()));
+            }
+            None if *required => {
+                return Err(Error::HeaderError(format!(
+                    "column {header} for required field {field} not found in header row"
+                )))
+            }
+            None => {}
+        }
+    }
+    Ok(columns)
+}
+
+/// validates `row` as a `Wire` and adds it to `wires`
+fn add_wire(
+    row: &Row,
+    library: &Library,
+    wires: &mut HashMap<String, Wire>,
+    errors: &mut Vec<RowError>,
+) {
+    let id = row.required("id", errors);
+    let wire_type = row.required("type", errors);
+    let length = row.number("length", errors);
+    let end1 = row.required("end1", errors);
+    let end2 = row.required("end2", errors);
+    if let Some(wire_type) = &wire_type {
+        row.check_library("type", wire_type, "WireType", &library.wire_types, errors);
+    }
+    for (field, end) in [("end1", &end1), ("end2", &end2)] {
+        if let Some(end) = end {
+            row.check_library(
+                field,
+                end,
+                "ConnectorType",
+                &library.connector_types,
+                errors,
+            );
+        }
+    }
+    if let (Some(id), Some(wire_type), Some(length), Some(end1), Some(end2)) =
+        (id, wire_type, length, end1, end2)
+    {
+        wires.insert(
+            id.clone(),
+            Wire {
+                id,
+                wire_type,
+                identifier: row.get("identifier"),
+                description: row.get("description"),
+                length,
+                pathway: row.get("pathway"),
+                end1: WireConnector {
+                    connector_type: end1,
+                },
+                end2: WireConnector {
+                    connector_type: end2,
+                },
+            },
+        );
+    }
+}
+
+/// validates `row` as a `Cable` and adds it to `cables`
+fn add_cable(
+    row: &Row,
+    library: &Library,
+    cables: &mut HashMap<String, Cable>,
+    errors: &mut Vec<RowError>,
+) {
+    let id = row.required("id", errors);
+    let cable_type = row.required("type", errors);
+    let length = row.number("length", errors);
+    let end1 = row.connectors("end1", library, errors);
+    let end2 = row.connectors("end2", library, errors);
+    if let Some(cable_type) = &cable_type {
+        row.check_library(
+            "type",
+            cable_type,
+            "CableType",
+            &library.cable_types,
+            errors,
+        );
+    }
+    let to_connectors = |connector_types: Vec<String>| {
+        connector_types
+            .into_iter()
+            .map(|connector_type| CableConnector {
+                connector_type,
+                terminations: None,
+            })
+            .collect()
+    };
+    if let (Some(id), Some(cable_type), Some(length)) = (id, cable_type, length) {
+        cables.insert(
+            id.clone(),
+            Cable {
+                id,
+                cable_type,
+                identifier: row.get("identifier"),
+                description: row.get("description"),
+                length,
+                pathway: row.get("pathway"),
+                end1: to_connectors(end1),
+                end2: to_connectors(end2),
+            },
+        );
+    }
+}
+
+/// validates `row` as an `Equipment` instance and adds it to `equipment`
+fn add_equipment(
+    row: &Row,
+    library: &Library,
+    equipment: &mut HashMap<String, Equipment>,
+    errors: &mut Vec<RowError>,
+) {
+    let id = row.required("id", errors);
+    let equipment_type = row.required("type", errors);
+    let location = row.required("location", errors);
+    let sub_location = row.required("sub_location", errors);
+    if let Some(equipment_type) = &equipment_type {
+        row.check_library(
+            "type",
+            equipment_type,
+            "EquipmentType",
+            &library.equipment_types,
+            errors,
+        );
+    }
+    if let (Some(id), Some(equipment_type), Some(location), Some(sub_location)) =
+        (id, equipment_type, location, sub_location)
+    {
+        equipment.insert(
+            id,
+            Equipment {
+                equipment_type,
+                identifier: row.get("identifier"),
+                mounting_type: row.get("mounting_type"),
+                location,
+                sub_location,
+                description: row.get("description"),
+            },
+        );
+    }
+}
+
+/// validates `row` as a `Location` and adds it to `locations`
+fn add_location(
+    row: &Row,
+    library: &Library,
+    locations: &mut HashMap<String, Location>,
+    errors: &mut Vec<RowError>,
+) {
+    let id = row.required("id", errors);
+    let location_type = row.required("type", errors);
+    if let Some(location_type) = &location_type {
+        row.check_library(
+            "type",
+            location_type,
+            "LocationType",
+            &library.location_types,
+            errors,
+        );
+    }
+    if let (Some(id), Some(location_type)) = (id, location_type) {
+        locations.insert(
+            id.clone(),
+            Location {
+                id,
+                location_type,
+                identifier: row.get("identifier"),
+                description: row.get("description"),
+                physical_location: row.get("physical_location"),
+                sub_locations: HashMap::new(),
+            },
+        );
+    }
+}
+
+/// `RowError` describes a problem found in one cell of a CSV file
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RowError {
+    /// line number of row, starting at 1 for the header row
+    pub row: u64,
+    /// column number, starting at 1. `None` if the column is missing from the file
+    pub column: Option<usize>,
+    /// header of column
+    pub header: Option<String>,
+    /// description of problem
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}", self.row)?;
+        if let Some(column) = self.column {
+            write!(f, ", column {column}")?;
+        }
+        if let Some(header) = &self.header {
+            write!(f, " ({header})")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug)]
+/// `Error` is the error returned by CSV import
+pub enum Error {
+    /// Error resulting from reading or parsing a CSV file
+    CsvError(String),
+    /// Error resulting from a header row that does not match the `HeaderMapping`
+    HeaderError(String),
+    /// Errors found while validating rows
+    RowErrors(Vec<RowError>),
+    /// Error resulting from writing project YAML
+    WriteError(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::CsvError(ref e) => write!(f, "CSV Error: {e}"),
+            Error::HeaderError(ref e) => write!(f, "Header Error: {e}"),
+            Error::RowErrors(ref errors) => {
+                writeln!(f, "{} errors found in CSV file:", errors.len())?;
+                for error in errors {
+                    writeln!(f, "{error}")?;
+                }
+                Ok(())
+            }
+            Error::WriteError(ref e) => write!(f, "Write Error: {e}"),
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::CsvError(format!("{e}"))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::WriteError(format!("{e}"))
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::WriteError(format!("{e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::datatypes::internal_types::{connector_type::ConnectorType, wire_type::WireType};
+
+    /// `test_library` returns a `Library` with one `WireType` and one `ConnectorType`
+    fn test_library() -> Library {
+        let mut library = Library::new();
+        library.wire_types.insert(
+            "W18".to_string(),
+            Rc::new(RefCell::new(WireType {
+                id: "W18".to_string(),
+                ..Default::default()
+            })),
+        );
+        library.connector_types.insert(
+            "RJ45".to_string(),
+            Rc::new(RefCell::new(ConnectorType {
+                id: "RJ45".to_string(),
+                ..Default::default()
+            })),
+        );
+        library
+    }
+
+    /// `row_errors` imports `csv` as wires, and returns the row, column and header of each error
+    fn row_errors(csv: &str, mapping: &HeaderMapping) -> Vec<(u64, Option<usize>, Option<String>)> {
+        match import_csv(csv.as_bytes(), RecordType::Wire, mapping, &test_library()) {
+            Err(Error::RowErrors(errors)) => errors
+                .into_iter()
+                .map(|error| (error.row, error.column, error.header))
+                .collect(),
+            other => panic!("expected row errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn import_wires() {
+        let csv = "id,type,length,end1,end2\nW1,W18,2.5,RJ45,RJ45\n";
+        let datafile = import_csv(
+            csv.as_bytes(),
+            RecordType::Wire,
+            &HashMap::new(),
+            &test_library(),
+        )
+        .unwrap();
+        let wires = datafile.wires.unwrap();
+        assert_eq!(wires["W1"].wire_type, "W18");
+        assert!((wires["W1"].length - 2.5_f64).abs() < f64::EPSILON);
+        assert_eq!(wires["W1"].end2.connector_type, "RJ45");
+    }
+
+    #[test]
+    fn missing_field() {
+        let csv = "id,type,length,end1,end2\nW1,W18,2.5,RJ45,RJ45\nW2,W18,,RJ45,RJ45\n";
+        assert_eq!(
+            row_errors(csv, &HashMap::new()),
+            [(3, Some(3), Some("length".to_string()))]
+        );
+    }
+
+    #[test]
+    fn non_numeric_length() {
+        let csv = "id,type,end1,end2,Length (m)\nW1,W18,RJ45,RJ45,long\n";
+        let mapping = HashMap::from([("length".to_string(), "length (M)".to_string())]);
+        assert_eq!(
+            row_errors(csv, &mapping),
+            [(2, Some(5), Some("Length (m)".to_string()))]
+        );
+    }
+
+    #[test]
+    fn not_in_library() {
+        let csv = "id,type,length,end1,end2\nW1,W18,1,RJ45,RJ45\nW2,W99,1,RJ45,RJ11\n";
+        assert_eq!(
+            row_errors(csv, &HashMap::new()),
+            [
+                (3, Some(2), Some("type".to_string())),
+                (3, Some(5), Some("end2".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_id() {
+        let csv = "id,type,length,end1,end2\nW1,W18,1,RJ45,RJ45\nW1,W18,2,RJ45,RJ45\n";
+        assert_eq!(
+            row_errors(csv, &HashMap::new()),
+            [(3, Some(1), Some("id".to_string()))]
+        );
+    }
+
+    #[test]
+    fn missing_column() {
+        let csv = "id,type,end1,end2\nW1,W18,RJ45,RJ45\n";
+        assert!(matches!(
+            import_csv(
+                csv.as_bytes(),
+                RecordType::Wire,
+                &HashMap::new(),
+                &test_library()
+            ),
+            Err(Error::HeaderError(_))
+        ));
+    }
+}
@@ -29,6 +29,9 @@ pub mod term_cable;
 /// `wire` represents an instance of a `WireType`
 pub mod wire;
 
+/// `connection` represents a connection between two different elements
+pub mod connection;
+
 use log::trace;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -84,6 +87,9 @@ pub struct DataFile {
     /// stores all `Pathway` instances read in from file
     #[serde(rename = "pathways")]
     pub pathways: Option<HashMap<String, pathway::Pathway>>,
+    /// stores all `Connection` instances read in from file
+    #[serde(rename = "connection")]
+    pub connections: Option<Vec<connection::Connection>>,
 }
 
 /// `data_parser` deserializes a provided file handle into a `DataFile`
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// `Connection` represents a connection between two objects in a project.
+///
+/// Each end is referenced by id using dot notation. `Wire`, `Cable` and `TermCable` ends are
+/// referenced as `<id>.end1` or `<id>.end2`, while `Equipment` is referenced either as `<id>`
+/// or with a specific connector as `<id>.<face>.<connector id>`
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Connection {
+    /// unique identifier of the first connected object
+    pub end1: String,
+    /// unique identifier of the second connected object. Cannot be the same as `end1`
+    pub end2: String,
+}
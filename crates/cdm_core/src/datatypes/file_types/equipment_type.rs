@@ -51,6 +51,11 @@ pub struct EquipFace {
 /// a `EquipmentType`
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct EquipConnector {
+    /// identifier of connector on equipment. Must be unique per `EquipmentType`. Files written
+    /// before connectors had ids still parse, but are rejected with a `DefinitionProcessing`
+    /// error when the library is loaded
+    #[serde(default)]
+    pub id: String,
     /// `ConnectorType`
     pub connector_type: String,
     /// electrical direction, used for basic rule mapping, (input, output, power input, power
@@ -34,14 +34,14 @@ pub mod wire;
 pub mod connection;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use dimensioned::{f64prefixes, ucum};
 use log::{error, info, trace, warn};
 
-use super::file_types::DataFile;
+use super::file_types::{self, DataFile};
 use super::util_types::CrossSection;
 use cable_type::{CableCore, LayerType};
 use svg::Svg;
@@ -89,6 +89,9 @@ pub struct Project {
     /// `locations` contains all location instances read in from files and/or added in via program
     /// logic
     pub locations: HashMap<String, Rc<RefCell<location::Location>>>,
+    /// `connections` contains all connections read in from files and/or added in via program
+    /// logic
+    pub connections: Vec<connection::Connection>,
 }
 
 //TODO: need to add datafile reference to each internal_type struct so each appropriate datafile
@@ -225,15 +228,15 @@ impl Library {
                     wire_type_code: wire_types[k].wire_type_code.clone(),
                     conductor_cross_sect_area: wire_types[k].conductor_cross_sect_area
                         * ucum::M2
-                        * f64prefixes::MEGA, //TODO: implement unit text option in file_types
+                        * f64prefixes::MICRO, //TODO: implement unit text option in file_types
                     overall_cross_sect_area: wire_types[k].overall_cross_sect_area
                         * ucum::M2
-                        * f64prefixes::MEGA,
+                        * f64prefixes::MICRO,
                     stranded: wire_types[k].stranded,
                     num_strands: wire_types[k].num_strands,
                     strand_cross_sect_area: wire_types[k]
                         .strand_cross_sect_area
-                        .map(|x| x * ucum::M2 * f64prefixes::MEGA),
+                        .map(|x| x * ucum::M2 * f64prefixes::MICRO),
                     insul_volt_rating: wire_types[k].insul_volt_rating.map(|x| x * ucum::V),
                     insul_temp_rating: wire_types[k].insul_temp_rating.map(|x| x * ucum::K),
                     insul_color: wire_types[k].insul_color.clone(),
@@ -634,12 +637,8 @@ impl Library {
                             }
                         }
                     },
-                    nominal_length: term_cable_types[k]
-                        .nominal_length
-                        .map(|x| x * ucum::M * f64prefixes::KILO),
-                    actual_length: term_cable_types[k]
-                        .actual_length
-                        .map(|x| x * ucum::M * f64prefixes::KILO),
+                    nominal_length: term_cable_types[k].nominal_length.map(|x| x * ucum::M),
+                    actual_length: term_cable_types[k].actual_length.map(|x| x * ucum::M),
                     end1: {
                         let mut new_end1 = Vec::new();
                         for connector in &term_cable_types[k].end1 {
@@ -766,6 +765,28 @@ impl Library {
         // equipment_types
         if let Some(equipment_types) = datafile.equipment_types {
             for (k, v) in &equipment_types {
+                // connections refer to connectors by id, so every connector needs a distinct one
+                let mut connector_ids = HashSet::new();
+                for connector in v
+                    .faces
+                    .iter()
+                    .flatten()
+                    .flat_map(|face| face.connectors.iter().flatten())
+                {
+                    let message = if connector.id.is_empty() {
+                        "connector has no id. Add an id to every connector".to_string()
+                    } else if !connector_ids.insert(connector.id.as_str()) {
+                        format!("connector id: {} is used more than once", connector.id)
+                    } else {
+                        continue;
+                    };
+                    return Err(Error::DefinitionProcessing {
+                        datatype: "EquipmentType".to_string(),
+                        datatype_id: k.clone(),
+                        message,
+                        datafile_path: datafile.file_path.clone(),
+                    });
+                }
                 let new_equipment_type = equipment_type::EquipmentType {
                     id: k.to_string(),
                     manufacturer: equipment_types[k].manufacturer.clone(),
@@ -787,49 +808,44 @@ impl Library {
                                         if let Some(connectors) = &face.connectors {
                                             let mut new_connectors = Vec::new();
                                             for connector in connectors {
-                                                let new_connector =
-                                                    equipment_type::EquipConnector {
-                                                        connector_type: {
-                                                            if self.connector_types.contains_key(
-                                                                &connector.connector_type,
-                                                            ) {
-                                                                Rc::clone(
-                                                                    &self.connector_types
-                                                                        [&connector.connector_type],
-                                                                )
-                                                            } else {
-                                                                warn! {concat!{
-                                                                "ConnectorType: {} in Equipment: {} ",
-                                                                "from datafile: {}, not found ",
-                                                                "in any library data, ",
-                                                                "either read from file, or ",
-                                                                "created via program logic. ",
-                                                                "Creating empty object for now."},
-                                                                &connector.connector_type,
-                                                                k, datafile.file_path.clone().display()
-                                                                }
-                                                                let new_connector_type = Rc::new(RefCell::new(
-                                                                        connector_type::ConnectorType::new()));
-                                                                // insert new_connector_type into library
-                                                                self.connector_types.insert(
-                                                                    connector
-                                                                        .connector_type
-                                                                        .clone(),
-                                                                    Rc::clone(&new_connector_type),
-                                                                );
-                                                                // then return reference to insert into struct
-                                                                // field
-                                                                Rc::clone(&new_connector_type)
+                                                let new_connector = equipment_type::Connector {
+                                                    id: connector.id.clone(),
+                                                    connector_type: {
+                                                        if self
+                                                            .connector_types
+                                                            .contains_key(&connector.connector_type)
+                                                        {
+                                                            Rc::clone(
+                                                                &self.connector_types
+                                                                    [&connector.connector_type],
+                                                            )
+                                                        } else {
+                                                            warn! {concat!{
+                                                            "ConnectorType: {} in Equipment: {} ",
+                                                            "from datafile: {}, not found ",
+                                                            "in any library data, ",
+                                                            "either read from file, or ",
+                                                            "created via program logic. ",
+                                                            "Creating empty object for now."},
+                                                            &connector.connector_type,
+                                                            k, datafile.file_path.clone().display()
                                                             }
-                                                        },
-                                                        direction: connector.direction.clone(),
-                                                        x: connector.x
-                                                            * ucum::M
-                                                            * f64prefixes::MILLI,
-                                                        y: connector.y
-                                                            * ucum::M
-                                                            * f64prefixes::MILLI,
-                                                    };
+                                                            let new_connector_type = Rc::new(RefCell::new(
+                                                                        connector_type::ConnectorType::new()));
+                                                            // insert new_connector_type into library
+                                                            self.connector_types.insert(
+                                                                connector.connector_type.clone(),
+                                                                Rc::clone(&new_connector_type),
+                                                            );
+                                                            // then return reference to insert into struct
+                                                            // field
+                                                            Rc::clone(&new_connector_type)
+                                                        }
+                                                    },
+                                                    direction: connector.direction.clone(),
+                                                    x: connector.x * ucum::M * f64prefixes::MILLI,
+                                                    y: connector.y * ucum::M * f64prefixes::MILLI,
+                                                };
                                                 new_connectors.push(new_connector);
                                             }
                                             Some(new_connectors)
@@ -879,6 +895,7 @@ impl Project {
             wires: HashMap::new(),
             cables: HashMap::new(),
             term_cables: HashMap::new(),
+            connections: Vec::new(),
         }
     }
     /// `from_datafiles` converts from the textual representation of datafiles, and the struct
@@ -897,9 +914,21 @@ impl Project {
         prompt_fn: fn(ComparedStruct) -> ComparedStruct,
     ) -> Result<(), Error> {
         // parse all datafiles
+        let mut datafile_connections = Vec::new();
         for datafile in datafiles {
+            if let Some(connections) = &datafile.connections {
+                datafile_connections.push((connections.clone(), datafile.file_path.clone()));
+            }
             self.from_datafile(datafile, library, prompt_fn)?;
         }
+        // connections can reference objects defined in any datafile, so they are only resolved
+        // once every datafile has been parsed
+        for (connections, datafile_path) in datafile_connections {
+            for connection in connections {
+                let new_connection = self.resolve_connection(&connection, &datafile_path)?;
+                self.connections.push(new_connection);
+            }
+        }
         // check for empty dummy objects created because they were referenced by other objects
         // during the import process
         for location in self.locations.values() {
@@ -959,6 +988,87 @@ impl Project {
         Ok(())
     }
 
+    /// `resolve_connection` looks up both ends of `connection`, read from `datafile_path`, and
+    /// validates them as a `Connection`
+    fn resolve_connection(
+        &self,
+        connection: &file_types::connection::Connection,
+        datafile_path: &Path,
+    ) -> Result<connection::Connection, Error> {
+        connection::Connection::new(
+            self.resolve_connection_end(&connection.end1, connection, datafile_path)?,
+            self.resolve_connection_end(&connection.end2, connection, datafile_path)?,
+            datafile_path.to_path_buf(),
+        )
+        .map_err(|e| Error::DefinitionProcessing {
+            datatype: "Connection".to_string(),
+            datatype_id: format!("{}-{}", connection.end1, connection.end2),
+            message: e.to_string(),
+            datafile_path: datafile_path.to_path_buf(),
+        })
+    }
+
+    /// `resolve_connection_end` looks up the object referenced by the dot notation `end_id` of
+    /// `connection`
+    ///
+    /// `Wire`, `Cable` and `TermCable` ends are referenced as `<id>.end1` or `<id>.end2`.
+    /// `Equipment` is referenced as `<id>` or `<id>.<face>.<connector id>`
+    fn resolve_connection_end(
+        &self,
+        end_id: &str,
+        connection: &file_types::connection::Connection,
+        datafile_path: &Path,
+    ) -> Result<connection::ConnectionEnd, Error> {
+        let mut parts = end_id.splitn(3, '.');
+        let id = parts.next().unwrap_or_default();
+        let cable_end = match parts.clone().next() {
+            Some("end1") => Some(connection::CableEnd::End1),
+            Some("end2") => Some(connection::CableEnd::End2),
+            _ => None,
+        };
+        let not_found = |contained_type: &str| Error::NoContainedDefinitionFound {
+            contained_type: contained_type.to_string(),
+            contained_type_id: end_id.to_string(),
+            container_type: "Connection".to_string(),
+            container_type_id: format!("{}-{}", connection.end1, connection.end2),
+            datafile_path: datafile_path.to_path_buf(),
+        };
+
+        if let Some(wire) = self.wires.get(id) {
+            Ok(connection::ConnectionEnd::Wire {
+                wire: Rc::clone(wire),
+                end: cable_end.ok_or_else(|| not_found("Wire end"))?,
+            })
+        } else if let Some(cable) = self.cables.get(id) {
+            Ok(connection::ConnectionEnd::Cable {
+                cable: Rc::clone(cable),
+                end: cable_end.ok_or_else(|| not_found("Cable end"))?,
+            })
+        } else if let Some(term_cable) = self.term_cables.get(id) {
+            Ok(connection::ConnectionEnd::TermCable {
+                term_cable: Rc::clone(term_cable),
+                end: cable_end.ok_or_else(|| not_found("TermCable end"))?,
+            })
+        } else if let Some(equipment) = self.equipment.get(id) {
+            Ok(connection::ConnectionEnd::Equipment {
+                equipment: Rc::clone(equipment),
+                face: parts.next().map(str::to_string),
+                connector: parts.next().map(str::to_string),
+            })
+        } else {
+            Err(not_found("Wire, Cable, TermCable or Equipment"))
+        }
+    }
+
+    /// `connected_to` returns every `ConnectionEnd` that is connected to `end`
+    #[must_use]
+    pub fn connected_to(&self, end: &connection::ConnectionEnd) -> Vec<&connection::ConnectionEnd> {
+        self.connections
+            .iter()
+            .filter_map(|connection| connection.other_end(end))
+            .collect()
+    }
+
     /// `from_datafile` takes a `DataFile` and a `Library` and imports all Project data found
     /// within, into the `Project` struct this method is called on. It will check `Library` for
     /// defined types to assign as references within the various project data imported from
@@ -1042,12 +1152,12 @@ impl Project {
                     },
                     identifier: wires[k].identifier.clone(),
                     description: wires[k].description.clone(),
-                    length: wires[k].length * ucum::M * f64prefixes::KILO,
+                    length: wires[k].length * ucum::M,
                     pathway: {
                         // clone string here to avoid moving value out of hashmap.
                         if let Some(pathway) = wires[k].pathway.clone() {
-                            if self.pathways.contains_key(k) {
-                                Some(Rc::clone(&self.pathways[k]))
+                            if let Some(found_pathway) = self.pathways.get(&pathway) {
+                                Some(Rc::clone(found_pathway))
                             } else {
                                 //these are both project variables so may not be defined
                                 //erroring here is fine.
@@ -1171,12 +1281,12 @@ impl Project {
                     },
                     identifier: cables[k].identifier.clone(),
                     description: cables[k].description.clone(),
-                    length: cables[k].length * ucum::M * f64prefixes::KILO,
+                    length: cables[k].length * ucum::M,
                     pathway: {
                         // clone string here to avoid moving value out of hashmap.
                         if let Some(pathway) = cables[k].pathway.clone() {
-                            if self.pathways.contains_key(k) {
-                                Some(Rc::clone(&self.pathways[k]))
+                            if let Some(found_pathway) = self.pathways.get(&pathway) {
+                                Some(Rc::clone(found_pathway))
                             } else {
                                 //these are both project variables so may not be defined
                                 //erroring here is fine.
@@ -1345,8 +1455,8 @@ impl Project {
                     pathway: {
                         // clone string here to avoid moving value out of hashmap.
                         if let Some(pathway) = term_cables[k].pathway.clone() {
-                            if self.pathways.contains_key(k) {
-                                Some(Rc::clone(&self.pathways[k]))
+                            if let Some(found_pathway) = self.pathways.get(&pathway) {
+                                Some(Rc::clone(found_pathway))
                             } else {
                                 //these are both project variables so may not be defined
                                 //erroring here is fine.
@@ -1466,7 +1576,7 @@ impl Project {
                 #[allow(clippy::map_entry)]
                 // TODO: use entry mechanic to fix this, allowing for now
                 let temp_location = if self.locations.contains_key(&equipment[k].location) {
-                    Rc::clone(&self.locations[k])
+                    Rc::clone(&self.locations[&equipment[k].location])
                 } else {
                     // In theory, this location could be defined in another file
                     //TODO: return error here
@@ -1743,11 +1853,248 @@ mod tests {
                 locations: HashMap::new(),
                 equipment: HashMap::new(),
                 pathways: HashMap::new(),
-                wire_cables: HashMap::new(),
+                wires: HashMap::new(),
+                cables: HashMap::new(),
+                term_cables: HashMap::new(),
+                connections: Vec::new(),
             }
         )
     }
 
+    /// `keep` resolves merge conflicts by keeping the existing value
+    fn keep(compared: ComparedStruct) -> ComparedStruct {
+        compared
+    }
+
+    /// `equipment_type_datafile` returns a datafile defining connector type `RJ45` and equipment
+    /// type `switch`, with connectors of the given ids on faces `Front` and `Rear`
+    fn equipment_type_datafile(front: &[&str], rear: &[&str]) -> DataFile {
+        let face = |name: &str, ids: &[&str]| file_types::equipment_type::EquipFace {
+            name: name.to_string(),
+            connectors: Some(
+                ids.iter()
+                    .map(|id| file_types::equipment_type::EquipConnector {
+                        id: (*id).to_string(),
+                        connector_type: "RJ45".to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        DataFile {
+            connector_types: Some(HashMap::from([(
+                "RJ45".to_string(),
+                file_types::connector_type::ConnectorType {
+                    manufacturer: Some("Generic".to_string()),
+                    ..Default::default()
+                },
+            )])),
+            equipment_types: Some(HashMap::from([(
+                "switch".to_string(),
+                file_types::equipment_type::EquipmentType {
+                    faces: Some(vec![face("Front", front), face("Rear", rear)]),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn connector_id_defaults_to_empty() {
+        let connector: file_types::equipment_type::EquipConnector =
+            serde_yaml::from_str("connector_type: RJ45\nx: 0\ny: 0\n").unwrap();
+        assert_eq!(connector.id, "");
+    }
+
+    #[test]
+    fn connector_ids() {
+        let mut library = Library::new();
+        library
+            .from_datafiles(vec![equipment_type_datafile(&["J1", "J2"], &["J3"])], keep)
+            .unwrap();
+        let switch = library.equipment_types["switch"].borrow();
+        let faces = switch.faces.as_ref().unwrap();
+        let ids = |face: &str| {
+            faces[face]
+                .connectors
+                .iter()
+                .flatten()
+                .map(|connector| connector.id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("Front"), ["J1", "J2"]);
+        assert_eq!(ids("Rear"), ["J3"]);
+    }
+
+    #[test]
+    fn connector_ids_invalid() {
+        for (front, rear) in [(vec!["J1", ""], vec![]), (vec!["J1"], vec!["J1"])] {
+            let mut library = Library::new();
+            let result = library.from_datafiles(vec![equipment_type_datafile(&front, &rear)], keep);
+            assert!(
+                matches!(result, Err(Error::DefinitionProcessing { ref datatype, .. }) if datatype == "EquipmentType"),
+                "{front:?} {rear:?} loaded"
+            );
+        }
+    }
+
+    /// `connection_project` returns a project with wire `W1`, cable `C1`, term cable `TC1` and
+    /// equipment `EQ1`, which has connector `J1` on face `Front`
+    fn connection_project() -> Project {
+        let mut project = Project::new();
+        project.wires.insert(
+            "W1".to_string(),
+            Rc::new(RefCell::new(wire::Wire {
+                id: "W1".to_string(),
+                ..Default::default()
+            })),
+        );
+        project.cables.insert(
+            "C1".to_string(),
+            Rc::new(RefCell::new(cable::Cable {
+                id: "C1".to_string(),
+                ..Default::default()
+            })),
+        );
+        project.term_cables.insert(
+            "TC1".to_string(),
+            Rc::new(RefCell::new(term_cable::TermCable {
+                id: "TC1".to_string(),
+                ..Default::default()
+            })),
+        );
+        let equip_type = equipment_type::EquipmentType {
+            faces: Some(HashMap::from([(
+                "Front".to_string(),
+                equipment_type::EquipFace {
+                    connectors: Some(vec![equipment_type::Connector {
+                        id: "J1".to_string(),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        };
+        project.equipment.insert(
+            "EQ1".to_string(),
+            Rc::new(RefCell::new(equipment::Equipment {
+                id: "EQ1".to_string(),
+                equip_type: Rc::new(RefCell::new(equip_type)),
+                ..Default::default()
+            })),
+        );
+        project
+    }
+
+    /// `resolve` resolves a connection between `end1` and `end2` in `project`
+    fn resolve(project: &Project, end1: &str, end2: &str) -> Result<connection::Connection, Error> {
+        project.resolve_connection(
+            &file_types::connection::Connection {
+                end1: end1.to_string(),
+                end2: end2.to_string(),
+            },
+            Path::new("connections.yaml"),
+        )
+    }
+
+    #[test]
+    fn resolve_connection_ends() {
+        let project = connection_project();
+        let connection = resolve(&project, "W1.end1", "EQ1.Front.J1").unwrap();
+        assert!(matches!(
+            connection.end1,
+            connection::ConnectionEnd::Wire {
+                end: connection::CableEnd::End1,
+                ..
+            }
+        ));
+        assert_eq!(connection.end2.id(), "EQ1.Front.J1");
+        assert!(connection.end2.equipment_connector().is_some());
+
+        for (end1, end2) in [
+            ("C1.end2", "EQ1"),
+            ("TC1.end1", "W1.end2"),
+            ("W1.end1", "W1.end2"),
+        ] {
+            let resolved = resolve(&project, end1, end2).unwrap();
+            assert_eq!([resolved.end1.id(), resolved.end2.id()], [end1, end2]);
+        }
+    }
+
+    #[test]
+    fn resolve_unknown_id() {
+        let project = connection_project();
+        assert!(matches!(
+            resolve(&project, "X9.end1", "EQ1"),
+            Err(Error::NoContainedDefinitionFound { ref contained_type_id, .. }) if contained_type_id == "X9.end1"
+        ));
+    }
+
+    #[test]
+    fn resolve_missing_end() {
+        let project = connection_project();
+        for (end, contained) in [
+            ("W1", "Wire end"),
+            ("C1.end3", "Cable end"),
+            ("TC1.", "TermCable end"),
+        ] {
+            assert!(
+                matches!(
+                    resolve(&project, end, "EQ1"),
+                    Err(Error::NoContainedDefinitionFound { ref contained_type, .. }) if contained_type == contained
+                ),
+                "{end} resolved"
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_unknown_face_or_connector() {
+        let project = connection_project();
+        for end in ["EQ1.Rear.J1", "EQ1.Front.J9", "EQ1.Front"] {
+            assert!(
+                matches!(
+                    resolve(&project, "W1.end1", end),
+                    Err(Error::DefinitionProcessing { ref datatype, .. }) if datatype == "Connection"
+                ),
+                "{end} resolved"
+            );
+        }
+        // an end can't be connected to itself
+        assert!(matches!(
+            resolve(&project, "W1.end1", "W1.end1"),
+            Err(Error::DefinitionProcessing { .. })
+        ));
+    }
+
+    #[test]
+    fn connected_to() {
+        let mut project = connection_project();
+        for (end1, end2) in [("W1.end1", "EQ1.Front.J1"), ("W1.end2", "C1.end1")] {
+            let connection = resolve(&project, end1, end2).unwrap();
+            project.connections.push(connection);
+        }
+        let wire_end = |end| connection::ConnectionEnd::Wire {
+            wire: Rc::clone(&project.wires["W1"]),
+            end,
+        };
+        let ids = |end: &connection::ConnectionEnd| {
+            project
+                .connected_to(end)
+                .into_iter()
+                .map(connection::ConnectionEnd::id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&wire_end(connection::CableEnd::End1)), ["EQ1.Front.J1"]);
+        assert_eq!(ids(&wire_end(connection::CableEnd::End2)), ["C1.end1"]);
+        let far_end = wire_end(connection::CableEnd::End2).opposite_end().unwrap();
+        assert_eq!(ids(&far_end), ["EQ1.Front.J1"]);
+        assert!(ids(&project.connections[0].end2).contains(&"W1.end1".to_string()));
+    }
+
     // TODO:  testing ideas (for both project and library):
     // - test import of datafile containing each individual object
     // - test import of basic datafile, minimal amount of data necessary
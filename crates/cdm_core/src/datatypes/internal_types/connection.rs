@@ -1,29 +1,251 @@
 use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use crate::datatypes::internal_types::{
-    cable, equipment::Equipment, equipment_type::EquipConnector, location::Location,
-    term_cable_type, wire,
+    cable::Cable, equipment::Equipment, equipment_type::Connector as EquipConnector,
+    term_cable::TermCable, wire::Wire,
 };
 
-// WireCableConnector can connect to WireCableConnector, wire_cable
-
 /// `Connection` represents a connection between two different elements. Use methods to create as
 /// they perform data validation.
-#[derive(Default, Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Connection {
-    /// `wire_connector` is a connector on a `Wire`
-    wire_connectors: Option<Vec<wire::Connector>>,
-    /// `cable_connector` is a connector on a `Cable`
-    cable_connectors: Option<Vec<cable::Connector>>,
-    /// `term_cable_connector` is a connector on a `TermCable`
-    term_cable_connectors: Option<Vec<term_cable_type::Connector>>,
-    /// `equipment` is a piece of equipment
-    equipment: Option<Rc<RefCell<Equipment>>>,
-    /// `equipment_connector` is a reference to a connector on the specified `equipment`
-    equipment_connector: Option<EquipConnector>,
-    /// where the `connection` is located
-    location: Rc<RefCell<Location>>,
+    /// `end1` is the first connected object
+    pub end1: ConnectionEnd,
+    /// `end2` is the second connected object
+    pub end2: ConnectionEnd,
+    /// datafile the struct instance was read in from
+    pub contained_datafile_path: PathBuf,
+}
+
+/// `ConnectionEnd` represents one side of a `Connection`
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConnectionEnd {
+    /// one end of a `Wire`
+    Wire {
+        /// connected `Wire`
+        wire: Rc<RefCell<Wire>>,
+        /// which end of `wire` is connected
+        end: CableEnd,
+    },
+    /// one end of a `Cable`
+    Cable {
+        /// connected `Cable`
+        cable: Rc<RefCell<Cable>>,
+        /// which end of `cable` is connected
+        end: CableEnd,
+    },
+    /// one end of a `TermCable`
+    TermCable {
+        /// connected `TermCable`
+        term_cable: Rc<RefCell<TermCable>>,
+        /// which end of `term_cable` is connected
+        end: CableEnd,
+    },
+    /// a piece of `Equipment`, optionally narrowed down to one of its connectors
+    Equipment {
+        /// connected `Equipment`
+        equipment: Rc<RefCell<Equipment>>,
+        /// face of the `EquipmentType` the connector is located on
+        face: Option<String>,
+        /// id of the connector on `face`
+        connector: Option<String>,
+    },
+}
+
+/// `CableEnd` selects one of the two ends of a `Wire`, `Cable` or `TermCable`
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CableEnd {
+    /// `end1`
+    End1,
+    /// `end2`
+    End2,
+}
+
+impl CableEnd {
+    /// returns the opposite end of the same object
+    #[must_use]
+    pub fn other(self) -> Self {
+        match self {
+            CableEnd::End1 => CableEnd::End2,
+            CableEnd::End2 => CableEnd::End1,
+        }
+    }
+}
+
+impl fmt::Display for CableEnd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CableEnd::End1 => write!(f, "end1"),
+            CableEnd::End2 => write!(f, "end2"),
+        }
+    }
+}
+
+impl ConnectionEnd {
+    /// returns the dot notation id of this end, as used in datafiles
+    #[must_use]
+    pub fn id(&self) -> String {
+        match self {
+            ConnectionEnd::Wire { wire, end } => format!("{}.{end}", wire.borrow().id),
+            ConnectionEnd::Cable { cable, end } => format!("{}.{end}", cable.borrow().id),
+            ConnectionEnd::TermCable { term_cable, end } => {
+                format!("{}.{end}", term_cable.borrow().id)
+            }
+            ConnectionEnd::Equipment {
+                equipment,
+                face,
+                connector,
+            } => match (face, connector) {
+                (Some(face), Some(connector)) => {
+                    format!("{}.{face}.{connector}", equipment.borrow().id)
+                }
+                _ => equipment.borrow().id.clone(),
+            },
+        }
+    }
+
+    /// returns a human readable description of this end, using the `identifier` of the
+    /// connected object if one is set, and falling back to its `id`
+    #[must_use]
+    pub fn identifier(&self) -> String {
+        match self {
+            ConnectionEnd::Wire { wire, end } => {
+                let wire = wire.borrow();
+                format!("{} {end}", wire.identifier.as_ref().unwrap_or(&wire.id))
+            }
+            ConnectionEnd::Cable { cable, end } => {
+                let cable = cable.borrow();
+                format!("{} {end}", cable.identifier.as_ref().unwrap_or(&cable.id))
+            }
+            ConnectionEnd::TermCable { term_cable, end } => {
+                let term_cable = term_cable.borrow();
+                format!(
+                    "{} {end}",
+                    term_cable.identifier.as_ref().unwrap_or(&term_cable.id)
+                )
+            }
+            ConnectionEnd::Equipment {
+                equipment,
+                connector,
+                ..
+            } => {
+                let equipment = equipment.borrow();
+                let equipment_identifier = equipment.identifier.as_ref().unwrap_or(&equipment.id);
+                match connector {
+                    Some(connector) => format!("{equipment_identifier} {connector}"),
+                    None => equipment_identifier.clone(),
+                }
+            }
+        }
+    }
+
+    /// returns true if `other` refers to the same end of the same object as `self`
+    ///
+    /// Unlike `PartialEq`, this compares object identity rather than object contents.
+    #[must_use]
+    pub fn is_same(&self, other: &ConnectionEnd) -> bool {
+        match (self, other) {
+            (
+                ConnectionEnd::Wire { wire, end },
+                ConnectionEnd::Wire {
+                    wire: other_wire,
+                    end: other_end,
+                },
+            ) => Rc::ptr_eq(wire, other_wire) && end == other_end,
+            (
+                ConnectionEnd::Cable { cable, end },
+                ConnectionEnd::Cable {
+                    cable: other_cable,
+                    end: other_end,
+                },
+            ) => Rc::ptr_eq(cable, other_cable) && end == other_end,
+            (
+                ConnectionEnd::TermCable { term_cable, end },
+                ConnectionEnd::TermCable {
+                    term_cable: other_term_cable,
+                    end: other_end,
+                },
+            ) => Rc::ptr_eq(term_cable, other_term_cable) && end == other_end,
+            (
+                ConnectionEnd::Equipment {
+                    equipment,
+                    face,
+                    connector,
+                },
+                ConnectionEnd::Equipment {
+                    equipment: other_equipment,
+                    face: other_face,
+                    connector: other_connector,
+                },
+            ) => {
+                Rc::ptr_eq(equipment, other_equipment)
+                    && face == other_face
+                    && connector == other_connector
+            }
+            _ => false,
+        }
+    }
+
+    /// returns the opposite end of the same `Wire`, `Cable` or `TermCable`. Equipment has no
+    /// opposite end, so returns `None`
+    #[must_use]
+    pub fn opposite_end(&self) -> Option<ConnectionEnd> {
+        match self {
+            ConnectionEnd::Wire { wire, end } => Some(ConnectionEnd::Wire {
+                wire: Rc::clone(wire),
+                end: end.other(),
+            }),
+            ConnectionEnd::Cable { cable, end } => Some(ConnectionEnd::Cable {
+                cable: Rc::clone(cable),
+                end: end.other(),
+            }),
+            ConnectionEnd::TermCable { term_cable, end } => Some(ConnectionEnd::TermCable {
+                term_cable: Rc::clone(term_cable),
+                end: end.other(),
+            }),
+            ConnectionEnd::Equipment { .. } => None,
+        }
+    }
+
+    /// returns the `Equipment` this end is attached to, if it is an equipment end
+    #[must_use]
+    pub fn equipment(&self) -> Option<Rc<RefCell<Equipment>>> {
+        match self {
+            ConnectionEnd::Equipment { equipment, .. } => Some(Rc::clone(equipment)),
+            _ => None,
+        }
+    }
+
+    /// returns the connector on the `EquipmentType` referenced by this end, if it is an
+    /// equipment end that specifies a connector
+    #[must_use]
+    pub fn equipment_connector(&self) -> Option<EquipConnector> {
+        match self {
+            ConnectionEnd::Equipment {
+                equipment,
+                face: Some(face),
+                connector: Some(connector),
+            } => {
+                let equipment = equipment.borrow();
+                let equip_type = equipment.equip_type.borrow();
+                equip_type
+                    .faces
+                    .as_ref()?
+                    .get(face)?
+                    .connectors
+                    .as_ref()?
+                    .iter()
+                    .find(|equip_connector| &equip_connector.id == connector)
+                    .cloned()
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Connection {
@@ -31,73 +253,76 @@ impl Connection {
     ///
     /// # Errors
     ///
-    /// Will error if too many or too few connection parameters are passed in
-    ///
-    #[allow(clippy::unwrap_in_result, clippy::missing_panics_doc)]
+    /// Will error if:
+    /// - both ends refer to the same end of the same object
+    /// - an equipment end specifies only one of `face` and `connector`
+    /// - an equipment end specifies a connector not found on the `EquipmentType`
     pub fn new(
-        wire_connectors: Option<Vec<wire::Connector>>,
-        cable_connectors: Option<Vec<cable::Connector>>,
-        term_cable_connectors: Option<Vec<term_cable_type::Connector>>,
-        equipment: Option<Rc<RefCell<Equipment>>>,
-        equipment_connector: Option<EquipConnector>,
-        location: Rc<RefCell<Location>>,
+        end1: ConnectionEnd,
+        end2: ConnectionEnd,
+        contained_datafile_path: PathBuf,
     ) -> Result<Self, Error> {
-        if wire_connectors.is_some()
-            && cable_connectors.is_some()
-            && term_cable_connectors.is_some()
-            && (equipment.is_some() || equipment_connector.is_some())
-        {
-            return Err(Error::TooManyArguments("A connection may only be defined between two of WireConnector,CableConnector, TermCableConnector, or EquipConnector. Reduce the number of Some() arguments.".to_owned()));
-        }
-        if (equipment.is_some() && equipment_connector.is_none())
-            || (equipment.is_none() && equipment_connector.is_some())
-        {
-            return Err(Error::MismatchedArguments(
-                "equipment and equipment_connector must both be defined together".to_owned(),
-            ));
-        }
-
-        // if number of connectors specified is more than 2
-        // allowing all these, since unwrap is checked beforehand, and the unwrap_in_result is a
-        // false positive
-        #[allow(clippy::unwrap_used, clippy::arithmetic_side_effects)]
-        if wire_connectors.is_some()
-            && cable_connectors.is_some()
-            && term_cable_connectors.is_some()
-            && (wire_connectors.as_ref().unwrap().len()
-                + cable_connectors.as_ref().unwrap().len()
-                + term_cable_connectors.as_ref().unwrap().len())
-                > 2
-        {
-            return Err(Error::TooManyArguments("More than two WireConnectors, CableConnectors and TermCableConnectors are specified. A connection may only be defined between 2 connectors".to_owned()));
+        if end1.is_same(&end2) {
+            return Err(Error::MismatchedArguments(format!(
+                "end1 and end2 of a connection cannot be the same: {}",
+                end1.id()
+            )));
         }
-
-        // if number of connectors is less than 2
-        // allowing all these, since unwrap is checked beforehand, and the unwrap_in_result is a
-        // false positive
-        #[allow(clippy::unwrap_used, clippy::arithmetic_side_effects)]
-        if wire_connectors.is_some()
-            && cable_connectors.is_some()
-            && term_cable_connectors.is_some()
-            && (wire_connectors.as_ref().unwrap().len()
-                + cable_connectors.as_ref().unwrap().len()
-                + term_cable_connectors.as_ref().unwrap().len())
-                < 2
-        {
-            return Err(Error::NotEnoughArguments("Less than two WireCableConnectors and TermCableConnectors are specified. A connection must have 2 connectors.".to_owned()));
+        for end in [&end1, &end2] {
+            if let ConnectionEnd::Equipment {
+                face, connector, ..
+            } = end
+            {
+                if face.is_some() != connector.is_some() {
+                    return Err(Error::MismatchedArguments(format!(
+                        "face and connector must both be defined together: {}",
+                        end.id()
+                    )));
+                }
+                if connector.is_some() && end.equipment_connector().is_none() {
+                    return Err(Error::MismatchedArguments(format!(
+                        "connector not found on equipment type: {}",
+                        end.id()
+                    )));
+                }
+            }
         }
 
         // TODO: validate that number of pins is the same on both connectors
 
         Ok(Self {
-            wire_connectors,
-            cable_connectors,
-            term_cable_connectors,
-            equipment,
-            equipment_connector,
-            location,
+            end1,
+            end2,
+            contained_datafile_path,
         })
     }
+
+    /// returns the unique id of the connection, made by concatenating the ids of both ends
+    #[must_use]
+    pub fn id(&self) -> String {
+        format!("{}-{}", self.end1.id(), self.end2.id())
+    }
+
+    /// returns the opposite end of this connection if `end` is one of its ends
+    #[must_use]
+    pub fn other_end(&self, end: &ConnectionEnd) -> Option<&ConnectionEnd> {
+        if self.end1.is_same(end) {
+            Some(&self.end2)
+        } else if self.end2.is_same(end) {
+            Some(&self.end1)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Connection:")?;
+        writeln!(f, "End 1: {}", self.end1.id())?;
+        writeln!(f, "End 2: {}", self.end2.id())?;
+        Ok(())
+    }
 }
 
 #[non_exhaustive]
@@ -66,6 +66,7 @@ pub struct EquipFace {
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct Connector {
     /// Internal ID of `EquipmentConnector`
+    pub id: String,
     /// `ConnectorType`
     pub connector_type: Rc<RefCell<ConnectorType>>,
     /// electrical direction, used for basic rule mapping, (input, output, power input, power
@@ -76,6 +77,10 @@ pub struct Connector {
     /// location of connector on face from bottom of visrep. Origin is bottom left
     pub y: ucum::Meter<f64>,
 }
+/// `DRAWN_FACE` is the face of an `EquipmentType` shown by `visual_rep`, so connectors on any
+/// other face aren't visible where the equipment is drawn
+pub const DRAWN_FACE: &str = "Front";
+
 impl EquipmentType {
     /// Creates an empty instance of `EquipmentType`
     #[must_use]
@@ -86,8 +91,8 @@ impl EquipmentType {
     /// Returns representative svg representation of `EquipmentType`
     #[must_use]
     pub fn visual_rep(&self) -> Svg {
-        match &self.faces {
-            Some(faces) => faces["Front"].visual_rep.clone(),
+        match self.faces.as_ref().and_then(|faces| faces.get(DRAWN_FACE)) {
+            Some(face) => face.visual_rep.clone(),
             None => self.visual_rep.clone(),
         }
     }
@@ -96,6 +101,7 @@ impl EquipmentType {
 impl fmt::Display for Connector {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Equipment Connector:")?;
+        writeln!(f, "ID: {}", &self.id)?;
         writeln!(f, "Connector: {}", &self.connector_type.borrow())?;
         if let Some(direction) = &self.direction {
             writeln!(f, "Direction: {direction}")?;
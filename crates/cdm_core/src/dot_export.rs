@@ -0,0 +1,632 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+use crate::datatypes::internal_types::{
+    connection::{CableEnd, ConnectionEnd},
+    equipment::Equipment,
+    Project,
+};
+
+/// `Filter` selects which part of a `Project` is exported
+#[non_exhaustive]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub enum Filter {
+    /// export the whole project
+    #[default]
+    All,
+    /// export the equipment in one `Location`, specified by id, along with everything directly
+    /// connected to it
+    Location(String),
+    /// export one signal trace, starting at a `Wire`, `Cable`, `TermCable` or `Equipment`
+    /// specified by id. The trace follows wires and cables through any splices until it reaches
+    /// equipment.
+    Trace(String),
+}
+
+/// `Endpoint` is one end of an edge in the exported graph
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+enum Endpoint {
+    /// a piece of equipment, optionally at one of its connectors
+    Equipment {
+        /// id of `Equipment`
+        id: String,
+        /// port name of connector in the record node
+        port: Option<String>,
+    },
+    /// a loose cable end that is not connected to any equipment. Contains the dot notation id
+    /// of the cable end
+    Point(String),
+}
+
+/// `Edge` is a connection drawn between two `Endpoint`s
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+struct Edge {
+    /// start of edge
+    from: Endpoint,
+    /// end of edge
+    to: Endpoint,
+    /// id of the `Wire`, `Cable` or `TermCable` this edge represents, if any
+    object_id: Option<String>,
+    /// text drawn on edge
+    label: Option<String>,
+}
+
+impl Endpoint {
+    /// returns the graph node this endpoint is attached to, ignoring the port
+    fn node(&self) -> &str {
+        match self {
+            Endpoint::Equipment { id, .. } | Endpoint::Point(id) => id,
+        }
+    }
+
+    /// returns true if this endpoint is a piece of equipment
+    fn is_equipment(&self) -> bool {
+        matches!(self, Endpoint::Equipment { .. })
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Endpoint::Equipment {
+                id,
+                port: Some(port),
+            } => write!(f, "{}:{}", quote(id), quote(port)),
+            Endpoint::Equipment { id, port: None } | Endpoint::Point(id) => {
+                write!(f, "{}", quote(id))
+            }
+        }
+    }
+}
+
+/// `project_to_dot` exports `project` as a Graphviz DOT graph.
+///
+/// Equipment is drawn as record nodes with one port per connector, clustered by `Location`.
+/// `Wire`s, `Cable`s and `TermCable`s are drawn as edges labelled with their identifier. Cable
+/// ends that are not connected to any equipment are drawn as points, and connections directly
+/// between two cables are drawn as dashed edges.
+///
+/// # Errors
+///
+/// Will error if the id specified in `filter` is not found in `project`
+pub fn project_to_dot(project: &Project, filter: &Filter) -> Result<String, Error> {
+    let (edges, mut equipment_ids) = select(project, project_edges(project), filter)?;
+
+    for edge in &edges {
+        for endpoint in [&edge.from, &edge.to] {
+            if let Endpoint::Equipment { id, .. } = endpoint {
+                equipment_ids.insert(id.clone());
+            }
+        }
+    }
+
+    // group equipment by location so each location can be drawn as a cluster
+    let mut clusters: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for equipment_id in &equipment_ids {
+        if let Some(equipment) = project.equipment.get(equipment_id) {
+            clusters
+                .entry(equipment.borrow().location.borrow().id.clone())
+                .or_default()
+                .push(equipment_node(&equipment.borrow()));
+        }
+    }
+
+    let mut output = String::new();
+    // writing into a String can't fail, so results of writeln! are ignored
+    let _ = writeln!(output, "graph project {{");
+    let _ = writeln!(output, "    rankdir=LR;");
+    let _ = writeln!(output, "    node [shape=record];");
+    for (location_id, nodes) in &clusters {
+        let location_label = project.locations.get(location_id).map_or_else(
+            || location_id.clone(),
+            |location| {
+                let location = location.borrow();
+                location
+                    .identifier
+                    .clone()
+                    .unwrap_or_else(|| location.id.clone())
+            },
+        );
+        let _ = writeln!(
+            output,
+            "    subgraph {} {{",
+            quote(&format!("cluster_{location_id}"))
+        );
+        let _ = writeln!(output, "        label={};", quote(&location_label));
+        for node in nodes {
+            let _ = writeln!(output, "        {node}");
+        }
+        let _ = writeln!(output, "    }}");
+    }
+    let points: BTreeSet<&str> = edges
+        .iter()
+        .flat_map(|edge| [&edge.from, &edge.to])
+        .filter_map(|endpoint| match endpoint {
+            Endpoint::Point(id) => Some(id.as_str()),
+            Endpoint::Equipment { .. } => None,
+        })
+        .collect();
+    for point in points {
+        let _ = writeln!(
+            output,
+            "    {} [shape=point, xlabel={}];",
+            quote(point),
+            quote(point)
+        );
+    }
+    for edge in &edges {
+        let mut attributes = Vec::new();
+        if let Some(label) = &edge.label {
+            attributes.push(format!("label={}", quote(label)));
+        }
+        if edge.object_id.is_none() {
+            attributes.push("style=dashed".to_string());
+        }
+        let _ = writeln!(
+            output,
+            "    {} -- {} [{}];",
+            edge.from,
+            edge.to,
+            attributes.join(", ")
+        );
+    }
+    let _ = writeln!(output, "}}");
+    Ok(output)
+}
+
+/// `select` applies `filter` to `all_edges`, returning the edges to draw and any equipment that
+/// should be drawn even if it has no edges
+fn select(
+    project: &Project,
+    all_edges: Vec<Edge>,
+    filter: &Filter,
+) -> Result<(Vec<Edge>, BTreeSet<String>), Error> {
+    match filter {
+        Filter::All => Ok((all_edges, project.equipment.keys().cloned().collect())),
+        Filter::Location(location_id) => {
+            if !project.locations.contains_key(location_id) {
+                return Err(Error::FilterError(format!(
+                    "Location {location_id} not found in project"
+                )));
+            }
+            let located: BTreeSet<String> = project
+                .equipment
+                .values()
+                .filter(|equipment| &equipment.borrow().location.borrow().id == location_id)
+                .map(|equipment| equipment.borrow().id.clone())
+                .collect();
+            let edges = all_edges
+                .into_iter()
+                .filter(|edge| {
+                    located.contains(edge.from.node()) || located.contains(edge.to.node())
+                })
+                .collect();
+            Ok((edges, located))
+        }
+        Filter::Trace(start_id) => trace(project, all_edges, start_id),
+    }
+}
+
+/// `project_edges` converts all wires, cables, term cables and connections in `project` into a
+/// sorted list of edges
+fn project_edges(project: &Project) -> Vec<Edge> {
+    let mut edges = Vec::new();
+
+    let mut cable_ends = Vec::new();
+    for wire in project.wires.values() {
+        cable_ends.push(ConnectionEnd::Wire {
+            wire: Rc::clone(wire),
+            end: CableEnd::End1,
+        });
+    }
+    for cable in project.cables.values() {
+        cable_ends.push(ConnectionEnd::Cable {
+            cable: Rc::clone(cable),
+            end: CableEnd::End1,
+        });
+    }
+    for term_cable in project.term_cables.values() {
+        cable_ends.push(ConnectionEnd::TermCable {
+            term_cable: Rc::clone(term_cable),
+            end: CableEnd::End1,
+        });
+    }
+
+    for end1 in &cable_ends {
+        let Some(end2) = end1.opposite_end() else {
+            continue;
+        };
+        let (object_id, label) = match end1 {
+            ConnectionEnd::Wire { wire, .. } => {
+                let wire = wire.borrow();
+                (wire.id.clone(), wire.identifier.clone())
+            }
+            ConnectionEnd::Cable { cable, .. } => {
+                let cable = cable.borrow();
+                (cable.id.clone(), cable.identifier.clone())
+            }
+            ConnectionEnd::TermCable { term_cable, .. } => {
+                let term_cable = term_cable.borrow();
+                (term_cable.id.clone(), term_cable.identifier.clone())
+            }
+            ConnectionEnd::Equipment { .. } => continue,
+        };
+        for from in cable_endpoints(project, end1) {
+            for to in cable_endpoints(project, &end2) {
+                edges.push(Edge {
+                    from: from.clone(),
+                    to,
+                    object_id: Some(object_id.clone()),
+                    label: Some(label.clone().unwrap_or_else(|| object_id.clone())),
+                });
+            }
+        }
+    }
+
+    // connections that don't have a cable in between them
+    for connection in &project.connections {
+        match (
+            connection.end1.equipment().is_some(),
+            connection.end2.equipment().is_some(),
+        ) {
+            // equipment plugged directly into equipment
+            (true, true) => edges.push(Edge {
+                from: endpoint(&connection.end1),
+                to: endpoint(&connection.end2),
+                object_id: None,
+                label: None,
+            }),
+            // cables spliced together
+            (false, false) => {
+                for from in cable_endpoints(project, &connection.end1) {
+                    for to in cable_endpoints(project, &connection.end2) {
+                        edges.push(Edge {
+                            from: from.clone(),
+                            to,
+                            object_id: None,
+                            label: None,
+                        });
+                    }
+                }
+            }
+            // equipment connected to a cable is already drawn as part of the cable edge
+            _ => {}
+        }
+    }
+    edges.sort();
+    edges
+}
+
+/// `cable_endpoints` returns the graph endpoints for one end of a cable. This is all equipment
+/// the end is connected to, or a point node if it isn't connected to any equipment
+fn cable_endpoints(project: &Project, cable_end: &ConnectionEnd) -> Vec<Endpoint> {
+    let endpoints: Vec<Endpoint> = project
+        .connected_to(cable_end)
+        .into_iter()
+        .filter(|end| end.equipment().is_some())
+        .map(endpoint)
+        .collect();
+    if endpoints.is_empty() {
+        vec![Endpoint::Point(cable_end.id())]
+    } else {
+        endpoints
+    }
+}
+
+/// `endpoint` converts a `ConnectionEnd` into a graph endpoint
+fn endpoint(end: &ConnectionEnd) -> Endpoint {
+    match end {
+        ConnectionEnd::Equipment {
+            equipment,
+            face,
+            connector,
+        } => Endpoint::Equipment {
+            id: equipment.borrow().id.clone(),
+            port: match (face, connector) {
+                (Some(face), Some(connector)) => Some(port_name(face, connector)),
+                _ => None,
+            },
+        },
+        _ => Endpoint::Point(end.id()),
+    }
+}
+
+/// `trace` selects all edges reachable from `start_id`, following splices but stopping at
+/// equipment other than the starting object
+fn trace(
+    project: &Project,
+    edges: Vec<Edge>,
+    start_id: &str,
+) -> Result<(Vec<Edge>, BTreeSet<String>), Error> {
+    let mut visited = vec![false; edges.len()];
+    let mut queue = VecDeque::new();
+    let mut equipment_ids = BTreeSet::new();
+
+    if project.equipment.contains_key(start_id) {
+        equipment_ids.insert(start_id.to_string());
+        queue.push_back(start_id.to_string());
+    } else if project.wires.contains_key(start_id)
+        || project.cables.contains_key(start_id)
+        || project.term_cables.contains_key(start_id)
+    {
+        for (index, edge) in edges.iter().enumerate() {
+            if edge.object_id.as_deref() == Some(start_id) {
+                if let Some(is_visited) = visited.get_mut(index) {
+                    *is_visited = true;
+                }
+                for endpoint in [&edge.from, &edge.to] {
+                    if !endpoint.is_equipment() {
+                        queue.push_back(endpoint.node().to_string());
+                    }
+                }
+            }
+        }
+    } else {
+        return Err(Error::FilterError(format!(
+            "Wire, Cable, TermCable or Equipment {start_id} not found in project"
+        )));
+    }
+
+    while let Some(node) = queue.pop_front() {
+        for (index, edge) in edges.iter().enumerate() {
+            if visited.get(index).copied().unwrap_or(true) {
+                continue;
+            }
+            let other = if edge.from.node() == node {
+                &edge.to
+            } else if edge.to.node() == node {
+                &edge.from
+            } else {
+                continue;
+            };
+            if let Some(is_visited) = visited.get_mut(index) {
+                *is_visited = true;
+            }
+            if !other.is_equipment() {
+                queue.push_back(other.node().to_string());
+            }
+        }
+    }
+
+    let traced = edges
+        .into_iter()
+        .zip(visited)
+        .filter_map(|(edge, is_visited)| is_visited.then_some(edge))
+        .collect();
+    Ok((traced, equipment_ids))
+}
+
+/// `equipment_node` returns the DOT node statement for one piece of equipment as a record
+/// with a port for each connector
+fn equipment_node(equipment: &Equipment) -> String {
+    let identifier = equipment
+        .identifier
+        .clone()
+        .unwrap_or_else(|| equipment.id.clone());
+    let mut ports = Vec::new();
+    if let Some(faces) = &equipment.equip_type.borrow().faces {
+        let mut face_names: Vec<&String> = faces.keys().collect();
+        face_names.sort();
+        for face_name in face_names {
+            for connector in faces
+                .get(face_name)
+                .and_then(|face| face.connectors.as_ref())
+                .into_iter()
+                .flatten()
+            {
+                ports.push(format!(
+                    "<{}> {}",
+                    port_name(face_name, &connector.id),
+                    escape_record(&format!("{face_name} {}", connector.id))
+                ));
+            }
+        }
+    }
+    let label = if ports.is_empty() {
+        escape_record(&identifier)
+    } else {
+        format!("{{{}|{{{}}}}}", escape_record(&identifier), ports.join("|"))
+    };
+    format!("{} [label={}];", quote(&equipment.id), quote_record(&label))
+}
+
+/// `port_name` returns a record port name for a connector that only contains characters that
+/// are valid in a port name
+fn port_name(face: &str, connector: &str) -> String {
+    format!("{face}_{connector}")
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `escape_record` escapes backslashes and characters that have special meaning inside of record
+/// labels
+fn escape_record(text: &str) -> String {
+    let mut output = String::new();
+    for character in text.chars() {
+        if matches!(character, '{' | '}' | '|' | '<' | '>' | '\\') {
+            output.push('\\');
+        }
+        output.push(character);
+    }
+    output
+}
+
+/// `quote` returns `text` as a quoted DOT id. Backslashes are escaped before quotes, so a
+/// backslash in `text` can't escape the quote after it or start a label escape such as `\n`.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `quote_record` returns a record label escaped with `escape_record` as a quoted DOT id. Only
+/// quotes are escaped, as the backslashes in `label` are already record escapes.
+fn quote_record(label: &str) -> String {
+    format!("\"{}\"", label.replace('"', "\\\""))
+}
+
+/// `Error` is the list of errors that can occur in `DotExport`
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error resulting from an invalid `Filter`
+    FilterError(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::FilterError(ref e) => write!(f, "Filter Error: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use crate::datatypes::internal_types::{
+        cable::Cable,
+        connection::Connection,
+        equipment_type::{Connector, EquipFace, EquipmentType},
+        location::Location,
+    };
+
+    /// `test_project` returns two pieces of equipment in one location, connected by cable `C1`,
+    /// and cable `C2` which has a loose end
+    fn test_project() -> Project {
+        let mut project = Project::new();
+        let location = Rc::new(RefCell::new(Location {
+            id: "L1".to_string(),
+            identifier: Some("Rack \"A\"".to_string()),
+            ..Default::default()
+        }));
+        project
+            .locations
+            .insert("L1".to_string(), Rc::clone(&location));
+        let equip_type = Rc::new(RefCell::new(EquipmentType {
+            id: "switch".to_string(),
+            faces: Some(HashMap::from([(
+                "Front".to_string(),
+                EquipFace {
+                    connectors: Some(
+                        ["J1", "J2"]
+                            .map(|id| Connector {
+                                id: id.to_string(),
+                                ..Default::default()
+                            })
+                            .to_vec(),
+                    ),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        }));
+        for (id, identifier) in [("EQ1", "Switch {core}"), ("EQ2", "C:\\panel")] {
+            project.equipment.insert(
+                id.to_string(),
+                Rc::new(RefCell::new(Equipment {
+                    id: id.to_string(),
+                    identifier: Some(identifier.to_string()),
+                    equip_type: Rc::clone(&equip_type),
+                    location: Rc::clone(&location),
+                    ..Default::default()
+                })),
+            );
+        }
+        for (cable_id, ends) in [
+            ("C1", vec![("EQ1", "J1"), ("EQ2", "J1")]),
+            ("C2", vec![("EQ1", "J2")]),
+        ] {
+            let cable = Rc::new(RefCell::new(Cable {
+                id: cable_id.to_string(),
+                ..Default::default()
+            }));
+            project
+                .cables
+                .insert(cable_id.to_string(), Rc::clone(&cable));
+            for (end, (equipment, connector)) in
+                [CableEnd::End1, CableEnd::End2].into_iter().zip(ends)
+            {
+                project.connections.push(
+                    Connection::new(
+                        ConnectionEnd::Cable {
+                            cable: Rc::clone(&cable),
+                            end,
+                        },
+                        ConnectionEnd::Equipment {
+                            equipment: Rc::clone(&project.equipment[equipment]),
+                            face: Some("Front".to_string()),
+                            connector: Some(connector.to_string()),
+                        },
+                        PathBuf::new(),
+                    )
+                    .unwrap(),
+                );
+            }
+        }
+        project
+    }
+
+    #[test]
+    fn whole_project() {
+        assert_eq!(
+            project_to_dot(&test_project(), &Filter::All).unwrap(),
+            r#"graph project {
+    rankdir=LR;
+    node [shape=record];
+    subgraph "cluster_L1" {
+        label="Rack \"A\"";
+        "EQ1" [label="{Switch \{core\}|{<Front_J1> Front J1|<Front_J2> Front J2}}"];
+        "EQ2" [label="{C:\\panel|{<Front_J1> Front J1|<Front_J2> Front J2}}"];
+    }
+    "C2.end2" [shape=point, xlabel="C2.end2"];
+    "EQ1":"Front_J1" -- "EQ2":"Front_J1" [label="C1"];
+    "EQ1":"Front_J2" -- "C2.end2" [label="C2"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let dot = project_to_dot(&test_project(), &Filter::Trace("C2".to_string())).unwrap();
+        assert!(dot.contains(r#""EQ1":"Front_J2" -- "C2.end2" [label="C2"];"#));
+        assert!(!dot.contains("C1"));
+        assert!(!dot.contains("EQ2"));
+    }
+
+    #[test]
+    fn unknown_filter_id() {
+        for filter in [
+            Filter::Location("L2".to_string()),
+            Filter::Trace("C3".to_string()),
+        ] {
+            assert!(matches!(
+                project_to_dot(&test_project(), &filter),
+                Err(Error::FilterError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote(r#"a "b""#), r#""a \"b\"""#);
+        assert_eq!(quote(r"C:\"), r#""C:\\""#);
+        assert_eq!(quote(r"a\nb"), r#""a\\nb""#);
+        assert_eq!(escape_record(r"{a|b}<c>\"), r"\{a\|b\}\<c\>\\");
+        assert_eq!(quote_record(r#"\{"a"\}"#), r#""\{\"a\"\}""#);
+    }
+}
@@ -0,0 +1,663 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use dimensioned::{f64prefixes, ucum};
+use log::trace;
+
+use pdf_helper::{
+    paper::PaperSize, BreakOptions, Margins, PDFDocument, PDFFont, PDFPage, PDFTextRenderMode,
+};
+
+use crate::datatypes::internal_types::{
+    connection::{CableEnd, ConnectionEnd},
+    Project,
+};
+use crate::pdf_generation;
+
+/// `LabelTemplate` describes the layout of a sheet of labels.
+///
+/// Labels are laid out in a grid starting from the top left corner of the page, filling each row
+/// before moving on to the next one.
+#[derive(Clone, Copy)]
+pub struct LabelTemplate {
+    /// size of the label sheet
+    pub page_size: PaperSize,
+    /// width of an individual label
+    pub label_width: ucum::Meter<f64>,
+    /// height of an individual label
+    pub label_height: ucum::Meter<f64>,
+    /// number of rows of labels on a sheet
+    pub rows: u16,
+    /// number of columns of labels on a sheet
+    pub columns: u16,
+    /// distance from top of sheet to top of first row of labels
+    pub top_margin: ucum::Meter<f64>,
+    /// distance from left of sheet to left of first column of labels
+    pub left_margin: ucum::Meter<f64>,
+    /// distance between the left edges of two adjacent labels in a row
+    pub horizontal_pitch: ucum::Meter<f64>,
+    /// distance between the top edges of two adjacent labels in a column
+    pub vertical_pitch: ucum::Meter<f64>,
+    /// blank space kept between the edge of a label and its text
+    pub padding: ucum::Meter<f64>,
+    /// font size labels start with, in points
+    pub max_font_size: u32,
+    /// smallest font size text will be shrunk to, in points
+    pub min_font_size: u32,
+}
+
+/// `Label` is the text printed on one label. Each paragraph starts on a new line.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Label {
+    /// paragraphs of text on the label
+    pub paragraphs: Vec<String>,
+}
+
+impl LabelTemplate {
+    /// Avery 5160 / 8160 address labels. 30 labels per US Letter sheet, 2-5/8" x 1"
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn avery_5160() -> Self {
+        Self {
+            page_size: PaperSize::Letter,
+            label_width: 2.625 * ucum::IN_US,
+            label_height: 1.0 * ucum::IN_US,
+            rows: 10,
+            columns: 3,
+            top_margin: 0.5 * ucum::IN_US,
+            left_margin: 0.1875 * ucum::IN_US,
+            horizontal_pitch: 2.75 * ucum::IN_US,
+            vertical_pitch: 1.0 * ucum::IN_US,
+            padding: 0.0625 * ucum::IN_US,
+            max_font_size: 14,
+            min_font_size: 4,
+        }
+    }
+
+    /// Avery 5163 / 8163 shipping labels. 10 labels per US Letter sheet, 4" x 2"
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn avery_5163() -> Self {
+        Self {
+            page_size: PaperSize::Letter,
+            label_width: 4.0 * ucum::IN_US,
+            label_height: 2.0 * ucum::IN_US,
+            rows: 5,
+            columns: 2,
+            top_margin: 0.5 * ucum::IN_US,
+            left_margin: 0.15625 * ucum::IN_US,
+            horizontal_pitch: 4.1875 * ucum::IN_US,
+            vertical_pitch: 2.0 * ucum::IN_US,
+            padding: 0.125 * ucum::IN_US,
+            max_font_size: 24,
+            min_font_size: 4,
+        }
+    }
+
+    /// Avery L7163 address labels. 14 labels per A4 sheet, 99.1mm x 38.1mm
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn avery_l7163() -> Self {
+        let mm = ucum::M * f64prefixes::MILLI;
+        Self {
+            page_size: PaperSize::A4,
+            label_width: 99.1 * mm,
+            label_height: 38.1 * mm,
+            rows: 7,
+            columns: 2,
+            top_margin: 15.15 * mm,
+            left_margin: 4.65 * mm,
+            horizontal_pitch: 101.6 * mm,
+            vertical_pitch: 38.1 * mm,
+            padding: 2.0 * mm,
+            max_font_size: 18,
+            min_font_size: 4,
+        }
+    }
+
+    /// returns the number of labels that fit on one sheet
+    #[must_use]
+    pub fn labels_per_page(&self) -> usize {
+        usize::from(self.rows).saturating_mul(usize::from(self.columns))
+    }
+
+    /// returns the position of the top left corner of the label at `index` on a sheet,
+    /// measured from the bottom left corner of the page
+    #[allow(clippy::arithmetic_side_effects)]
+    fn label_origin(&self, index: usize) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+        let columns = usize::from(self.columns.max(1));
+        let row = f64::from(u32::try_from(index / columns).unwrap_or(u32::MAX));
+        let column = f64::from(u32::try_from(index % columns).unwrap_or(u32::MAX));
+        let x = self.left_margin + self.horizontal_pitch * column;
+        let y = self.page_size.size().1 - self.top_margin - self.vertical_pitch * row;
+        (x, y)
+    }
+}
+
+impl Default for LabelTemplate {
+    fn default() -> Self {
+        Self::avery_5160()
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Label:")?;
+        for paragraph in &self.paragraphs {
+            writeln!(f, "{paragraph}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `project_labels` returns every label needed for `project`
+///
+/// Each end of every `Wire`, `Cable` and `TermCable` gets its own label, which lists what the
+/// other end of it is connected to. Each piece of `Equipment` gets one label with its location.
+/// Labels are sorted by `id` so the output is the same between runs.
+#[must_use]
+pub fn project_labels(project: &Project) -> Vec<Label> {
+    let mut labels = Vec::new();
+
+    // each end of every wire, cable and term cable, paired with the other end of the same object
+    let mut cable_ends = Vec::new();
+    for end in [CableEnd::End1, CableEnd::End2] {
+        for wire in project.wires.values() {
+            cable_ends.push([end, end.other()].map(|end| ConnectionEnd::Wire {
+                wire: Rc::clone(wire),
+                end,
+            }));
+        }
+        for cable in project.cables.values() {
+            cable_ends.push([end, end.other()].map(|end| ConnectionEnd::Cable {
+                cable: Rc::clone(cable),
+                end,
+            }));
+        }
+        for term_cable in project.term_cables.values() {
+            cable_ends.push([end, end.other()].map(|end| ConnectionEnd::TermCable {
+                term_cable: Rc::clone(term_cable),
+                end,
+            }));
+        }
+    }
+    cable_ends.sort_by_key(|[cable_end, _]| cable_end.id());
+
+    for [cable_end, far_end] in &cable_ends {
+        let mut paragraphs = vec![cable_end.identifier()];
+        let destinations: Vec<String> = project
+            .connected_to(far_end)
+            .into_iter()
+            .map(destination)
+            .collect();
+        if !destinations.is_empty() {
+            paragraphs.push(format!("TO {}", destinations.join(", ")));
+        }
+        labels.push(Label { paragraphs });
+    }
+
+    let mut sorted_equipment: Vec<_> = project.equipment.values().collect();
+    sorted_equipment.sort_by_key(|equipment| equipment.borrow().id.clone());
+    for equipment in sorted_equipment {
+        let equipment = equipment.borrow();
+        let location = equipment.location.borrow();
+        labels.push(Label {
+            paragraphs: vec![
+                equipment
+                    .identifier
+                    .clone()
+                    .unwrap_or_else(|| equipment.id.clone()),
+                location
+                    .identifier
+                    .clone()
+                    .unwrap_or_else(|| location.id.clone()),
+            ],
+        });
+    }
+    labels
+}
+
+/// `destination` describes a far end for a label, prefixing equipment with its location
+fn destination(end: &ConnectionEnd) -> String {
+    match end.equipment() {
+        Some(equipment) => {
+            let equipment = equipment.borrow();
+            let location = equipment.location.borrow();
+            format!(
+                "{} / {}",
+                location.identifier.as_ref().unwrap_or(&location.id),
+                end.identifier()
+            )
+        }
+        None => end.identifier(),
+    }
+}
+
+/// `pdf_labels` generates a PDF of label sheets for every label in `project`
+///
+/// # Errors
+///
+/// Will error if fonts fail to load, or if the text of any label doesn't fit at
+/// `template.min_font_size`
+pub fn pdf_labels(
+    project: &Project,
+    template: &LabelTemplate,
+    config_font_paths: Vec<PathBuf>,
+) -> Result<PDFDocument<'static>, Error> {
+    let mut pdf = PDFDocument::new(template.page_size, config_font_paths)?;
+    render_labels(&project_labels(project), template, &mut pdf)?;
+    Ok(pdf)
+}
+
+/// `render_labels` lays out `labels` on as many sheets as necessary, appending the pages to
+/// `pdf`. The first font loaded into `pdf` is used for all text.
+///
+/// # Errors
+///
+/// Will error if `pdf` has no fonts, or if the text of any label doesn't fit at
+/// `template.min_font_size`
+#[allow(clippy::arithmetic_side_effects)]
+pub fn render_labels(
+    labels: &[Label],
+    template: &LabelTemplate,
+    pdf: &mut PDFDocument,
+) -> Result<(), Error> {
+    let labels_per_page = template.labels_per_page();
+    if labels_per_page == 0 {
+        return Err(Error::LayoutError(
+            "label template has no rows or columns".to_string(),
+        ));
+    }
+    let font = pdf
+        .available_fonts
+        .first()
+        .ok_or_else(|| Error::LayoutError("no fonts loaded".to_string()))?;
+    let language = pdf_generation::text_language()?;
+    let text_width = template.label_width - template.padding * 2.0_f64;
+    let text_height = template.label_height - template.padding * 2.0_f64;
+
+    for (index, label) in labels.iter().enumerate() {
+        let index_on_page = index % labels_per_page;
+        if index_on_page == 0 {
+            pdf.pages
+                .push(PDFPage::new(template.page_size, Margins::default()));
+        }
+        let page = pdf
+            .pages
+            .last_mut()
+            .ok_or_else(|| Error::LayoutError("no page to render label on".to_string()))?;
+
+        let (font_size, line_count) = fit_label(label, template, font, &language)?;
+        trace! {"label {index} fits at font size {font_size} with {line_count} lines"}
+        let line_height = font.line_height(font_size);
+
+        let (label_x, label_top) = template.label_origin(index_on_page);
+        // center the block of text vertically within the label
+        let block_height = line_height * f64::from(line_count);
+        let mut baseline = label_top
+            - template.padding
+            - (text_height - block_height) / 2.0_f64
+            - font.ascent(font_size);
+        for paragraph in &label.paragraphs {
+            let lines = font
+                .to_lines(
+                    paragraph,
+                    font_size,
+                    text_width,
+                    rustybuzz::Direction::LeftToRight,
+                    language.clone(),
+                    &BreakOptions::default(),
+                )
+                .map_err(|e| Error::LayoutError(format!("{e}")))?
+                .len();
+            page.add_text(
+                paragraph.clone(),
+                font_size,
+                font,
+                1,
+                text_width,
+                label_x + template.padding,
+                baseline,
+                rustybuzz::Direction::LeftToRight,
+                language.clone(),
+                &PDFTextRenderMode::Fill,
+            )?;
+            baseline -= line_height * f64::from(u32::try_from(lines).unwrap_or(u32::MAX));
+        }
+    }
+    Ok(())
+}
+
+/// `fit_label` finds the largest font size that fits all paragraphs of `label` within the label
+/// size specified by `template`. Returns the font size and the total number of lines.
+#[allow(clippy::arithmetic_side_effects)]
+fn fit_label(
+    label: &Label,
+    template: &LabelTemplate,
+    font: &PDFFont,
+    language: &rustybuzz::Language,
+) -> Result<(u32, u32), Error> {
+    let text_width = template.label_width - template.padding * 2.0_f64;
+    let text_height = template.label_height - template.padding * 2.0_f64;
+
+    let min_font_size = template.min_font_size.max(1);
+    'font_size: for font_size in (min_font_size..=template.max_font_size).rev() {
+        // shrink the text before breaking words between characters
+        let break_options = BreakOptions {
+            break_anywhere: font_size == min_font_size,
+            ..BreakOptions::default()
+        };
+        let mut line_count = 0;
+        for paragraph in &label.paragraphs {
+            match font.to_lines(
+                paragraph,
+                font_size,
+                text_width,
+                rustybuzz::Direction::LeftToRight,
+                language.clone(),
+                &break_options,
+            ) {
+                Ok(lines) => {
+                    line_count += u32::try_from(lines.len()).unwrap_or(u32::MAX);
+                }
+                Err(paragraph_breaker::Error::LineLengthTooLong) => continue 'font_size,
+                Err(e) => return Err(Error::LayoutError(format!("{e}"))),
+            }
+        }
+        if font.line_height(font_size) * f64::from(line_count) <= text_height {
+            return Ok((font_size, line_count));
+        }
+    }
+    Err(Error::LayoutError(format!(
+        "label text does not fit at minimum font size {}: {}",
+        template.min_font_size,
+        label.paragraphs.join(" ")
+    )))
+}
+
+/// `Error` is the list of errors that can occur in `LabelGeneration`
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error resulting from layout or rendering
+    LayoutError(String),
+    /// Error resulting during PDF Creation
+    PDFCreationError(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::LayoutError(ref e) => write!(f, "Layout Error: {e}"),
+            Error::PDFCreationError(ref e) => write!(f, "PDF Creation Error: {e}"),
+        }
+    }
+}
+impl From<pdf_helper::Error> for Error {
+    fn from(e: pdf_helper::Error) -> Self {
+        Error::PDFCreationError(format!("{e}"))
+    }
+}
+impl From<pdf_generation::Error> for Error {
+    fn from(e: pdf_generation::Error) -> Self {
+        match e {
+            pdf_generation::Error::LayoutError(e) => Error::LayoutError(e),
+            pdf_generation::Error::PDFCreationError(e) => Error::PDFCreationError(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use crate::datatypes::internal_types::{
+        cable::Cable, connection::Connection, equipment::Equipment, location::Location,
+        term_cable::TermCable, wire::Wire,
+    };
+
+    /// `label_project` builds a project with W1 running between EQ1 and EQ2, C1 with only end2
+    /// connected to EQ2, and TC1 left unconnected
+    fn label_project() -> Project {
+        let mut project = Project::new();
+        let wire = Rc::new(RefCell::new(Wire {
+            id: "W1".to_string(),
+            ..Default::default()
+        }));
+        let cable = Rc::new(RefCell::new(Cable {
+            id: "C1".to_string(),
+            ..Default::default()
+        }));
+        let term_cable = Rc::new(RefCell::new(TermCable {
+            id: "TC1".to_string(),
+            ..Default::default()
+        }));
+        let equipment1 = Rc::new(RefCell::new(Equipment {
+            id: "EQ1".to_string(),
+            identifier: Some("Panel".to_string()),
+            location: Rc::new(RefCell::new(Location {
+                id: "L1".to_string(),
+                identifier: Some("Rack A".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }));
+        let equipment2 = Rc::new(RefCell::new(Equipment {
+            id: "EQ2".to_string(),
+            location: Rc::new(RefCell::new(Location {
+                id: "L2".to_string(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }));
+
+        for (cable_end, equipment) in [
+            (
+                ConnectionEnd::Wire {
+                    wire: Rc::clone(&wire),
+                    end: CableEnd::End1,
+                },
+                &equipment1,
+            ),
+            (
+                ConnectionEnd::Wire {
+                    wire: Rc::clone(&wire),
+                    end: CableEnd::End2,
+                },
+                &equipment2,
+            ),
+            (
+                ConnectionEnd::Cable {
+                    cable: Rc::clone(&cable),
+                    end: CableEnd::End2,
+                },
+                &equipment2,
+            ),
+        ] {
+            let equipment_end = ConnectionEnd::Equipment {
+                equipment: Rc::clone(equipment),
+                face: None,
+                connector: None,
+            };
+            project
+                .connections
+                .push(Connection::new(cable_end, equipment_end, PathBuf::new()).unwrap());
+        }
+
+        project.wires.insert("W1".to_string(), wire);
+        project.cables.insert("C1".to_string(), cable);
+        project.term_cables.insert("TC1".to_string(), term_cable);
+        project.equipment.insert("EQ1".to_string(), equipment1);
+        project.equipment.insert("EQ2".to_string(), equipment2);
+        project
+    }
+
+    /// `label` builds a `Label` from `paragraphs`
+    fn label(paragraphs: &[&str]) -> Label {
+        Label {
+            paragraphs: paragraphs.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// `test_template` is a small 2x2 sheet of 2" x 1" labels
+    #[allow(clippy::arithmetic_side_effects)]
+    fn test_template() -> LabelTemplate {
+        LabelTemplate {
+            page_size: PaperSize::Letter,
+            label_width: 2.0 * ucum::IN_US,
+            label_height: 1.0 * ucum::IN_US,
+            rows: 2,
+            columns: 2,
+            top_margin: 0.5 * ucum::IN_US,
+            left_margin: 0.25 * ucum::IN_US,
+            horizontal_pitch: 2.5 * ucum::IN_US,
+            vertical_pitch: 1.25 * ucum::IN_US,
+            padding: 0.125 * ucum::IN_US,
+            max_font_size: 14,
+            min_font_size: 4,
+        }
+    }
+
+    #[test]
+    fn labels_for_each_end() {
+        let labels = project_labels(&label_project());
+        assert_eq!(
+            labels,
+            vec![
+                label(&["C1 end1", "TO L2 / EQ2"]),
+                label(&["C1 end2"]),
+                label(&["TC1 end1"]),
+                label(&["TC1 end2"]),
+                label(&["W1 end1", "TO L2 / EQ2"]),
+                label(&["W1 end2", "TO Rack A / Panel"]),
+                label(&["Panel", "Rack A"]),
+                label(&["EQ2", "L2"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_name_far_end() {
+        let mut project = label_project();
+        // connect C1.end1 as well, so both ends of C1 have a far end destination
+        let equipment1 = Rc::clone(&project.equipment["EQ1"]);
+        let cable = Rc::clone(&project.cables["C1"]);
+        project.connections.push(
+            Connection::new(
+                ConnectionEnd::Cable {
+                    cable,
+                    end: CableEnd::End1,
+                },
+                ConnectionEnd::Equipment {
+                    equipment: equipment1,
+                    face: None,
+                    connector: None,
+                },
+                PathBuf::new(),
+            )
+            .unwrap(),
+        );
+        let labels = project_labels(&project);
+        assert_eq!(labels[0], label(&["C1 end1", "TO L2 / EQ2"]));
+        assert_eq!(labels[1], label(&["C1 end2", "TO Rack A / Panel"]));
+    }
+
+    #[test]
+    #[allow(clippy::arithmetic_side_effects)]
+    fn fit_label_shrinks_font() {
+        let pdf = PDFDocument::new(PaperSize::Letter, Vec::new()).unwrap();
+        let font = pdf.available_fonts.first().unwrap();
+        let language = pdf_generation::text_language().unwrap();
+        let template = test_template();
+        let text_height = template.label_height - template.padding * 2.0_f64;
+
+        let short = label(&["W1 end1", "TO L2 / EQ2"]);
+        assert_eq!(
+            fit_label(&short, &template, font, &language).unwrap(),
+            (14, 2)
+        );
+
+        let long = label(&[
+            "W1 end1",
+            "TO Rack A / Panel, Rack B / Panel, Rack C / Panel, Rack D / Panel",
+        ]);
+        let (font_size, line_count) = fit_label(&long, &template, font, &language).unwrap();
+        assert!(font_size < template.max_font_size);
+        assert!(font_size >= template.min_font_size);
+        assert!(font.line_height(font_size) * f64::from(line_count) <= text_height);
+
+        // one size larger no longer fits
+        let larger = LabelTemplate {
+            min_font_size: font_size + 1,
+            max_font_size: font_size + 1,
+            ..template
+        };
+        assert!(fit_label(&long, &larger, font, &language).is_err());
+
+        let too_long = Label {
+            paragraphs: vec!["word ".repeat(200)],
+        };
+        assert!(matches!(
+            fit_label(&too_long, &template, font, &language),
+            Err(Error::LayoutError(_))
+        ));
+    }
+
+    #[test]
+    #[allow(clippy::arithmetic_side_effects)]
+    fn label_origins() {
+        let template = test_template();
+        let page_height = template.page_size.size().1;
+        assert_eq!(
+            template.label_origin(0),
+            (template.left_margin, page_height - template.top_margin)
+        );
+        assert_eq!(
+            template.label_origin(1),
+            (
+                template.left_margin + template.horizontal_pitch,
+                page_height - template.top_margin
+            )
+        );
+        assert_eq!(
+            template.label_origin(2),
+            (
+                template.left_margin,
+                page_height - template.top_margin - template.vertical_pitch
+            )
+        );
+        assert_eq!(
+            template.label_origin(3),
+            (
+                template.left_margin + template.horizontal_pitch,
+                page_height - template.top_margin - template.vertical_pitch
+            )
+        );
+    }
+
+    #[test]
+    fn render_labels_pages() {
+        let template = test_template();
+        assert_eq!(template.labels_per_page(), 4);
+        let labels = vec![label(&["W1 end1", "TO L2 / EQ2"]); 5];
+
+        let mut full_page = PDFDocument::new(PaperSize::Letter, Vec::new()).unwrap();
+        render_labels(&labels[..4], &template, &mut full_page).unwrap();
+        assert_eq!(full_page.pages.len(), 1);
+
+        let mut pdf = PDFDocument::new(PaperSize::Letter, Vec::new()).unwrap();
+        render_labels(&labels, &template, &mut pdf).unwrap();
+        assert_eq!(pdf.pages.len(), 2);
+
+        let empty_template = LabelTemplate {
+            rows: 0,
+            ..template
+        };
+        assert!(render_labels(&labels, &empty_template, &mut pdf).is_err());
+    }
+}
@@ -16,4 +16,18 @@ pub mod datatypes;
 /// of `Projects`
 pub mod pdf_generation;
 
+/// `label_generation` contains the functions used to generate printable label sheets for
+/// the wires, cables and equipment in `Projects`
+pub mod label_generation;
+
+/// `dot_export` contains the functions used to export `Projects` as Graphviz DOT graphs
+pub mod dot_export;
+
+/// `wireviz` contains the functions used to import and export WireViz harness YAML
+pub mod wireviz;
+
+/// `csv_import` contains the functions used to bulk import wires, cables, equipment and
+/// locations from CSV files
+pub mod csv_import;
+
 pub use cdm_macros;
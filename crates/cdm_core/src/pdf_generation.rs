@@ -1,63 +1,1019 @@
+/// `routing` lays out connection lines between equipment
+mod routing;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
 
-use pdf_helper::{paper::PaperSize, scale::ScalingFactor, Margins, PDFDocument, PDFPage};
+use dimensioned::{f64prefixes, ucum, Abs, Dimensionless};
+use pdf_helper::{
+    outline::OutlineItem,
+    paper::{Orientation, PaperSize},
+    scale::{DrawingScale, ScalingFactor},
+    surface::DrawingSurface,
+    svg::SVGPage,
+    template::PageTemplate,
+    tile::{TileGrid, Tiling},
+    BreakOptions, Margins, PDFDocument, PDFFont, PDFPage, PDFTextRenderMode,
+};
 
-use crate::datatypes::internal_types::{location::Location, Library, Project};
+use crate::datatypes::internal_types::{
+    connection::{CableEnd, ConnectionEnd},
+    equipment::Equipment,
+    equipment_type::DRAWN_FACE,
+    location::Location,
+    pathway::Pathway,
+    Project,
+};
 
 use log::{trace, warn};
 
-//TODO: add page templates with proper borders and titleblocks
+/// `FOOTER_FONT_SIZE` is the font size of the page footer, in points
+const FOOTER_FONT_SIZE: u32 = 10;
+/// `LABEL_MAX_FONT_SIZE` is the largest font size used for text on the overview page, in points
+const LABEL_MAX_FONT_SIZE: u32 = 12;
+/// `TILE_DRAWING_SLACK` is added to the size of a location drawn for tiling so it fits
+/// within the drawing, in mm
+const TILE_DRAWING_SLACK: f64 = 1.0;
+/// `LABEL_MIN_FONT_SIZE` is the smallest font size used for text on the overview page, in points
+const LABEL_MIN_FONT_SIZE: u32 = 4;
+/// `EQUIPMENT_LAYER` is the name of the layer equipment and the location boxes of the overview
+/// page are drawn on
+const EQUIPMENT_LAYER: &str = "Equipment";
+/// `CONNECTION_LAYER` is the name of the layer connection lines and off-page connectors are
+/// drawn on
+const CONNECTION_LAYER: &str = "Connections";
+/// `PATHWAY_LAYER` is the name of the layer pathways between locations are drawn on
+const PATHWAY_LAYER: &str = "Pathways";
+/// `LABEL_LAYER` is the name of the layer the labels of connections, pathways and the location
+/// boxes of the overview page are drawn on
+const LABEL_LAYER: &str = "Labels";
+
+/// `pdf_all_locations` generates a multi-page drawing set for the entire project.
+///
+/// The first page is an overview of every location in the project, with lines drawn between
+/// locations that share a pathway. It is followed by one page per location containing
+/// equipment, rendered with `render_location`. Connections between locations end in off-page
+/// connectors that link to the page of the other location. If `template` is given it is drawn on
+/// every page, otherwise each page has a footer with the page title and page number.
+///
+/// Location boxes, pathway lines and their labels on the overview page are drawn on the
+/// "Equipment", "Pathways" and "Labels" layers, the same layers as the location pages, so the
+/// layers can be hidden throughout the document.
+///
+/// The outline of the document has a bookmark for the overview and for each location, with the
+/// equipment in the location nested under it. The overview and the first page of each location
+/// are also named destinations, `overview` and `location-<id>`, so they can be linked to from
+/// other documents. If the template has a drawing number, pages are labelled with it and their
+/// sheet number, such as "E-101".
+///
+/// If `tiling` is given, locations that don't fit on a page at the requested scale are split
+/// across several tiled pages instead of failing. Auto scales tile at the smallest scale in their
+/// series.
+///
+/// # Arguments
+///
+/// * `project` - the `Project` to render
+/// * `margins` - the margin sizes of each PDF page
+/// * `page_size` - the target page size of the PDF file
+/// * `orientation` - orientation of every page
+/// * `scale` - how the scale of each location page is chosen, passed to `render_location`
+/// * `template` - optional - border and title block drawn on each page
+/// * `tiling` - optional - how locations too large for a page are split across pages
+/// * `config_font_paths` - additional font paths to search for fonts. The standard PDF fonts are
+///   used if there are none
+///
+/// # Errors
+///
+/// will Error if the project has no locations, no fonts are found, or any page fails to layout
+#[allow(clippy::too_many_arguments)]
+pub fn pdf_all_locations(
+    project: &Project,
+    margins: Margins,
+    page_size: PaperSize,
+    orientation: Orientation,
+    scale: DrawingScale,
+    template: Option<&PageTemplate>,
+    tiling: Option<&Tiling>,
+    config_font_paths: Vec<PathBuf>,
+) -> Result<PDFDocument<'static>, Error> {
+    let mut locations = project.locations.values().collect::<Vec<_>>();
+    if locations.is_empty() {
+        return Err(Error::LayoutError("project has no locations".to_string()));
+    }
+    locations.sort_by(|a, b| a.borrow().id.cmp(&b.borrow().id));
+
+    let mut pdf = PDFDocument::new(page_size, config_font_paths)?;
+    let font = pdf
+        .available_fonts
+        .first()
+        .ok_or_else(|| Error::LayoutError("no fonts loaded".to_string()))?;
+
+    let mut drawn_locations = Vec::new();
+    for location in &locations {
+        if has_equipment(project, &location.borrow()) {
+            drawn_locations.push(*location);
+        } else {
+            warn! {"location {} has no equipment, skipping its page", location.borrow().id}
+        }
+    }
+
+    // reserve space for the template, or at the bottom of each page for the footer
+    #[allow(clippy::arithmetic_side_effects)]
+    let content_margins = match template {
+        Some(template) => template.content_margins(margins, font),
+        None => Margins {
+            bottom: margins.bottom + font.line_height(FOOTER_FONT_SIZE) * 2.0_f64,
+            ..margins
+        },
+    };
+
+    let mut blank_page = PDFPage::new(page_size, content_margins);
+    blank_page.orientation = orientation;
+    let layouts = drawn_locations
+        .iter()
+        .map(|location| plan_location(&location.borrow(), scale, tiling, &blank_page, font))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // the overview is the first page, followed by the pages of each location in order
+    let mut location_pages = BTreeMap::new();
+    let mut page_count = 1_usize;
+    for (location, layout) in drawn_locations.iter().zip(&layouts) {
+        location_pages.insert(location.borrow().id.clone(), page_count);
+        page_count = page_count.saturating_add(layout.page_count());
+    }
+
+    let mut overview = PDFPage::new(page_size, content_margins);
+    overview.orientation = orientation;
+    render_overview(project, &locations, font, &mut overview)?;
+    overview.margins = margins;
+    let overview_sheet = Sheet {
+        title: "Project Overview".to_string(),
+        scale: None,
+        number: 1,
+        count: page_count,
+    };
+    add_frame(&mut overview, template, font, &overview_sheet)?;
+    overview.label = sheet_label(template, overview_sheet.number);
+    pdf.pages.push(overview);
+    pdf.outline.push(OutlineItem::new(overview_sheet.title, 0));
+    pdf.destinations.insert("overview".to_string(), 0);
+
+    let mut page_number = 1_usize;
+    for (location, layout) in drawn_locations.iter().zip(&layouts) {
+        let location = location.borrow();
+        let first_page = pdf.pages.len();
+        pdf.outline
+            .push(location_outline(project, &location, layout, first_page));
+        pdf.destinations
+            .insert(format!("location-{}", location.id), first_page);
+        let (pages, location_scale) = render_location_pages(
+            project,
+            &location,
+            layout,
+            Some(&location_pages),
+            font,
+            &blank_page,
+        )?;
+        for mut page in pages {
+            page_number = page_number.saturating_add(1);
+            page.margins = margins;
+            let sheet = Sheet {
+                title: location_title(&location),
+                scale: Some(location_scale),
+                number: page_number,
+                count: page_count,
+            };
+            add_frame(&mut page, template, font, &sheet)?;
+            page.label = sheet_label(template, sheet.number);
+            pdf.pages.push(page);
+        }
+    }
+    Ok(pdf)
+}
+
+/// `LocationLayout` is how a location is laid out across pages
+enum LocationLayout {
+    /// on one page, at the scale chosen by `render_location`
+    Page(DrawingScale),
+    /// split across tiled pages at a fixed scale
+    Tiled(Tiling, ScalingFactor, TileGrid),
+}
+
+impl LocationLayout {
+    /// `page_count` returns the number of pages the location is laid out on
+    fn page_count(&self) -> usize {
+        match self {
+            LocationLayout::Page(_) => 1,
+            LocationLayout::Tiled(_, _, grid) => grid.count(),
+        }
+    }
+}
+
+/// `plan_location` decides how `location` is laid out on pages like `page`. It is tiled if
+/// `tiling` is set and the location doesn't fit on one page at `scale`.
+///
+/// # Errors
+///
+/// will Error if the scale is invalid or tiles are smaller than their overlap
+fn plan_location(
+    location: &Location,
+    scale: DrawingScale,
+    tiling: Option<&Tiling>,
+    page: &PDFPage,
+    font: &PDFFont,
+) -> Result<LocationLayout, Error> {
+    let Some(tiling) = tiling else {
+        return Ok(LocationLayout::Page(scale));
+    };
+    if choose_scale(location, scale, page).is_ok() {
+        return Ok(LocationLayout::Page(scale));
+    }
+    let tile_scale = match scale {
+        DrawingScale::Fixed(fixed) => fixed,
+        DrawingScale::Auto(series) => series.scales().last().copied().unwrap_or_default(),
+    };
+    let factor = tile_scale
+        .factor()
+        .ok_or_else(|| Error::LayoutError(format!("invalid scale: {tile_scale}")))?;
+    let location_type = location.location_type.borrow();
+    #[allow(clippy::arithmetic_side_effects)]
+    let drawing_size = (location_type.width * factor, location_type.height * factor);
+    let grid = tiling.grid(drawing_size, page, font)?;
+    trace!(
+        "location {} is split into {}x{} tiles at {tile_scale} scale",
+        location.id,
+        grid.columns,
+        grid.rows
+    );
+    Ok(LocationLayout::Tiled(*tiling, tile_scale, grid))
+}
+
+/// `render_location_pages` renders `location` with `render_location` as planned in `layout`,
+/// onto pages like `page`. Returns the pages and the scale the location is drawn at.
+///
+/// # Errors
+///
+/// will Error if the location fails to render or split into tiles
+fn render_location_pages(
+    project: &Project,
+    location: &Location,
+    layout: &LocationLayout,
+    location_pages: Option<&BTreeMap<String, usize>>,
+    font: &PDFFont,
+    page: &PDFPage,
+) -> Result<(Vec<PDFPage>, ScalingFactor), Error> {
+    match layout {
+        LocationLayout::Page(scale) => {
+            let mut location_page = PDFPage::new(page.page_size, page.margins);
+            location_page.orientation = page.orientation;
+            let location_scale = render_location(
+                project,
+                location,
+                *scale,
+                location_pages,
+                font,
+                &mut location_page,
+            )?;
+            Ok((vec![location_page], location_scale))
+        }
+        LocationLayout::Tiled(tiling, scale, _) => {
+            let factor = scale
+                .factor()
+                .ok_or_else(|| Error::LayoutError(format!("invalid scale: {scale}")))?;
+            // the whole location is drawn on one page large enough to fit it, then split
+            let location_type = location.location_type.borrow();
+            #[allow(clippy::arithmetic_side_effects)]
+            let drawing_size = PaperSize::Custom(
+                location_type.width * factor + TILE_DRAWING_SLACK * ucum::M * f64prefixes::MILLI,
+                location_type.height * factor + TILE_DRAWING_SLACK * ucum::M * f64prefixes::MILLI,
+            );
+            let mut drawing = PDFPage::new(drawing_size, Margins::default());
+            render_location(
+                project,
+                location,
+                DrawingScale::Fixed(*scale),
+                location_pages,
+                font,
+                &mut drawing,
+            )?;
+            Ok((tiling.split(&drawing, page, font)?, *scale))
+        }
+    }
+}
+
+/// `location_outline` returns the bookmark of `location`, which is laid out with `layout`
+/// starting at zero indexed `first_page`. It contains a bookmark for each tile if the location
+/// is tiled, followed by a bookmark for each piece of equipment in it.
+fn location_outline(
+    project: &Project,
+    location: &Location,
+    layout: &LocationLayout,
+    first_page: usize,
+) -> OutlineItem {
+    let mut item = OutlineItem::new(
+        location.identifier.as_ref().unwrap_or(&location.id),
+        first_page,
+    );
+    if let LocationLayout::Tiled(_, _, grid) = layout {
+        // tiles are split off row by row
+        let mut page = first_page;
+        for row in 0..grid.rows {
+            for column in 0..grid.columns {
+                item.children.push(OutlineItem::new(
+                    format!("Tile {}", grid.label(column, row)),
+                    page,
+                ));
+                page = page.saturating_add(1);
+            }
+        }
+    }
+    let mut equipment = project
+        .equipment
+        .values()
+        .map(|equipment| equipment.borrow())
+        .filter(|equipment| equipment.location.borrow().id == location.id)
+        .map(|equipment| {
+            equipment
+                .identifier
+                .clone()
+                .unwrap_or_else(|| equipment.id.clone())
+        })
+        .collect::<Vec<_>>();
+    equipment.sort();
+    item.children.extend(
+        equipment
+            .into_iter()
+            .map(|equipment| OutlineItem::new(equipment, first_page)),
+    );
+    item
+}
+
+/// `sheet_label` returns the page label of sheet `number`, which is prefixed with the drawing
+/// number of `template` if it has one, such as "E-101"
+fn sheet_label(template: Option<&PageTemplate>, number: usize) -> Option<String> {
+    template
+        .map(|template| &template.title_block.drawing_number)
+        .filter(|drawing_number| !drawing_number.is_empty())
+        .map(|drawing_number| format!("{drawing_number}-{number}"))
+}
+
+/// `location_title` returns the title of the pages of `location`
+fn location_title(location: &Location) -> String {
+    format!(
+        "Location: {}",
+        location.identifier.as_ref().unwrap_or(&location.id)
+    )
+}
+
+/// `Sheet` describes one page of a drawing set, for its title block or footer
+struct Sheet {
+    /// title of the page
+    title: String,
+    /// scale the page is drawn at, or `None` if it isn't drawn to scale
+    scale: Option<ScalingFactor>,
+    /// one indexed page number
+    number: usize,
+    /// number of pages in the drawing set
+    count: usize,
+}
+
+/// `add_frame` draws `template` on `pdf_page` if there is one, and a footer otherwise
+fn add_frame(
+    pdf_page: &mut PDFPage,
+    template: Option<&PageTemplate>,
+    font: &PDFFont,
+    sheet: &Sheet,
+) -> Result<(), Error> {
+    if let Some(template) = template {
+        return Ok(template.apply(
+            pdf_page,
+            font,
+            &sheet.title,
+            sheet.scale,
+            sheet.number,
+            sheet.count,
+        )?);
+    }
+    let title = match sheet.scale {
+        Some(scale) => format!("{}, Scale {scale}", sheet.title),
+        None => sheet.title.clone(),
+    };
+    add_footer(pdf_page, font, &title, sheet.number, sheet.count)
+}
+
+/// `choose_scale` returns the scale `reference_location` is drawn at on `pdf_page`, which is
+/// either the fixed scale or the largest standard scale that fits within the margins
+///
+/// # Errors
+///
+/// will Error if the scale is invalid or the location doesn't fit on the page at the scale
+fn choose_scale(
+    reference_location: &Location,
+    scale: DrawingScale,
+    pdf_page: &impl DrawingSurface,
+) -> Result<ScalingFactor, Error> {
+    let (page_width, page_height) = pdf_page.size();
+    let margins = pdf_page.margins();
+    #[allow(clippy::arithmetic_side_effects)]
+    let available = (
+        page_width - margins.left - margins.right,
+        page_height - margins.top - margins.bottom,
+    );
+    let size = {
+        let location_type = reference_location.location_type.borrow();
+        (location_type.width, location_type.height)
+    };
+    let does_not_fit = |shown_scale: &dyn fmt::Display| {
+        Error::LayoutError(format!(
+            "Location {} did not fit on Page Size {} at scale: {}",
+            reference_location.id,
+            pdf_page.page_size(),
+            shown_scale,
+        ))
+    };
+    match scale {
+        DrawingScale::Fixed(scale) => {
+            if scale.factor().is_none() {
+                return Err(Error::LayoutError(format!("invalid scale: {scale}")));
+            }
+            if !scale.fits(size, available) {
+                return Err(does_not_fit(&scale));
+            }
+            if scale != ScalingFactor::default() && ScalingFactor::default().fits(size, available) {
+                //TODO: change this to info
+                warn!(
+                    "location fits within page at 1:1 scale. Scale does not need to be specified"
+                );
+            } else {
+                trace!("location fits within page at {scale} scale");
+            }
+            Ok(scale)
+        }
+        DrawingScale::Auto(series) => {
+            let best_fit = series
+                .best_fit(size, available)
+                .ok_or_else(|| does_not_fit(&format!("any {series} scale")))?;
+            trace!("location fits within page at {best_fit} scale");
+            Ok(best_fit)
+        }
+    }
+}
+
+/// `has_equipment` returns true if any equipment in `project` is placed in `location`
+fn has_equipment(project: &Project, location: &Location) -> bool {
+    project
+        .equipment
+        .values()
+        .any(|equipment| equipment.borrow().location.borrow().id == location.id)
+}
+
+/// `render_overview` draws every location in `locations` as a labelled box in a grid, then
+/// draws the pathways between them
+#[allow(clippy::arithmetic_side_effects)]
+fn render_overview(
+    project: &Project,
+    locations: &[&Rc<RefCell<Location>>],
+    font: &PDFFont,
+    pdf_page: &mut PDFPage,
+) -> Result<(), Error> {
+    let (page_width, page_height) = pdf_page.size();
+    let usable_width = page_width - pdf_page.margins.left - pdf_page.margins.right;
+    let usable_height = page_height - pdf_page.margins.top - pdf_page.margins.bottom;
+    if usable_width <= 0.0_f64 * ucum::M || usable_height <= 0.0_f64 * ucum::M {
+        return Err(Error::LayoutError(format!(
+            "margins leave no space on page size {}",
+            pdf_page.page_size
+        )));
+    }
+    let language = text_language()?;
+    let line_width = 1.0_f64 * PDFDocument::pdf_point();
+
+    // lay out locations in a grid that is roughly as wide as it is tall
+    let columns = (1..=locations.len())
+        .find(|columns| columns.saturating_mul(*columns) >= locations.len())
+        .unwrap_or(1);
+    let rows = locations.len().div_ceil(columns);
+    let cell_width = usable_width / f64::from(u32::try_from(columns).unwrap_or(u32::MAX));
+    let cell_height = usable_height / f64::from(u32::try_from(rows).unwrap_or(u32::MAX));
+    let box_width = cell_width * 0.6_f64;
+    let box_height = cell_height * 0.5_f64;
+    let padding = if box_width < box_height {
+        box_width * 0.05_f64
+    } else {
+        box_height * 0.05_f64
+    };
+
+    let mut centers = HashMap::new();
+    for (index, location) in locations.iter().enumerate() {
+        let location = location.borrow();
+        let column = f64::from(u32::try_from(index % columns).unwrap_or(u32::MAX));
+        let row = f64::from(u32::try_from(index / columns).unwrap_or(u32::MAX));
+        let center_x = cell_width * (column + 0.5_f64);
+        let center_y = usable_height - cell_height * (row + 0.5_f64);
+        centers.insert(location.id.clone(), (center_x, center_y));
+
+        pdf_page.in_layer(EQUIPMENT_LAYER, |page| {
+            page.add_rectangle(
+                center_x - box_width / 2.0_f64,
+                center_y - box_height / 2.0_f64,
+                box_width,
+                box_height,
+                line_width,
+            );
+        });
+        let text = location.identifier.as_ref().unwrap_or(&location.id);
+        let text_width = box_width - padding * 2.0_f64;
+        let (font_size, line_count) = fit_text(
+            text,
+            font,
+            text_width,
+            box_height - padding * 2.0_f64,
+            &language,
+        )?;
+        // center the block of text vertically within the box
+        let block_height = font.line_height(font_size) * f64::from(line_count);
+        pdf_page.in_layer(LABEL_LAYER, |page| {
+            page.add_text(
+                text.clone(),
+                font_size,
+                font,
+                1,
+                text_width,
+                center_x - text_width / 2.0_f64,
+                center_y + block_height / 2.0_f64 - font.ascent(font_size),
+                rustybuzz::Direction::LeftToRight,
+                language.clone(),
+                &PDFTextRenderMode::Fill,
+            )
+        })?;
+    }
+
+    draw_pathways(
+        project,
+        &centers,
+        (box_width / 2.0_f64, box_height / 2.0_f64),
+        font,
+        pdf_page,
+    )
+}
+
+/// `draw_pathways` draws a line labelled with the pathway identifier between each pair of
+/// locations that share a pathway, from the edges of the location boxes centered on `centers`
+#[allow(clippy::arithmetic_side_effects)]
+fn draw_pathways(
+    project: &Project,
+    centers: &HashMap<String, (ucum::Meter<f64>, ucum::Meter<f64>)>,
+    half_size: (ucum::Meter<f64>, ucum::Meter<f64>),
+    font: &PDFFont,
+    pdf_page: &mut PDFPage,
+) -> Result<(), Error> {
+    let line_width = 1.0_f64 * PDFDocument::pdf_point();
+    for (label, location_ids) in pathway_locations(project).values() {
+        let points = location_ids
+            .iter()
+            .filter_map(|id| centers.get(id))
+            .collect::<Vec<_>>();
+        for pair in points.windows(2) {
+            let (Some(start), Some(end)) = (pair.first(), pair.get(1)) else {
+                continue;
+            };
+            let start_edge = box_edge(**start, **end, half_size);
+            let end_edge = box_edge(**end, **start, half_size);
+            pdf_page.in_layer(PATHWAY_LAYER, |page| {
+                page.add_line(&[start_edge, end_edge], line_width, false);
+            });
+
+            add_line_label(
+                pdf_page,
+                font,
+                LABEL_MIN_FONT_SIZE.max(FOOTER_FONT_SIZE - 2),
+                label,
+                (start_edge, end_edge),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// `add_line_label` writes `label` next to the middle of the straight line between the two
+/// points of `segment`. Mostly horizontal lines are labelled above the line, and mostly
+/// vertical lines beside it, so the line doesn't run through the text.
+#[allow(clippy::arithmetic_side_effects)]
+fn add_line_label(
+    pdf_page: &mut impl DrawingSurface,
+    font: &PDFFont,
+    font_size: u32,
+    label: &str,
+    segment: (routing::Point, routing::Point),
+) -> Result<(), Error> {
+    let language = text_language()?;
+    let (start, end) = segment;
+    let offset = f64::from(font_size) * PDFDocument::pdf_point() / 4.0_f64;
+    let label_width = font.text_width(label, font_size);
+    let middle = ((start.0 + end.0) / 2.0_f64, (start.1 + end.1) / 2.0_f64);
+    let (label_x, label_y) = if (end.0 - start.0).abs() >= (end.1 - start.1).abs() {
+        (middle.0 - label_width / 2.0_f64, middle.1 + offset)
+    } else {
+        (middle.0 + offset, middle.1)
+    };
+    pdf_page.in_layer(LABEL_LAYER, |page| {
+        page.add_text(
+            label.to_string(),
+            font_size,
+            font,
+            1,
+            // small allowance so rounding doesn't wrap the text
+            label_width + offset,
+            label_x,
+            label_y,
+            rustybuzz::Direction::LeftToRight,
+            language,
+            &PDFTextRenderMode::Fill,
+        )
+    })?;
+    Ok(())
+}
+
+/// `box_edge` returns the point where the line from `center` towards `other` leaves a box
+/// centered on `center` with half width and half height of `half_size`
+#[allow(clippy::arithmetic_side_effects)]
+fn box_edge(
+    center: (ucum::Meter<f64>, ucum::Meter<f64>),
+    other: (ucum::Meter<f64>, ucum::Meter<f64>),
+    half_size: (ucum::Meter<f64>, ucum::Meter<f64>),
+) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+    let delta_x = *((other.0 - center.0) / ucum::M).value();
+    let delta_y = *((other.1 - center.1) / ucum::M).value();
+    let half_width = *(half_size.0 / ucum::M).value();
+    let half_height = *(half_size.1 / ucum::M).value();
+    let fraction = match (delta_x == 0.0_f64, delta_y == 0.0_f64) {
+        (true, true) => return center,
+        (true, false) => half_height / delta_y.abs(),
+        (false, true) => half_width / delta_x.abs(),
+        (false, false) => (half_width / delta_x.abs()).min(half_height / delta_y.abs()),
+    };
+    (
+        center.0 + delta_x * fraction * ucum::M,
+        center.1 + delta_y * fraction * ucum::M,
+    )
+}
+
+/// `CableEnds` is a wire, cable or term cable in a project, along with both of its ends
+struct CableEnds {
+    /// identifier of the wire, cable or term cable, falling back to its id
+    label: String,
+    /// pathway the wire, cable or term cable is routed through
+    pathway: Option<Rc<RefCell<Pathway>>>,
+    /// `end1` and `end2` of the wire, cable or term cable
+    ends: [ConnectionEnd; 2],
+}
+
+/// `cable_ends` returns every wire, cable and term cable in `project`, sorted by label, then by
+/// the id of their ends for ones with the same label
+fn cable_ends(project: &Project) -> Vec<CableEnds> {
+    let mut cable_ends = Vec::new();
+    for wire in project.wires.values() {
+        let ends = [CableEnd::End1, CableEnd::End2].map(|end| ConnectionEnd::Wire {
+            wire: Rc::clone(wire),
+            end,
+        });
+        let wire = wire.borrow();
+        cable_ends.push(CableEnds {
+            label: wire.identifier.as_ref().unwrap_or(&wire.id).clone(),
+            pathway: wire.pathway.clone(),
+            ends,
+        });
+    }
+    for cable in project.cables.values() {
+        let ends = [CableEnd::End1, CableEnd::End2].map(|end| ConnectionEnd::Cable {
+            cable: Rc::clone(cable),
+            end,
+        });
+        let cable = cable.borrow();
+        cable_ends.push(CableEnds {
+            label: cable.identifier.as_ref().unwrap_or(&cable.id).clone(),
+            pathway: cable.pathway.clone(),
+            ends,
+        });
+    }
+    for term_cable in project.term_cables.values() {
+        let ends = [CableEnd::End1, CableEnd::End2].map(|end| ConnectionEnd::TermCable {
+            term_cable: Rc::clone(term_cable),
+            end,
+        });
+        let term_cable = term_cable.borrow();
+        cable_ends.push(CableEnds {
+            label: term_cable
+                .identifier
+                .as_ref()
+                .unwrap_or(&term_cable.id)
+                .clone(),
+            pathway: term_cable.pathway.clone(),
+            ends,
+        });
+    }
+    cable_ends.sort_by_cached_key(|cable| {
+        (
+            cable.label.clone(),
+            cable.ends.iter().map(ConnectionEnd::id).collect::<Vec<_>>(),
+        )
+    });
+    cable_ends
+}
+
+/// `pathway_locations` returns the label and the ids of the locations connected by each pathway
+/// in `project`, keyed by pathway id. Locations are found from the equipment directly connected
+/// to the ends of each wire, cable and term cable routed through the pathway.
+fn pathway_locations(project: &Project) -> BTreeMap<String, (String, BTreeSet<String>)> {
+    let mut pathway_locations: BTreeMap<String, (String, BTreeSet<String>)> = BTreeMap::new();
+    for cable in cable_ends(project) {
+        let Some(pathway) = cable.pathway else {
+            continue;
+        };
+        let pathway = pathway.borrow();
+        let (_, location_ids) = pathway_locations
+            .entry(pathway.id.clone())
+            .or_insert_with(|| {
+                (
+                    pathway.identifier.as_ref().unwrap_or(&pathway.id).clone(),
+                    BTreeSet::new(),
+                )
+            });
+        for end in &cable.ends {
+            for connected_end in project.connected_to(end) {
+                if let Some(equipment) = connected_end.equipment() {
+                    location_ids.insert(equipment.borrow().location.borrow().id.clone());
+                }
+            }
+        }
+    }
+    pathway_locations
+}
 
-/// `pdf_all_locations` generates a pdf for the entire project
-pub fn pdf_all_locations(_project: &Project, _library: &Library, _page_size: PaperSize) {
-    // first thing is to layout all locations
-    //
-    // then layout all pathways between locations
-    //
-    // then layout all equipment in each location
+/// `fit_text` finds the largest font size between `LABEL_MIN_FONT_SIZE` and
+/// `LABEL_MAX_FONT_SIZE` at which `text` fits within `width` and `height`. Returns the font size
+/// and the number of lines.
+#[allow(clippy::arithmetic_side_effects)]
+fn fit_text(
+    text: &str,
+    font: &PDFFont,
+    width: ucum::Meter<f64>,
+    height: ucum::Meter<f64>,
+    language: &rustybuzz::Language,
+) -> Result<(u32, u32), Error> {
+    for font_size in (LABEL_MIN_FONT_SIZE..=LABEL_MAX_FONT_SIZE).rev() {
+        // shrink the text before breaking words between characters
+        let break_options = BreakOptions {
+            break_anywhere: font_size == LABEL_MIN_FONT_SIZE,
+            ..BreakOptions::default()
+        };
+        match font.to_lines(
+            text,
+            font_size,
+            width,
+            rustybuzz::Direction::LeftToRight,
+            language.clone(),
+            &break_options,
+        ) {
+            Ok(lines) => {
+                let line_count = u32::try_from(lines.len()).unwrap_or(u32::MAX);
+                if font.line_height(font_size) * f64::from(line_count) <= height {
+                    return Ok((font_size, line_count));
+                }
+            }
+            Err(paragraph_breaker::Error::LineLengthTooLong) => {}
+            Err(e) => return Err(Error::LayoutError(format!("{e}"))),
+        }
+    }
+    Err(Error::LayoutError(format!(
+        "text does not fit at minimum font size {LABEL_MIN_FONT_SIZE}: {text}"
+    )))
 }
 
-/// `pdf_one_location` generates one PDF page with one location in the project
+/// `add_footer` draws a footer along the bottom margin of `pdf_page`, with `title` on the left
+/// and the page number on the right
+#[allow(clippy::arithmetic_side_effects)]
+fn add_footer(
+    pdf_page: &mut PDFPage,
+    font: &PDFFont,
+    title: &str,
+    page_number: usize,
+    page_count: usize,
+) -> Result<(), Error> {
+    let language = text_language()?;
+    let usable_width = pdf_page.size().0 - pdf_page.margins.left - pdf_page.margins.right;
+    let line_height = font.line_height(FOOTER_FONT_SIZE);
+    let line_width = 0.5_f64 * PDFDocument::pdf_point();
+    let baseline = line_height / 2.0_f64;
+
+    pdf_page.add_line(
+        &[
+            (0.0_f64 * ucum::M, line_height * 1.5_f64),
+            (usable_width, line_height * 1.5_f64),
+        ],
+        line_width,
+        false,
+    );
+    pdf_page.add_text(
+        title.to_string(),
+        FOOTER_FONT_SIZE,
+        font,
+        1,
+        usable_width * 0.7_f64,
+        0.0_f64 * ucum::M,
+        baseline,
+        rustybuzz::Direction::LeftToRight,
+        language.clone(),
+        &PDFTextRenderMode::Fill,
+    )?;
+    let page_text = format!("Page {page_number} of {page_count}");
+    let page_text_width = font.text_width(&page_text, FOOTER_FONT_SIZE);
+    pdf_page.add_text(
+        page_text,
+        FOOTER_FONT_SIZE,
+        font,
+        1,
+        // small allowance so rounding doesn't wrap the text
+        page_text_width + line_width,
+        usable_width - page_text_width,
+        baseline,
+        rustybuzz::Direction::LeftToRight,
+        language,
+        &PDFTextRenderMode::Fill,
+    )?;
+    Ok(())
+}
+
+/// `pdf_one_location` generates a PDF document of one location in the project, on one page or
+/// on several tiled pages, and returns it ready to be written
 ///
 /// # Arguments
 ///
 /// * `project` - the `Project` that contains this location
 /// * `reference_location` - the `Location` that will be rendered as a PDF
 /// * `page_size` - the target page size of the PDF file
+/// * `orientation` - orientation of the PDF page
 /// * `margins` - the margin sizes of the PDF page
-/// * `scale` - optional - specifies the scale of the rendered objects relative to their full size,
-/// represented as a:b.
-/// For example, 1:2 would double the size of the object on the page, relative to its actual size,
-/// and 2:1 would half the size of the object
+/// * `scale` - how the scale of the rendered objects is chosen, passed to `render_location`
+/// * `tiling` - optional - if set, the location is split across several tiled pages if it
+///   doesn't fit on one page at `scale`
+/// * `config_font_paths` - additional font paths to search for fonts. The standard PDF fonts are
+///   used if there are none
+///
+/// Each page has a footer with the location and the scale it is drawn at.
 ///
 /// # Errors
 ///
-/// will Error if the location doesn't fit on page at specified scale
+/// will Error if no fonts are found, or the location doesn't fit on page at specified scale and
+/// isn't tiled
+#[allow(clippy::too_many_arguments)]
 pub fn pdf_one_location(
     project: &Project,
     reference_location: &Location,
     margins: Margins,
     page_size: PaperSize,
-    scale: Option<ScalingFactor>,
+    orientation: Orientation,
+    scale: DrawingScale,
+    tiling: Option<&Tiling>,
     config_font_paths: Vec<PathBuf>,
-) -> Result<(), Error> {
+) -> Result<PDFDocument<'static>, Error> {
     let mut pdf = PDFDocument::new(page_size, config_font_paths)?;
-    pdf.push_page(None, margins);
-    render_location(project, reference_location, scale, &mut pdf.pages[0])?;
-    Ok(())
+    let font = pdf
+        .available_fonts
+        .first()
+        .ok_or_else(|| Error::LayoutError("no fonts loaded".to_string()))?;
+    // reserve space at the bottom of the page for the footer
+    #[allow(clippy::arithmetic_side_effects)]
+    let content_margins = Margins {
+        bottom: margins.bottom + font.line_height(FOOTER_FONT_SIZE) * 2.0_f64,
+        ..margins
+    };
+    let mut blank_page = PDFPage::new(page_size, content_margins);
+    blank_page.orientation = orientation;
+    let layout = plan_location(reference_location, scale, tiling, &blank_page, font)?;
+    let (pages, location_scale) = render_location_pages(
+        project,
+        reference_location,
+        &layout,
+        None,
+        font,
+        &blank_page,
+    )?;
+    let page_count = pages.len();
+    for (page_number, mut page) in (1..).zip(pages) {
+        page.margins = margins;
+        let sheet = Sheet {
+            title: location_title(reference_location),
+            scale: Some(location_scale),
+            number: page_number,
+            count: page_count,
+        };
+        add_frame(&mut page, None, font, &sheet)?;
+        pdf.pages.push(page);
+    }
+    Ok(pdf)
 }
-//TODO: autoscale boolean?
-/// `render_location` generates one PDF page with one location in the project
+/// `svg_locations` draws each location in the project that has equipment on its own page with
+/// `render_location`, as standalone SVG images rather than a PDF document, and returns them by
+/// location id. Locations are not tiled, and have no footer or template.
+///
+/// The images are meant to be saved side by side as `<location id>.svg`. Off-page connectors
+/// link to the image of the location at the other end of the connection, and pages are numbered
+/// in order of location id.
+///
+/// # Arguments
+///
+/// * `project` - the `Project` to render
+/// * `margins` - the margin sizes of each image
+/// * `page_size` - the page size of each image
+/// * `orientation` - orientation of every image
+/// * `scale` - how the scale of each location is chosen, passed to `render_location`
+/// * `config_font_paths` - additional font paths to search for fonts. The standard PDF fonts are
+///   used if there are none
+///
+/// # Errors
+///
+/// will Error if no fonts are found, or any location doesn't fit on the page at `scale`
+pub fn svg_locations(
+    project: &Project,
+    margins: Margins,
+    page_size: PaperSize,
+    orientation: Orientation,
+    scale: DrawingScale,
+    config_font_paths: Vec<PathBuf>,
+) -> Result<BTreeMap<String, SVGPage>, Error> {
+    // the fonts are loaded the same way as for PDFs, so text is measured the same
+    let pdf = PDFDocument::new(page_size, config_font_paths)?;
+    let font = pdf
+        .available_fonts
+        .first()
+        .ok_or_else(|| Error::LayoutError("no fonts loaded".to_string()))?;
+
+    let mut locations = project
+        .locations
+        .values()
+        .filter(|location| has_equipment(project, &location.borrow()))
+        .map(|location| location.borrow().id.clone())
+        .collect::<Vec<_>>();
+    locations.sort();
+    let location_pages = locations
+        .iter()
+        .enumerate()
+        .map(|(page, id)| (id.clone(), page))
+        .collect::<BTreeMap<_, _>>();
+    let link_targets = locations
+        .iter()
+        .enumerate()
+        .map(|(page, id)| (page, format!("{id}.svg")))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut images = BTreeMap::new();
+    for id in locations {
+        let Some(location) = project.locations.get(&id) else {
+            continue;
+        };
+        let mut image = SVGPage::new(page_size, margins);
+        image.orientation = orientation;
+        image.link_targets.clone_from(&link_targets);
+        render_location(
+            project,
+            &location.borrow(),
+            scale,
+            Some(&location_pages),
+            font,
+            &mut image,
+        )?;
+        images.insert(id, image);
+    }
+    Ok(images)
+}
+
+/// `render_location` draws one location in the project on a page, including the connections
+/// between equipment in the location, and returns the scale it is drawn at. The page is
+/// normally a `PDFPage`, or an `SVGPage` to draw the location as an SVG image.
+///
+/// Equipment, connection lines and their labels are drawn on separate layers, named "Equipment",
+/// "Connections" and "Labels", which can be hidden in PDF readers and SVG editors. Dimensions
+/// aren't drawn, so there is no layer for them.
 ///
 /// # Arguments
 ///
 /// * `project` - the `Project` that contains this location
-/// * `reference_location` - the `Location` that will be rendered as a PDF
-/// * `scale` - optional - specifies the scale of the rendered objects relative to their full size,
-/// represented as `a`:`b`.
-/// For example, 1:2 would double the size of the object on the page, relative to its actual size,
-/// and 2:1 would half the size of the object. This is equal scaling in both X and Y direction.
+/// * `reference_location` - the `Location` that will be rendered
+/// * `scale` - specifies the scale of the rendered objects relative to their full size,
+///   represented as `a`:`b`, or picks the largest standard scale that fits on the page.
+///   For example, 1:2 would half the size of the object on the page, relative to its actual
+///   size, and 2:1 would double the size of the object. This is equal scaling in both X and Y
+///   direction.
+/// * `location_pages` - optional - zero indexed page number of each location in the document.
+///   If set, connections to equipment in other locations are drawn to off-page connectors that
+///   link to the page of the other location.
+/// * `font` - font used to label connections
+/// * `pdf_page` - the page to render the location on
 ///
 /// # Errors
 ///
@@ -66,61 +1022,30 @@ pub fn pdf_one_location(
 pub fn render_location(
     project: &Project,
     reference_location: &Location,
-    scale: Option<ScalingFactor>,
-    pdf_page: &mut PDFPage,
-) -> Result<(), Error> {
+    scale: DrawingScale,
+    location_pages: Option<&BTreeMap<String, usize>>,
+    font: &PDFFont,
+    pdf_page: &mut impl DrawingSurface,
+) -> Result<ScalingFactor, Error> {
     // layout all equipment in location
 
-    if project.equipment.is_empty() {
-        return Err(Error::PDFCreationError(
-            "no equipment in location".to_string(),
-        ));
-    }
-    let page_width = pdf_page.page_size.size().0;
-    let page_height = pdf_page.page_size.size().1;
-    // check if location will fit within page at 1:1 scale
-    #[allow(clippy::arithmetic_side_effects)]
-    let location_default_scale_fit = {
-        reference_location.location_type.borrow().width
-            < (page_width - pdf_page.margins.left - pdf_page.margins.right)
-            && reference_location.location_type.borrow().height
-                < (page_height - pdf_page.margins.top - pdf_page.margins.bottom)
-    };
-
-    // check if location will fit on page at specified scale
-    #[allow(clippy::arithmetic_side_effects)]
-    let location_scale_fit = {
-        (reference_location.location_type.borrow().width * f64::from(scale.unwrap_or_default().a)
-            / f64::from(scale.unwrap_or_default().b))
-            < (page_width - pdf_page.margins.left - pdf_page.margins.right)
-            && (reference_location.location_type.borrow().height
-                * f64::from(scale.unwrap_or_default().a)
-                / f64::from(scale.unwrap_or_default().b))
-                < (page_height - pdf_page.margins.top - pdf_page.margins.bottom)
-    };
-
-    if location_default_scale_fit {
-        if scale.is_some() {
-            //TODO: change this to info
-            warn!("location fits within page at 1:1 scale. Scale does not need to be specified");
-        }
-    } else if location_scale_fit {
-        trace!(
-            "location fits within page at {} scale",
-            scale.unwrap_or_default()
-        );
-    } else {
-        return Err(Error::LayoutError(format!(
-            "Location {} did not fit on Page Size {} at scale: {}",
-            reference_location.id,
-            pdf_page.page_size,
-            scale.unwrap_or_default(),
+    if !has_equipment(project, reference_location) {
+        return Err(Error::PDFCreationError(format!(
+            "no equipment in location {}",
+            reference_location.id
         )));
     }
+    let scale = choose_scale(reference_location, scale, pdf_page)?;
+    let factor = scale
+        .factor()
+        .ok_or_else(|| Error::LayoutError(format!("invalid scale: {scale}")))?;
     // loop through all equipment in project and render
 
-    let mut equipment_ids_in_location = Vec::new();
-    for equipment in project.equipment.values() {
+    // equipment is drawn in order of id, so the page is the same every time it is drawn
+    let mut sorted_equipment = project.equipment.values().collect::<Vec<_>>();
+    sorted_equipment.sort_by_key(|equipment| equipment.borrow().id.clone());
+    let mut equipment_bounds = BTreeMap::new();
+    for equipment in sorted_equipment {
         // select equipment that is within location
         if equipment.borrow().location.borrow().id == reference_location.id {
             let svg_text = equipment
@@ -129,26 +1054,310 @@ pub fn render_location(
                 .borrow()
                 .visual_rep()
                 .to_string();
-            let x = equipment.borrow().sub_location.x;
-            let y = equipment.borrow().sub_location.y;
-            pdf_page.add_svg(svg_text.as_str(), x, y, scale)?;
-            equipment_ids_in_location.push(equipment.borrow().id.clone());
+            // images in the SVG are referenced relative to the file the equipment type is in
+            let svg_dir = equipment
+                .borrow()
+                .equip_type
+                .borrow()
+                .contained_datafile_path
+                .parent()
+                .map(Path::to_path_buf);
+            #[allow(clippy::arithmetic_side_effects)]
+            let x = equipment.borrow().sub_location.x * factor;
+            #[allow(clippy::arithmetic_side_effects)]
+            let y = equipment.borrow().sub_location.y * factor;
+            let (width, height) = pdf_page.in_layer(EQUIPMENT_LAYER, |page| {
+                page.add_svg(
+                    svg_text.as_str(),
+                    x,
+                    y,
+                    Some(scale),
+                    std::slice::from_ref(font),
+                    svg_dir.as_deref(),
+                )
+            })?;
+            #[allow(clippy::arithmetic_side_effects)]
+            equipment_bounds.insert(
+                equipment.borrow().id.clone(),
+                routing::Rect {
+                    left: x,
+                    bottom: y,
+                    right: x + width,
+                    top: y + height,
+                },
+            );
         }
     }
 
-    for equipment in project.equipment.values() {
-        if equipment.borrow().location.borrow().id == reference_location.id {
-            //
+    draw_connections(
+        project,
+        &equipment_bounds,
+        factor,
+        location_pages,
+        font,
+        pdf_page,
+    )?;
+
+    Ok(scale)
+}
+
+/// `CONNECTION_FONT_SIZE` is the font size of connection labels, in points
+const CONNECTION_FONT_SIZE: u32 = 6;
+
+/// `draw_connections` draws a line between the connectors at each end of every wire, cable and
+/// term cable that has both ends connected to equipment in `equipment_bounds`. Lines are routed
+/// orthogonally around the equipment and labelled with the identifier of the wire or cable.
+///
+/// If `location_pages` is set, wires and cables with only one end in `equipment_bounds` are drawn
+/// to off-page connectors instead, see `draw_off_page_connectors`.
+fn draw_connections(
+    project: &Project,
+    equipment_bounds: &BTreeMap<String, routing::Rect>,
+    factor: f64,
+    location_pages: Option<&BTreeMap<String, usize>>,
+    font: &PDFFont,
+    pdf_page: &mut impl DrawingSurface,
+) -> Result<(), Error> {
+    let mut layout = ConnectionLayout {
+        obstacles: equipment_bounds.values().copied().collect(),
+        routes: Vec::new(),
+    };
+    let mut off_page_connections = Vec::new();
+    for cable in cable_ends(project) {
+        let positions = cable
+            .ends
+            .each_ref()
+            .map(|end| connector_position(project, end, equipment_bounds, factor));
+        match (positions, &cable.ends) {
+            ([Some(start), Some(end)], _) => {
+                layout.draw(start, end, &cable.label, font, pdf_page)?;
+            }
+            ([Some(start), None], [_, remote_end]) | ([None, Some(start)], [remote_end, _]) => {
+                if let Some(equipment) = project
+                    .connected_to(remote_end)
+                    .into_iter()
+                    .find_map(ConnectionEnd::equipment)
+                {
+                    off_page_connections.push((cable.label.clone(), start, equipment));
+                }
+            }
+            _ => {}
         }
     }
-    //TODO: now need to figure out which equipment is connected to which other equipment in the
-    //same location and draw those connections and pathways
-    //
-    //Then draw connections off page gated behind a boolean
+    if let Some(location_pages) = location_pages {
+        draw_off_page_connectors(
+            &mut layout,
+            off_page_connections,
+            location_pages,
+            font,
+            pdf_page,
+        )?;
+    }
+    Ok(())
+}
+
+/// `ConnectionLayout` keeps track of what has been drawn on a location page, so connection lines
+/// can be routed around it
+struct ConnectionLayout {
+    /// areas of the page that lines are routed around
+    obstacles: Vec<routing::Rect>,
+    /// lines that have already been drawn
+    routes: Vec<Vec<routing::Point>>,
+}
+
+impl ConnectionLayout {
+    /// `draw` routes a line from `start` to `end` around the obstacles, draws it on `pdf_page`
+    /// and labels its longest segment with `label`
+    #[allow(clippy::arithmetic_side_effects)]
+    fn draw(
+        &mut self,
+        start: routing::Point,
+        end: routing::Point,
+        label: &str,
+        font: &PDFFont,
+        pdf_page: &mut impl DrawingSurface,
+    ) -> Result<(), Error> {
+        let line_width = 0.75_f64 * PDFDocument::pdf_point();
+        let clearance = 4.0_f64 * PDFDocument::pdf_point();
+        let points = routing::route(start, end, &self.obstacles, &self.routes, clearance)
+            .unwrap_or_else(|| {
+                warn! {"no route around equipment found for {label}, drawing it directly"}
+                vec![start, (end.0, start.1), end]
+            });
+        pdf_page.in_layer(CONNECTION_LAYER, |page| {
+            page.add_line(&points, line_width, false);
+        });
+
+        // label the longest segment, which has the most space for the text
+        let longest = points
+            .windows(2)
+            .filter_map(|pair| Some((*pair.first()?, *pair.get(1)?)))
+            .max_by(|a, b| {
+                let length = |(from, to): &(routing::Point, routing::Point)| {
+                    *(((to.0 - from.0).abs() + (to.1 - from.1).abs()) / ucum::M).value()
+                };
+                length(a).total_cmp(&length(b))
+            });
+        if let Some(segment) = longest {
+            add_line_label(pdf_page, font, CONNECTION_FONT_SIZE, label, segment)?;
+        }
+        self.routes.push(points);
+        Ok(())
+    }
+}
+
+/// `draw_off_page_connectors` draws an off-page connector symbol for each connection in
+/// `connections`, stacked down the right side of the page, and routes a line to it from the
+/// local connector. Each symbol is labelled with the equipment, location and page number at the
+/// other end of the connection, and links to that page.
+///
+/// `connections` holds the label of the wire or cable, the position of the local connector and
+/// the equipment at the other end.
+#[allow(clippy::arithmetic_side_effects)]
+fn draw_off_page_connectors(
+    layout: &mut ConnectionLayout,
+    connections: Vec<(String, routing::Point, Rc<RefCell<Equipment>>)>,
+    location_pages: &BTreeMap<String, usize>,
+    font: &PDFFont,
+    pdf_page: &mut impl DrawingSurface,
+) -> Result<(), Error> {
+    let language = text_language()?;
+    let (page_width, page_height) = pdf_page.size();
+    let margins = pdf_page.margins();
+    let usable_width = page_width - margins.left - margins.right;
+    let usable_height = page_height - margins.top - margins.bottom;
+    let line_height = font.line_height(CONNECTION_FONT_SIZE);
+    let height = line_height * 1.6_f64;
+    let padding = line_height / 2.0_f64;
+    let gap = line_height / 2.0_f64;
 
+    // symbols are stacked from the top right corner down, moving left a column when full
+    let mut column_right = usable_width;
+    let mut column_width = 0.0_f64 * ucum::M;
+    let mut top = usable_height;
+    for (label, start, equipment) in connections {
+        let equipment = equipment.borrow();
+        let location = equipment.location.borrow();
+        let page = location_pages.get(&location.id);
+        let text = format!(
+            "{} @ {}{}",
+            equipment.identifier.as_ref().unwrap_or(&equipment.id),
+            location.identifier.as_ref().unwrap_or(&location.id),
+            page.map(|page| format!(", page {}", page.saturating_add(1)))
+                .unwrap_or_default(),
+        );
+        let text_width = font.text_width(&text, CONNECTION_FONT_SIZE);
+        // the pointed end of the symbol is half as long as the symbol is high
+        let width = text_width + padding * 2.0_f64 + height / 2.0_f64;
+
+        if top - height < 0.0_f64 * ucum::M {
+            column_right = column_right - column_width - gap;
+            column_width = 0.0_f64 * ucum::M;
+            top = usable_height;
+        }
+        let (left, bottom) = (column_right - width, top - height);
+        if left < 0.0_f64 * ucum::M {
+            return Err(Error::LayoutError(
+                "off-page connectors do not fit on page".to_string(),
+            ));
+        }
+        top = bottom - gap;
+        if width > column_width {
+            column_width = width;
+        }
+
+        layout.obstacles.push(routing::Rect {
+            left,
+            bottom,
+            right: left + width,
+            top: bottom + height,
+        });
+        layout.draw(
+            start,
+            (left, bottom + height / 2.0_f64),
+            &label,
+            font,
+            pdf_page,
+        )?;
+        let point = left + width - height / 2.0_f64;
+        pdf_page.in_layer(CONNECTION_LAYER, |layer_page| {
+            layer_page.add_line(
+                &[
+                    (left, bottom),
+                    (point, bottom),
+                    (left + width, bottom + height / 2.0_f64),
+                    (point, bottom + height),
+                    (left, bottom + height),
+                    (left, bottom),
+                ],
+                0.75_f64 * PDFDocument::pdf_point(),
+                false,
+            );
+        });
+        pdf_page.in_layer(LABEL_LAYER, |layer_page| {
+            layer_page.add_text(
+                text,
+                CONNECTION_FONT_SIZE,
+                font,
+                1,
+                text_width + padding,
+                left + padding,
+                bottom + (height - line_height) / 2.0_f64 + line_height
+                    - font.ascent(CONNECTION_FONT_SIZE),
+                rustybuzz::Direction::LeftToRight,
+                language.clone(),
+                &PDFTextRenderMode::Fill,
+            )
+        })?;
+        if let Some(page) = page {
+            pdf_page.add_link(left, bottom, width, height, *page);
+        }
+    }
     Ok(())
 }
 
+/// `connector_position` returns the position on the page of the equipment connector that `end`
+/// is connected to, if the equipment is in `equipment_bounds`. Connections to equipment without
+/// a specific connector, or to a connector on a face that isn't drawn, use the center of the
+/// equipment.
+#[allow(clippy::arithmetic_side_effects)]
+fn connector_position(
+    project: &Project,
+    end: &ConnectionEnd,
+    equipment_bounds: &BTreeMap<String, routing::Rect>,
+    factor: f64,
+) -> Option<routing::Point> {
+    project.connected_to(end).into_iter().find_map(|connected| {
+        let equipment = connected.equipment()?;
+        let bounds = equipment_bounds.get(&equipment.borrow().id)?;
+        let on_drawn_face = matches!(
+            connected,
+            ConnectionEnd::Equipment { face: Some(face), .. } if face == DRAWN_FACE
+        );
+        Some(match connected.equipment_connector() {
+            Some(connector) if on_drawn_face => (
+                bounds.left + connector.x * factor,
+                bounds.bottom + connector.y * factor,
+            ),
+            _ => (
+                (bounds.left + bounds.right) / 2.0_f64,
+                (bounds.bottom + bounds.top) / 2.0_f64,
+            ),
+        })
+    })
+}
+
+/// `text_language` returns the language text on generated pages is shaped and broken into
+/// lines in
+///
+/// # Errors
+///
+/// Will error if the language tag is invalid
+pub(crate) fn text_language() -> Result<rustybuzz::Language, Error> {
+    rustybuzz::Language::from_str("en")
+        .map_err(|e| Error::LayoutError(format!("invalid text language: {e}")))
+}
+
 /// `Error` is the list of errors that can occur in `PDFGeneration`
 #[derive(Debug)]
 #[non_exhaustive]
@@ -174,3 +1383,242 @@ impl From<pdf_helper::Error> for Error {
         Error::PDFCreationError(format!("{e}"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::datatypes::internal_types::{
+        cable::Cable,
+        connection::Connection,
+        equipment_type::{Connector, EquipFace, EquipmentType},
+        location::SubLocation,
+        location_type::LocationType,
+        svg::Svg,
+        wire::Wire,
+    };
+
+    /// `FIXTURE` is the checked in output of `render_project`, relative to the crate root. Run the
+    /// tests with `CDM_UPDATE_FIXTURES` set to regenerate it after an intended change to the output
+    const FIXTURE: &str = "tests/fixtures/all_locations.pdf";
+
+    /// `test_project` returns two locations with a rack in each, connected by a cable
+    #[allow(clippy::arithmetic_side_effects)]
+    fn test_project() -> Project {
+        let mut project = Project::new();
+        let location_type = Rc::new(RefCell::new(LocationType {
+            width: 0.15_f64 * ucum::M,
+            height: 0.2_f64 * ucum::M,
+            ..Default::default()
+        }));
+        let rack = Rc::new(RefCell::new(EquipmentType {
+            id: "rack".to_string(),
+            visual_rep: Svg(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="40mm" viewBox="0 0 100 40"><rect x="1" y="1" width="98" height="38" fill="none" stroke="black"/></svg>"#.to_string()),
+            ..Default::default()
+        }));
+        for id in ["MDF", "IDF"] {
+            let location = Rc::new(RefCell::new(Location {
+                id: id.to_string(),
+                identifier: Some(format!("{id} room")),
+                location_type: Rc::clone(&location_type),
+                ..Default::default()
+            }));
+            project
+                .locations
+                .insert(id.to_string(), Rc::clone(&location));
+            let equipment = Rc::new(RefCell::new(Equipment {
+                id: format!("{id}-rack"),
+                equip_type: Rc::clone(&rack),
+                location,
+                sub_location: SubLocation {
+                    x: 0.02_f64 * ucum::M,
+                    y: 0.05_f64 * ucum::M,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }));
+            project.equipment.insert(format!("{id}-rack"), equipment);
+        }
+        let cable = Rc::new(RefCell::new(Cable {
+            id: "C1".to_string(),
+            ..Default::default()
+        }));
+        project.cables.insert("C1".to_string(), Rc::clone(&cable));
+        for (end, equipment) in [(CableEnd::End1, "MDF-rack"), (CableEnd::End2, "IDF-rack")] {
+            project.connections.push(
+                Connection::new(
+                    ConnectionEnd::Cable {
+                        cable: Rc::clone(&cable),
+                        end,
+                    },
+                    ConnectionEnd::Equipment {
+                        equipment: Rc::clone(&project.equipment[equipment]),
+                        face: None,
+                        connector: None,
+                    },
+                    PathBuf::new(),
+                )
+                .unwrap(),
+            );
+        }
+        project
+    }
+
+    /// `render_project` renders `test_project` with the standard fonts, and returns the bytes of
+    /// the written PDF
+    #[allow(clippy::arithmetic_side_effects)]
+    fn render_project(file_name: &str) -> Vec<u8> {
+        let margin = 0.5_f64 * ucum::IN_US;
+        let mut pdf = pdf_all_locations(
+            &test_project(),
+            Margins {
+                top: margin,
+                bottom: margin,
+                left: margin,
+                right: margin,
+            },
+            PaperSize::Letter,
+            Orientation::Portrait,
+            DrawingScale::default(),
+            None,
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+        let out_dir = std::env::temp_dir();
+        pdf.write(&out_dir, Path::new(file_name), false).unwrap();
+        let bytes = std::fs::read(out_dir.join(file_name)).unwrap();
+        std::fs::remove_file(out_dir.join(file_name)).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn output_is_reproducible() {
+        let first = render_project("cdm_reproducible_first.pdf");
+        let second = render_project("cdm_reproducible_second.pdf");
+        assert!(first == second, "rendering the same project twice differs");
+
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURE);
+        if std::env::var_os("CDM_UPDATE_FIXTURES").is_some() {
+            std::fs::write(&fixture, &first).unwrap();
+        }
+        let expected = std::fs::read(&fixture).unwrap();
+        assert!(first == expected, "output differs from {FIXTURE}");
+    }
+
+    /// `face` returns an `EquipFace` with a single connector `id` at (`x`, `y`)
+    #[allow(clippy::arithmetic_side_effects)]
+    fn face(id: &str, x: f64, y: f64) -> EquipFace {
+        EquipFace {
+            connectors: Some(vec![Connector {
+                id: id.to_string(),
+                x: x * ucum::M,
+                y: y * ucum::M,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    #[allow(clippy::arithmetic_side_effects)]
+    fn connector_positions() {
+        let mut project = Project::new();
+        let patch_panel = EquipmentType {
+            id: "patch panel".to_string(),
+            faces: Some(HashMap::from([
+                ("Front".to_string(), face("J1", 0.01_f64, 0.02_f64)),
+                ("Rear".to_string(), face("J2", 0.03_f64, 0.04_f64)),
+            ])),
+            ..Default::default()
+        };
+        let equipment = Rc::new(RefCell::new(Equipment {
+            id: "EQ1".to_string(),
+            equip_type: Rc::new(RefCell::new(patch_panel)),
+            ..Default::default()
+        }));
+        let wire_ends = [
+            ("W1", Some(("Front", "J1"))),
+            ("W2", Some(("Rear", "J2"))),
+            ("W3", None),
+        ]
+        .map(|(id, connector)| {
+            let wire = ConnectionEnd::Wire {
+                wire: Rc::new(RefCell::new(Wire {
+                    id: id.to_string(),
+                    ..Default::default()
+                })),
+                end: CableEnd::End1,
+            };
+            let equipment_end = ConnectionEnd::Equipment {
+                equipment: Rc::clone(&equipment),
+                face: connector.map(|(face, _)| face.to_string()),
+                connector: connector.map(|(_, connector)| connector.to_string()),
+            };
+            project
+                .connections
+                .push(Connection::new(wire.clone(), equipment_end, PathBuf::new()).unwrap());
+            wire
+        });
+        let bounds = routing::Rect {
+            left: 0.1_f64 * ucum::M,
+            bottom: 0.2_f64 * ucum::M,
+            right: 0.3_f64 * ucum::M,
+            top: 0.4_f64 * ucum::M,
+        };
+        let equipment_bounds = BTreeMap::from([("EQ1".to_string(), bounds)]);
+        let factor = 0.5_f64;
+        let center = (
+            (bounds.left + bounds.right) / 2.0_f64,
+            (bounds.bottom + bounds.top) / 2.0_f64,
+        );
+        let [front, rear, no_connector] = wire_ends
+            .each_ref()
+            .map(|end| connector_position(&project, end, &equipment_bounds, factor));
+
+        assert_eq!(
+            front,
+            Some((
+                bounds.left + 0.01_f64 * ucum::M * factor,
+                bounds.bottom + 0.02_f64 * ucum::M * factor
+            ))
+        );
+        // the rear face isn't drawn, so its connectors are placed at the center of the equipment
+        assert_eq!(rear, Some(center));
+        assert_eq!(no_connector, Some(center));
+        // equipment that isn't on the page has no position
+        assert_eq!(
+            connector_position(&project, &wire_ends[0], &BTreeMap::new(), factor),
+            None
+        );
+    }
+
+    #[test]
+    #[allow(clippy::arithmetic_side_effects)]
+    fn unroutable_connection_drawn_directly() {
+        let pdf = PDFDocument::new(PaperSize::Letter, Vec::new()).unwrap();
+        let mut page = SVGPage::new(PaperSize::Letter, Margins::default());
+        let wall = |left: f64, bottom: f64, right: f64, top: f64| routing::Rect {
+            left: left * ucum::M,
+            bottom: bottom * ucum::M,
+            right: right * ucum::M,
+            top: top * ucum::M,
+        };
+        // the end is walled in on every side, so no route around the walls reaches it
+        let mut layout = ConnectionLayout {
+            obstacles: vec![
+                wall(0.1_f64, 0.1_f64, 0.11_f64, 0.2_f64),
+                wall(0.19_f64, 0.1_f64, 0.2_f64, 0.2_f64),
+                wall(0.1_f64, 0.1_f64, 0.2_f64, 0.11_f64),
+                wall(0.1_f64, 0.19_f64, 0.2_f64, 0.2_f64),
+            ],
+            routes: Vec::new(),
+        };
+        let start = (0.02_f64 * ucum::M, 0.02_f64 * ucum::M);
+        let end = (0.15_f64 * ucum::M, 0.15_f64 * ucum::M);
+        layout
+            .draw(start, end, "W1", &pdf.available_fonts[0], &mut page)
+            .unwrap();
+        assert_eq!(layout.routes, vec![vec![start, (end.0, start.1), end]]);
+    }
+}
@@ -0,0 +1,441 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use dimensioned::{ucum, Dimensionless};
+
+/// `Point` is a position on the page, relative to the bottom left corner inside the margins
+pub type Point = (ucum::Meter<f64>, ucum::Meter<f64>);
+
+/// `Rect` is an axis aligned rectangle on the page
+#[derive(Debug, Clone, Copy)]
+pub struct Rect {
+    /// position of the left side
+    pub left: ucum::Meter<f64>,
+    /// position of the bottom side
+    pub bottom: ucum::Meter<f64>,
+    /// position of the right side
+    pub right: ucum::Meter<f64>,
+    /// position of the top side
+    pub top: ucum::Meter<f64>,
+}
+
+impl Rect {
+    /// returns true if `point` is inside the rectangle grown by `margin` on every side,
+    /// excluding its edges
+    #[allow(clippy::arithmetic_side_effects)]
+    fn contains(&self, point: Point, margin: ucum::Meter<f64>) -> bool {
+        point.0 > self.left - margin
+            && point.0 < self.right + margin
+            && point.1 > self.bottom - margin
+            && point.1 < self.top + margin
+    }
+}
+
+/// `Direction` is the direction of travel along a grid line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// along the x axis
+    Horizontal,
+    /// along the y axis
+    Vertical,
+}
+
+/// `State` is an entry in the search queue of `route`
+#[derive(Debug)]
+struct State {
+    /// total cost of the route so far
+    cost: f64,
+    /// index of the grid node
+    node: usize,
+    /// direction the route arrived at `node` from
+    direction: Direction,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    // reversed so `BinaryHeap` pops the lowest cost first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// `route` finds an orthogonal route from `start` to `end` that avoids every rectangle in
+/// `obstacles` by at least half of `clearance`.
+///
+/// `start` and `end` are usually connectors drawn on top of equipment, so the route first leaves
+/// the obstacle containing each of them vertically, by the closest of its top or bottom side.
+/// The route then follows the lines `clearance` away from each obstacle, preferring routes with
+/// fewer bends and avoiding running along any of the `existing` routes. Returns `None` if no
+/// route exists.
+#[allow(clippy::arithmetic_side_effects)]
+pub fn route(
+    start: Point,
+    end: Point,
+    obstacles: &[Rect],
+    existing: &[Vec<Point>],
+    clearance: ucum::Meter<f64>,
+) -> Option<Vec<Point>> {
+    let start_exit = exit_point(start, obstacles, clearance);
+    let end_exit = exit_point(end, obstacles, clearance);
+
+    let (xs, ys) = grid_lines([start_exit, end_exit], obstacles, clearance);
+    let columns = xs.len();
+    let position = |node: usize| -> Point {
+        (
+            xs.get(node % columns).copied().unwrap_or_default() * ucum::M,
+            ys.get(node / columns).copied().unwrap_or_default() * ucum::M,
+        )
+    };
+    let index_of = |point: Point| -> Option<usize> {
+        let column = xs
+            .iter()
+            .position(|x| (x - to_f64(point.0)).abs() < f64::EPSILON)?;
+        let row = ys
+            .iter()
+            .position(|y| (y - to_f64(point.1)).abs() < f64::EPSILON)?;
+        Some(row * columns + column)
+    };
+    let (start_node, end_node) = (index_of(start_exit)?, index_of(end_exit)?);
+    let blocked = |point: Point| {
+        obstacles
+            .iter()
+            .any(|obstacle| obstacle.contains(point, clearance / 2.0_f64))
+    };
+    let bend_cost = to_f64(clearance) * 4.0_f64;
+
+    let node_count = columns * ys.len();
+    let mut best = vec![[f64::INFINITY; 2]; node_count];
+    let mut previous: Vec<[Option<(usize, Direction)>; 2]> = vec![[None; 2]; node_count];
+    let mut queue = BinaryHeap::new();
+    for direction in [Direction::Horizontal, Direction::Vertical] {
+        if let Some(entry) = best.get_mut(start_node) {
+            entry[direction as usize] = 0.0_f64;
+        }
+        queue.push(State {
+            cost: 0.0_f64,
+            node: start_node,
+            direction,
+        });
+    }
+
+    while let Some(State {
+        cost,
+        node,
+        direction,
+    }) = queue.pop()
+    {
+        if node == end_node {
+            return Some(build_route(
+                start,
+                end,
+                (node, direction),
+                &previous,
+                position,
+            ));
+        }
+        if best
+            .get(node)
+            .is_some_and(|entry| cost > entry[direction as usize])
+        {
+            continue;
+        }
+        for (neighbour, next_direction) in neighbours(node, columns, ys.len()) {
+            let (from, to) = (position(node), position(neighbour));
+            let middle = ((from.0 + to.0) / 2.0_f64, (from.1 + to.1) / 2.0_f64);
+            // the start and end points may sit right next to other obstacles, so only the
+            // nodes in between are checked
+            if blocked(middle) || (neighbour != end_node && blocked(to)) {
+                continue;
+            }
+            let mut next_cost = cost + to_f64(to.0 - from.0).abs() + to_f64(to.1 - from.1).abs();
+            if next_direction != direction {
+                next_cost += bend_cost;
+            }
+            if existing.iter().any(|route| on_route(middle, route)) {
+                next_cost += bend_cost * 2.0_f64;
+            }
+            if let Some(entry) = best.get_mut(neighbour) {
+                if next_cost < entry[next_direction as usize] {
+                    entry[next_direction as usize] = next_cost;
+                    if let Some(link) = previous.get_mut(neighbour) {
+                        link[next_direction as usize] = Some((node, direction));
+                    }
+                    queue.push(State {
+                        cost: next_cost,
+                        node: neighbour,
+                        direction: next_direction,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// `grid_lines` returns the sorted x and y positions, in meters, of the candidate grid lines
+/// that routes can follow. These run through each of `points` and `clearance` away from the
+/// sides of every obstacle.
+#[allow(clippy::arithmetic_side_effects)]
+fn grid_lines(
+    points: [Point; 2],
+    obstacles: &[Rect],
+    clearance: ucum::Meter<f64>,
+) -> (Vec<f64>, Vec<f64>) {
+    let mut xs = points
+        .iter()
+        .map(|point| to_f64(point.0))
+        .collect::<Vec<_>>();
+    let mut ys = points
+        .iter()
+        .map(|point| to_f64(point.1))
+        .collect::<Vec<_>>();
+    for obstacle in obstacles {
+        xs.push(to_f64(obstacle.left - clearance));
+        xs.push(to_f64(obstacle.right + clearance));
+        ys.push(to_f64(obstacle.bottom - clearance));
+        ys.push(to_f64(obstacle.top + clearance));
+    }
+    for lines in [&mut xs, &mut ys] {
+        lines.sort_by(f64::total_cmp);
+        lines.dedup_by(|a, b| (*a - *b).abs() < f64::EPSILON);
+    }
+    (xs, ys)
+}
+
+/// `neighbours` returns the grid nodes next to `node` in a grid of `columns` by `rows` nodes,
+/// along with the direction of travel to reach them
+#[allow(clippy::arithmetic_side_effects)]
+fn neighbours(node: usize, columns: usize, rows: usize) -> Vec<(usize, Direction)> {
+    let (column, row) = (node % columns, node / columns);
+    let mut neighbours = Vec::new();
+    if column > 0 {
+        neighbours.push((node - 1, Direction::Horizontal));
+    }
+    if column + 1 < columns {
+        neighbours.push((node + 1, Direction::Horizontal));
+    }
+    if row > 0 {
+        neighbours.push((node - columns, Direction::Vertical));
+    }
+    if row + 1 < rows {
+        neighbours.push((node + columns, Direction::Vertical));
+    }
+    neighbours
+}
+
+/// `exit_point` returns the point just outside the obstacle containing `point`, directly above
+/// or below it, whichever is closer. Returns `point` if it isn't inside any obstacle.
+#[allow(clippy::arithmetic_side_effects)]
+fn exit_point(point: Point, obstacles: &[Rect], clearance: ucum::Meter<f64>) -> Point {
+    let zero = 0.0_f64 * ucum::M;
+    match obstacles
+        .iter()
+        .find(|obstacle| obstacle.contains(point, zero))
+    {
+        Some(obstacle) if obstacle.top - point.1 <= point.1 - obstacle.bottom => {
+            (point.0, obstacle.top + clearance)
+        }
+        Some(obstacle) => (point.0, obstacle.bottom - clearance),
+        None => point,
+    }
+}
+
+/// `on_route` returns true if `point` lies on one of the segments of `route`
+fn on_route(point: Point, route: &[Point]) -> bool {
+    route.windows(2).any(|pair| {
+        let (Some(start), Some(end)) = (pair.first(), pair.get(1)) else {
+            return false;
+        };
+        let (x, y) = (to_f64(point.0), to_f64(point.1));
+        let (start_x, start_y) = (to_f64(start.0), to_f64(start.1));
+        let (end_x, end_y) = (to_f64(end.0), to_f64(end.1));
+        let between = |value: f64, a: f64, b: f64| value >= a.min(b) && value <= a.max(b);
+        ((x - start_x).abs() < f64::EPSILON
+            && (x - end_x).abs() < f64::EPSILON
+            && between(y, start_y, end_y))
+            || ((y - start_y).abs() < f64::EPSILON
+                && (y - end_y).abs() < f64::EPSILON
+                && between(x, start_x, end_x))
+    })
+}
+
+/// `build_route` follows `previous` back from `last` to the start of the route, and returns the
+/// corners of the route from `start` to `end`
+fn build_route(
+    start: Point,
+    end: Point,
+    last: (usize, Direction),
+    previous: &[[Option<(usize, Direction)>; 2]],
+    position: impl Fn(usize) -> Point,
+) -> Vec<Point> {
+    let mut nodes = vec![last.0];
+    let mut current = last;
+    while let Some(Some(link)) = previous
+        .get(current.0)
+        .map(|entry| entry[current.1 as usize])
+    {
+        nodes.push(link.0);
+        current = link;
+    }
+    nodes.reverse();
+
+    let mut points = vec![start];
+    points.extend(nodes.into_iter().map(position));
+    points.push(end);
+    simplify(points)
+}
+
+/// `simplify` removes repeated points and points in the middle of straight segments
+#[allow(clippy::arithmetic_side_effects)]
+fn simplify(points: Vec<Point>) -> Vec<Point> {
+    let mut simplified: Vec<Point> = Vec::new();
+    for point in points {
+        if simplified.last() == Some(&point) {
+            continue;
+        }
+        if let [.., before, last] = simplified.as_slice() {
+            let straight = (before.0 == last.0 && last.0 == point.0)
+                || (before.1 == last.1 && last.1 == point.1);
+            if straight {
+                simplified.pop();
+            }
+        }
+        simplified.push(point);
+    }
+    simplified
+}
+
+/// `to_f64` returns `length` in meters
+#[allow(clippy::arithmetic_side_effects)]
+fn to_f64(length: ucum::Meter<f64>) -> f64 {
+    *(length / ucum::M).value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `point` returns the point `x`, `y` meters from the origin
+    #[allow(clippy::arithmetic_side_effects)]
+    fn point(x: f64, y: f64) -> Point {
+        (x * ucum::M, y * ucum::M)
+    }
+
+    /// `rect` returns the rectangle between `left`, `bottom` and `right`, `top` in meters
+    #[allow(clippy::arithmetic_side_effects)]
+    fn rect(left: f64, bottom: f64, right: f64, top: f64) -> Rect {
+        Rect {
+            left: left * ucum::M,
+            bottom: bottom * ucum::M,
+            right: right * ucum::M,
+            top: top * ucum::M,
+        }
+    }
+
+    /// `crosses` returns true if the straight segment from `from` to `to` passes through the
+    /// inside of `obstacle`
+    fn crosses(from: Point, to: Point, obstacle: &Rect) -> bool {
+        let (from_x, from_y, to_x, to_y) =
+            (to_f64(from.0), to_f64(from.1), to_f64(to.0), to_f64(to.1));
+        from_x.min(to_x) < to_f64(obstacle.right)
+            && from_x.max(to_x) > to_f64(obstacle.left)
+            && from_y.min(to_y) < to_f64(obstacle.top)
+            && from_y.max(to_y) > to_f64(obstacle.bottom)
+    }
+
+    #[test]
+    fn direct() {
+        let clearance = 0.01_f64 * ucum::M;
+        assert_eq!(
+            route(point(0.0, 0.0), point(0.1, 0.0), &[], &[], clearance),
+            Some(vec![point(0.0, 0.0), point(0.1, 0.0)])
+        );
+        // an obstacle off to the side doesn't change a straight route
+        assert_eq!(
+            route(
+                point(0.0, 0.0),
+                point(0.0, 0.1),
+                &[rect(0.05, 0.0, 0.1, 0.1)],
+                &[],
+                clearance
+            ),
+            Some(vec![point(0.0, 0.0), point(0.0, 0.1)])
+        );
+    }
+
+    #[test]
+    fn around_obstacle() {
+        let clearance = 0.01_f64 * ucum::M;
+        let obstacle = rect(0.08, 0.0, 0.12, 0.1);
+        let (start, end) = (point(0.0, 0.05), point(0.2, 0.05));
+        let points = route(start, end, &[obstacle], &[], clearance).unwrap();
+
+        // out to one side of the obstacle, across it and back, with two bends
+        assert_eq!(points.len(), 4);
+        assert_eq!(points.first(), Some(&start));
+        assert_eq!(points.last(), Some(&end));
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            assert!(
+                from.0 == to.0 || from.1 == to.1,
+                "segment is not orthogonal"
+            );
+            assert!(!crosses(from, to, &obstacle), "segment crosses obstacle");
+        }
+        let side = points[1].1;
+        assert!(side == obstacle.top + clearance || side == obstacle.bottom - clearance);
+    }
+
+    #[test]
+    fn leaves_obstacle_vertically() {
+        // connectors drawn on equipment leave it by the closest of its top or bottom side
+        let clearance = 0.01_f64 * ucum::M;
+        let equipment = rect(0.0, 0.0, 0.1, 0.1);
+        let points = route(
+            point(0.05, 0.08),
+            point(0.3, 0.08),
+            &[equipment],
+            &[],
+            clearance,
+        )
+        .unwrap();
+        assert_eq!(
+            points,
+            vec![
+                point(0.05, 0.08),
+                point(0.05, 0.11),
+                point(0.3, 0.11),
+                point(0.3, 0.08)
+            ]
+        );
+    }
+
+    #[test]
+    fn no_route() {
+        // the end is walled in on every side
+        let walls = [
+            rect(0.4, 0.4, 0.42, 0.6),
+            rect(0.58, 0.4, 0.6, 0.6),
+            rect(0.4, 0.4, 0.6, 0.42),
+            rect(0.4, 0.58, 0.6, 0.6),
+        ];
+        let clearance = 0.01_f64 * ucum::M;
+        assert_eq!(
+            route(point(0.0, 0.0), point(0.5, 0.5), &walls, &[], clearance),
+            None
+        );
+    }
+}
@@ -0,0 +1,1247 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use dimensioned::{f64prefixes, ucum, Dimensionless};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::file_types::{
+    cable::{Cable as FileCable, Connector as FileConnector, Termination as FileTermination},
+    cable_type::{
+        CableCore as FileCableCore, CableLayer as FileCableLayer, CableType as FileCableType,
+    },
+    connector_type::{ConnectorPin as FileConnectorPin, ConnectorType as FileConnectorType},
+    svg::Svg as FileSvg,
+    wire_type::WireType as FileWireType,
+    DataFile,
+};
+use crate::datatypes::internal_types::{
+    cable::Cable,
+    cable_type::{CableCore, CableType, LayerType},
+    connection::CableEnd,
+    connector_type::ConnectorType,
+    term_cable_type::{TermCableType, WireCable},
+    wire_type::WireType,
+};
+use crate::datatypes::util_types::CrossSection;
+
+/// `Harness` represents a WireViz harness document
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Harness {
+    /// connectors in harness, keyed by designator
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub connectors: BTreeMap<String, HarnessConnector>,
+    /// cables and wire bundles in harness, keyed by designator
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cables: BTreeMap<String, HarnessCable>,
+    /// connection sets, each alternating between connectors and cables
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connections: Vec<Vec<ConnectionEntry>>,
+}
+
+/// `HarnessConnector` represents a connector in a WireViz harness
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct HarnessConnector {
+    /// connector type or family, e.g. D-Sub, Molex KK 254
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub connector_type: Option<String>,
+    /// connector subtype, usually gender
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtype: Option<String>,
+    /// number of pins. Derived from `pins`, `pinlabels` or `pincolors` if omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pincount: Option<u64>,
+    /// pin names. Defaults to 1 to `pincount`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pins: Option<Vec<PinRef>>,
+    /// pin labels, in the same order as `pins`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinlabels: Option<Vec<PinRef>>,
+    /// pin colors, in the same order as `pins`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pincolors: Option<Vec<String>>,
+    /// manufacturer of connector
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    /// manufacturer part number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mpn: Option<String>,
+    /// internal part number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pn: Option<String>,
+    /// supplier of connector
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supplier: Option<String>,
+    /// supplier part number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spn: Option<String>,
+    /// free text notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// `HarnessCable` represents a cable or wire bundle in a WireViz harness
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct HarnessCable {
+    /// cable type, e.g. SOOW
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub cable_type: Option<String>,
+    /// set to `bundle` for loose wires that are not inside a jacket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// number of wires. Derived from `colors` if omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wirecount: Option<u64>,
+    /// wire colors, in wire order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<Vec<String>>,
+    /// name of a color code used to generate `colors`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_code: Option<String>,
+    /// conductor size, either a number in `gauge_unit` or a string such as `0.25 mm2` or `20 AWG`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gauge: Option<Quantity>,
+    /// unit of `gauge` if it is a number. Defaults to mm2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gauge_unit: Option<String>,
+    /// cable length, either a number in `length_unit` or a string such as `1.5 m`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<Quantity>,
+    /// unit of `length` if it is a number. Defaults to m
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length_unit: Option<String>,
+    /// whether the cable is shielded, or the color of the shield
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shield: Option<Shield>,
+    /// manufacturer of cable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    /// manufacturer part number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mpn: Option<String>,
+    /// internal part number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pn: Option<String>,
+    /// supplier of cable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supplier: Option<String>,
+    /// supplier part number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spn: Option<String>,
+    /// free text notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// `PinRef` is a pin name or number, a wire number, or a range such as `1-4`
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum PinRef {
+    /// numeric pin or wire
+    Number(u64),
+    /// named pin, range of pins, or `s` for a cable shield
+    Name(String),
+}
+
+impl fmt::Display for PinRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PinRef::Number(number) => write!(f, "{number}"),
+            PinRef::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl From<&str> for PinRef {
+    fn from(name: &str) -> Self {
+        name.parse()
+            .map_or_else(|_| PinRef::Name(name.to_string()), PinRef::Number)
+    }
+}
+
+/// `PinList` is the list of pins or wires in one entry of a connection set
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum PinList {
+    /// several pins or wires
+    Many(Vec<PinRef>),
+    /// a single pin, wire or range
+    One(PinRef),
+}
+
+/// `ConnectionEntry` is one item of a WireViz connection set
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ConnectionEntry {
+    /// a single entry map of designator to the pins or wires used
+    Pins(BTreeMap<String, PinList>),
+    /// a bare designator, using all pins or wires in order
+    Designator(String),
+}
+
+/// `Quantity` is a number, or a number followed by a unit
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum Quantity {
+    /// number in the default unit
+    Number(f64),
+    /// number followed by a unit, e.g. `0.25 mm2`
+    Text(String),
+}
+
+impl Quantity {
+    /// splits the quantity into its value and unit, using `default_unit` if none is given
+    fn split(&self, default_unit: &str) -> Result<(f64, String), Error> {
+        match self {
+            Quantity::Number(value) => Ok((*value, default_unit.to_string())),
+            Quantity::Text(text) => {
+                let text = text.trim();
+                let unit_start = text
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+                    .unwrap_or(text.len());
+                let (value, unit) = text.split_at(unit_start);
+                let value = value.parse::<f64>().map_err(|e| {
+                    Error::ConversionError(format!("{text} does not start with a number: {e}"))
+                })?;
+                let unit = unit.trim();
+                Ok((
+                    value,
+                    if unit.is_empty() { default_unit } else { unit }.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// returns the quantity as a conductor cross sectional area in mm^2
+    #[allow(clippy::arithmetic_side_effects)]
+    fn to_square_millimeters(&self, default_unit: Option<&str>) -> Result<f64, Error> {
+        let (value, unit) = self.split(default_unit.unwrap_or("mm2"))?;
+        match unit.to_lowercase().as_str() {
+            "mm2" | "mm²" | "mm^2" => Ok(value),
+            "awg" => {
+                // diameter in mm of a solid conductor of gauge `value`
+                let diameter = 0.127 * 92.0_f64.powf((36.0 - value) / 39.0);
+                Ok(std::f64::consts::PI / 4.0 * diameter.powi(2))
+            }
+            _ => Err(Error::ConversionError(format!(
+                "gauge unit {unit} not recognized"
+            ))),
+        }
+    }
+
+    /// returns the quantity as a length in meters
+    #[allow(clippy::arithmetic_side_effects)]
+    fn to_meters(&self, default_unit: Option<&str>) -> Result<f64, Error> {
+        let (value, unit) = self.split(default_unit.unwrap_or("m"))?;
+        match unit.to_lowercase().as_str() {
+            "m" => Ok(value),
+            "cm" => Ok(value / 100.0),
+            "mm" => Ok(value / 1000.0),
+            "ft" => Ok(value * 0.3048),
+            "in" => Ok(value * 0.0254),
+            _ => Err(Error::ConversionError(format!(
+                "length unit {unit} not recognized"
+            ))),
+        }
+    }
+}
+
+/// `Shield` is the `shield` attribute of a WireViz cable
+#[non_exhaustive]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum Shield {
+    /// cable is, or is not, shielded
+    Present(bool),
+    /// cable is shielded, with a shield of the given color
+    Color(String),
+}
+
+/// `Resolved` is a connection set entry with its pins or wires converted to 1 indexed numbers
+enum Resolved {
+    /// pins of a connector, as positions in its pin list
+    Connector {
+        /// designator of connector
+        designator: String,
+        /// pin positions
+        pins: Vec<u64>,
+    },
+    /// wires of a cable. `None` represents the shield
+    Cable {
+        /// designator of cable
+        designator: String,
+        /// wire numbers
+        wires: Vec<Option<u64>>,
+    },
+}
+
+/// `import_harness` converts a WireViz harness YAML document into a `DataFile`
+///
+/// Every WireViz connector becomes a `ConnectorType`, and every cable or bundle becomes a
+/// `CableType` and a `Cable` instance of that type, all using the WireViz designator as their id.
+/// One `WireType` is created per cable and wire color, and used for the cable cores, which are
+/// numbered from 1. Connection sets are turned into `Termination`s on the cable ends: the
+/// connector before a cable in a set is placed on `end1`, and the connector after it on `end2`.
+///
+/// WireViz color codes are not expanded, and shield connections are skipped with a warning, as
+/// neither can be represented in the `DataFile`.
+///
+/// # Errors
+///
+/// Will error if:
+/// - `harness_yaml` is not a valid WireViz document
+/// - a connection set references an unknown designator, pin or wire
+/// - a connection set places two connectors or two cables next to each other
+/// - the connector and cable in a connection set use different numbers of pins and wires
+/// - a gauge or length uses an unrecognized unit
+pub fn import_harness(harness_yaml: &str) -> Result<DataFile, Error> {
+    let harness: Harness = serde_yaml::from_str(harness_yaml)?;
+
+    let mut connector_types = HashMap::new();
+    for (designator, connector) in &harness.connectors {
+        connector_types.insert(designator.clone(), connector_type(designator, connector)?);
+    }
+
+    let mut wire_types = HashMap::new();
+    let mut cable_types = HashMap::new();
+    let mut cables = HashMap::new();
+    for (designator, cable) in &harness.cables {
+        cable_types.insert(
+            designator.clone(),
+            cable_type(designator, cable, &mut wire_types)?,
+        );
+        cables.insert(
+            designator.clone(),
+            FileCable {
+                id: designator.clone(),
+                cable_type: designator.clone(),
+                identifier: None,
+                description: cable.notes.clone(),
+                length: cable
+                    .length
+                    .as_ref()
+                    .map(|length| length.to_meters(cable.length_unit.as_deref()))
+                    .transpose()?
+                    .unwrap_or_default(),
+                pathway: None,
+                end1: Vec::new(),
+                end2: Vec::new(),
+            },
+        );
+    }
+
+    for connection_set in &harness.connections {
+        let resolved = connection_set
+            .iter()
+            .map(|entry| resolve_entry(&harness, entry))
+            .collect::<Result<Vec<_>, _>>()?;
+        for pair in resolved.windows(2) {
+            let [first, second] = pair else {
+                continue;
+            };
+            match (first, second) {
+                (Resolved::Connector { designator, pins }, Resolved::Cable {
+                    designator: cable,
+                    wires,
+                }) => terminate(&mut cables, cable, CableEnd::End1, designator, pins, wires)?,
+                (Resolved::Cable {
+                    designator: cable,
+                    wires,
+                }, Resolved::Connector { designator, pins }) => {
+                    terminate(&mut cables, cable, CableEnd::End2, designator, pins, wires)?;
+                }
+                (Resolved::Connector { designator, .. }, Resolved::Connector {
+                    designator: other_designator,
+                    ..
+                })
+                | (Resolved::Cable { designator, .. }, Resolved::Cable {
+                    designator: other_designator,
+                    ..
+                }) => {
+                    return Err(Error::ConversionError(format!(
+                        "{designator} and {other_designator} are next to each other in a connection set. Connectors and cables must alternate"
+                    )))
+                }
+            }
+        }
+    }
+
+    Ok(DataFile {
+        wire_types: (!wire_types.is_empty()).then_some(wire_types),
+        cable_types: (!cable_types.is_empty()).then_some(cable_types),
+        connector_types: (!connector_types.is_empty()).then_some(connector_types),
+        cables: (!cables.is_empty()).then_some(cables),
+        ..Default::default()
+    })
+}
+
+/// returns the pins of a WireViz connector, generating them from the pin count if they are not
+/// listed explicitly
+fn connector_pins(designator: &str, connector: &HarnessConnector) -> Result<Vec<PinRef>, Error> {
+    let pins = if let Some(pins) = &connector.pins {
+        pins.clone()
+    } else {
+        let pincount = connector
+            .pincount
+            .or_else(|| {
+                connector
+                    .pinlabels
+                    .as_ref()
+                    .map(Vec::len)
+                    .or_else(|| connector.pincolors.as_ref().map(Vec::len))
+                    .and_then(|count| u64::try_from(count).ok())
+            })
+            .unwrap_or_default();
+        (1..=pincount).map(PinRef::Number).collect()
+    };
+    let pin_count = u64::try_from(pins.len()).ok();
+    if connector.pincount.is_some() && connector.pincount != pin_count {
+        return Err(Error::ConversionError(format!(
+            "pincount of connector {designator} does not match its pin list"
+        )));
+    }
+    if connector
+        .pinlabels
+        .as_ref()
+        .is_some_and(|labels| labels.len() != pins.len())
+    {
+        return Err(Error::ConversionError(format!(
+            "pinlabels of connector {designator} do not match its pin count"
+        )));
+    }
+    Ok(pins)
+}
+
+/// returns the number of wires in a WireViz cable
+fn wirecount(designator: &str, cable: &HarnessCable) -> Result<u64, Error> {
+    let color_count = cable
+        .colors
+        .as_ref()
+        .and_then(|colors| u64::try_from(colors.len()).ok());
+    match (cable.wirecount, color_count) {
+        (Some(wirecount), Some(color_count)) if wirecount != color_count => {
+            Err(Error::ConversionError(format!(
+                "wirecount of cable {designator} does not match its colors"
+            )))
+        }
+        (Some(count), _) | (None, Some(count)) => Ok(count),
+        (None, None) => Err(Error::ConversionError(format!(
+            "cable {designator} must specify wirecount or colors"
+        ))),
+    }
+}
+
+/// converts a WireViz connector into a `ConnectorType`
+fn connector_type(
+    designator: &str,
+    connector: &HarnessConnector,
+) -> Result<FileConnectorType, Error> {
+    let pins = connector_pins(designator, connector)?;
+    Ok(FileConnectorType {
+        manufacturer: connector.manufacturer.clone(),
+        model: connector.connector_type.clone(),
+        part_number: connector.pn.clone(),
+        manufacturer_part_number: connector.mpn.clone(),
+        supplier: connector.supplier.clone(),
+        supplier_part_number: connector.spn.clone(),
+        description: connector.notes.clone(),
+        mount_type: None,
+        panel_cutout: None,
+        gender: connector.subtype.clone().filter(|subtype| {
+            subtype.eq_ignore_ascii_case("male") || subtype.eq_ignore_ascii_case("female")
+        }),
+        height: 0.0,
+        width: 0.0,
+        depth: 0.0,
+        diameter: None,
+        pins: pins
+            .iter()
+            .enumerate()
+            .map(|(index, pin)| FileConnectorPin {
+                id: pin.to_string(),
+                label: connector
+                    .pinlabels
+                    .as_ref()
+                    .and_then(|labels| labels.get(index))
+                    .map(ToString::to_string),
+                signal_type: None,
+                color: connector
+                    .pincolors
+                    .as_ref()
+                    .and_then(|colors| colors.get(index))
+                    .cloned(),
+                visual_rep: None,
+                gender: None,
+            })
+            .collect(),
+        visual_rep: FileSvg::default(),
+    })
+}
+
+/// converts a WireViz cable into a `CableType`, adding the `WireType`s used for its cores to
+/// `wire_types`
+fn cable_type(
+    designator: &str,
+    cable: &HarnessCable,
+    wire_types: &mut HashMap<String, FileWireType>,
+) -> Result<FileCableType, Error> {
+    let gauge = cable
+        .gauge
+        .as_ref()
+        .map(|gauge| gauge.to_square_millimeters(cable.gauge_unit.as_deref()))
+        .transpose()?
+        .unwrap_or_default();
+    if cable.color_code.is_some() && cable.colors.is_none() {
+        warn! {"color_code of cable {designator} is not supported. Wire colors will not be imported"}
+    }
+
+    let mut cable_cores = HashMap::new();
+    for core in 1..=wirecount(designator, cable)? {
+        let color = usize::try_from(core)
+            .ok()
+            .and_then(|core| cable.colors.as_ref()?.get(core.checked_sub(1)?));
+        let wire_type_id = match color {
+            Some(color) => format!("{designator}_{color}"),
+            None => format!("{designator}_wire"),
+        };
+        wire_types
+            .entry(wire_type_id.clone())
+            .or_insert_with(|| FileWireType {
+                insulated: true,
+                conductor_cross_sect_area: gauge,
+                insul_color: color.cloned(),
+                ..Default::default()
+            });
+        cable_cores.insert(
+            core.to_string(),
+            FileCableCore {
+                type_str: wire_type_id,
+                is_wire: true,
+            },
+        );
+    }
+
+    let insul_layers = match &cable.shield {
+        Some(Shield::Present(true)) => vec![shield_layer(None)],
+        Some(Shield::Color(color)) => vec![shield_layer(Some(color.clone()))],
+        _ => Vec::new(),
+    };
+
+    Ok(FileCableType {
+        manufacturer: cable.manufacturer.clone(),
+        model: None,
+        part_number: cable.pn.clone(),
+        manufacturer_part_number: cable.mpn.clone(),
+        supplier: cable.supplier.clone(),
+        supplier_part_number: cable.spn.clone(),
+        cable_type_code: cable.cable_type.clone(),
+        cross_sect_area: 0.0,
+        cross_section: if cable.category.as_deref() == Some("bundle") {
+            "Siamese"
+        } else {
+            "Circular"
+        }
+        .to_string(),
+        height: 0.0,
+        width: 0.0,
+        diameter: None,
+        cable_cores,
+        insul_layers,
+    })
+}
+
+/// returns the outer shield layer of an imported cable
+fn shield_layer(color: Option<String>) -> FileCableLayer {
+    FileCableLayer {
+        layer_number: 1,
+        layer_type: "Shield".to_string(),
+        material: None,
+        volt_rating: None,
+        temp_rating: None,
+        color,
+    }
+}
+
+/// expands pin ranges such as `1-4` into individual pin numbers
+fn expand_ranges(pins: &[PinRef]) -> Vec<PinRef> {
+    let mut expanded = Vec::new();
+    for pin in pins {
+        let range = match pin {
+            PinRef::Name(name) => name.split_once('-').and_then(|(start, end)| {
+                Some((
+                    start.trim().parse::<u64>().ok()?,
+                    end.trim().parse::<u64>().ok()?,
+                ))
+            }),
+            PinRef::Number(_) => None,
+        };
+        match range {
+            Some((start, end)) if start <= end => {
+                expanded.extend((start..=end).map(PinRef::Number));
+            }
+            Some((start, end)) => expanded.extend((end..=start).rev().map(PinRef::Number)),
+            None => expanded.push(pin.clone()),
+        }
+    }
+    expanded
+}
+
+/// resolves one entry of a connection set against the connectors and cables in `harness`
+fn resolve_entry(harness: &Harness, entry: &ConnectionEntry) -> Result<Resolved, Error> {
+    let (designator, pin_list) = match entry {
+        ConnectionEntry::Designator(designator) => (designator, None),
+        ConnectionEntry::Pins(pins) => {
+            let mut pins = pins.iter();
+            match (pins.next(), pins.next()) {
+                (Some((designator, pin_list)), None) => (designator, Some(pin_list)),
+                _ => {
+                    return Err(Error::ConversionError(
+                        "each entry in a connection set must have exactly one designator"
+                            .to_string(),
+                    ))
+                }
+            }
+        }
+    };
+    let pin_refs = pin_list.map(|pin_list| match pin_list {
+        PinList::Many(pins) => expand_ranges(pins),
+        PinList::One(pin) => expand_ranges(std::slice::from_ref(pin)),
+    });
+
+    if let Some(connector) = harness.connectors.get(designator) {
+        let connector_pins = connector_pins(designator, connector)?;
+        let pins = match pin_refs {
+            Some(pin_refs) => pin_refs
+                .iter()
+                .map(|pin_ref| {
+                    let name = pin_ref.to_string();
+                    connector_pins
+                        .iter()
+                        .position(|pin| pin.to_string() == name)
+                        .or_else(|| {
+                            connector
+                                .pinlabels
+                                .as_ref()?
+                                .iter()
+                                .position(|label| label.to_string() == name)
+                        })
+                        .and_then(|index| u64::try_from(index).ok()?.checked_add(1))
+                        .ok_or_else(|| {
+                            Error::ConversionError(format!(
+                                "pin {name} not found on connector {designator}"
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => (1..=u64::try_from(connector_pins.len()).unwrap_or_default()).collect(),
+        };
+        Ok(Resolved::Connector {
+            designator: designator.clone(),
+            pins,
+        })
+    } else if let Some(cable) = harness.cables.get(designator) {
+        let wirecount = wirecount(designator, cable)?;
+        let wires = match pin_refs {
+            Some(pin_refs) => pin_refs
+                .iter()
+                .map(|pin_ref| match pin_ref {
+                    PinRef::Number(wire) if (1..=wirecount).contains(wire) => Ok(Some(*wire)),
+                    PinRef::Name(name)
+                        if name.eq_ignore_ascii_case("s") && cable.shield.is_some() =>
+                    {
+                        Ok(None)
+                    }
+                    _ => Err(Error::ConversionError(format!(
+                        "wire {pin_ref} not found in cable {designator}"
+                    ))),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => (1..=wirecount).map(Some).collect(),
+        };
+        Ok(Resolved::Cable {
+            designator: designator.clone(),
+            wires,
+        })
+    } else {
+        Err(Error::ConversionError(format!(
+            "{designator} is not defined as a connector or cable"
+        )))
+    }
+}
+
+/// adds the terminations between `connector` and one end of `cable` to the cable instance
+fn terminate(
+    cables: &mut HashMap<String, FileCable>,
+    cable: &str,
+    end: CableEnd,
+    connector: &str,
+    pins: &[u64],
+    wires: &[Option<u64>],
+) -> Result<(), Error> {
+    if pins.len() != wires.len() {
+        return Err(Error::ConversionError(format!(
+            "connector {connector} uses {} pins but cable {cable} uses {} wires in the same connection set",
+            pins.len(),
+            wires.len()
+        )));
+    }
+    let Some(file_cable) = cables.get_mut(cable) else {
+        return Err(Error::ConversionError(format!("cable {cable} not found")));
+    };
+    let cable_end = match end {
+        CableEnd::End1 => &mut file_cable.end1,
+        CableEnd::End2 => &mut file_cable.end2,
+    };
+    let position = if let Some(position) = cable_end
+        .iter()
+        .position(|end_connector| end_connector.connector_type == connector)
+    {
+        position
+    } else {
+        cable_end.push(FileConnector {
+            connector_type: connector.to_string(),
+            terminations: None,
+        });
+        cable_end.len().saturating_sub(1)
+    };
+    let terminations = cable_end[position]
+        .terminations
+        .get_or_insert_with(Vec::new);
+    for (pin, wire) in pins.iter().zip(wires) {
+        let Some(core) = wire else {
+            warn! {"shield of cable {cable} connected to pin {pin} of connector {connector} cannot be imported. Skipping"}
+            continue;
+        };
+        if !terminations
+            .iter()
+            .any(|termination| termination.core == Some(*core) && termination.pin == Some(*pin))
+        {
+            terminations.push(FileTermination {
+                core: Some(*core),
+                pin: Some(*pin),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// `EndConnector` is a connector on one end of an exported cable, with its terminations
+/// reduced to (core, pin) pairs
+struct EndConnector {
+    /// connector type of connector
+    connector_type: Rc<RefCell<ConnectorType>>,
+    /// (core, pin) pairs of connector
+    terminations: Vec<(u64, u64)>,
+}
+
+impl EndConnector {
+    /// `new` returns a connector of `connector_type`, keeping only the `terminations` that
+    /// specify both a core and a pin
+    fn new(
+        connector_type: &Rc<RefCell<ConnectorType>>,
+        terminations: impl Iterator<Item = (Option<u64>, Option<u64>)>,
+    ) -> Self {
+        Self {
+            connector_type: Rc::clone(connector_type),
+            terminations: terminations
+                .filter_map(|(core, pin)| Some((core?, pin?)))
+                .collect(),
+        }
+    }
+}
+
+/// `export_cable` writes a `Cable`, its connectors and their `Termination`s as a WireViz harness
+/// YAML document
+///
+/// The cable uses its identifier, or id if unset, as its designator, and the connectors are
+/// named X1, X2, ... starting with `end1`.
+///
+/// # Errors
+///
+/// Will error if:
+/// - a `Termination` references a pin that does not exist on its `ConnectorType`
+/// - the harness fails to serialize
+pub fn export_cable(cable: &Cable) -> Result<String, Error> {
+    let mut harness_cable = cable_type_to_harness(&cable.cable_type.borrow());
+    harness_cable.length = Some(Quantity::Number(meters(cable.length)));
+    harness_cable.notes.clone_from(&cable.description);
+
+    let [end1, end2] = [&cable.end1, &cable.end2].map(|end| {
+        end.iter()
+            .map(|connector| {
+                EndConnector::new(
+                    &connector.connector_type,
+                    connector
+                        .terminations
+                        .iter()
+                        .flatten()
+                        .map(|termination| (termination.core, termination.pin)),
+                )
+            })
+            .collect::<Vec<_>>()
+    });
+
+    harness_yaml(
+        cable.identifier.as_ref().unwrap_or(&cable.id),
+        harness_cable,
+        &end1,
+        &end2,
+    )
+}
+
+/// `export_term_cable_type` writes a `TermCableType`, its connectors and their `Termination`s as a
+/// WireViz harness YAML document
+///
+/// The cable uses the id of the `TermCableType` as its designator, and the connectors are named
+/// X1, X2, ... starting with `end1`. The actual length is used if set, otherwise the nominal
+/// length.
+///
+/// # Errors
+///
+/// Will error if:
+/// - a `Termination` references a pin that does not exist on its `ConnectorType`
+/// - the harness fails to serialize
+pub fn export_term_cable_type(term_cable_type: &TermCableType) -> Result<String, Error> {
+    let mut harness_cable = match &term_cable_type.wire_cable {
+        WireCable::CableType(cable_type) => cable_type_to_harness(&cable_type.borrow()),
+        WireCable::WireType(wire_type) => wire_type_to_harness(&wire_type.borrow()),
+    };
+    harness_cable.length = term_cable_type
+        .actual_length
+        .or(term_cable_type.nominal_length)
+        .map(|length| Quantity::Number(meters(length)));
+    harness_cable.notes.clone_from(&term_cable_type.description);
+    for (field, value) in [
+        (
+            &mut harness_cable.manufacturer,
+            &term_cable_type.manufacturer,
+        ),
+        (
+            &mut harness_cable.mpn,
+            &term_cable_type.manufacturer_part_number,
+        ),
+        (&mut harness_cable.pn, &term_cable_type.part_number),
+        (&mut harness_cable.supplier, &term_cable_type.supplier),
+        (
+            &mut harness_cable.spn,
+            &term_cable_type.supplier_part_number,
+        ),
+    ] {
+        if value.is_some() {
+            field.clone_from(value);
+        }
+    }
+
+    let [end1, end2] = [&term_cable_type.end1, &term_cable_type.end2].map(|end| {
+        end.iter()
+            .map(|connector| {
+                EndConnector::new(
+                    &connector.connector_type,
+                    connector
+                        .terminations
+                        .iter()
+                        .flatten()
+                        .map(|termination| (termination.core, termination.pin)),
+                )
+            })
+            .collect::<Vec<_>>()
+    });
+
+    harness_yaml(&term_cable_type.id, harness_cable, &end1, &end2)
+}
+
+/// returns `length` in meters
+#[allow(clippy::arithmetic_side_effects)]
+fn meters(length: ucum::Meter<f64>) -> f64 {
+    *(length / ucum::M).value()
+}
+
+/// returns `area` in mm^2
+#[allow(clippy::arithmetic_side_effects)]
+fn square_millimeters(area: ucum::Meter2<f64>) -> f64 {
+    *(area / (ucum::M2 * f64prefixes::MICRO)).value()
+}
+
+/// converts a `CableType` into a WireViz cable, without length
+fn cable_type_to_harness(cable_type: &CableType) -> HarnessCable {
+    let mut cores = cable_type.cable_cores.iter().collect::<Vec<_>>();
+    cores.sort_by(|(a, _), (b, _)| (a.parse::<u64>().ok(), *a).cmp(&(b.parse::<u64>().ok(), *b)));
+    // (insulation color, conductor area) of each core, if every core is a wire
+    let wires = cores
+        .iter()
+        .map(|(_, core)| match core {
+            CableCore::WireType(wire_type) => {
+                let wire_type = wire_type.borrow();
+                Some((
+                    wire_type.insul_color.clone(),
+                    wire_type.conductor_cross_sect_area,
+                ))
+            }
+            CableCore::CableType(_) => None,
+        })
+        .collect::<Option<Vec<_>>>();
+
+    let colors = wires.as_ref().and_then(|wires| {
+        wires
+            .iter()
+            .map(|(color, _)| color.clone())
+            .collect::<Option<Vec<_>>>()
+    });
+    let gauge = wires.as_ref().and_then(|wires| {
+        let (_, first) = wires.first()?;
+        (wires.iter().all(|(_, area)| area == first) && square_millimeters(*first) > 0.0_f64)
+            .then(|| Quantity::Number(square_millimeters(*first)))
+    });
+    let shield = cable_type
+        .insul_layers
+        .iter()
+        .find(|layer| matches!(layer.layer_type, LayerType::Shield | LayerType::Screen))
+        .map(|layer| match &layer.color {
+            Some(color) => Shield::Color(color.clone()),
+            None => Shield::Present(true),
+        });
+
+    HarnessCable {
+        cable_type: cable_type.cable_type_code.clone(),
+        category: (cable_type.cross_section == CrossSection::Siamese).then(|| "bundle".to_string()),
+        wirecount: u64::try_from(cores.len()).ok(),
+        colors,
+        gauge,
+        shield,
+        manufacturer: cable_type.manufacturer.clone(),
+        mpn: cable_type.manufacturer_part_number.clone(),
+        pn: cable_type.part_number.clone(),
+        supplier: cable_type.supplier.clone(),
+        spn: cable_type.supplier_part_number.clone(),
+        ..Default::default()
+    }
+}
+
+/// converts a `WireType` into a single wire WireViz cable, without length
+fn wire_type_to_harness(wire_type: &WireType) -> HarnessCable {
+    let gauge = square_millimeters(wire_type.conductor_cross_sect_area);
+    HarnessCable {
+        cable_type: wire_type.wire_type_code.clone(),
+        wirecount: Some(1),
+        colors: wire_type.insul_color.clone().map(|color| vec![color]),
+        gauge: (gauge > 0.0).then_some(Quantity::Number(gauge)),
+        manufacturer: wire_type.manufacturer.clone(),
+        mpn: wire_type.manufacturer_part_number.clone(),
+        pn: wire_type.part_number.clone(),
+        supplier: wire_type.supplier.clone(),
+        spn: wire_type.supplier_part_number.clone(),
+        ..Default::default()
+    }
+}
+
+/// converts a `ConnectorType` into a WireViz connector
+fn connector_type_to_harness(connector_type: &ConnectorType) -> HarnessConnector {
+    let labels = connector_type
+        .pins
+        .iter()
+        .any(|pin| pin.label.is_some())
+        .then(|| {
+            connector_type
+                .pins
+                .iter()
+                .map(|pin| PinRef::Name(pin.label.clone().unwrap_or_default()))
+                .collect()
+        });
+    let colors = connector_type
+        .pins
+        .iter()
+        .map(|pin| pin.color.clone())
+        .collect::<Option<Vec<_>>>()
+        .filter(|colors| !colors.is_empty());
+    HarnessConnector {
+        connector_type: Some(
+            connector_type
+                .model
+                .clone()
+                .unwrap_or_else(|| connector_type.id.clone()),
+        ),
+        subtype: connector_type.gender.clone(),
+        pins: Some(
+            connector_type
+                .pins
+                .iter()
+                .map(|pin| PinRef::from(pin.id.as_str()))
+                .collect(),
+        ),
+        pinlabels: labels,
+        pincolors: colors,
+        manufacturer: connector_type.manufacturer.clone(),
+        mpn: connector_type.manufacturer_part_number.clone(),
+        pn: connector_type.part_number.clone(),
+        supplier: connector_type.supplier.clone(),
+        spn: connector_type.supplier_part_number.clone(),
+        notes: connector_type.description.clone(),
+        ..Default::default()
+    }
+}
+
+/// builds and serializes a harness containing one cable and the connectors on both of its ends
+fn harness_yaml(
+    designator: &str,
+    harness_cable: HarnessCable,
+    end1: &[EndConnector],
+    end2: &[EndConnector],
+) -> Result<String, Error> {
+    let mut harness = Harness::default();
+    let connector_designators = (1..)
+        .map(|number: usize| format!("X{number}"))
+        .take(end1.len().saturating_add(end2.len()))
+        .collect::<Vec<_>>();
+    let (end1_designators, end2_designators) = connector_designators.split_at(end1.len());
+    for (connector_designator, connector) in
+        connector_designators.iter().zip(end1.iter().chain(end2))
+    {
+        harness.connectors.insert(
+            connector_designator.clone(),
+            connector_type_to_harness(&connector.connector_type.borrow()),
+        );
+    }
+
+    for ((end1_index, end2_index), wires) in group_wires(end1, end2) {
+        let mut connection_set = Vec::new();
+        if let Some(index) = end1_index {
+            connection_set.push(connector_entry(
+                &end1_designators[index],
+                &end1[index].connector_type.borrow(),
+                wires.iter().filter_map(|(_, pin, _)| *pin),
+            )?);
+        }
+        connection_set.push(ConnectionEntry::Pins(BTreeMap::from([(
+            designator.to_string(),
+            PinList::Many(
+                wires
+                    .iter()
+                    .map(|(core, _, _)| PinRef::Number(*core))
+                    .collect(),
+            ),
+        )])));
+        if let Some(index) = end2_index {
+            connection_set.push(connector_entry(
+                &end2_designators[index],
+                &end2[index].connector_type.borrow(),
+                wires.iter().filter_map(|(_, _, pin)| *pin),
+            )?);
+        }
+        harness.connections.push(connection_set);
+    }
+
+    harness.cables.insert(designator.to_string(), harness_cable);
+    Ok(serde_yaml::to_string(&harness)?)
+}
+
+/// `WireGroups` maps a pair of connector indices, one on each end, to the (core, end1 pin, end2 pin)
+/// of every wire running between them. `None` is used where a wire end is not terminated.
+type WireGroups = BTreeMap<(Option<usize>, Option<usize>), Vec<(u64, Option<u64>, Option<u64>)>>;
+
+/// groups the wires of a cable by the pair of connectors they run between, so each group can be
+/// written as one connection set
+fn group_wires(end1: &[EndConnector], end2: &[EndConnector]) -> WireGroups {
+    // every termination as (core, (connector index, pin))
+    let flatten_terminations = |end: &[EndConnector]| {
+        end.iter()
+            .enumerate()
+            .flat_map(|(index, connector)| {
+                connector
+                    .terminations
+                    .iter()
+                    .map(move |&(core, pin)| (core, (index, pin)))
+            })
+            .collect::<Vec<_>>()
+    };
+    let end1_terminations = flatten_terminations(end1);
+    let end2_terminations = flatten_terminations(end2);
+    let mut cores = end1_terminations
+        .iter()
+        .chain(&end2_terminations)
+        .map(|(core, _)| *core)
+        .collect::<Vec<_>>();
+    cores.sort_unstable();
+    cores.dedup();
+
+    // (connector index, pin) of each termination of `core`, or a single `None` if unterminated
+    let core_ends = |terminations: &[(u64, (usize, u64))], core: u64| {
+        let core_terminations = terminations
+            .iter()
+            .filter(|(termination_core, _)| *termination_core == core)
+            .map(|(_, end)| Some(*end))
+            .collect::<Vec<_>>();
+        if core_terminations.is_empty() {
+            vec![None]
+        } else {
+            core_terminations
+        }
+    };
+
+    let mut groups = WireGroups::new();
+    for core in cores {
+        for from in core_ends(&end1_terminations, core) {
+            for to in core_ends(&end2_terminations, core) {
+                groups
+                    .entry((from.map(|(index, _)| index), to.map(|(index, _)| index)))
+                    .or_default()
+                    .push((core, from.map(|(_, pin)| pin), to.map(|(_, pin)| pin)));
+            }
+        }
+    }
+    groups
+}
+
+/// builds the connection set entry for the given 1 indexed pin positions of a connector
+fn connector_entry(
+    designator: &str,
+    connector_type: &ConnectorType,
+    pins: impl Iterator<Item = u64>,
+) -> Result<ConnectionEntry, Error> {
+    let pins = pins
+        .map(|pin| {
+            usize::try_from(pin)
+                .ok()
+                .and_then(|pin| connector_type.pins.get(pin.checked_sub(1)?))
+                .map(|connector_pin| PinRef::from(connector_pin.id.as_str()))
+                .ok_or_else(|| {
+                    Error::ConversionError(format!(
+                        "pin {pin} not found on connector type {}",
+                        connector_type.id
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ConnectionEntry::Pins(BTreeMap::from([(
+        designator.to_string(),
+        PinList::Many(pins),
+    )])))
+}
+
+#[non_exhaustive]
+#[derive(Debug)]
+/// `Error` is the error returned by WireViz import and export
+pub enum Error {
+    /// Error resulting from reading or writing WireViz YAML
+    YamlError(String),
+    /// Error resulting from data that cannot be converted between formats
+    ConversionError(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::YamlError(ref e) => write!(f, "YAML Error: {e}"),
+            Error::ConversionError(ref e) => write!(f, "Conversion Error: {e}"),
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::YamlError(format!("{e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cdm_traits::merge::ComparedStruct;
+
+    use crate::datatypes::internal_types::{Library, Project};
+
+    /// `HARNESS` is a three wire cable between two connectors
+    const HARNESS: &str = "
+connectors:
+  X1:
+    type: Molex KK 254
+    subtype: female
+    pincount: 4
+  X2:
+    type: Molex KK 254
+    subtype: female
+    pincount: 3
+cables:
+  W1:
+    colors: [BK, RD, WH]
+    gauge: 0.25 mm2
+    length: 0.5
+connections:
+  - - X1: [1-3]
+    - W1: [1-3]
+    - X2: [3, 2, 1]
+";
+
+    /// `keep` resolves merge conflicts by keeping the existing value
+    fn keep(compared: ComparedStruct) -> ComparedStruct {
+        compared
+    }
+
+    /// `export_imported` imports `harness_yaml`, loads it into a project and exports cable `W1`
+    fn export_imported(harness_yaml: &str) -> String {
+        let datafile = import_harness(harness_yaml).unwrap();
+        let mut library = Library::new();
+        library
+            .from_datafiles(vec![datafile.clone()], keep)
+            .unwrap();
+        let mut project = Project::new();
+        project
+            .from_datafiles(vec![datafile], &library, keep)
+            .unwrap();
+        let cable = project.cables["W1"].borrow();
+        export_cable(&cable).unwrap()
+    }
+
+    /// `terminations` returns the (core, pin) pairs of each end of cable `W1` in `datafile`
+    fn terminations(datafile: &DataFile) -> [Vec<(Option<u64>, Option<u64>)>; 2] {
+        let cable = &datafile.cables.as_ref().unwrap()["W1"];
+        [&cable.end1, &cable.end2].map(|end| {
+            end.iter()
+                .flat_map(|connector| connector.terminations.iter().flatten())
+                .map(|termination| (termination.core, termination.pin))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn import() {
+        let datafile = import_harness(HARNESS).unwrap();
+        let connector_types = datafile.connector_types.as_ref().unwrap();
+        assert_eq!(connector_types["X1"].pins.len(), 4);
+        let cable = &datafile.cables.as_ref().unwrap()["W1"];
+        assert!((cable.length - 0.5_f64).abs() < f64::EPSILON);
+        assert_eq!(cable.end1.first().unwrap().connector_type, "X1");
+        assert_eq!(cable.end2.first().unwrap().connector_type, "X2");
+        assert_eq!(
+            terminations(&datafile),
+            [
+                vec![(Some(1), Some(1)), (Some(2), Some(2)), (Some(3), Some(3))],
+                vec![(Some(1), Some(3)), (Some(2), Some(2)), (Some(3), Some(1))],
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let exported = export_imported(HARNESS);
+        let harness: Harness = serde_yaml::from_str(&exported).unwrap();
+        assert_eq!(
+            harness.cables["W1"].colors.as_deref(),
+            Some(["BK", "RD", "WH"].map(String::from).as_slice())
+        );
+        assert_eq!(
+            terminations(&import_harness(&exported).unwrap()),
+            terminations(&import_harness(HARNESS).unwrap())
+        );
+        // exporting the imported export changes nothing
+        assert_eq!(export_imported(&exported), exported);
+    }
+}
@@ -1,20 +1,57 @@
 //https://github.com/jaroslov/knuth-plass-thoughts/blob/master/plass.md
 
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+
 use dimensioned::{ucum, MapUnsafe};
+use hyphenation::{Hyphenator, Load};
 
 use log::trace;
 
 #[derive(Debug, Default)]
-/// `ParagraphWord` represents one word in a paragraph
+/// `ParagraphWord` represents one word in a paragraph, or one part of a word that can be broken
+/// across lines
 struct ParagraphWord {
-    /// index of first character in word
+    /// byte index of first character in word
     first: Option<usize>,
-    /// index of last character in word
+    /// byte index one past the last character in word
     last: Option<usize>,
     /// index of next word
     next: Option<usize>,
     /// word breaking score
     score: Option<ucum::Meter<f64>>,
+    /// the next word is the rest of the same word, so there is no space between them
+    joined: bool,
+    /// a hyphen is added if a line is broken after this word
+    hyphen: bool,
+}
+
+/// `BreakOptions` controls where words can be broken across lines, on top of the spaces between
+/// them. Breaking inside a word is scored as worse than breaking at a space, so words are only
+/// broken when it makes the lines fit noticeably better.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakOptions {
+    /// break words between syllables, found using the hyphenation patterns of the text language,
+    /// and add a hyphen at the end of the line
+    pub hyphenate: bool,
+    /// characters that words can be broken after without adding a hyphen, such as the
+    /// separators in identifiers like `MDF-R03-SW01-GE1/0/24`
+    pub break_after: Vec<char>,
+    /// as a last resort, break words that still don't fit on a line by themselves between any
+    /// two characters, instead of returning [`Error::LineLengthTooLong`]. Text being shrunk to
+    /// fit a space can turn this off until it reaches its smallest size.
+    pub break_anywhere: bool,
+}
+
+impl Default for BreakOptions {
+    fn default() -> Self {
+        Self {
+            hyphenate: true,
+            break_after: vec!['-', '/', '.', '_'],
+            break_anywhere: true,
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -25,7 +62,9 @@ struct ParagraphWord {
 ///
 /// # Errors
 ///
-/// Can error due to failure to split words into lines
+/// Can error due to failure to split words into lines, including
+/// [`Error::LineLengthTooLong`] if a word can't be broken small enough to fit in
+/// `textbox_width`
 pub fn to_lines(
     text: &str,
     font_data: &rustybuzz::Face,
@@ -33,6 +72,7 @@ pub fn to_lines(
     textbox_width: ucum::Meter<f64>,
     text_direction: rustybuzz::Direction,
     text_language: rustybuzz::Language,
+    break_options: &BreakOptions,
 ) -> Result<(Vec<String>, rustybuzz::GlyphBuffer), Error> {
     if text.is_empty() {
         return Err(Error::EmptyString);
@@ -52,17 +92,21 @@ pub fn to_lines(
     if glyph_buffer.is_empty() {
         return Err(Error::EmptyString);
     }
-    // calculate approx space width
-    let mut space_buffer = rustybuzz::UnicodeBuffer::new();
-    space_buffer.push_str(" ");
-    space_buffer.set_direction(text_direction);
-    space_buffer.set_language(text_language);
-    // see harfbuzz documentation for what this means
-    space_buffer.set_cluster_level(rustybuzz::BufferClusterLevel::MonotoneCharacters);
-    let space_buffer = rustybuzz::shape(font_data, &features, space_buffer);
+    // calculate approx space and hyphen widths
+    let shape_single = |character: &str| {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(character);
+        buffer.set_direction(text_direction);
+        buffer.set_language(text_language.clone());
+        // see harfbuzz documentation for what this means
+        buffer.set_cluster_level(rustybuzz::BufferClusterLevel::MonotoneCharacters);
+        rustybuzz::shape(font_data, &features, buffer)
+    };
+    let space_buffer = shape_single(" ");
     if space_buffer.is_empty() {
         return Err(Error::SpaceFailedToShape);
     }
+    let hyphen_buffer = shape_single("-");
 
     // https://github.com/RazrFalcon/rustybuzz/issues/33#issuecomment-784716703
     let units_per_em: u32 = u32::try_from(font_data.units_per_em()).unwrap_or(u32::MIN);
@@ -72,199 +116,534 @@ pub fn to_lines(
     //https://stackoverflow.com/a/68387730/3342767
     let em_width = f64::from(font_size) * point;
 
-    let space_width =
-        f64::from(space_buffer.glyph_positions()[0].x_advance) * em_width / f64::from(units_per_em);
+    let advance = |glyphs: &rustybuzz::GlyphBuffer| {
+        let advance: i32 = glyphs
+            .glyph_positions()
+            .iter()
+            .map(|position| position.x_advance)
+            .sum();
+        f64::from(advance) * em_width / f64::from(units_per_em)
+    };
+
+    let space_width = advance(&space_buffer);
+    let hyphen_width = advance(&hyphen_buffer);
+    let lines = break_lines(
+        text,
+        &|word| word_width(word, &glyph_buffer, em_width, units_per_em),
+        &|_| space_width,
+        &|_| hyphen_width,
+        textbox_width,
+        &text_language,
+        break_options,
+    )?;
+    Ok((
+        lines.iter().map(|line| line.text(text)).collect(),
+        glyph_buffer,
+    ))
+}
+
+/// `to_lines_by_width` splits `text` into lines no wider than `textbox_width`, the same way as
+/// [`to_lines`], for text that isn't shaped with `rustybuzz`. `word_width` returns the width of
+/// a single word, and `space_width` is the width of the space between words.
+///
+/// # Errors
+///
+/// Can error due to failure to split words into lines, including
+/// [`Error::LineLengthTooLong`] if a word can't be broken small enough to fit in
+/// `textbox_width`
+pub fn to_lines_by_width(
+    text: &str,
+    word_width: impl Fn(&str) -> ucum::Meter<f64>,
+    space_width: ucum::Meter<f64>,
+    textbox_width: ucum::Meter<f64>,
+    text_language: &rustybuzz::Language,
+    break_options: &BreakOptions,
+) -> Result<Vec<String>, Error> {
+    if text.is_empty() {
+        return Err(Error::EmptyString);
+    }
+    let hyphen_width = word_width("-");
+    let lines = break_lines(
+        text,
+        &|word| match (word.first, word.last) {
+            (Some(first), Some(last)) => word_width(&text[first..last]),
+            _ => 0.0_f64 * ucum::M,
+        },
+        &|_| space_width,
+        &|_| hyphen_width,
+        textbox_width,
+        text_language,
+        break_options,
+    )?;
+    Ok(lines.iter().map(|line| line.text(text)).collect())
+}
+
+/// `Line` is one line of a paragraph broken by [`to_line_ranges`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// byte ranges of the words on the line, which are separated by a single space
+    pub words: Vec<Range<usize>>,
+    /// whether the last word was broken between syllables, so the line ends with a hyphen
+    pub hyphen: bool,
+}
+
+impl Line {
+    /// `text` returns the line as a string, with the words of `paragraph` separated by single
+    /// spaces
+    #[must_use]
+    pub fn text(&self, paragraph: &str) -> String {
+        let mut line = self
+            .words
+            .iter()
+            .filter_map(|word| paragraph.get(word.clone()))
+            .collect::<Vec<&str>>()
+            .join(" ");
+        if self.hyphen {
+            line.push('-');
+        }
+        line
+    }
+}
+
+/// `to_line_ranges` splits `text` into lines no wider than `textbox_width`, the same way as
+/// [`to_lines`], and returns where each line is in `text`. This is for text whose width depends
+/// on where it is, such as text in several fonts.
+///
+/// * `word_width` returns the width of the text in a byte range.
+/// * `space_width` returns the width of the space between words at a byte offset.
+/// * `hyphen_width` returns the width of a hyphen added at a byte offset.
+///
+/// # Errors
+///
+/// Can error due to failure to split words into lines, including
+/// [`Error::LineLengthTooLong`] if a word can't be broken small enough to fit in
+/// `textbox_width`
+pub fn to_line_ranges(
+    text: &str,
+    word_width: impl Fn(Range<usize>) -> ucum::Meter<f64>,
+    space_width: impl Fn(usize) -> ucum::Meter<f64>,
+    hyphen_width: impl Fn(usize) -> ucum::Meter<f64>,
+    textbox_width: ucum::Meter<f64>,
+    text_language: &rustybuzz::Language,
+    break_options: &BreakOptions,
+) -> Result<Vec<Line>, Error> {
+    if text.is_empty() {
+        return Err(Error::EmptyString);
+    }
+    let end = |word: &ParagraphWord| word.last.unwrap_or(text.len());
+    break_lines(
+        text,
+        &|word| match (word.first, word.last) {
+            (Some(first), Some(last)) => word_width(first..last),
+            _ => 0.0_f64 * ucum::M,
+        },
+        &|word| space_width(end(word)),
+        &|word| hyphen_width(end(word)),
+        textbox_width,
+        text_language,
+        break_options,
+    )
+}
 
-    let mut words = text_to_words(text)?;
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::arithmetic_side_effects)]
+/// `break_lines` splits `text` into words, and finds the best lines to set them on, measuring
+/// each word with `word_width`, and the space and hyphen after a word with `space_width` and
+/// `hyphen_width`
+fn break_lines(
+    text: &str,
+    word_width: &dyn Fn(&ParagraphWord) -> ucum::Meter<f64>,
+    space_width: &dyn Fn(&ParagraphWord) -> ucum::Meter<f64>,
+    hyphen_width: &dyn Fn(&ParagraphWord) -> ucum::Meter<f64>,
+    textbox_width: ucum::Meter<f64>,
+    text_language: &rustybuzz::Language,
+    break_options: &BreakOptions,
+) -> Result<Vec<Line>, Error> {
+    let dictionary = if break_options.hyphenate {
+        hyphenation_dictionary(text_language)
+    } else {
+        None
+    };
+    let words = text_to_words(text, break_options, dictionary.as_deref());
+    if words.len() < 2 {
+        return Err(Error::EmptyString);
+    }
+    let mut words = if break_options.break_anywhere {
+        split_long_words(words, text, word_width, textbox_width)
+    } else {
+        words
+    };
 
     let max_width = textbox_width;
     let ideal_width = textbox_width - (textbox_width * 0.1_f64);
 
     line_break_internal(
         &mut words,
-        &glyph_buffer,
-        em_width,
-        space_width,
-        units_per_em,
+        &LineMetrics {
+            word_width,
+            space_width,
+            hyphen_width,
+            ideal_width,
+            max_width,
+        },
         0,
-        ideal_width,
-        max_width,
     )?;
-    Ok((to_lines_internal(&words, text), glyph_buffer))
+    Ok(to_lines_internal(&words))
 }
-#[allow(clippy::too_many_arguments)]
+
+/// `LineMetrics` are the measurements used to score the lines of a paragraph
+struct LineMetrics<'a> {
+    /// returns the width of a word
+    word_width: &'a dyn Fn(&ParagraphWord) -> ucum::Meter<f64>,
+    /// returns the width of the space after a word
+    space_width: &'a dyn Fn(&ParagraphWord) -> ucum::Meter<f64>,
+    /// returns the width of the hyphen added after a word when it is broken between syllables
+    hyphen_width: &'a dyn Fn(&ParagraphWord) -> ucum::Meter<f64>,
+    /// width lines are scored against
+    ideal_width: ucum::Meter<f64>,
+    /// width no line can be wider than
+    max_width: ucum::Meter<f64>,
+}
+
 #[allow(clippy::arithmetic_side_effects)]
-#[allow(clippy::shadow_unrelated)]
 /// `lineBreakInternal` scores each `ParagraphWord` for breaking possibilities
+///
+/// The score of a word is the lowest total score of all lines from that word until the end of
+/// the paragraph, with `next` pointing at the first word of the following line. Lines that end
+/// inside a word are penalised as if they were a tenth of the width further from the ideal
+/// width.
 fn line_break_internal(
     words: &mut [ParagraphWord],
-    shaped_text: &rustybuzz::GlyphBuffer,
-    em_width: ucum::Meter<f64>,
-    space_width: ucum::Meter<f64>,
-    units_per_em: u32,
+    metrics: &LineMetrics,
     current_word_index: usize,
-    ideal_width: ucum::Meter<f64>,
-    max_width: ucum::Meter<f64>,
 ) -> Result<(), Error> {
-    let glyph_infos = shaped_text.glyph_infos();
-    let glyph_positions = shaped_text.glyph_positions();
-
-    let mut next_word_index = current_word_index + 1;
+    // the last entry in `words` is an empty sentinel marking the end of the paragraph
+    let sentinel_index = words.len() - 1;
+    let word_break_penalty = (metrics.max_width * 0.1_f64).map_unsafe(|v| v * v);
 
-    let mut line_length = 0.0_f64 * ucum::M; // any unit will do here
-    let first = words[current_word_index].first.unwrap_or(0);
-    let last = words[current_word_index].last.unwrap_or(0);
     // current line length is length of first word.
-    for glyph in &glyph_positions[first..last] {
-        line_length += f64::from(glyph.x_advance) * em_width / f64::from(units_per_em);
+    let mut line_length = (metrics.word_width)(&words[current_word_index]);
+    // a single word that doesn't fit can't be broken any further
+    if line_length > metrics.max_width {
+        return Err(Error::LineLengthTooLong);
     }
-    // the best score is current line length, squared
-    let mut best_score = ideal_width - line_length;
-    // I think this works?
-    best_score = best_score.map_unsafe(|v| v * v);
-    // best tail is current break
-    let mut best_tail = next_word_index;
 
-    // scan down word list looking for better entries
-    while next_word_index < words.len() {
-        // get width of new potential word
-        let mut word_width = 0.0_f64 * ucum::M; // any unit will do here
+    let mut best_score: Option<ucum::Meter<f64>> = None;
+    let mut best_tail = current_word_index + 1;
+    let mut next_word_index = current_word_index + 1;
 
-        let first = words[next_word_index].first.unwrap_or(0);
-        let last = words[next_word_index].last.unwrap_or(0);
-        for glyph in &glyph_positions[first..last] {
-            word_width += f64::from(glyph.x_advance) * em_width / f64::from(units_per_em);
-        }
-        // if the new word will make the line too long, stop
-        if (line_length + word_width) >= max_width {
-            break;
-        }
-        // compute a new line length score
-        let mut line_score = ideal_width - (line_length + word_width);
-        // I think this works?
-        line_score = line_score.map_unsafe(|v| v * v);
-        // add a word and a space to the current line
-        line_length += word_width + space_width; // need to figure out space width
-
-        // if we haven't solved the subproblem at this i
+    // scan down word list looking for better entries
+    loop {
+        // if we haven't solved the subproblem at this
         // potential line break, go ahead and do so now.
         if words[next_word_index].score.is_none() {
-            line_break_internal(
-                words,
-                shaped_text,
-                em_width,
-                space_width,
-                units_per_em,
-                next_word_index,
-                ideal_width,
-                max_width,
-            )?;
+            line_break_internal(words, metrics, next_word_index)?;
         }
-
-        // is this new line_score better than current best_score
-        if (line_score + words[next_word_index].score.unwrap_or(0.0_f64 * ucum::M)) < best_score {
-            // update to this new score
-            best_score = line_score + words[next_word_index].score.unwrap_or(0.0_f64 * ucum::M);
-            // track the new tail
+        let last_word = &words[next_word_index - 1];
+        let hyphen_width = if last_word.joined && last_word.hyphen {
+            (metrics.hyphen_width)(last_word)
+        } else {
+            0.0_f64 * ucum::M
+        };
+        // the last line of the paragraph doesn't contribute to the score
+        let line_score = if next_word_index == sentinel_index {
+            0.0_f64 * ucum::M
+        } else if line_length + hyphen_width > metrics.max_width {
+            // the hyphen doesn't fit, so the line can't end here
+            f64::INFINITY * ucum::M
+        } else if last_word.joined {
+            (metrics.ideal_width - line_length - hyphen_width).map_unsafe(|v| v * v)
+                + word_break_penalty
+        } else {
+            (metrics.ideal_width - line_length).map_unsafe(|v| v * v)
+        };
+        let score = line_score + words[next_word_index].score.unwrap_or(0.0_f64 * ucum::M);
+        // is this new score better than current best_score
+        if best_score.is_none_or(|best_score| score < best_score) {
+            best_score = Some(score);
             best_tail = next_word_index;
         }
+
+        if next_word_index == sentinel_index {
+            break;
+        }
+        // if the next word will make the line too long, stop
+        let gap_width = if words[next_word_index - 1].joined {
+            0.0_f64 * ucum::M
+        } else {
+            (metrics.space_width)(&words[next_word_index - 1])
+        };
+        let next_width = (metrics.word_width)(&words[next_word_index]);
+        if (line_length + gap_width + next_width) > metrics.max_width {
+            break;
+        }
+        // add a space, if the words aren't joined, and the word to the current line
+        line_length += gap_width + next_width;
         // Look at the next word
         next_word_index += 1;
     }
     // write down the best score and tail for this subproblem
-    words[current_word_index].score = Some(best_score);
+    words[current_word_index].score = best_score;
     words[current_word_index].next = Some(best_tail);
-
-    // the last word of the paragraph doesn't contribute to the score
-    if (words[current_word_index].next.unwrap_or(0) + 1) == words.len() {
-        words[current_word_index].score = Some(0.0_f64 * ucum::M);
-    }
     Ok(())
 }
 
 #[allow(clippy::arithmetic_side_effects)]
+/// `word_width` sums the advances of all glyphs that belong to `word`
+///
+/// Glyphs are matched to the word by cluster, as ligatures mean there isn't always one glyph
+/// per character.
+fn word_width(
+    word: &ParagraphWord,
+    shaped_text: &rustybuzz::GlyphBuffer,
+    em_width: ucum::Meter<f64>,
+    units_per_em: u32,
+) -> ucum::Meter<f64> {
+    let (Some(first), Some(last)) = (word.first, word.last) else {
+        return 0.0_f64 * ucum::M;
+    };
+    let mut width = 0.0_f64 * ucum::M; // any unit will do here
+    for (info, position) in shaped_text
+        .glyph_infos()
+        .iter()
+        .zip(shaped_text.glyph_positions())
+    {
+        let cluster = usize::try_from(info.cluster).unwrap_or(usize::MAX);
+        if (first..last).contains(&cluster) {
+            width += f64::from(position.x_advance) * em_width / f64::from(units_per_em);
+        }
+    }
+    width
+}
+
+/// `HYPHENATION_DICTIONARIES` caches the hyphenation patterns of each language once loaded, as
+/// text is often broken into lines many times while fitting it to a space
+static HYPHENATION_DICTIONARIES: LazyLock<
+    Mutex<HashMap<hyphenation::Language, Arc<hyphenation::Standard>>>,
+> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// `hyphenation_dictionary` returns the hyphenation patterns for `text_language`, or `None` if
+/// there aren't any for it
+///
+/// Languages are matched on their full tag first, then on their primary language, using the
+/// current spelling of languages that have several.
+fn hyphenation_dictionary(
+    text_language: &rustybuzz::Language,
+) -> Option<Arc<hyphenation::Standard>> {
+    let tag = text_language.as_str().to_lowercase();
+    let primary = tag.split(['-', '_']).next().unwrap_or_default();
+    let language = hyphenation::Language::try_from_code(&tag).or_else(|| {
+        hyphenation::Language::try_from_code(match primary {
+            "en" => "en-us",
+            "de" => "de-1996",
+            "el" => "el-monoton",
+            "mn" => "mn-cyrl",
+            "sr" => "sr-cyrl",
+            "no" => "nb",
+            _ => primary,
+        })
+    })?;
+    let mut dictionaries = HYPHENATION_DICTIONARIES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(dictionary) = dictionaries.get(&language) {
+        return Some(Arc::clone(dictionary));
+    }
+    let dictionary = match hyphenation::Standard::from_embedded(language) {
+        Ok(dictionary) => Arc::new(dictionary),
+        Err(e) => {
+            trace! {"no hyphenation patterns for {language}: {e}"};
+            return None;
+        }
+    };
+    dictionaries.insert(language, Arc::clone(&dictionary));
+    Some(dictionary)
+}
+
 /// `text_to_words` splits a utf8 string into an array of [`ParagraphWord`]s
-fn text_to_words(text: &str) -> Result<Vec<ParagraphWord>, Error> {
-    let mut index = 0;
+///
+/// `first` and `last` are byte offsets into `text`, matching the cluster values produced by
+/// shaping.
+fn text_to_words(
+    text: &str,
+    break_options: &BreakOptions,
+    dictionary: Option<&hyphenation::Standard>,
+) -> Vec<ParagraphWord> {
     let mut words = Vec::new();
+    let mut start = None;
 
-    while index < text.len() {
-        // find next (first) non whitespace char index in string
-        while index < text.len()
-            && text
-                .chars()
-                .nth(index)
-                .ok_or(Error::CharacterNotFound)?
-                .is_whitespace()
-        {
-            index += 1;
-        }
-        let start = index;
-        // find ending index of word
-        while index < text.len()
-            && !text
-                .chars()
-                .nth(index)
-                .ok_or(Error::CharacterNotFound)?
-                .is_whitespace()
-        {
-            index += 1;
-        }
-        if start < index {
-            words.push(ParagraphWord {
-                first: Some(start),
-                last: Some(index),
-                next: None,
-                score: None,
-            });
+    for (index, character) in text.char_indices() {
+        match (character.is_whitespace(), start) {
+            (true, Some(first)) => {
+                push_word(&mut words, text, first, index, break_options, dictionary);
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
         }
     }
+    if let Some(first) = start {
+        push_word(
+            &mut words,
+            text,
+            first,
+            text.len(),
+            break_options,
+            dictionary,
+        );
+    }
     words.push(ParagraphWord {
         first: None,
         last: None,
         next: None,
         score: Some(0.0_f64 * ucum::M),
+        joined: false,
+        hyphen: false,
+    });
+    words
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+/// `push_word` adds the word from byte `first` to `last` of `text` to `words`, split into parts
+/// wherever it can be broken across lines
+fn push_word(
+    words: &mut Vec<ParagraphWord>,
+    text: &str,
+    first: usize,
+    last: usize,
+    break_options: &BreakOptions,
+    dictionary: Option<&hyphenation::Standard>,
+) {
+    let word = &text[first..last];
+    // byte offsets within the word that it can be broken at, and whether a hyphen is added
+    let mut breaks = Vec::new();
+    let mut letters_start = None;
+    for (index, character) in word.char_indices().chain([(word.len(), ' ')]) {
+        let end = index + character.len_utf8();
+        if break_options.break_after.contains(&character) && end < word.len() {
+            breaks.push((end, false));
+        }
+        // each run of letters is hyphenated separately, so punctuation and digits don't stop
+        // the patterns matching. Runs without lowercase letters are acronyms or parts of
+        // identifiers rather than words, so they aren't hyphenated.
+        if character.is_alphabetic() {
+            letters_start.get_or_insert(index);
+        } else if let (Some(start), Some(dictionary)) = (letters_start.take(), dictionary) {
+            if !word[start..index].chars().any(char::is_lowercase) {
+                continue;
+            }
+            breaks.extend(
+                dictionary
+                    .hyphenate(&word[start..index])
+                    .breaks
+                    .into_iter()
+                    .map(|offset| (start + offset, true)),
+            );
+        }
+    }
+    breaks.sort_unstable();
+    breaks.dedup_by_key(|(offset, _)| *offset);
+
+    let mut start = 0;
+    for (offset, hyphen) in breaks {
+        words.push(ParagraphWord {
+            first: Some(first + start),
+            last: Some(first + offset),
+            next: None,
+            score: None,
+            joined: true,
+            hyphen,
+        });
+        start = offset;
+    }
+    words.push(ParagraphWord {
+        first: Some(first + start),
+        last: Some(last),
+        next: None,
+        score: None,
+        joined: false,
+        hyphen: false,
     });
-    Ok(words)
 }
-#[allow(clippy::similar_names)]
+
 #[allow(clippy::arithmetic_side_effects)]
-/// `toLines` takes in a vector of `ParagraphWord`s and converts them to a vector of strings
-/// of the correct lengths.
-fn to_lines_internal(words: &[ParagraphWord], text: &str) -> Vec<String> {
+/// `split_long_words` splits any word wider than `max_width` between characters, with as many
+/// characters on each part as fit. This is the last resort for words that can't otherwise be
+/// broken, such as long identifiers in narrow table cells.
+fn split_long_words(
+    words: Vec<ParagraphWord>,
+    text: &str,
+    word_width: &dyn Fn(&ParagraphWord) -> ucum::Meter<f64>,
+    max_width: ucum::Meter<f64>,
+) -> Vec<ParagraphWord> {
+    let mut split_words = Vec::with_capacity(words.len());
+    for word in words {
+        let (Some(first), Some(last)) = (word.first, word.last) else {
+            split_words.push(word);
+            continue;
+        };
+        if word_width(&word) <= max_width {
+            split_words.push(word);
+            continue;
+        }
+        let part_width = |start: usize, end: usize| {
+            word_width(&ParagraphWord {
+                first: Some(start),
+                last: Some(end),
+                ..ParagraphWord::default()
+            })
+        };
+        let mut start = first;
+        let mut end = first;
+        for (index, character) in text[first..last].char_indices() {
+            let character_end = first + index + character.len_utf8();
+            // every part keeps at least one character, even if it doesn't fit
+            if end > start && part_width(start, character_end) > max_width {
+                split_words.push(ParagraphWord {
+                    first: Some(start),
+                    last: Some(end),
+                    joined: true,
+                    ..ParagraphWord::default()
+                });
+                start = end;
+            }
+            end = character_end;
+        }
+        split_words.push(ParagraphWord {
+            first: Some(start),
+            last: Some(last),
+            ..word
+        });
+    }
+    split_words
+}
+
+/// `toLines` takes in a vector of `ParagraphWord`s and converts them to the `Line`s chosen by
+/// their scores, joining the parts of broken words back together
+fn to_lines_internal(words: &[ParagraphWord]) -> Vec<Line> {
     let mut index = 0;
     let mut output = Vec::new();
 
-    while index < words.len() {
-        let next = words[index].next.unwrap_or(0);
-        let mut line = String::new();
-
-        {
-            let internal_index = index;
-            while (internal_index <= next) && (internal_index + 1 < words.len()) {
-                let first = match words[internal_index].first {
-                    None => break,
-                    Some(first) => first,
-                };
-                let last = match words[internal_index].last {
-                    None => break,
-                    Some(last) => last,
-                };
-                if last - first == 0 {
-                    break;
-                }
-                if internal_index == index {
-                    line += "";
-                } else {
-                    line += " ";
-                }
-                //TODO: add in check with glyph_info to see if it doesn't like breaking there.
-                line += &text[words[internal_index].first.unwrap_or(0)
-                    ..words[internal_index].last.unwrap_or(0)];
+    while let Some(next) = words.get(index).and_then(|word| word.next) {
+        let mut line = Line {
+            words: Vec::new(),
+            hyphen: false,
+        };
+        let mut joined = false;
+        for word in &words[index..next] {
+            let (Some(first), Some(last)) = (word.first, word.last) else {
+                continue;
+            };
+            //TODO: add in check with glyph_info to see if it doesn't like breaking there.
+            match line.words.last_mut() {
+                Some(previous) if joined => previous.end = last,
+                _ => line.words.push(first..last),
             }
-            output.push(line);
-            index = words[index].next.unwrap_or(0);
+            joined = word.joined;
+            line.hyphen = word.joined && word.hyphen;
         }
+        output.push(line);
+        index = next;
     }
     output
 }
@@ -313,6 +692,50 @@ fn greedy_break(
     Ok(())
 }
 
+/// `Alignment` is how the lines of a paragraph are placed across the width they were broken to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// lines start at the left side
+    #[default]
+    Left,
+    /// lines end at the right side
+    Right,
+    /// lines are centered
+    Center,
+    /// the space between words is stretched so lines fill the width, apart from the last line,
+    /// which is left aligned
+    Justify,
+}
+
+impl Alignment {
+    /// `place_line` returns where a line of `line_width` with `gaps` spaces between words starts,
+    /// as an offset from the left side of `textbox_width`, and the extra width added to each
+    /// space.
+    ///
+    /// Lines are broken to keep the slack at the end of each line as even as possible, and the
+    /// last line is scored as having no slack, so only lines before the last are justified.
+    #[allow(clippy::arithmetic_side_effects)]
+    #[must_use]
+    pub fn place_line(
+        self,
+        line_width: ucum::Meter<f64>,
+        gaps: usize,
+        textbox_width: ucum::Meter<f64>,
+        last_line: bool,
+    ) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+        let slack = textbox_width - line_width;
+        let none = 0.0_f64 * ucum::M;
+        match self {
+            Alignment::Left => (none, none),
+            Alignment::Right => (slack, none),
+            Alignment::Center => (slack / 2.0_f64, none),
+            Alignment::Justify if last_line || gaps == 0 || slack < none => (none, none),
+            #[allow(clippy::cast_precision_loss)]
+            Alignment::Justify => (none, slack / gaps as f64),
+        }
+    }
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 /// list of errors for this library
@@ -338,3 +761,96 @@ impl std::fmt::Display for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `lines` breaks `text` into lines `width` characters wide, with every character 1mm wide
+    #[allow(clippy::arithmetic_side_effects, clippy::cast_precision_loss)]
+    fn lines(text: &str, width: f64, break_options: &BreakOptions) -> Result<Vec<String>, Error> {
+        let millimeter = 0.001_f64 * ucum::M;
+        to_lines_by_width(
+            text,
+            |word| word.chars().count() as f64 * millimeter,
+            millimeter,
+            width * millimeter,
+            &"en".parse().unwrap(),
+            break_options,
+        )
+    }
+
+    #[test]
+    fn hyphenation() {
+        assert_eq!(
+            lines(
+                "the hyphenation of extraordinary words",
+                12.0_f64,
+                &BreakOptions::default()
+            )
+            .unwrap(),
+            ["the hyphen-", "ation of ex-", "traordinary", "words"]
+        );
+        let options = BreakOptions {
+            hyphenate: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            lines("the hyphenation of extraordinary words", 13.0_f64, &options).unwrap(),
+            ["the", "hyphenation", "of", "extraordinary", "words"]
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        let options = BreakOptions::default();
+        // no hyphen is added where identifiers are broken
+        assert_eq!(
+            lines("MDF-R03-SW01-GE1/0/24", 10.0_f64, &options).unwrap(),
+            ["MDF-R03-", "SW01-GE1/", "0/24"]
+        );
+        assert_eq!(
+            lines("switch.port_24.vlan", 8.0_f64, &options).unwrap(),
+            ["switch.", "port_24.", "vlan"]
+        );
+        assert_eq!(
+            lines("port_24_vlan_10", 8.0_f64, &options).unwrap(),
+            ["port_24_", "vlan_10"]
+        );
+        // acronyms and digits aren't hyphenated, so are only broken anywhere once nothing else
+        // fits
+        let options = BreakOptions {
+            break_after: Vec::new(),
+            ..Default::default()
+        };
+        assert_eq!(
+            lines("MDF-R03-SW01-GE1/0/24", 10.0_f64, &options).unwrap(),
+            ["MDF-R03-SW", "01-GE1/0/2", "4"]
+        );
+    }
+
+    #[test]
+    fn break_anywhere() {
+        assert_eq!(
+            lines(
+                "12345678901234567890123",
+                10.0_f64,
+                &BreakOptions::default()
+            )
+            .unwrap(),
+            ["1234567890", "1234567890", "123"]
+        );
+        let options = BreakOptions {
+            break_anywhere: false,
+            ..Default::default()
+        };
+        assert!(matches!(
+            lines("12345678901234567890123", 10.0_f64, &options),
+            Err(Error::LineLengthTooLong)
+        ));
+        assert_eq!(
+            lines("MDF-R03-SW01-GE1/0/24", 10.0_f64, &options).unwrap(),
+            ["MDF-R03-", "SW01-GE1/", "0/24"]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use lopdf::content::{Content, Operation};
+
+/// `ContentHasher` computes 64 bit FNV-1a hashes of explicitly encoded values. Unlike
+/// `std::hash::DefaultHasher`, the algorithm is fixed, so resource names and document IDs
+/// hashed with it don't change between Rust releases or platforms.
+pub(crate) struct ContentHasher {
+    /// hash of the bytes written so far
+    state: u64,
+}
+
+impl ContentHasher {
+    /// `OFFSET_BASIS` is the initial state of a 64 bit FNV-1a hash
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    /// `PRIME` is the multiplier of a 64 bit FNV-1a hash
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    /// `new` returns a hasher that nothing has been written to
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Self::OFFSET_BASIS,
+        }
+    }
+
+    /// `write_raw` adds `bytes` to the hash as they are
+    fn write_raw(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// `write` adds `bytes` to the hash, prefixed with their length, so consecutive values can't
+    /// run into each other
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        self.write_u64(u64::try_from(bytes.len()).unwrap_or(u64::MAX));
+        self.write_raw(bytes);
+    }
+
+    /// `write_u64` adds `value` to the hash as 8 little endian bytes
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.write_raw(&value.to_le_bytes());
+    }
+
+    /// `write_f64` adds the bits of `value` to the hash
+    pub(crate) fn write_f64(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    /// `write_bool` adds `value` to the hash as one byte
+    pub(crate) fn write_bool(&mut self, value: bool) {
+        self.write_raw(&[u8::from(value)]);
+    }
+
+    /// `finish` returns the hash of everything written
+    pub(crate) fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+/// `ContentHash` is a value that can be written to a `ContentHasher`. Values that are equal
+/// write the same bytes, and values of different types that can be confused with each other
+/// write different bytes.
+pub(crate) trait ContentHash {
+    /// `content_hash` writes an encoding of the value to `hasher`
+    fn content_hash(&self, hasher: &mut ContentHasher);
+}
+
+impl ContentHash for str {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write(self.as_bytes());
+    }
+}
+
+impl ContentHash for f64 {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_f64(*self);
+    }
+}
+
+impl ContentHash for [f64] {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_u64(u64::try_from(self.len()).unwrap_or(u64::MAX));
+        for value in self {
+            hasher.write_f64(*value);
+        }
+    }
+}
+
+impl<const N: usize> ContentHash for [f64; N] {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        self.as_slice().content_hash(hasher);
+    }
+}
+
+impl ContentHash for [Operation] {
+    /// operations are written as the content stream they are saved as
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        // operations that fail to encode also fail when the document is written, so it doesn't
+        // matter what they hash to
+        let encoded = Content { operations: self }.encode().unwrap_or_default();
+        hasher.write(&encoded);
+    }
+}
+
+impl<T: ContentHash> ContentHash for Option<T> {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_bool(self.is_some());
+        if let Some(value) = self {
+            value.content_hash(hasher);
+        }
+    }
+}
+
+impl<T: ContentHash + ?Sized> ContentHash for &T {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        (**self).content_hash(hasher);
+    }
+}
@@ -0,0 +1,414 @@
+use std::io::Cursor;
+
+use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+
+use crate::Error;
+
+/// `PDFImage` is a raster image, written in the PDF as an image XObject
+#[derive(Clone)]
+pub(crate) struct PDFImage {
+    /// width of the image in pixels
+    width: u32,
+    /// height of the image in pixels
+    height: u32,
+    /// name of the PDF color space of the samples
+    color_space: &'static str,
+    /// range of values each color component is mapped to, if not the default for the color
+    /// space
+    decode: Option<Vec<f64>>,
+    /// the image data, either a JPEG file or uncompressed 8 bit samples
+    data: ImageData,
+    /// 8 bit grayscale samples of the alpha channel of the image, if it isn't fully opaque
+    soft_mask: Option<Vec<u8>>,
+}
+
+/// `ImageData` is the image data of a `PDFImage`
+#[derive(Clone)]
+enum ImageData {
+    /// a complete JPEG file, which PDF readers can decode directly
+    Jpeg(Vec<u8>),
+    /// uncompressed 8 bit samples, which are compressed when written
+    Samples(Vec<u8>),
+}
+
+impl PDFImage {
+    /// `from_jpeg` reads the size and color space of a JPEG image. The image data itself is not
+    /// decoded, it is written to the PDF as is.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the JPEG is malformed or has an unsupported number of color components
+    pub(crate) fn from_jpeg(data: &[u8]) -> Result<Self, Error> {
+        let header = jpeg_header(data)
+            .ok_or_else(|| Error::ImageDecoding("JPEG has no frame header".to_string()))?;
+        let color_space = match header.components {
+            1 => "DeviceGray",
+            3 => "DeviceRGB",
+            4 => "DeviceCMYK",
+            components => {
+                return Err(Error::ImageDecoding(format!(
+                    "JPEGs with {components} color components are not supported"
+                )))
+            }
+        };
+        // Adobe applications write CMYK JPEGs with inverted components
+        let decode = (header.components == 4 && header.adobe).then(|| [1.0, 0.0].repeat(4));
+        Ok(Self {
+            width: u32::from(header.width),
+            height: u32::from(header.height),
+            color_space,
+            decode,
+            data: ImageData::Jpeg(data.to_vec()),
+            soft_mask: None,
+        })
+    }
+
+    /// `from_png` decodes a PNG image into 8 bit samples, separating any alpha channel into a
+    /// soft mask
+    ///
+    /// # Errors
+    ///
+    /// Will error if the PNG is malformed
+    pub(crate) fn from_png(data: &[u8]) -> Result<Self, Error> {
+        use png::ColorType;
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        // expands palettes, bit depths under 8 and transparent colors, and strips 16 bit samples
+        // down to 8 bits, leaving gray or RGB samples with an optional alpha channel
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![
+            0;
+            reader.output_buffer_size().ok_or_else(|| {
+                Error::ImageDecoding("PNG is too large to decode".to_string())
+            })?
+        ];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let (color_space, has_alpha) = match info.color_type {
+            ColorType::Grayscale => ("DeviceGray", false),
+            ColorType::GrayscaleAlpha => ("DeviceGray", true),
+            ColorType::Rgb => ("DeviceRGB", false),
+            ColorType::Rgba => ("DeviceRGB", true),
+            ColorType::Indexed => {
+                return Err(Error::ImageDecoding(
+                    "PNG palette was not expanded".to_string(),
+                ))
+            }
+        };
+        let (samples, soft_mask) = if has_alpha {
+            let channels = info.color_type.samples();
+            let mut samples = Vec::with_capacity(buffer.len());
+            let mut alpha = Vec::with_capacity(buffer.len() / channels);
+            for pixel in buffer.chunks_exact(channels) {
+                if let Some((pixel_alpha, color)) = pixel.split_last() {
+                    samples.extend_from_slice(color);
+                    alpha.push(*pixel_alpha);
+                }
+            }
+            // fully opaque images don't need a mask
+            let opaque = alpha.iter().all(|&pixel_alpha| pixel_alpha == u8::MAX);
+            (samples, (!opaque).then_some(alpha))
+        } else {
+            (buffer, None)
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            color_space,
+            decode: None,
+            data: ImageData::Samples(samples),
+            soft_mask,
+        })
+    }
+
+    /// `write` adds the image, and its soft mask if it has one, to `doc` as image XObjects and
+    /// returns the ID of the image
+    ///
+    /// # Errors
+    ///
+    /// Will error if the image samples fail to compress
+    pub(crate) fn write(&self, doc: &mut Document) -> Result<ObjectId, Error> {
+        let mut dict = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => self.width,
+            "Height" => self.height,
+            "ColorSpace" => self.color_space,
+            "BitsPerComponent" => 8,
+        };
+        if let Some(decode) = &self.decode {
+            dict.set(
+                "Decode",
+                decode
+                    .iter()
+                    .map(|&value| value.into())
+                    .collect::<Vec<Object>>(),
+            );
+        }
+        if let Some(alpha) = &self.soft_mask {
+            let mut mask = Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => self.width,
+                    "Height" => self.height,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => 8,
+                },
+                alpha.clone(),
+            );
+            mask.compress()?;
+            dict.set("SMask", doc.add_object(mask));
+        }
+        let stream = match &self.data {
+            ImageData::Jpeg(data) => {
+                dict.set("Filter", "DCTDecode");
+                // JPEG data is already compressed
+                Stream::new(dict, data.clone()).with_compression(false)
+            }
+            ImageData::Samples(samples) => {
+                let mut stream = Stream::new(dict, samples.clone());
+                stream.compress()?;
+                stream
+            }
+        };
+        Ok(doc.add_object(stream))
+    }
+}
+
+/// `JpegHeader` is the information about a JPEG image needed to embed it in a PDF
+struct JpegHeader {
+    /// width of the image in pixels
+    width: u16,
+    /// height of the image in pixels
+    height: u16,
+    /// number of color components of each pixel
+    components: u8,
+    /// whether the image has an Adobe marker segment
+    adobe: bool,
+}
+
+/// `jpeg_header` reads the marker segments at the start of a JPEG image, up to the frame header.
+/// Returns `None` if the data isn't a JPEG or ends before the frame header.
+#[allow(clippy::arithmetic_side_effects)]
+fn jpeg_header(data: &[u8]) -> Option<JpegHeader> {
+    let read_u16 = |position: usize| {
+        Some(u16::from_be_bytes([
+            *data.get(position)?,
+            *data.get(position + 1)?,
+        ]))
+    };
+    // start of image marker
+    if data.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut adobe = false;
+    let mut position = 2;
+    loop {
+        if *data.get(position)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(position + 1)?;
+        match marker {
+            // markers can be padded with any number of fill bytes
+            0xFF => {
+                position += 1;
+                continue;
+            }
+            // markers without a segment
+            0x01 | 0xD0..=0xD7 => {
+                position += 2;
+                continue;
+            }
+            _ => {}
+        }
+        let length = usize::from(read_u16(position + 2)?);
+        let segment = data.get(position + 4..position + 2 + length)?;
+        match marker {
+            // start of frame markers, other than huffman and arithmetic coding tables
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some(JpegHeader {
+                    height: read_u16(position + 5)?,
+                    width: read_u16(position + 7)?,
+                    components: *segment.get(5)?,
+                    adobe,
+                });
+            }
+            // application segment 14
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            _ => {}
+        }
+        position += 2 + length;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `png` encodes `samples` as a PNG `width` pixels wide with `color_type`
+    fn png(width: u32, height: u32, color_type: png::ColorType, samples: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, width, height);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(samples).unwrap();
+        writer.finish().unwrap();
+        data
+    }
+
+    /// `jpeg` returns the marker segments of a 4x3 JPEG with `components` color components, up to
+    /// the end of the frame header, with an Adobe segment if `adobe` is set
+    fn jpeg(components: u8, adobe: bool) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        if adobe {
+            data.extend_from_slice(&[0xFF, 0xEE, 0x00, 0x0E]);
+            data.extend_from_slice(b"Adobe");
+            data.extend_from_slice(&[0x00, 0x64, 0x00, 0x00, 0x00, 0x00, 0x02]);
+        }
+        let length = 8 + 3 * u16::from(components);
+        data.extend_from_slice(&[0xFF, 0xC0]);
+        data.extend_from_slice(&length.to_be_bytes());
+        data.extend_from_slice(&[8, 0x00, 0x03, 0x00, 0x04, components]);
+        for component in 1..=components {
+            data.extend_from_slice(&[component, 0x11, 0x00]);
+        }
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data
+    }
+
+    /// `written` writes `image` to a new document, and returns the document and the image
+    /// XObject
+    fn written(image: &PDFImage) -> (Document, Stream) {
+        let mut doc = Document::with_version("1.5");
+        let id = image.write(&mut doc).unwrap();
+        let stream = doc.get_object(id).unwrap().as_stream().unwrap().clone();
+        (doc, stream)
+    }
+
+    /// `decoded` returns the decompressed samples of image `stream`
+    fn decoded(stream: &Stream) -> Vec<u8> {
+        // lopdf refuses to decompress images, as they can use image specific filters
+        let mut stream = stream.clone();
+        stream.dict.remove(b"Subtype");
+        stream.decompressed_content().unwrap()
+    }
+
+    /// `name` returns the name `key` of `stream`'s dictionary
+    fn name<'a>(stream: &'a Stream, key: &[u8]) -> &'a [u8] {
+        stream.dict.get(key).unwrap().as_name().unwrap()
+    }
+
+    #[test]
+    fn png_rgb() {
+        // large enough for the samples to compress
+        let samples = [255, 0, 0].repeat(16 * 16);
+        let image = PDFImage::from_png(&png(16, 16, png::ColorType::Rgb, &samples)).unwrap();
+        let (_, stream) = written(&image);
+        assert_eq!(name(&stream, b"Subtype"), b"Image");
+        assert_eq!(name(&stream, b"ColorSpace"), b"DeviceRGB");
+        assert_eq!(stream.dict.get(b"Width").unwrap().as_i64().unwrap(), 16);
+        assert_eq!(stream.dict.get(b"Height").unwrap().as_i64().unwrap(), 16);
+        assert_eq!(
+            stream
+                .dict
+                .get(b"BitsPerComponent")
+                .unwrap()
+                .as_i64()
+                .unwrap(),
+            8
+        );
+        assert_eq!(name(&stream, b"Filter"), b"FlateDecode");
+        assert!(stream.dict.get(b"SMask").is_err());
+        assert_eq!(decoded(&stream), samples);
+    }
+
+    #[test]
+    fn png_alpha() {
+        // gray and alpha, with every other pixel half transparent
+        let samples = [10, 255, 20, 128].repeat(8 * 16);
+        let image =
+            PDFImage::from_png(&png(16, 16, png::ColorType::GrayscaleAlpha, &samples)).unwrap();
+        let (doc, stream) = written(&image);
+        assert_eq!(name(&stream, b"ColorSpace"), b"DeviceGray");
+        assert_eq!(decoded(&stream), [10, 20].repeat(8 * 16));
+
+        let mask_id = stream.dict.get(b"SMask").unwrap().as_reference().unwrap();
+        let mask = doc.get_object(mask_id).unwrap().as_stream().unwrap();
+        assert_eq!(name(mask, b"Subtype"), b"Image");
+        assert_eq!(name(mask, b"ColorSpace"), b"DeviceGray");
+        assert_eq!(
+            mask.dict
+                .get(b"BitsPerComponent")
+                .unwrap()
+                .as_i64()
+                .unwrap(),
+            8
+        );
+        assert_eq!(mask.dict.get(b"Width").unwrap().as_i64().unwrap(), 16);
+        assert_eq!(decoded(mask), [255, 128].repeat(8 * 16));
+
+        // fully opaque images have no mask
+        let opaque = [1, 2, 3, 255].repeat(16 * 16);
+        let image = PDFImage::from_png(&png(16, 16, png::ColorType::Rgba, &opaque)).unwrap();
+        let (_, stream) = written(&image);
+        assert!(stream.dict.get(b"SMask").is_err());
+        assert_eq!(decoded(&stream), [1, 2, 3].repeat(16 * 16));
+    }
+
+    #[test]
+    fn jpeg_color_spaces() {
+        for (components, color_space) in [(1, "DeviceGray"), (3, "DeviceRGB"), (4, "DeviceCMYK")] {
+            let data = jpeg(components, false);
+            let (_, stream) = written(&PDFImage::from_jpeg(&data).unwrap());
+            assert_eq!(name(&stream, b"ColorSpace"), color_space.as_bytes());
+            assert_eq!(stream.dict.get(b"Width").unwrap().as_i64().unwrap(), 4);
+            assert_eq!(stream.dict.get(b"Height").unwrap().as_i64().unwrap(), 3);
+            assert_eq!(
+                stream
+                    .dict
+                    .get(b"BitsPerComponent")
+                    .unwrap()
+                    .as_i64()
+                    .unwrap(),
+                8
+            );
+            assert_eq!(name(&stream, b"Filter"), b"DCTDecode");
+            assert!(stream.dict.get(b"Decode").is_err());
+            // the JPEG is embedded unchanged
+            assert_eq!(stream.content, data);
+        }
+    }
+
+    #[test]
+    fn jpeg_adobe_cmyk() {
+        let (_, stream) = written(&PDFImage::from_jpeg(&jpeg(4, true)).unwrap());
+        let decode = stream
+            .dict
+            .get(b"Decode")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|value| value.as_float().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(decode, [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+
+        // Adobe RGB JPEGs aren't inverted
+        let (_, stream) = written(&PDFImage::from_jpeg(&jpeg(3, true)).unwrap());
+        assert!(stream.dict.get(b"Decode").is_err());
+    }
+
+    #[test]
+    fn jpeg_invalid() {
+        assert!(matches!(
+            PDFImage::from_jpeg(&jpeg(2, false)),
+            Err(Error::ImageDecoding(_))
+        ));
+        assert!(PDFImage::from_jpeg(b"not a jpeg").is_err());
+        // ends before the frame header
+        assert!(PDFImage::from_jpeg(&jpeg(3, true)[..10]).is_err());
+    }
+}
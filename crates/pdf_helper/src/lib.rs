@@ -1,23 +1,78 @@
+/// `content_hash` hashes resources and documents with an
+/// algorithm that doesn't change between Rust releases
+mod content_hash;
+
+/// `image` embeds raster images from SVGs as PDF
+/// image XObjects
+mod image;
+
+/// `outline` holds the bookmarks, page labels, named destinations
+/// and metadata that make documents easy to navigate
+pub mod outline;
+
 /// `paper` contains information about various
 /// physical paper sizes.
 pub mod paper;
 
+/// `resources` holds the forms, images, patterns and graphics
+/// states drawn on pages
+mod resources;
+
+/// `rich_text` is text made of spans in different fonts, sizes and
+/// colors
+pub mod rich_text;
+
 /// `scale` is a ratio for scaling objects during PDF rendering
 pub mod scale;
 
+/// `standard_font` contains the metrics of the standard fonts built
+/// into every PDF reader
+pub mod standard_font;
+
+/// `subset` reduces TrueType fonts to the glyphs drawn in
+/// a document
+mod subset;
+
+/// `surface` is the page drawings are drawn on, which is either
+/// written into a PDF document or as an SVG image
+pub mod surface;
+
+/// `svg` draws pages as standalone SVG images instead of PDF
+pub mod svg;
+
+/// `table` lays out rows of text in columns, continued across
+/// pages
+pub mod table;
+
+/// `template` draws a drawing frame with zones, a title block and
+/// revision history onto pages
+pub mod template;
+
+/// `tile` splits drawings larger than a page across several
+/// overlapping pages
+pub mod tile;
+
 use lopdf::content::{Content, Operation};
 use lopdf::dictionary;
-use lopdf::{Document, Object, Stream};
+use lopdf::{Document, Object, Stream, StringFormat};
+use outline::{DocumentInfo, OutlineItem, PDFDate};
 
 use dimensioned::{ucum, Dimensionless};
 
 use log::warn;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::str;
 
+use content_hash::{ContentHash, ContentHasher};
+use image::PDFImage;
 use paragraph_breaker::Error as ParagraphError;
+pub use paragraph_breaker::{Alignment, BreakOptions};
+use resources::{PDFForm, PDFGraphicsState, PDFPattern, PDFShading, PDFSoftMask, Resources};
+use rich_text::{ParagraphLayout, RichParagraph, TextSpan, TextStyle};
+use standard_font::StandardFont;
 use usvg::Error as USVGError;
 
 /// `PDFDocument` is a helper type to properly generate PDFs
@@ -27,9 +82,16 @@ pub struct PDFDocument<'a> {
     /// default page size for PDF
     default_page_size: paper::PaperSize,
     /// Vector of fonts available to be used in a pdf.
-    available_fonts: Vec<PDFFont<'a>>,
+    pub available_fonts: Vec<PDFFont<'a>>,
     /// all the pages in the PDF document
     pub pages: Vec<PDFPage>,
+    /// title, author and other metadata of the document
+    pub info: DocumentInfo,
+    /// bookmarks shown in the outline of the document
+    pub outline: Vec<OutlineItem>,
+    /// names other documents and URLs can use to open the document at a page, mapped to the
+    /// zero indexed page number. These are checked when the document is written
+    pub destinations: BTreeMap<String, usize>,
 }
 /// `PDFFont` contains information about a font.
 pub struct PDFFont<'a> {
@@ -39,25 +101,60 @@ pub struct PDFFont<'a> {
     font_id_str: String,
     /// internal pdf font object id
     font_id: Option<lopdf::ObjectId>,
-    /// font data
-    font_face: rustybuzz::Face<'a>,
-    /// raw_font_data
-    font_data: Vec<u8>,
+    /// where the glyphs and metrics of the font come from
+    source: FontSource<'a>,
+}
+
+/// `FontSource` is where the glyphs and metrics of a `PDFFont` come from
+// there are only ever a few fonts in a document, so the size of the parsed face doesn't matter
+#[allow(clippy::large_enum_variant)]
+enum FontSource<'a> {
+    /// a TrueType or OpenType font file, which text is shaped with, and which is embedded in the
+    /// PDF
+    Embedded {
+        /// font data
+        font_face: rustybuzz::Face<'a>,
+        /// raw_font_data
+        font_data: Vec<u8>,
+        /// index of the font in `font_data`, which is only non zero for font collections
+        font_index: u32,
+    },
+    /// one of the standard fonts built into every PDF reader, which is measured with its AFM
+    /// metrics and isn't embedded
+    Standard(StandardFont),
 }
 /// `PDFPage` represents an individual page of a pdf file
 pub struct PDFPage {
     /// The list of operations for the page
     operations: Vec<Operation>,
+    /// The list of links to other pages placed on the page
+    links: Vec<PDFLink>,
+    /// forms, images and other resources drawn on the page
+    resources: Resources,
     /// The paper size of the page
     pub page_size: paper::PaperSize,
+    /// The orientation the page is laid out in
+    pub orientation: paper::Orientation,
     pub margins: Margins,
+    /// label PDF readers show for the page instead of its page number, such as a sheet number
+    pub label: Option<String>,
+}
+
+/// `PDFLink` is a clickable area of a page that jumps to another page of the same document
+struct PDFLink {
+    /// clickable area in PDF user space units, as left, bottom, right and top
+    rect: [f64; 4],
+    /// zero indexed page number of the destination page
+    destination_page: usize,
 }
 
 /// `PDFTextRenderMode` is an enumeration
 /// of defined text rendering modes in pdf documents
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 #[allow(clippy::exhaustive_enums)]
 pub enum PDFTextRenderMode {
     /// Normal Text, colored with current non-stroking color
+    #[default]
     Fill,
     /// Outline text, outlined with current stroking color
     Stroke,
@@ -86,6 +183,65 @@ pub struct Margins {
     pub right: ucum::Meter<f64>,
 }
 
+/// `TextBox` is a rectangular area of a page that text is set in, and how the text is placed
+/// within it
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct TextBox {
+    /// horizontal position of the left side of the box, with 0 on left side of page inside the
+    /// margin
+    pub x_pos: ucum::Meter<f64>,
+    /// vertical position of the bottom side of the box, with 0 on the bottom side of page inside
+    /// the margin
+    pub y_pos: ucum::Meter<f64>,
+    /// width of the box, which text is broken into lines to fit
+    pub width: ucum::Meter<f64>,
+    /// height of the box
+    pub height: ucum::Meter<f64>,
+    /// how lines are placed across the width of the box
+    pub alignment: Alignment,
+    /// how the lines are placed within the height of the box
+    pub vertical_alignment: VerticalAlignment,
+    /// what happens to text that doesn't fit in the box
+    pub overflow: Overflow,
+    /// where words can be broken across lines
+    pub break_options: BreakOptions,
+}
+
+/// `VerticalAlignment` is how the lines of text in a `TextBox` are placed within its height
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[allow(clippy::exhaustive_enums)]
+pub enum VerticalAlignment {
+    /// the first line is at the top of the box
+    #[default]
+    Top,
+    /// the lines are centered in the box
+    Middle,
+    /// the last line is at the bottom of the box
+    Bottom,
+}
+
+/// `Overflow` is what happens to text that is too tall or too wide for its `TextBox`
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[allow(clippy::exhaustive_enums)]
+pub enum Overflow {
+    /// text outside the box is drawn
+    #[default]
+    Visible,
+    /// text is clipped to the box
+    Clip,
+}
+
+/// `TextLayout` describes how text was set in a `TextBox`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TextLayout {
+    /// number of lines the text was broken into
+    pub line_count: usize,
+    /// total height of the lines
+    pub height: ucum::Meter<f64>,
+    /// whether the text is taller than the box, or has a word wider than the box
+    pub overflowed: bool,
+}
+
 impl PDFTextRenderMode {
     /// `value` returns the integer value of `PDFTextRenderMode` as needed in the internal PDF
     /// formatting
@@ -101,7 +257,302 @@ impl PDFTextRenderMode {
     }
 }
 
+impl<'a> PDFFont<'a> {
+    /// `face` returns the parsed font face, as used for shaping text, or `None` for a standard
+    /// font
+    #[must_use]
+    pub fn face(&self) -> Option<&rustybuzz::Face<'a>> {
+        match &self.source {
+            FontSource::Embedded { font_face, .. } => Some(font_face),
+            FontSource::Standard(_) => None,
+        }
+    }
+
+    /// `standard_font` returns which standard font this is, or `None` for a font loaded from a
+    /// file
+    #[must_use]
+    pub fn standard_font(&self) -> Option<StandardFont> {
+        match self.source {
+            FontSource::Embedded { .. } => None,
+            FontSource::Standard(font) => Some(font),
+        }
+    }
+
+    /// `units_per_em` returns the number of font units in the font size. The metrics of standard
+    /// fonts are always in thousandths of the font size.
+    fn units_per_em(&self) -> i32 {
+        match &self.source {
+            FontSource::Embedded { font_face, .. } => font_face.units_per_em(),
+            FontSource::Standard(_) => 1000,
+        }
+    }
+
+    /// `scale` converts a value in font units into a physical length at `font_size`
+    fn scale(&self, font_units: i16, font_size: u32) -> ucum::Meter<f64> {
+        let units_per_em = f64::from(self.units_per_em());
+        #[allow(clippy::arithmetic_side_effects)]
+        let length =
+            f64::from(font_units) * f64::from(font_size) * PDFDocument::pdf_point() / units_per_em;
+        length
+    }
+
+    /// `ascent` returns the height of the font above the baseline at `font_size`
+    #[must_use]
+    pub fn ascent(&self, font_size: u32) -> ucum::Meter<f64> {
+        match &self.source {
+            FontSource::Embedded { font_face, .. } => self.scale(font_face.ascender(), font_size),
+            FontSource::Standard(font) => self.scale(font.metrics().ascender, font_size),
+        }
+    }
+
+    /// `line_height` returns the distance between two baselines at `font_size`, without any
+    /// additional line spacing
+    ///
+    /// AFM metrics have no line gap, so lines of standard fonts are as far apart as the height
+    /// of the font bounding box, which is close to the spacing of similar TrueType fonts.
+    #[must_use]
+    pub fn line_height(&self, font_size: u32) -> ucum::Meter<f64> {
+        #[allow(clippy::arithmetic_side_effects)]
+        let line_height = match &self.source {
+            FontSource::Embedded { font_face, .. } => {
+                self.scale(font_face.ascender(), font_size)
+                    - self.scale(font_face.descender(), font_size)
+                    + self.scale(font_face.line_gap(), font_size)
+            }
+            FontSource::Standard(font) => {
+                let [_, bottom, _, top] = font.metrics().bounding_box;
+                self.scale(top, font_size) - self.scale(bottom, font_size)
+            }
+        };
+        line_height
+    }
+
+    /// `has_glyph` returns whether the font can show `character`
+    #[must_use]
+    pub fn has_glyph(&self, character: char) -> bool {
+        match &self.source {
+            FontSource::Embedded { font_face, .. } => font_face.glyph_index(character).is_some(),
+            FontSource::Standard(_) => standard_font::win_ansi_code(character).is_some(),
+        }
+    }
+
+    /// `text_width` returns the width of `text` set on a single line at `font_size`
+    #[must_use]
+    pub fn text_width(&self, text: &str, font_size: u32) -> ucum::Meter<f64> {
+        let advance: i64 = match &self.source {
+            FontSource::Embedded { font_face, .. } => {
+                let mut buffer = rustybuzz::UnicodeBuffer::new();
+                buffer.push_str(text);
+                let glyphs = rustybuzz::shape(font_face, &[], buffer);
+                glyphs
+                    .glyph_positions()
+                    .iter()
+                    .map(|position| i64::from(position.x_advance))
+                    .sum()
+            }
+            FontSource::Standard(font) => {
+                let metrics = font.metrics();
+                standard_font::encode(text)
+                    .into_iter()
+                    .map(|code| i64::from(metrics.width(code)))
+                    .sum()
+            }
+        };
+        let units_per_em = f64::from(self.units_per_em());
+        #[allow(clippy::arithmetic_side_effects, clippy::cast_precision_loss)]
+        let width = advance as f64 * f64::from(font_size) * PDFDocument::pdf_point() / units_per_em;
+        width
+    }
+
+    /// `to_lines` splits `text` into lines no wider than `text_width` at `font_size`
+    ///
+    /// # Errors
+    ///
+    /// Can error if the text is empty, or a word can't be broken small enough to fit in
+    /// `text_width`
+    pub fn to_lines(
+        &self,
+        text: &str,
+        font_size: u32,
+        text_width: ucum::Meter<f64>,
+        text_direction: rustybuzz::Direction,
+        text_language: rustybuzz::Language,
+        break_options: &BreakOptions,
+    ) -> Result<Vec<String>, ParagraphError> {
+        match &self.source {
+            FontSource::Embedded { font_face, .. } => {
+                let (lines, _glyphs) = paragraph_breaker::to_lines(
+                    text,
+                    font_face,
+                    font_size,
+                    text_width,
+                    text_direction,
+                    text_language,
+                    break_options,
+                )?;
+                Ok(lines)
+            }
+            FontSource::Standard(_) => paragraph_breaker::to_lines_by_width(
+                text,
+                |word| self.text_width(word, font_size),
+                self.text_width(" ", font_size),
+                text_width,
+                &text_language,
+                break_options,
+            ),
+        }
+    }
+
+    /// `glyph_width` returns the advance width of `glyph` in glyph space, where 1000 units are
+    /// the font size. Glyphs of standard fonts are their character codes.
+    fn glyph_width(&self, glyph: u16) -> f64 {
+        let advance = match &self.source {
+            FontSource::Embedded { font_face, .. } => font_face
+                .glyph_hor_advance(rustybuzz::ttf_parser::GlyphId(glyph))
+                .unwrap_or(0),
+            FontSource::Standard(font) => u8::try_from(glyph)
+                .map(|code| font.metrics().width(code))
+                .unwrap_or(0),
+        };
+        self.to_glyph_space(f64::from(advance))
+    }
+
+    /// `to_glyph_space` converts a value in font units into glyph space, where 1000 units are the
+    /// font size
+    #[allow(clippy::arithmetic_side_effects)]
+    fn to_glyph_space(&self, font_units: f64) -> f64 {
+        font_units * 1000.0 / f64::from(self.units_per_em())
+    }
+
+    /// `shape_line` shapes a line of `text` into the glyph IDs of this font, and returns them as
+    /// the operand of a `TJ` operator. Each glyph is recorded in `resources`, along with the text
+    /// it shows, so the font can be subset and the text extracted from the PDF. Standard fonts
+    /// are written as character codes instead.
+    ///
+    /// `word_spacing` is added after each space, in thousandths of the font size. `Tw` can't be
+    /// used for this, as it only applies to single byte character codes.
+    fn shape_line(
+        &self,
+        text: &str,
+        text_direction: rustybuzz::Direction,
+        text_language: rustybuzz::Language,
+        word_spacing: f64,
+        resources: &mut Resources,
+    ) -> Object {
+        let font_face = match &self.source {
+            FontSource::Embedded { font_face, .. } => font_face,
+            FontSource::Standard(_) => {
+                // standard fonts are written in WinAnsiEncoding, one byte per character, and
+                // aren't shaped
+                let mut operands = Vec::new();
+                let mut codes = Vec::new();
+                for character in text.chars() {
+                    let code = standard_font::win_ansi_code(character).unwrap_or_else(|| {
+                        warn!(
+                            "{} can't show {character:?}, so it is replaced with ?",
+                            self.font_name
+                        );
+                        b'?'
+                    });
+                    resources.add_glyph(&self.font_id_str, u16::from(code), &character.to_string());
+                    codes.push(code);
+                    if character == ' ' && word_spacing != 0.0 {
+                        operands.push(Object::String(
+                            std::mem::take(&mut codes),
+                            lopdf::StringFormat::Literal,
+                        ));
+                        operands.push((-word_spacing).into());
+                    }
+                }
+                if !codes.is_empty() {
+                    operands.push(Object::String(codes, lopdf::StringFormat::Literal));
+                }
+                return Object::Array(operands);
+            }
+        };
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(text_direction);
+        buffer.set_language(text_language);
+        buffer.set_cluster_level(rustybuzz::BufferClusterLevel::MonotoneCharacters);
+        let glyphs = rustybuzz::shape(font_face, &[], buffer);
+
+        // each cluster is the text from its byte offset up to the start of the next cluster
+        let mut cluster_starts = glyphs
+            .glyph_infos()
+            .iter()
+            .map(|info| info.cluster as usize)
+            .collect::<Vec<_>>();
+        cluster_starts.sort_unstable();
+        cluster_starts.dedup();
+        let cluster_text = |cluster: usize| {
+            let end = cluster_starts
+                .iter()
+                .find(|&&start| start > cluster)
+                .copied()
+                .unwrap_or(text.len());
+            text.get(cluster..end).unwrap_or_default()
+        };
+
+        let mut operands = Vec::new();
+        let mut run = Vec::new();
+        let mut previous_cluster = None;
+        for (info, position) in glyphs.glyph_infos().iter().zip(glyphs.glyph_positions()) {
+            // glyph IDs are 16 bit in TrueType and OpenType fonts
+            #[allow(clippy::cast_possible_truncation)]
+            let glyph = info.glyph_id as u16;
+            let cluster = info.cluster as usize;
+            let glyph_text = if previous_cluster == Some(cluster) {
+                ""
+            } else {
+                cluster_text(cluster)
+            };
+            previous_cluster = Some(cluster);
+            resources.add_glyph(&self.font_id_str, glyph, glyph_text);
+            run.extend_from_slice(&glyph.to_be_bytes());
+
+            // numbers in a TJ array move the next glyph back by thousandths of the font size
+            let spacing = if glyph_text == " " { word_spacing } else { 0.0 };
+            #[allow(clippy::arithmetic_side_effects)]
+            let adjustment = self.glyph_width(glyph)
+                - self.to_glyph_space(f64::from(position.x_advance))
+                - spacing;
+            if adjustment.abs() > 0.01 {
+                operands.push(Object::String(
+                    std::mem::take(&mut run),
+                    lopdf::StringFormat::Hexadecimal,
+                ));
+                operands.push(adjustment.into());
+            }
+        }
+        if !run.is_empty() {
+            operands.push(Object::String(run, lopdf::StringFormat::Hexadecimal));
+        }
+        Object::Array(operands)
+    }
+}
+
 impl PDFPage {
+    /// `new` returns an empty `PDFPage` of the specified size
+    #[must_use]
+    pub fn new(page_size: paper::PaperSize, margins: Margins) -> Self {
+        Self {
+            operations: Vec::new(),
+            links: Vec::new(),
+            resources: Resources::default(),
+            page_size,
+            orientation: paper::Orientation::Portrait,
+            margins,
+            label: None,
+        }
+    }
+
+    /// `size` returns the width and height of the page in its orientation, including margins
+    #[must_use]
+    pub fn size(&self) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+        self.page_size.oriented_size(self.orientation)
+    }
+
     /// `add_text` writes text into a page of a pdf at a specified position.
     ///
     /// # Arguments
@@ -135,20 +586,19 @@ impl PDFPage {
         text_language: rustybuzz::Language,
         text_render_mode: &PDFTextRenderMode,
     ) -> Result<(), Error> {
-        use paragraph_breaker::to_lines;
-        let (lines, glyphs) = to_lines(
+        let lines = font.to_lines(
             &text,
-            &font.font_face,
             font_size,
             text_width,
             text_direction,
-            text_language,
+            text_language.clone(),
+            &BreakOptions::default(),
         )?;
 
         let num_lines = lines.len();
 
         // then check to see if text starts inside page boundaries.
-        let current_page_size = self.page_size.size();
+        let current_page_size = self.size();
 
         #[allow(clippy::arithmetic_side_effects)]
         if x_pos > current_page_size.0
@@ -176,11 +626,11 @@ impl PDFPage {
             current_page_size.0, current_page_size.1}
         }
 
+        // save/push current graphics state. This is not allowed inside of a text element
+        self.operations.push(Operation::new("q", vec![]));
         // BT begins a text element. it takes no operands
         self.operations.push(Operation::new("BT", vec![]));
 
-        // save/push current graphics state
-        self.operations.push(Operation::new("q", vec![]));
         // Tf specifies the font and font size. Font scaling is complicated in PDFs. Reference
         // the reference for more info.
         // The into() methods are defined based on their paired .from() methods (this
@@ -205,157 +655,1003 @@ impl PDFPage {
         self.operations
             .push(Operation::new("Tr", vec![text_render_mode.value().into()]));
 
-        if num_lines == 1 {
-            // Tj prints a string literal to the page.
+        if num_lines > 1 {
+            // set leading / distance between baselines. This is in unscaled text space units,
+            // which are points.
+            #[allow(clippy::arithmetic_side_effects)]
+            let leading = *((font.line_height(font_size) * f64::from(line_spacing.max(1)))
+                / PDFDocument::pdf_point())
+            .value();
             self.operations
-                .push(Operation::new("Tj", vec![Object::string_literal(text)]));
+                .push(Operation::new("TL", vec![leading.into()]));
+        }
+        for (line_number, line) in lines.iter().enumerate() {
+            if line_number > 0 {
+                // T* moves to the start of the next line, using the spacing set by TL
+                self.operations.push(Operation::new("T*", vec![]));
+            }
+            // TJ shows the glyphs of the line, with adjustments where shaping moved glyphs away
+            // from their default advance widths
+            let glyphs = font.shape_line(
+                line,
+                text_direction,
+                text_language.clone(),
+                0.0,
+                &mut self.resources,
+            );
+            self.operations.push(Operation::new("TJ", vec![glyphs]));
+        }
+        // ET ends the text element
+        self.operations.push(Operation::new("ET", vec![]));
+        // restore/pop old graphics state
+        self.operations.push(Operation::new("Q", vec![]));
+        Ok(())
+    }
+
+    /// `add_text_box` sets text inside `text_box`, broken into lines that fit its width and
+    /// aligned within it. Returns how the text was laid out, including whether it overflowed the
+    /// box, so callers can shrink the text or warn about it.
+    ///
+    /// Text that is taller than the box starts at the top of the box whatever its vertical
+    /// alignment, and runs past the bottom unless it is clipped. Words too wide for the box are
+    /// broken as set by its `break_options`, or between characters if they can't be.
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: text to set. Empty text draws nothing.
+    /// * `font_size`: size of the font in points
+    /// * `font`: font to set the text in
+    /// * `line_spacing`: distance between baselines, as a multiple of the line height of the
+    ///   font
+    /// * `text_box`: position, size and alignment of the text, with 0 on the left and bottom side
+    ///   of the page inside the margin
+    ///
+    /// # Errors
+    ///
+    /// Can error if text fails to shape or split into lines
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_text_box(
+        &mut self,
+        text: &str,
+        font_size: u32,
+        font: &PDFFont,
+        line_spacing: u32,
+        text_box: &TextBox,
+        text_direction: rustybuzz::Direction,
+        text_language: rustybuzz::Language,
+        text_render_mode: &PDFTextRenderMode,
+    ) -> Result<TextLayout, Error> {
+        let span = TextSpan {
+            render_mode: *text_render_mode,
+            ..TextSpan::new(text, font, font_size)
+        };
+        self.add_rich_text(
+            &[span],
+            &[],
+            line_spacing,
+            text_box,
+            text_direction,
+            text_language,
+        )
+    }
+
+    /// `add_rich_text` sets a paragraph made of `spans` in different fonts, sizes and colors
+    /// inside `text_box`, the same way as `add_text_box`. Lines are broken across spans, and
+    /// each line is as tall as the largest text on it.
+    ///
+    /// Characters the font of their span has no glyph for, such as symbols missing from a
+    /// standard font, are set in the first of `fallback_fonts` that has one.
+    ///
+    /// # Errors
+    ///
+    /// Can error if text fails to shape or split into lines
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn add_rich_text(
+        &mut self,
+        spans: &[TextSpan],
+        fallback_fonts: &[PDFFont],
+        line_spacing: u32,
+        text_box: &TextBox,
+        text_direction: rustybuzz::Direction,
+        text_language: rustybuzz::Language,
+    ) -> Result<TextLayout, Error> {
+        let paragraph = RichParagraph::new(spans, fallback_fonts);
+        let text = paragraph.text.as_str();
+        let ParagraphLayout {
+            lines,
+            line_metrics,
+            spacing,
+            text_layout: layout,
+        } = paragraph.layout(line_spacing, text_box, &text_language)?;
+        let height = layout.height;
+        if lines.is_empty() {
+            return Ok(layout);
+        }
+
+        let box_top = text_box.y_pos + text_box.height;
+        let mut line_top = if height > text_box.height {
+            box_top
         } else {
-            // set leading / line gap
-            self.operations
-                .push(Operation::new("TL", vec![font.font_face.line_gap().into()]));
-            // push first line
+            match text_box.vertical_alignment {
+                VerticalAlignment::Top => box_top,
+                VerticalAlignment::Middle => box_top - (text_box.height - height) / 2.0,
+                VerticalAlignment::Bottom => text_box.y_pos + height,
+            }
+        };
+
+        // save/push current graphics state, so the clipping path and colors only apply to this
+        // text
+        self.operations.push(Operation::new("q", vec![]));
+        if text_box.overflow == Overflow::Clip {
+            // re adds a rectangle to the path, W makes it the clipping path and n ends the path
+            // without filling or stroking it
             self.operations.push(Operation::new(
-                "Tj",
-                vec![Object::string_literal(lines[1].as_str())],
+                "re",
+                vec![
+                    to_points(text_box.x_pos + self.margins.left).into(),
+                    to_points(text_box.y_pos + self.margins.bottom).into(),
+                    to_points(text_box.width).into(),
+                    to_points(text_box.height).into(),
+                ],
             ));
-            // push remaining lines
-            for line in &lines[2..] {
-                // `'` operation moves to next line using value specified by TL for spacing
-                // and shows a line of text
-                // TODO: add line spacing here
-                self.operations.push(Operation::new(
-                    "'",
-                    vec![Object::string_literal(line.as_str())],
-                ));
+            self.operations.push(Operation::new("W", vec![]));
+            self.operations.push(Operation::new("n", vec![]));
+        }
+        self.operations.push(Operation::new("BT", vec![]));
+        let mut style = None;
+        for (line_number, (line, (ascent, line_height))) in
+            lines.iter().zip(&line_metrics).enumerate()
+        {
+            // the pieces of text on the line, with the words separated by spaces in the style of
+            // the text before them
+            let mut pieces = Vec::new();
+            for (word_number, word) in line.words.iter().enumerate() {
+                for (piece, run) in paragraph.pieces(word.clone()) {
+                    pieces.push((&text[piece], run));
+                }
+                let separator = if word_number + 1 < line.words.len() {
+                    " "
+                } else if line.hyphen {
+                    "-"
+                } else {
+                    continue;
+                };
+                if let Some(run) = paragraph.run_at(word.end.saturating_sub(1)) {
+                    pieces.push((separator, run));
+                }
             }
+            let line_width = pieces
+                .iter()
+                .fold(0.0_f64 * ucum::M, |width, (piece, run)| {
+                    width + run.font.text_width(piece, run.span.font_size)
+                });
+            let (offset, extra_space) = text_box.alignment.place_line(
+                line_width,
+                line.words.len().saturating_sub(1),
+                text_box.width,
+                line_number + 1 == lines.len(),
+            );
+
+            // Tm sets the text matrix, which places each line separately as they can start at
+            // different positions
+            self.operations.push(Operation::new(
+                "Tm",
+                vec![
+                    1.into(),
+                    0.into(),
+                    0.into(),
+                    1.into(),
+                    to_points(text_box.x_pos + self.margins.left + offset).into(),
+                    to_points(line_top - *ascent + self.margins.bottom).into(),
+                ],
+            ));
+            for (piece, run) in pieces {
+                let span = run.span;
+                let piece_style = TextStyle::of(run);
+                if style != Some(piece_style) {
+                    self.operations
+                        .extend(piece_style.operations(style.as_ref()));
+                    style = Some(piece_style);
+                }
+                // TJ shows the glyphs of the piece, with the extra word spacing of justified
+                // lines in thousandths of its font size
+                let glyphs = run.font.shape_line(
+                    piece,
+                    text_direction,
+                    text_language.clone(),
+                    to_points(extra_space) * 1000.0 / f64::from(span.font_size),
+                    &mut self.resources,
+                );
+                self.operations.push(Operation::new("TJ", vec![glyphs]));
+            }
+            line_top -= *line_height * spacing;
         }
-        // restore/pop old graphics state
-        self.operations.push(Operation::new("Q", vec![]));
-        // ET ends the text element
         self.operations.push(Operation::new("ET", vec![]));
-        Ok(())
+        self.operations.push(Operation::new("Q", vec![]));
+        Ok(layout)
+    }
+
+    /// `add_line` strokes a straight line through each of `points` in turn.
+    ///
+    /// # Arguments
+    ///
+    /// * `points`: (x, y) positions of the line, with 0 on the left and bottom side of the page
+    ///   inside the margin
+    /// * `line_width`: width of the stroked line
+    /// * `dashed`: draws a dashed line instead of a solid one
+    pub fn add_line(
+        &mut self,
+        points: &[(ucum::Meter<f64>, ucum::Meter<f64>)],
+        line_width: ucum::Meter<f64>,
+        dashed: bool,
+    ) {
+        let Some(((first_x, first_y), remaining_points)) = points.split_first() else {
+            return;
+        };
+        // save graphics state so the line width and dash pattern don't leak into later
+        // operations
+        self.operations.push(Operation::new("q", vec![]));
+        self.operations
+            .push(Operation::new("w", vec![to_points(line_width).into()]));
+        if dashed {
+            // d sets the dash pattern: an array of dash and gap lengths, and the phase to start at
+            let dash = to_points(line_width).max(1.0) * 3.0;
+            self.operations.push(Operation::new(
+                "d",
+                vec![vec![dash.into(), dash.into()].into(), 0.into()],
+            ));
+        }
+        let (x, y) = self.page_position(*first_x, *first_y);
+        self.operations
+            .push(Operation::new("m", vec![x.into(), y.into()]));
+        for (x_pos, y_pos) in remaining_points {
+            let (x, y) = self.page_position(*x_pos, *y_pos);
+            self.operations
+                .push(Operation::new("l", vec![x.into(), y.into()]));
+        }
+        // S strokes the current path
+        self.operations.push(Operation::new("S", vec![]));
+        self.operations.push(Operation::new("Q", vec![]));
+    }
+
+    /// `add_rectangle` strokes the outline of a rectangle.
+    ///
+    /// # Arguments
+    ///
+    /// * `x_pos`: horizontal position of the left side of the rectangle, with 0 on left side of
+    ///   page inside the margin
+    /// * `y_pos`: vertical position of the bottom side of the rectangle, with 0 on the bottom side
+    ///   of page inside the margin
+    /// * `width`: width of the rectangle
+    /// * `height`: height of the rectangle
+    /// * `line_width`: width of the stroked outline
+    pub fn add_rectangle(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        line_width: ucum::Meter<f64>,
+    ) {
+        let (x, y) = self.page_position(x_pos, y_pos);
+        self.operations.push(Operation::new("q", vec![]));
+        self.operations
+            .push(Operation::new("w", vec![to_points(line_width).into()]));
+        // re appends a rectangle to the current path as a complete subpath
+        self.operations.push(Operation::new(
+            "re",
+            vec![
+                x.into(),
+                y.into(),
+                to_points(width).into(),
+                to_points(height).into(),
+            ],
+        ));
+        self.operations.push(Operation::new("S", vec![]));
+        self.operations.push(Operation::new("Q", vec![]));
+    }
+
+    /// `in_layer` marks everything `draw` draws on the page as part of the layer named `layer`,
+    /// and returns what `draw` returns. Layers are optional content groups, which PDF readers
+    /// list by name so they can be hidden and shown. Layers with the same name on different
+    /// pages are the same layer, and layers can be nested.
+    pub fn in_layer<T>(&mut self, layer: &str, draw: impl FnOnce(&mut Self) -> T) -> T {
+        let name = self.resources.add_layer(layer);
+        // BDC begins a marked content sequence, which is optional content with the OC tag
+        self.operations.push(Operation::new(
+            "BDC",
+            vec!["OC".into(), Object::Name(name.into_bytes())],
+        ));
+        let drawn = draw(self);
+        // EMC ends the marked content sequence
+        self.operations.push(Operation::new("EMC", vec![]));
+        drawn
+    }
+
+    /// `add_link` makes a rectangular area of the page a link to another page in the same
+    /// document. The area is not drawn, so it is normally placed over text or a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `x_pos`: horizontal position of the left side of the area, with 0 on left side of
+    ///   page inside the margin
+    /// * `y_pos`: vertical position of the bottom side of the area, with 0 on the bottom side
+    ///   of page inside the margin
+    /// * `width`: width of the area
+    /// * `height`: height of the area
+    /// * `destination_page`: zero indexed page number in the document to link to. This is
+    ///   checked when the document is written
+    pub fn add_link(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        destination_page: usize,
+    ) {
+        let (left, bottom) = self.page_position(x_pos, y_pos);
+        #[allow(clippy::arithmetic_side_effects)]
+        let (right, top) = self.page_position(x_pos + width, y_pos + height);
+        self.links.push(PDFLink {
+            rect: [left, bottom, right, top],
+            destination_page,
+        });
+    }
+
+    /// `page_position` converts a position inside the margins into PDF user space coordinates
+    #[allow(clippy::arithmetic_side_effects)]
+    fn page_position(&self, x_pos: ucum::Meter<f64>, y_pos: ucum::Meter<f64>) -> (f64, f64) {
+        (
+            to_points(x_pos + self.margins.left),
+            to_points(y_pos + self.margins.bottom),
+        )
+    }
+
+    /// `media_transform` returns the matrix that maps positions in the page layout onto its media
+    /// box, or `None` if they are the same.
+    ///
+    /// Landscape pages are written as portrait media rotated a quarter turn clockwise, so the
+    /// layout is turned a quarter turn anticlockwise onto the media.
+    fn media_transform(&self) -> Option<[f64; 6]> {
+        match self.orientation {
+            paper::Orientation::Portrait => None,
+            paper::Orientation::Landscape => {
+                Some([0.0, 1.0, -1.0, 0.0, to_points(self.page_size.size().0), 0.0])
+            }
+        }
     }
 
-    //TODO: need to be able to specify location
     /// `add_svg` parses an SVG image, converts it into pdf graphics operators,
-    /// and inserts it into the referenced pdf document at the specified page
+    /// and places it on the page with its bottom left corner at (`x_pos`, `y_pos`). Returns the
+    /// width and height of the image on the page.
     ///
-    /// Heavily inspired by [resvg](https://github.com/RazrFalcon/resvg)
+    /// Each distinct SVG is converted once into a Form XObject that is written once in the
+    /// document, however many times it is placed on any page. An SVG placed again with
+    /// different `fonts` is converted again.
     ///
     /// # Arguments
     ///
-    /// * `page_index`: The zero indexed page number that the text should be inserted into.
-    /// * `text`: the text that will be added to the pdf page
-    /// * `font`: a `PDFFont` object representing a font defined for use in a PDF document
-    /// * `font_size`: Font size specified in points. This is used internally as a multiple
-    /// of the standard PDF user space unit size 1/72 inch
-    /// * `line_spacing`: text line spacing in multiples of line height
-    /// * `text width`: An optional parameter that defines the width of the text element. If
-    /// `None`, the width of the page minus the margins is used.
-    /// * `x_pos`: horizontal starting position of text insertion, with 0 on left side of page
-    /// * `y_pos`: vertical starting position of text insertion, with 0 on the bottom side of page
+    /// * `svg_string`: the SVG image to add to the page
+    /// * `x_pos`: horizontal position of the left side of the image, with 0 on left side of
+    ///   page inside the margin
+    /// * `y_pos`: vertical position of the bottom side of the image, with 0 on the bottom side
+    ///   of page inside the margin
+    /// * `scale`: optional - scale of the image relative to its full size. See `ScalingFactor`
+    /// * `fonts`: fonts used to draw text in the SVG. Text in a font family that isn't
+    ///   available is drawn in the first font, and text is left out if there are no fonts.
+    ///   Standard fonts have no outlines, so can't be used to draw SVG text.
+    /// * `resources_dir`: optional - directory that images referenced by relative paths in the
+    ///   SVG are loaded from. Defaults to the current directory.
     ///
     /// # Errors
     ///
-    /// May error due to malformed SVGs or other errors
+    /// May error due to malformed SVGs or an invalid scale
     pub fn add_svg(
         &mut self,
         svg_string: &str,
         x_pos: ucum::Meter<f64>,
         y_pos: ucum::Meter<f64>,
         scale: Option<scale::ScalingFactor>,
-    ) -> Result<(), Error> {
-        use usvg::Tree;
-        let parse_options = usvg::Options::default();
-        let tree = Tree::from_str(svg_string, &parse_options)?;
-        // TODO: either handle or forbid text nodes
-        if tree.has_text_nodes() {
-            return Err(Error::Other("Text Nodes in tree".to_string()));
-        }
-        let new_operations = loop_nodes(tree.root(), x_pos, y_pos, scale);
-        self.operations.extend(new_operations);
-        Ok(())
+        fonts: &[PDFFont],
+        resources_dir: Option<&Path>,
+    ) -> Result<(ucum::Meter<f64>, ucum::Meter<f64>), Error> {
+        let name = form_name(svg_string, resources_dir, fonts);
+        let form = if let Some(form) = self.resources.forms.get(&name) {
+            form
+        } else {
+            let tree = usvg::Tree::from_str(svg_string, &svg_options(fonts, resources_dir))?;
+            let form = PDFForm {
+                operations: loop_nodes(
+                    tree.root(),
+                    usvg::Transform::identity(),
+                    &mut self.resources,
+                ),
+                bbox: [
+                    0.0,
+                    0.0,
+                    f64::from(tree.size().width()),
+                    f64::from(tree.size().height()),
+                ],
+                matrix: None,
+                transparency_group: false,
+            };
+            self.resources.forms.entry(name.clone()).or_insert(form)
+        };
+        let [_, _, width, height] = form.bbox;
+        let factor = scale
+            .unwrap_or_default()
+            .factor()
+            .ok_or_else(|| Error::Other(format!("invalid scale: {}", scale.unwrap_or_default())))?;
+        let (x, y) = self.page_position(x_pos, y_pos);
+        // SVG user units are CSS pixels, 96 per inch, with the y axis pointing down the page.
+        // Map them onto PDF points with the y axis flipped, so the whole image can be emitted
+        // in its own coordinates.
+        let units = factor * 72.0 / 96.0;
+        #[allow(clippy::arithmetic_side_effects)]
+        let top = y + height * units;
+        self.operations.push(Operation::new("q", vec![]));
+        self.operations.push(Operation::new(
+            "cm",
+            vec![
+                units.into(),
+                0.into(),
+                0.into(),
+                (-units).into(),
+                x.into(),
+                top.into(),
+            ],
+        ));
+        // Do draws the named XObject
+        self.operations
+            .push(Operation::new("Do", vec![Object::Name(name.into_bytes())]));
+        self.operations.push(Operation::new("Q", vec![]));
+        let pixel = (1.0_f64 / 96.0_f64) * ucum::IN_US;
+        #[allow(clippy::arithmetic_side_effects)]
+        Ok((width * factor * pixel, height * factor * pixel))
     }
 }
-/// `loop_nodes` loops over a SVG tree or subtree and outputs a vector of PDF operations
+
+/// `form_name` returns the resource name of the Form XObject of an SVG. The name is based on the
+/// SVG source, the directory its images are loaded from and the fonts its text is drawn in, so
+/// the same SVG drawn the same way always has the same name.
+fn form_name(svg_string: &str, resources_dir: Option<&Path>, fonts: &[PDFFont]) -> String {
+    let mut hasher = ContentHasher::new();
+    svg_string.content_hash(&mut hasher);
+    resources_dir
+        .map(|dir| dir.to_string_lossy())
+        .as_deref()
+        .content_hash(&mut hasher);
+    // the first font is the fallback for the others, so the order of the fonts matters too
+    hasher.write_u64(u64::try_from(fonts.len()).unwrap_or(u64::MAX));
+    for font in fonts {
+        font.font_name.as_str().content_hash(&mut hasher);
+        font.font_id_str.as_str().content_hash(&mut hasher);
+        if let FontSource::Embedded {
+            font_data,
+            font_index,
+            ..
+        } = &font.source
+        {
+            hasher.write_bool(true);
+            hasher.write_u64(u64::try_from(font_data.len()).unwrap_or(u64::MAX));
+            hasher.write_u64(u64::from(*font_index));
+        } else {
+            hasher.write_bool(false);
+        }
+    }
+    format!("SVG{:016x}", hasher.finish())
+}
+
+/// `image_name` returns the resource name of the image XObject of raster image data
+fn image_name(data: &[u8]) -> String {
+    let mut hasher = ContentHasher::new();
+    hasher.write(data);
+    format!("IMG{:016x}", hasher.finish())
+}
+
+/// `document_id` returns a 16 byte identifier for a document, hashed from its `contents`
+fn document_id(contents: &[u8]) -> Vec<u8> {
+    // each half of the identifier is hashed with a different prefix
+    [0_u64, 1]
+        .into_iter()
+        .flat_map(|half| {
+            let mut hasher = ContentHasher::new();
+            hasher.write_u64(half);
+            hasher.write(contents);
+            hasher.finish().to_be_bytes()
+        })
+        .collect()
+}
+
+/// `subset_tag` returns the six capital letters that prefix the name of a subset of the font
+/// named `font_name` containing `glyphs`
+fn subset_tag(font_name: &str, glyphs: &BTreeSet<u16>) -> String {
+    let mut hasher = ContentHasher::new();
+    font_name.content_hash(&mut hasher);
+    hasher.write_u64(u64::try_from(glyphs.len()).unwrap_or(u64::MAX));
+    for glyph in glyphs {
+        hasher.write_u64(u64::from(*glyph));
+    }
+    let mut hash = hasher.finish();
+    (0..6)
+        .map(|_| {
+            // always less than 26
+            #[allow(clippy::cast_possible_truncation)]
+            let letter = (hash % 26) as u8;
+            hash /= 26;
+            char::from(b'A' + letter)
+        })
+        .collect()
+}
+
+/// `glyph_widths` returns the `W` array of a CIDFont, giving the advance width of each of
+/// `glyphs` of `font` in glyph space. Glyphs with consecutive IDs share an entry.
+fn glyph_widths(font: &PDFFont, glyphs: &BTreeMap<u16, String>) -> Vec<Object> {
+    let mut widths: Vec<Object> = Vec::new();
+    let mut run: Vec<Object> = Vec::new();
+    let mut previous = None;
+    for &glyph in glyphs.keys() {
+        if previous.is_none_or(|previous: u16| previous.checked_add(1) != Some(glyph)) {
+            if !run.is_empty() {
+                widths.push(std::mem::take(&mut run).into());
+            }
+            widths.push(glyph.into());
+        }
+        run.push(font.glyph_width(glyph).into());
+        previous = Some(glyph);
+    }
+    if !run.is_empty() {
+        widths.push(run.into());
+    }
+    widths
+}
+
+/// `to_unicode_cmap` returns a `ToUnicode` CMap that maps the two byte glyph ID of each of
+/// `glyphs` to the text it shows, encoded as UTF-16
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, String>) -> Vec<u8> {
+    use std::fmt::Write;
+    let mut cmap = String::from(concat!(
+        "/CIDInit /ProcSet findresource begin\n",
+        "12 dict begin\n",
+        "begincmap\n",
+        "/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n",
+        "/CMapName /Adobe-Identity-UCS def\n",
+        "/CMapType 2 def\n",
+        "1 begincodespacerange\n",
+        "<0000> <FFFF>\n",
+        "endcodespacerange\n",
+    ));
+    let mappings = glyphs
+        .iter()
+        .filter(|(_, text)| !text.is_empty())
+        .collect::<Vec<_>>();
+    // each bfchar section can hold at most 100 mappings
+    for section in mappings.chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfchar", section.len());
+        for (glyph, text) in section {
+            let utf16 = text
+                .encode_utf16()
+                .map(|unit| format!("{unit:04X}"))
+                .collect::<String>();
+            let _ = writeln!(cmap, "<{glyph:04X}> <{utf16}>");
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str(concat!(
+        "endcmap\n",
+        "CMapName currentdict /CMap defineresource pop\n",
+        "end\n",
+        "end\n",
+    ));
+    cmap.into_bytes()
+}
+
+/// `svg_options` returns the options for parsing SVGs with text drawn in `fonts`, and images
+/// referenced by relative paths loaded from `resources_dir`.
+///
+/// Every generic font family is set to the first font loaded from a file, which makes it the
+/// fallback for any font family in the SVG that isn't loaded.
+fn svg_options(fonts: &[PDFFont], resources_dir: Option<&Path>) -> usvg::Options<'static> {
+    let mut font_database = usvg::fontdb::Database::new();
+    // standard fonts have no outlines to draw text with
+    for font in fonts {
+        if let FontSource::Embedded { font_data, .. } = &font.source {
+            font_database.load_font_data(font_data.clone());
+        }
+    }
+    let default_family = font_database
+        .faces()
+        .next()
+        .and_then(|face| face.families.first())
+        .map(|(family, _language)| family.clone());
+    let mut options = usvg::Options::default();
+    if let Some(family) = default_family {
+        font_database.set_serif_family(family.as_str());
+        font_database.set_sans_serif_family(family.as_str());
+        font_database.set_monospace_family(family.as_str());
+        font_database.set_cursive_family(family.as_str());
+        font_database.set_fantasy_family(family.as_str());
+        options.font_family = family;
+    }
+    options.fontdb = std::sync::Arc::new(font_database);
+    options.resources_dir = resources_dir.map(Path::to_path_buf);
+    options
+}
+
+/// `svg_size` returns the full size width and height of an SVG image, as placed by
+/// `PDFPage::add_svg` at a 1:1 scale
+///
+/// # Errors
+///
+/// Will error if the SVG fails to parse
+pub fn svg_size(svg_string: &str) -> Result<(ucum::Meter<f64>, ucum::Meter<f64>), Error> {
+    let tree = usvg::Tree::from_str(svg_string, &usvg::Options::default())?;
+    let pixel = (1.0_f64 / 96.0_f64) * ucum::IN_US;
+    #[allow(clippy::arithmetic_side_effects)]
+    Ok((
+        f64::from(tree.size().width()) * pixel,
+        f64::from(tree.size().height()) * pixel,
+    ))
+}
+
+/// `transform_rect` applies the PDF transformation `matrix` to `rect`, given as left, bottom,
+/// right and top, and returns the rectangle bounding the result in the same form
+#[allow(clippy::arithmetic_side_effects)]
+fn transform_rect(rect: [f64; 4], matrix: [f64; 6]) -> [f64; 4] {
+    let [a, b, c, d, e, f] = matrix;
+    let [left, bottom, right, top] = rect;
+    let corners =
+        [(left, bottom), (right, top)].map(|(x, y)| (a * x + c * y + e, b * x + d * y + f));
+    let [(x1, y1), (x2, y2)] = corners;
+    [x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)]
+}
+
+/// `to_points` converts a length into PDF user space units
+#[allow(clippy::arithmetic_side_effects)]
+fn to_points(length: ucum::Meter<f64>) -> f64 {
+    *(length / PDFDocument::pdf_point()).value()
+}
+
+/// `loop_nodes` loops over a SVG tree or subtree and outputs a vector of PDF operations.
+/// Images, patterns and other resources drawn by the operations are added to `resources`.
+///
+/// `base` is the transform from the coordinates the nodes are positioned in onto the content
+/// stream the operations are written to. Patterns are positioned relative to the content stream
+/// rather than the current transform, so they are transformed by it.
 fn loop_nodes(
     parent: &usvg::Group,
-    x_pos: ucum::Meter<f64>,
-    y_pos: ucum::Meter<f64>,
-    scale: Option<scale::ScalingFactor>,
+    base: usvg::Transform,
+    resources: &mut Resources,
 ) -> Vec<Operation> {
     let mut group_operations = Vec::new();
     for node in parent.children() {
         //TODO: investigate subroots
         let operation = match node {
-            usvg::Node::Group(group) => loop_nodes(group, x_pos, y_pos, scale),
-            usvg::Node::Path(ref path) => convert_path(path, x_pos, y_pos, scale),
-            usvg::Node::Image(ref image) => convert_image(image, x_pos, y_pos, scale),
-            usvg::Node::Text(_) => Vec::new(), // should already be converted into paths
+            usvg::Node::Group(group) => convert_group(group, base, resources),
+            usvg::Node::Path(ref path) => convert_path(path, base, resources),
+            usvg::Node::Image(ref image) => convert_image(image, base, resources),
+            usvg::Node::Text(ref text) => convert_text(text, base, resources),
         };
         group_operations.extend(operation);
     }
     group_operations
 }
 
-/// `convert_path` convets an SVG path element into a vector of PDF operations
-/// `scale` parameter - optional - specifies the scale of the rendered objects relative to their full size,
-/// represented as `a`:`b`.
-/// For example, 1:2 would double the size of the object on the page, relative to its actual size,
-/// and 2:1 would half the size of the object. This is equal scaling in both X and Y direction.
+/// `convert_group` converts an SVG group into a vector of PDF operations, applying its opacity,
+/// mask and clip path to its children.
+///
+/// Groups with an opacity or a mask are drawn as transparency group forms, so their children
+/// are combined before the opacity and mask are applied to them as a whole.
+fn convert_group(
+    group: &usvg::Group,
+    base: usvg::Transform,
+    resources: &mut Resources,
+) -> Vec<Operation> {
+    if !group.filters().is_empty() {
+        warn! {"svg filters are not supported, ignoring"};
+    }
+    let opacity = group.opacity().get();
+    let mut new_operations = if opacity < 1.0 || group.mask().is_some() {
+        // the form is drawn in the current coordinate space, which patterns inside it are
+        // positioned relative to
+        let form = PDFForm {
+            operations: loop_nodes(group, usvg::Transform::identity(), resources),
+            bbox: rect_bounds(group.abs_layer_bounding_box().to_rect()),
+            matrix: None,
+            transparency_group: true,
+        };
+        let form_name = resources.add_form(form);
+        let mut group_operations = vec![Operation::new("q", vec![])];
+        if let Some(mask) = group.mask() {
+            let state = mask_graphics_state(mask, group.abs_transform(), resources);
+            group_operations.push(Operation::new("gs", vec![Object::Name(state.into_bytes())]));
+        }
+        if opacity < 1.0 {
+            let state = resources.add_graphics_state(PDFGraphicsState {
+                fill_alpha: Some(f64::from(opacity)),
+                stroke_alpha: Some(f64::from(opacity)),
+                soft_mask: None,
+            });
+            group_operations.push(Operation::new("gs", vec![Object::Name(state.into_bytes())]));
+        }
+        group_operations.push(Operation::new(
+            "Do",
+            vec![Object::Name(form_name.into_bytes())],
+        ));
+        group_operations.push(Operation::new("Q", vec![]));
+        group_operations
+    } else {
+        loop_nodes(group, base, resources)
+    };
+    if let Some(clip_path) = group.clip_path() {
+        let mut clipped_operations = vec![Operation::new("q", vec![])];
+        clipped_operations.extend(clip_operations(clip_path, group.abs_transform()));
+        clipped_operations.append(&mut new_operations);
+        clipped_operations.push(Operation::new("Q", vec![]));
+        new_operations = clipped_operations;
+    }
+    new_operations
+}
+
+/// `mask_graphics_state` adds a graphics state that masks everything drawn with it by `mask`,
+/// and returns its name. `transform` maps the coordinates of the masked element onto the current
+/// coordinate space.
+fn mask_graphics_state(
+    mask: &usvg::Mask,
+    transform: usvg::Transform,
+    resources: &mut Resources,
+) -> String {
+    let rect = mask.rect().to_rect();
+    let [left, top, right, bottom] = rect_bounds(rect);
+    // nothing outside of the mask rectangle is drawn
+    let mut operations = vec![
+        Operation::new(
+            "re",
+            vec![
+                left.into(),
+                top.into(),
+                (right - left).into(),
+                (bottom - top).into(),
+            ],
+        ),
+        Operation::new("W", vec![]),
+        Operation::new("n", vec![]),
+    ];
+    // a mask can be masked itself, in the same coordinates
+    if let Some(inner_mask) = mask.mask() {
+        let state = mask_graphics_state(inner_mask, usvg::Transform::identity(), resources);
+        operations.push(Operation::new("gs", vec![Object::Name(state.into_bytes())]));
+    }
+    operations.extend(loop_nodes(
+        mask.root(),
+        usvg::Transform::identity(),
+        resources,
+    ));
+    let form = resources.add_form(PDFForm {
+        operations,
+        bbox: [left, top, right, bottom],
+        matrix: Some(transform_matrix(transform)),
+        transparency_group: true,
+    });
+    resources.add_graphics_state(PDFGraphicsState {
+        soft_mask: Some(PDFSoftMask {
+            luminosity: mask.kind() == usvg::MaskType::Luminance,
+            form,
+        }),
+        ..PDFGraphicsState::default()
+    })
+}
+
+/// `clip_operations` returns the PDF operations that clip everything drawn after them to
+/// `clip_path`. `transform` maps the coordinates of the clipped element onto the current
+/// coordinate space.
+///
+/// The outlines of every shape in the clip path are combined into one clipping path.
+fn clip_operations(clip_path: &usvg::ClipPath, transform: usvg::Transform) -> Vec<Operation> {
+    // a clip path can be clipped itself, and successive clipping paths intersect
+    let mut new_operations = clip_path
+        .clip_path()
+        .map(|inner_clip_path| clip_operations(inner_clip_path, transform))
+        .unwrap_or_default();
+    let mut outlines = Vec::new();
+    let mut even_odd = true;
+    clip_outlines(
+        clip_path.root(),
+        transform.pre_concat(clip_path.transform()),
+        &mut outlines,
+        &mut even_odd,
+    );
+    if outlines.is_empty() {
+        // an empty clip path hides everything
+        outlines.push(Operation::new(
+            "re",
+            vec![0.into(), 0.into(), 0.into(), 0.into()],
+        ));
+    }
+    new_operations.extend(outlines);
+    // W sets the clipping path to the current path, and n ends the path without drawing it
+    new_operations.push(Operation::new(if even_odd { "W*" } else { "W" }, vec![]));
+    new_operations.push(Operation::new("n", vec![]));
+    new_operations
+}
+
+/// `clip_outlines` adds the outlines of every shape in a clip path group to `outlines`,
+/// transformed by `transform`. Clears `even_odd` if any of them use the nonzero clip rule.
+fn clip_outlines(
+    parent: &usvg::Group,
+    transform: usvg::Transform,
+    outlines: &mut Vec<Operation>,
+    even_odd: &mut bool,
+) {
+    for node in parent.children() {
+        match node {
+            usvg::Node::Group(group) => {
+                if group.clip_path().is_some() {
+                    warn! {"svg clip paths on shapes inside clip paths are not supported, ignoring"};
+                }
+                clip_outlines(group, transform, outlines, even_odd);
+            }
+            usvg::Node::Path(ref path) => {
+                if !path.is_visible() {
+                    continue;
+                }
+                let Some(data) = path
+                    .data()
+                    .clone()
+                    .transform(transform.pre_concat(path.abs_transform()))
+                else {
+                    continue;
+                };
+                // the clip rule of shapes in clip paths is stored as their fill rule
+                if path
+                    .fill()
+                    .is_none_or(|fill| fill.rule() == usvg::FillRule::NonZero)
+                {
+                    *even_odd = false;
+                }
+                outlines.extend(path_segments(&data));
+            }
+            usvg::Node::Text(ref text) => clip_outlines(
+                text.flattened(),
+                transform.pre_concat(text.abs_transform()),
+                outlines,
+                even_odd,
+            ),
+            usvg::Node::Image(_) => {}
+        }
+    }
+}
+
+/// `convert_text` converts an SVG text element into a vector of PDF operations, drawing the
+/// outlines of its glyphs.
+///
+/// The glyph outlines are positioned relative to the text element, so its transform is applied
+/// before drawing them.
+fn convert_text(
+    text: &usvg::Text,
+    base: usvg::Transform,
+    resources: &mut Resources,
+) -> Vec<Operation> {
+    let transform = text.abs_transform();
+    let mut new_operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("cm", transform_operands(transform)),
+    ];
+    new_operations.extend(loop_nodes(
+        text.flattened(),
+        base.pre_concat(transform),
+        resources,
+    ));
+    new_operations.push(Operation::new("Q", vec![]));
+    new_operations
+}
 
+/// `convert_path` converts an SVG path element into a vector of PDF operations.
+///
+/// Coordinates are left in SVG user units, with the transform of the path applied by a `cm`
+/// operation, so the caller must set up the mapping from SVG user units to the page.
+///
+/// The fill and stroke are painted together, unless one of them has a mask or the stroke is
+/// painted first.
 fn convert_path(
     path: &usvg::Path,
-    x_pos: ucum::Meter<f64>,
-    y_pos: ucum::Meter<f64>,
-    scale: Option<scale::ScalingFactor>,
+    base: usvg::Transform,
+    resources: &mut Resources,
 ) -> Vec<Operation> {
-    use usvg::tiny_skia_path::{PathSegment, Point};
     let mut new_operations = Vec::new();
+    if !path.is_visible() {
+        return new_operations;
+    }
+    let transform = path.abs_transform();
+    let paint_transform = base.pre_concat(transform);
+    let bounds = path.stroke_bounding_box();
+    let even_odd = path
+        .fill()
+        .is_some_and(|fill| fill.rule() == usvg::FillRule::EvenOdd);
+    let fill = path.fill().map(|fill| {
+        let (mut operations, masked) = paint_operations(
+            fill.paint(),
+            fill.opacity().get(),
+            false,
+            paint_transform,
+            bounds,
+            resources,
+        );
+        operations.push(Operation::new(if even_odd { "f*" } else { "f" }, vec![]));
+        (operations, masked)
+    });
+    let stroke = path.stroke().map(|stroke| {
+        let (mut operations, masked) = paint_operations(
+            stroke.paint(),
+            stroke.opacity().get(),
+            true,
+            paint_transform,
+            bounds,
+            resources,
+        );
+        operations.extend(stroke_style(stroke));
+        operations.push(Operation::new("S", vec![]));
+        (operations, masked)
+    });
 
-    let int_scale: f32 = if let Some(scale) = scale {
-        f32::from(scale.a / scale.b)
-    } else {
-        1.0
+    let segments = path_segments(path.data());
+    // each painting operator ends the path, so the segments are repeated for each of them
+    let mut paint = |mut paint_operations: Vec<Operation>| {
+        let painting_operator = paint_operations.pop();
+        // save graphics state so the transform and paint of this path don't leak into others
+        new_operations.push(Operation::new("q", vec![]));
+        new_operations.push(Operation::new("cm", transform_operands(transform)));
+        new_operations.extend(paint_operations);
+        new_operations.extend(segments.iter().cloned());
+        new_operations.extend(painting_operator);
+        new_operations.push(Operation::new("Q", vec![]));
     };
+    match (fill, stroke) {
+        (Some((mut fill, false)), Some((mut stroke, false)))
+            if path.paint_order() == usvg::PaintOrder::FillAndStroke =>
+        {
+            // B fills then strokes the path
+            fill.pop();
+            stroke.pop();
+            fill.append(&mut stroke);
+            fill.push(Operation::new(if even_odd { "B*" } else { "B" }, vec![]));
+            paint(fill);
+        }
+        (Some((fill, _)), Some((stroke, _))) => {
+            if path.paint_order() == usvg::PaintOrder::FillAndStroke {
+                paint(fill);
+                paint(stroke);
+            } else {
+                paint(stroke);
+                paint(fill);
+            }
+        }
+        (Some((operations, _)), None) | (None, Some((operations, _))) => paint(operations),
+        (None, None) => {}
+    }
+    new_operations
+}
 
-    //TODO: determine if we need to start with m operation by looking at the first point in
-    //data.points()
+/// `path_segments` converts the segments of an SVG path into PDF path construction operations
+fn path_segments(data: &usvg::tiny_skia_path::Path) -> Vec<Operation> {
+    use usvg::tiny_skia_path::{PathSegment, Point};
+    let mut new_operations = Vec::new();
     let mut last_point = Point::zero();
-    for segment in path.data().segments() {
+    for segment in data.segments() {
         match segment {
             PathSegment::MoveTo(p) => {
-                let mut scaled_p = p;
-                scaled_p.x *= int_scale;
-                scaled_p.y *= int_scale;
-                last_point = scaled_p;
+                last_point = p;
                 // begin a new path (subpath in pdf language) by moving the current point to
                 // coordinates (x,y)
-                let x = *(((f64::from(scaled_p.x) * x_pos) / PDFDocument::pdf_point()).value());
-                let y = *(((f64::from(scaled_p.y) * y_pos) / PDFDocument::pdf_point()).value());
-                new_operations.push(Operation::new("m", vec![x.into(), y.into()]));
+                new_operations.push(Operation::new("m", vec![p.x.into(), p.y.into()]));
             }
             PathSegment::LineTo(p) => {
-                let mut scaled_p = p;
-                scaled_p.x *= int_scale;
-                scaled_p.y *= int_scale;
-                last_point = scaled_p;
+                last_point = p;
                 // append a straight line segment from current point to the point (x,y).
-                new_operations.push(Operation::new(
-                    "l",
-                    vec![scaled_p.x.into(), scaled_p.y.into()],
-                ));
+                new_operations.push(Operation::new("l", vec![p.x.into(), p.y.into()]));
             }
-            // https://web.archive.org/web/20240625010856/https://www.reddit.com/r/AskComputerScience/comments/x0rrd2/does_applying_a_transformation_to_the_control/?rdt=60310
-            // Scaling control points is ok, because the control points define the curve
             // p0 is control point, p1 is end point
             PathSegment::QuadTo(p0, p1) => {
-                let mut scaled_p0 = p0;
-                scaled_p0.x *= int_scale;
-                scaled_p0.y *= int_scale;
-                let mut scaled_p1 = p1;
-                scaled_p1.x *= int_scale;
-                scaled_p1.y *= int_scale;
                 // create psuedo control points for cubic from quadratic.
                 // Formuala from https://stackoverflow.com/a/3162732/3342767
 
@@ -363,17 +1659,13 @@ fn convert_path(
                 #[allow(clippy::arithmetic_side_effects)]
                 let cp1 = last_point
                     + Point::from_xy(
-                        (2.0 / 3.0) * (scaled_p0 - last_point).x,
-                        (2.0 / 3.0) * (scaled_p0 - last_point).y,
+                        (2.0 / 3.0) * (p0 - last_point).x,
+                        (2.0 / 3.0) * (p0 - last_point).y,
                     );
                 // end control point
                 #[allow(clippy::arithmetic_side_effects)]
-                let cp2 = scaled_p1
-                    + Point::from_xy(
-                        (2.0 / 3.0) * (scaled_p0 - scaled_p1).x,
-                        (2.0 / 3.0) * (scaled_p0 - scaled_p1).y,
-                    );
-                last_point = scaled_p1;
+                let cp2 = p1 + Point::from_xy((2.0 / 3.0) * (p0 - p1).x, (2.0 / 3.0) * (p0 - p1).y);
+                last_point = p1;
                 // append a cubic bezier curve to current path.
                 // Last 2 points are end point,
                 // First 2 points are begining control point
@@ -385,23 +1677,14 @@ fn convert_path(
                         cp1.y.into(),
                         cp2.x.into(),
                         cp2.y.into(),
-                        scaled_p1.x.into(),
-                        scaled_p1.y.into(),
+                        p1.x.into(),
+                        p1.y.into(),
                     ],
                 ));
             }
             // p0 is begining control point, p1 is end control point, p2 is end point
             PathSegment::CubicTo(p0, p1, p2) => {
-                let mut scaled_p0 = p0;
-                scaled_p0.x *= int_scale;
-                scaled_p0.y *= int_scale;
-                let mut scaled_p1 = p1;
-                scaled_p1.x *= int_scale;
-                scaled_p1.y *= int_scale;
-                let mut scaled_p2 = p2;
-                scaled_p2.x *= int_scale;
-                scaled_p2.y *= int_scale;
-                last_point = scaled_p2;
+                last_point = p2;
                 // append a cubic bezier curve to current path.
                 // Last 2 points are end point,
                 // First 2 points are begining control point
@@ -409,12 +1692,12 @@ fn convert_path(
                 new_operations.push(Operation::new(
                     "c",
                     vec![
-                        scaled_p0.x.into(),
-                        scaled_p0.y.into(),
-                        scaled_p1.x.into(),
-                        scaled_p1.y.into(),
-                        scaled_p2.x.into(),
-                        scaled_p2.y.into(),
+                        p0.x.into(),
+                        p0.y.into(),
+                        p1.x.into(),
+                        p1.y.into(),
+                        p2.x.into(),
+                        p2.y.into(),
                     ],
                 ));
             }
@@ -428,34 +1711,376 @@ fn convert_path(
     new_operations
 }
 
+/// `stroke_style` returns the PDF operations that set the width, line cap, line join and dash
+/// pattern of an SVG stroke
+fn stroke_style(stroke: &usvg::Stroke) -> Vec<Operation> {
+    // J sets the line cap, j the line join and M the miter limit
+    let line_cap = match stroke.linecap() {
+        usvg::LineCap::Butt => 0,
+        usvg::LineCap::Round => 1,
+        usvg::LineCap::Square => 2,
+    };
+    let line_join = match stroke.linejoin() {
+        usvg::LineJoin::Miter | usvg::LineJoin::MiterClip => 0,
+        usvg::LineJoin::Round => 1,
+        usvg::LineJoin::Bevel => 2,
+    };
+    let mut new_operations = vec![
+        Operation::new("w", vec![stroke.width().get().into()]),
+        Operation::new("J", vec![line_cap.into()]),
+        Operation::new("j", vec![line_join.into()]),
+        Operation::new("M", vec![stroke.miterlimit().get().into()]),
+    ];
+    if let Some(dashes) = stroke.dasharray() {
+        // d sets the lengths of alternating dashes and gaps, and how far into them the stroke
+        // starts
+        new_operations.push(Operation::new(
+            "d",
+            vec![
+                dashes
+                    .iter()
+                    .map(|&length| length.into())
+                    .collect::<Vec<Object>>()
+                    .into(),
+                stroke.dashoffset().into(),
+            ],
+        ));
+    }
+    new_operations
+}
+
+/// `paint_operations` returns the PDF operations that set the fill color of a path to `paint`,
+/// or the stroke color if `stroke` is true, with the opacity `opacity`. Also returns true if the
+/// paint is masked, which masks everything painted along with it.
+///
+/// `transform` maps the coordinates of the path onto the content stream, which gradients and
+/// patterns are positioned relative to, and `bounds` is the area painted by the path in its own
+/// coordinates.
+///
+/// Gradients that repeat or reflect are painted as if they pad.
+fn paint_operations(
+    paint: &usvg::Paint,
+    opacity: f32,
+    stroke: bool,
+    transform: usvg::Transform,
+    bounds: usvg::Rect,
+    resources: &mut Resources,
+) -> (Vec<Operation>, bool) {
+    // the lowercase operators set the fill color, and the uppercase ones the stroke color
+    let pattern_operations = |name: String| {
+        vec![
+            Operation::new(
+                if stroke { "CS" } else { "cs" },
+                vec![Object::Name(b"Pattern".to_vec())],
+            ),
+            Operation::new(
+                if stroke { "SCN" } else { "scn" },
+                vec![Object::Name(name.into_bytes())],
+            ),
+        ]
+    };
+    let (mut new_operations, stop_opacities) = match paint {
+        usvg::Paint::Color(color) => {
+            // rg and RG set the color in the DeviceRGB color space
+            let operator = if stroke { "RG" } else { "rg" };
+            (
+                vec![Operation::new(operator, color_components(*color))],
+                Vec::new(),
+            )
+        }
+        usvg::Paint::LinearGradient(gradient) => {
+            let coords = [gradient.x1(), gradient.y1(), gradient.x2(), gradient.y2()];
+            let shading = gradient_shading(gradient, &coords, false);
+            let name = resources.add_pattern(PDFPattern::Shading {
+                shading,
+                matrix: transform_matrix(transform.pre_concat(gradient.transform())),
+            });
+            (pattern_operations(name), stop_opacities(gradient))
+        }
+        usvg::Paint::RadialGradient(gradient) => {
+            // SVG gradients start at the focal point and end at the outer circle
+            let coords = [
+                gradient.fx(),
+                gradient.fy(),
+                0.0,
+                gradient.cx(),
+                gradient.cy(),
+                gradient.r().get(),
+            ];
+            let shading = gradient_shading(gradient, &coords, true);
+            let name = resources.add_pattern(PDFPattern::Shading {
+                shading,
+                matrix: transform_matrix(transform.pre_concat(gradient.transform())),
+            });
+            (pattern_operations(name), stop_opacities(gradient))
+        }
+        usvg::Paint::Pattern(pattern) => {
+            let rect = pattern.rect();
+            // tiles are drawn with their top left corner at the origin
+            let operations = loop_nodes(pattern.root(), usvg::Transform::identity(), resources);
+            let name = resources.add_pattern(PDFPattern::Tiling {
+                operations,
+                size: [f64::from(rect.width()), f64::from(rect.height())],
+                matrix: transform_matrix(
+                    transform
+                        .pre_concat(pattern.transform())
+                        .pre_translate(rect.x(), rect.y()),
+                ),
+            });
+            (pattern_operations(name), Vec::new())
+        }
+    };
+
+    let uniform_opacity = match stop_opacities.as_slice() {
+        [] => Some(opacity),
+        [first, rest @ ..] if rest.iter().all(|stop_opacity| stop_opacity == first) => {
+            Some(opacity * first)
+        }
+        _ => None,
+    };
+    if let Some(alpha) = uniform_opacity {
+        if alpha < 1.0 {
+            let state = resources.add_graphics_state(PDFGraphicsState {
+                fill_alpha: (!stroke).then_some(f64::from(alpha)),
+                stroke_alpha: stroke.then_some(f64::from(alpha)),
+                soft_mask: None,
+            });
+            new_operations.push(Operation::new("gs", vec![Object::Name(state.into_bytes())]));
+        }
+        return (new_operations, false);
+    }
+
+    // the opacity of each stop varies, so it is drawn as a gray gradient in a luminosity mask
+    let gradient = match paint {
+        usvg::Paint::LinearGradient(gradient) => {
+            let coords = [gradient.x1(), gradient.y1(), gradient.x2(), gradient.y2()];
+            Some((
+                gradient_mask_shading(gradient, &coords, false, opacity),
+                gradient.transform(),
+            ))
+        }
+        usvg::Paint::RadialGradient(gradient) => {
+            let coords = [
+                gradient.fx(),
+                gradient.fy(),
+                0.0,
+                gradient.cx(),
+                gradient.cy(),
+                gradient.r().get(),
+            ];
+            Some((
+                gradient_mask_shading(gradient, &coords, true, opacity),
+                gradient.transform(),
+            ))
+        }
+        usvg::Paint::Color(_) | usvg::Paint::Pattern(_) => None,
+    };
+    if let Some((shading, gradient_transform)) = gradient {
+        // the mask is drawn in the coordinates of the path, which is the current coordinate
+        // space when the mask is set
+        let pattern = resources.add_pattern(PDFPattern::Shading {
+            shading,
+            matrix: transform_matrix(gradient_transform),
+        });
+        let [left, top, right, bottom] = rect_bounds(bounds);
+        let form = resources.add_form(PDFForm {
+            operations: vec![
+                Operation::new("cs", vec![Object::Name(b"Pattern".to_vec())]),
+                Operation::new("scn", vec![Object::Name(pattern.into_bytes())]),
+                Operation::new(
+                    "re",
+                    vec![
+                        left.into(),
+                        top.into(),
+                        (right - left).into(),
+                        (bottom - top).into(),
+                    ],
+                ),
+                Operation::new("f", vec![]),
+            ],
+            bbox: [left, top, right, bottom],
+            matrix: None,
+            transparency_group: true,
+        });
+        let state = resources.add_graphics_state(PDFGraphicsState {
+            soft_mask: Some(PDFSoftMask {
+                luminosity: true,
+                form,
+            }),
+            ..PDFGraphicsState::default()
+        });
+        new_operations.push(Operation::new("gs", vec![Object::Name(state.into_bytes())]));
+    }
+    (new_operations, true)
+}
+
+/// `gradient_shading` returns the colors of an SVG gradient as a shading, with the start and
+/// end points or circles in `coords`
+fn gradient_shading(gradient: &usvg::BaseGradient, coords: &[f32], radial: bool) -> PDFShading {
+    if gradient.spread_method() != usvg::SpreadMethod::Pad {
+        warn! {"svg gradients that repeat or reflect are not supported, padding instead"};
+    }
+    PDFShading {
+        radial,
+        coords: coords.iter().map(|&coord| f64::from(coord)).collect(),
+        gray: false,
+        stops: gradient
+            .stops()
+            .iter()
+            .map(|stop| {
+                let components = [stop.color().red, stop.color().green, stop.color().blue]
+                    .into_iter()
+                    .map(|component| f64::from(component) / 255.0)
+                    .collect();
+                (f64::from(stop.offset().get()), components)
+            })
+            .collect(),
+    }
+}
+
+/// `gradient_mask_shading` returns the opacity of each stop of an SVG gradient, multiplied by
+/// `opacity`, as a gray shading with the start and end points or circles in `coords`
+fn gradient_mask_shading(
+    gradient: &usvg::BaseGradient,
+    coords: &[f32],
+    radial: bool,
+    opacity: f32,
+) -> PDFShading {
+    PDFShading {
+        radial,
+        coords: coords.iter().map(|&coord| f64::from(coord)).collect(),
+        gray: true,
+        stops: gradient
+            .stops()
+            .iter()
+            .map(|stop| {
+                (
+                    f64::from(stop.offset().get()),
+                    vec![f64::from(stop.opacity().get() * opacity)],
+                )
+            })
+            .collect(),
+    }
+}
+
+/// `stop_opacities` returns the opacity of each stop of an SVG gradient
+fn stop_opacities(gradient: &usvg::BaseGradient) -> Vec<f32> {
+    gradient
+        .stops()
+        .iter()
+        .map(|stop| stop.opacity().get())
+        .collect()
+}
+
+/// `color_components` returns the RGB components of an SVG color as PDF operands
+fn color_components(color: usvg::Color) -> Vec<Object> {
+    [color.red, color.green, color.blue]
+        .into_iter()
+        .map(|component| (f32::from(component) / 255.0).into())
+        .collect()
+}
+
+/// `transform_operands` returns an SVG transform as the operands of a PDF `cm` operation
+fn transform_operands(transform: usvg::Transform) -> Vec<Object> {
+    transform_matrix(transform)
+        .into_iter()
+        .map(Object::from)
+        .collect()
+}
+
+/// `transform_matrix` returns an SVG transform as a PDF transformation matrix
+fn transform_matrix(transform: usvg::Transform) -> [f64; 6] {
+    [
+        transform.sx,
+        transform.ky,
+        transform.kx,
+        transform.sy,
+        transform.tx,
+        transform.ty,
+    ]
+    .map(f64::from)
+}
+
+/// `rect_bounds` returns the left, top, right and bottom sides of an SVG rectangle
+fn rect_bounds(rect: usvg::Rect) -> [f64; 4] {
+    [rect.left(), rect.top(), rect.right(), rect.bottom()].map(f64::from)
+}
+
 /// `convert_image` converts an embedded image in an SVG into a vector of PDF operations.
 ///
-/// Currently ignores all embedded images other than SVGs.
+/// JPEG and PNG images are added to `resources` and drawn by name, and SVG images are drawn
+/// directly. Images are placed in their own coordinates, with the transform of the image applied
+/// by a `cm` operation, so the caller must set up the mapping from SVG user units to the page.
+///
+/// Images that fail to decode are left out, as are GIF images.
 fn convert_image(
     image: &usvg::Image,
-    x_pos: ucum::Meter<f64>,
-    y_pos: ucum::Meter<f64>,
-    scale: Option<scale::ScalingFactor>,
+    base: usvg::Transform,
+    resources: &mut Resources,
 ) -> Vec<Operation> {
     use usvg::ImageKind;
-    match &image.kind() {
-        ImageKind::JPEG(_) => {
-            // only vector graphic elements allowed
-            warn! {"svg should not contain images or image tags, ignoring"};
-            Vec::new()
+    if !image.is_visible() {
+        return Vec::new();
+    }
+    let image_operations = match image.kind() {
+        ImageKind::JPEG(data) => {
+            raster_operations(image.size(), data, PDFImage::from_jpeg(data), resources)
         }
-        ImageKind::PNG(_) => {
-            // only vector graphic elements allowed
-            warn! {"svg should not contain images or image tags, ignoring"};
-            Vec::new()
+        ImageKind::PNG(data) => {
+            raster_operations(image.size(), data, PDFImage::from_png(data), resources)
         }
         ImageKind::GIF(_) => {
-            // only vector graphic elements allowed
-            warn! {"svg should not contain images or image tags, ignoring"};
-            Vec::new()
+            warn! {"svg GIF images are not supported, ignoring"};
+            return Vec::new();
         }
-        ImageKind::SVG(tree) => loop_nodes(tree.root(), x_pos, y_pos, scale),
-    }
+        ImageKind::SVG(tree) => loop_nodes(
+            tree.root(),
+            base.pre_concat(image.abs_transform()),
+            resources,
+        ),
+    };
+    let mut new_operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("cm", transform_operands(image.abs_transform())),
+    ];
+    new_operations.extend(image_operations);
+    new_operations.push(Operation::new("Q", vec![]));
+    new_operations
+}
+
+/// `raster_operations` adds a decoded raster image to `resources`, and returns the PDF operations
+/// that draw it at `size` in SVG user units. Returns no operations if the image failed to decode.
+fn raster_operations(
+    size: usvg::Size,
+    data: &[u8],
+    decoded: Result<PDFImage, Error>,
+    resources: &mut Resources,
+) -> Vec<Operation> {
+    let decoded = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            warn! {"unable to embed svg image, ignoring: {e}"};
+            return Vec::new();
+        }
+    };
+    let name = image_name(data);
+    resources.images.entry(name.clone()).or_insert(decoded);
+    // images are drawn into the unit square, with the first row of pixels at the top. SVG user
+    // units point down the page, so the square is flipped.
+    vec![
+        Operation::new(
+            "cm",
+            vec![
+                size.width().into(),
+                0.into(),
+                0.into(),
+                (-size.height()).into(),
+                0.into(),
+                size.height().into(),
+            ],
+        ),
+        Operation::new("Do", vec![Object::Name(name.into_bytes())]),
+    ]
 }
 
 impl<'a> PDFDocument<'a> {
@@ -469,6 +2094,9 @@ impl<'a> PDFDocument<'a> {
     /// with the specified font paths pre-populated and default page size,
     /// as specified via parameter
     ///
+    /// If there are no font paths, every standard font is loaded instead, with Helvetica first,
+    /// so PDFs can be generated without any font files.
+    ///
     /// # Errors
     ///
     /// Will error if loading configuration fonts fails
@@ -480,8 +2108,17 @@ impl<'a> PDFDocument<'a> {
             default_page_size,
             available_fonts: Vec::new(),
             pages: Vec::new(),
+            info: DocumentInfo::default(),
+            outline: Vec::new(),
+            destinations: BTreeMap::new(),
         };
-        output.load_cfg_fonts(font_paths)?;
+        if font_paths.is_empty() {
+            for font in StandardFont::ALL {
+                output.load_standard_font(font);
+            }
+        } else {
+            output.load_cfg_fonts(font_paths)?;
+        }
         Ok(output)
     }
 
@@ -493,6 +2130,9 @@ impl<'a> PDFDocument<'a> {
             default_page_size: paper::PaperSize::A4,
             available_fonts: Vec::new(),
             pages: Vec::new(),
+            info: DocumentInfo::default(),
+            outline: Vec::new(),
+            destinations: BTreeMap::new(),
         }
     }
 
@@ -533,13 +2173,28 @@ impl<'a> PDFDocument<'a> {
             font_name,
             font_id_str,
             font_id: None,
-            font_face,
-            font_data,
+            source: FontSource::Embedded {
+                font_face,
+                font_data,
+                font_index: face_index,
+            },
         });
 
         Ok(())
     }
 
+    /// `load_standard_font` makes one of the standard fonts available in the PDF document. These
+    /// need no font files, and are not embedded in the PDF.
+    pub fn load_standard_font(&mut self, font: StandardFont) {
+        let font_id_str = format! {"F{}", self.available_fonts.len()};
+        self.available_fonts.push(PDFFont {
+            font_name: font.name().to_string(),
+            font_id_str,
+            font_id: None,
+            source: FontSource::Standard(font),
+        });
+    }
+
     //TODO: need to add this to the config file, also this may not need to be public
     /// `load_cfg_font` loads the TTF font specified in the configuration file
     /// via `rustybuzz` and `ttf_parser` and makes it
@@ -568,12 +2223,15 @@ impl<'a> PDFDocument<'a> {
         &mut self,
         page_index: usize,
         page_size: Option<paper::PaperSize>,
+        orientation: paper::Orientation,
         margins: Margins,
     ) {
         self.pages.insert(
             page_index,
             PDFPage {
                 operations: Vec::new(),
+                links: Vec::new(),
+                resources: Resources::default(),
                 page_size: {
                     if let Some(page_size) = page_size {
                         page_size
@@ -581,16 +2239,25 @@ impl<'a> PDFDocument<'a> {
                         self.default_page_size
                     }
                 },
+                orientation,
                 margins,
+                label: None,
             },
         );
     }
     /// `push_page` inserts an empty `PDFPage` into self.pages vector
     /// at the end of the vector.
     /// This is a wrapper around vec.push() so it follows the same rules.
-    pub fn push_page(&mut self, page_size: Option<paper::PaperSize>, margins: Margins) {
+    pub fn push_page(
+        &mut self,
+        page_size: Option<paper::PaperSize>,
+        orientation: paper::Orientation,
+        margins: Margins,
+    ) {
         self.pages.push(PDFPage {
             operations: Vec::new(),
+            links: Vec::new(),
+            resources: Resources::default(),
             page_size: {
                 if let Some(page_size) = page_size {
                     page_size
@@ -598,7 +2265,9 @@ impl<'a> PDFDocument<'a> {
                     self.default_page_size
                 }
             },
+            orientation,
             margins,
+            label: None,
         });
     }
     /// `write` sets up and writes a pdf file.
@@ -623,6 +2292,23 @@ impl<'a> PDFDocument<'a> {
         // pages is the root node of the page tree
         let root_id = doc.new_object_id();
 
+        // font dictionaries need to be added into resource dictionaries
+        // in order to be used.
+        // Resource dictionaries can contain more than just fonts,
+        // but normally just contains fonts
+        // Only one resource dictionary is allowed per page tree root
+        //
+        // the resource dictionary is shared by every page and Form XObject, so its ID is needed
+        // before the forms are written
+        let resources_id = doc.new_object_id();
+
+        // forms, images, patterns and graphics states are written once, however many pages
+        // draw them. The glyphs drawn from each font on every page are needed to subset it.
+        let mut resources = Resources::default();
+        for page in &self.pages {
+            resources.extend(&page.resources);
+        }
+
         // fonts are dictionaries. The type, subtype and basefont tags
         // are straight out of the PDF reference manual
         //
@@ -630,17 +2316,73 @@ impl<'a> PDFDocument<'a> {
         // key, value relationships to be represented in a simpler
         // visual manner, similar to a match statement.
         // Dictionary is linkedHashMap of byte vector, and object
-
+        //
+        // Text is drawn by glyph ID rather than character code, so each font is a Type0 font
+        // with a single CIDFont as its descendant, and identity mappings from the two byte codes
+        // in strings to CIDs, and from CIDs to glyph IDs. Only the glyphs that are drawn are
+        // embedded, and fonts that aren't drawn at all are left out.
+        //
+        // fonts are actually triplely nested dictionaries. Fun!
+        let mut temp_dict = lopdf::Dictionary::new();
         for font in &mut self.available_fonts {
+            let Some(glyphs) = resources.glyphs(&font.font_id_str) else {
+                continue;
+            };
+            let (font_face, font_data, font_index) = match &font.source {
+                FontSource::Embedded {
+                    font_face,
+                    font_data,
+                    font_index,
+                } => (font_face, font_data, *font_index),
+                FontSource::Standard(standard_font) => {
+                    // standard fonts are built into PDF readers, so are only referred to by name
+                    let font_id = doc.add_object(dictionary! {
+                        "Type" => "Font",
+                        "Subtype" => "Type1",
+                        "BaseFont" => standard_font.name(),
+                        "Encoding" => "WinAnsiEncoding",
+                    });
+                    font.font_id = Some(font_id);
+                    temp_dict.set(font.font_id_str.as_str(), font_id);
+                    continue;
+                }
+            };
             //https://github.com/J-F-Liu/lopdf/issues/219#issuecomment-1464928118
 
-            // create a stream object of font data
-            let font_stream = Stream::new(dictionary! {}, font.font_data.clone());
-            let font_stream_id = doc.add_object(font_stream);
-            // create a font descriptor dictionary object
+            // fonts with TrueType outlines are subset to the glyphs used. Fonts with CFF outlines
+            // are embedded in full, with CIDs that are the same as their glyph IDs.
+            let truetype = subset::is_truetype(font_data, font_index);
+            let (font_file, font_file_key, base_font) = if truetype {
+                let glyph_ids = glyphs.keys().copied().collect();
+                let subset_data = subset::subset(font_data, font_index, &glyph_ids)?;
+                // Length1 is the length of the font file before the stream is compressed
+                let length = u32::try_from(subset_data.len()).unwrap_or(u32::MAX);
+                let mut font_stream = Stream::new(dictionary! {"Length1" => length}, subset_data);
+                font_stream.compress()?;
+                // subset fonts are named with a tag of six capital letters, unique to the subset
+                let base_font = format!(
+                    "{}+{}",
+                    subset_tag(&font.font_name, &glyph_ids),
+                    font.font_name.replace(' ', "")
+                );
+                (font_stream, "FontFile2", base_font)
+            } else {
+                warn!(
+                    "font {} does not have TrueType outlines, so it is embedded in full",
+                    font.font_name
+                );
+                let mut font_stream =
+                    Stream::new(dictionary! {"Subtype" => "OpenType"}, font_data.clone());
+                font_stream.compress()?;
+                (font_stream, "FontFile3", font.font_name.replace(' ', ""))
+            };
+            let font_stream_id = doc.add_object(font_file);
+            let bounding_box = font_face.global_bounding_box();
+            // create a font descriptor dictionary object. Its metrics are in glyph space, where
+            // 1000 units are the font size.
             let font_descriptor_dict = dictionary! {
                 "Type" => "FontDescriptor",
-                "FontName" => font.font_name.as_str(),
+                "FontName" => base_font.as_str(),
                 "Flags" => {
                     // font_descriptor_flag is a unsigned 32 bit integer containing bitflags
                     // that specify various characteristics of the font.
@@ -660,94 +2402,112 @@ impl<'a> PDFDocument<'a> {
                     // | 19           | ForceBold   |
                     //
                     // From table 123 of the PDF1.7 specification pdf.
-                    let mut font_descriptor_flag: u32 = 0;
+                    //
+                    // glyphs are drawn by ID rather than from the standard Latin character set,
+                    // so the font is symbolic
+                    let mut font_descriptor_flag: u32 = 2_u32.pow(2);
                     #[allow(clippy::arithmetic_side_effects)]
-                    if font.font_face.is_monospaced(){font_descriptor_flag += 2_u32.pow(0); }
-                    //if font.font_face.
-                    //if
-                    //if
+                    if font_face.is_monospaced(){font_descriptor_flag += 2_u32.pow(0); }
                     #[allow(clippy::arithmetic_side_effects)]
-                    if font.font_face.is_italic() || font.font_face.is_oblique() {font_descriptor_flag += 2_u32.pow(6);}
+                    if font_face.is_italic() || font_face.is_oblique() {font_descriptor_flag += 2_u32.pow(6);}
                     font_descriptor_flag
 
                 },
                 // this is a PDF rectangle
                 "FontBBox" => vec![
-                    font.font_face.global_bounding_box().x_min.into(),
-                    font.font_face.global_bounding_box().y_min.into(),
-                    font.font_face.global_bounding_box().x_max.into(),
-                    font.font_face.global_bounding_box().y_max.into(),
+                    font.to_glyph_space(f64::from(bounding_box.x_min)).into(),
+                    font.to_glyph_space(f64::from(bounding_box.y_min)).into(),
+                    font.to_glyph_space(f64::from(bounding_box.x_max)).into(),
+                    font.to_glyph_space(f64::from(bounding_box.y_max)).into(),
                 ],
-                "ItalicAngle" => font.font_face.italic_angle().unwrap_or(0.0),
-                "Ascent" => font.font_face.ascender(),
-                "Decent" => font.font_face.descender(),
-                "Leading" => font.font_face.line_gap(),
-                "CapHeight" => font.font_face.capital_height().unwrap_or(0),
-                "XHeight" => font.font_face.x_height().unwrap_or(0),
+                "ItalicAngle" => font_face.italic_angle().unwrap_or(0.0),
+                "Ascent" => font.to_glyph_space(f64::from(font_face.ascender())),
+                "Descent" => font.to_glyph_space(f64::from(font_face.descender())),
+                "Leading" => font.to_glyph_space(f64::from(font_face.line_gap())),
+                "CapHeight" => font.to_glyph_space(f64::from(font_face.capital_height().unwrap_or(0))),
+                "XHeight" => font.to_glyph_space(f64::from(font_face.x_height().unwrap_or(0))),
                 // Defaulted to 80. No way to pull this out of font files. Seems to not truely be
                 // required by most PDF readers
                 // https://stackoverflow.com/a/35543715/3342767
                 // https://www.truetype-typography.com/ttqa_1998.htm
                 "StemV" => 80_u16,
-                "FontFile2" => font_stream_id,
-
+                font_file_key => font_stream_id,
+            };
+            let font_descriptor_id = doc.add_object(font_descriptor_dict);
 
+            // the CIDFont holds the glyphs, and the widths of the glyphs used
+            let mut cid_font_dict = dictionary! {
+                "Type" => "Font",
+                "Subtype" => if truetype { "CIDFontType2" } else { "CIDFontType0" },
+                "BaseFont" => base_font.as_str(),
+                "CIDSystemInfo" => dictionary! {
+                    "Registry" => Object::string_literal("Adobe"),
+                    "Ordering" => Object::string_literal("Identity"),
+                    "Supplement" => 0,
+                },
+                "FontDescriptor" => font_descriptor_id,
+                "W" => glyph_widths(font, glyphs),
             };
+            if truetype {
+                cid_font_dict.set("CIDToGIDMap", "Identity");
+            }
+            let cid_font_id = doc.add_object(cid_font_dict);
 
-            let font_descriptor_id = doc.add_object(font_descriptor_dict);
-            // The encoding entry in a font dictionary is optional as long as the font has built in
-            // encoding.
-            // Left here for reference
-            //let encoding = dictionary! {
-            //    "Type" => "Encoding",
-            //    // the baseEncoding value is as specified in the PDF standard
-            //    "BaseEncoding" => "StandardEncoding",
-            //};
+            // the ToUnicode CMap maps glyphs back to the text they show, so text can be
+            // searched and copied out of the PDF
+            let mut to_unicode = Stream::new(dictionary! {}, to_unicode_cmap(glyphs));
+            to_unicode.compress()?;
+            let to_unicode_id = doc.add_object(to_unicode);
 
             // create a font dictionary object
             let font_dict = dictionary! {
                 "Type" => "Font",
-                "Subtype" => "TrueType",
-                "BaseFont" => font.font_name.as_str(),
-                //TODO:
-                //"FirstChar" => ,
-                //"LastChar" => ,
-                //"Widths" => ,
-                "FontDescriptor" => font_descriptor_id,
-                "Length1" => u32::try_from(font.font_data.clone().len()).unwrap_or(u32::MAX),
+                "Subtype" => "Type0",
+                "BaseFont" => base_font.as_str(),
+                "Encoding" => "Identity-H",
+                "DescendantFonts" => vec![cid_font_id.into()],
+                "ToUnicode" => to_unicode_id,
             };
-            font.font_id = Some(doc.add_object(font_dict));
+            let font_id = doc.add_object(font_dict);
+            font.font_id = Some(font_id);
+            temp_dict.set(font.font_id_str.as_str(), font_id);
         }
 
-        // font dictionaries need to be added into resource dictionaries
-        // in order to be used.
-        // Resource dictionaries can contain more than just fonts,
-        // but normally just contains fonts
-        // Only one resource dictionary is allowed per page tree root
-        let resources_id = doc.add_object(dictionary! {
-            // fonts are actually triplely nested dictionaries. Fun!
-            "Font" =>  {
-                let mut temp_dict = lopdf::Dictionary::new();
-                for font in &self.available_fonts{
-                    temp_dict.set(font.font_id_str.as_str(),
-                        font.font_id.ok_or(Error::FontLoading("Font Object ID not set".to_string()))?);
-            }
-                temp_dict
-            }
-        });
+        let mut resource_dict = resources.write(&mut doc, resources_id)?;
+        resource_dict.set("Font", temp_dict);
+        // layers are optional content groups, which marked content refers to by resource name
+        let (properties, layer_ids) = resources.write_layers(&mut doc);
+        resource_dict.set("Properties", properties);
+        doc.set_object(resources_id, resource_dict);
 
-        let mut page_ids: Vec<Object> = Vec::new();
+        // page IDs are created up front so links can point to pages later in the document
+        let page_ids: Vec<lopdf::ObjectId> =
+            self.pages.iter().map(|_| doc.new_object_id()).collect();
         // page is a vector of operations.
-        for page in &self.pages {
+        for (page, page_id) in self.pages.iter().zip(&page_ids) {
             // Content is a wrapper struct around an operations struct that contains a vector of operations
             // The operations struct contains a vector of operations that match up with a particular PDF
             // operator and operands.
             // Reference the PDF reference for more details on these operators and operands.
             // Note, the operators and operands are specified in a reverse order than they
             // actually appear in the PDF file itself.
-            let content = Content {
-                operations: page.operations.clone(),
-            };
+            //
+            // landscape pages are laid out sideways on portrait media, so their operations are
+            // wrapped in a transform that rotates them onto the media box
+            let media_transform = page.media_transform();
+            let mut operations = Vec::new();
+            if let Some(matrix) = media_transform {
+                operations.push(Operation::new("q", vec![]));
+                operations.push(Operation::new(
+                    "cm",
+                    matrix.iter().map(|&value| value.into()).collect(),
+                ));
+            }
+            operations.extend(page.operations.iter().cloned());
+            if media_transform.is_some() {
+                operations.push(Operation::new("Q", vec![]));
+            }
+            let content = Content { operations };
 
             // Streams are a dictionary followed by a sequence of bytes. What that sequence of bytes
             // represents depends on context
@@ -759,8 +2519,35 @@ impl<'a> PDFDocument<'a> {
             // TODO: return errors here
             let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
 
+            // link annotations are dictionaries placed in the Annots array of the page they are
+            // on. Dest is an explicit destination: the page to go to, and how to display it.
+            let mut annotations: Vec<Object> = Vec::new();
+            for link in &page.links {
+                let destination_id = page_ids.get(link.destination_page).ok_or_else(|| {
+                    Error::Other(format!(
+                        "link destination page {} does not exist",
+                        link.destination_page
+                    ))
+                })?;
+                annotations.push(
+                    dictionary! {
+                        "Type" => "Annot",
+                        "Subtype" => "Link",
+                        "Rect" => media_transform
+                            .map_or(link.rect, |matrix| transform_rect(link.rect, matrix))
+                            .iter()
+                            .map(|&side| side.into())
+                            .collect::<Vec<Object>>(),
+                        // no visible border around the clickable area
+                        "Border" => vec![0.into(), 0.into(), 0.into()],
+                        "Dest" => vec![(*destination_id).into(), "Fit".into()],
+                    }
+                    .into(),
+                );
+            }
+
             // Page is a page object dictionary dictionary that represents one page of a PDF file.
-            let page_id = doc.add_object(dictionary! {
+            let mut page_dict = dictionary! {
                 "Type" => "Page",
                 "Parent" => root_id, // root of page tree
                 "Contents" => content_id,
@@ -774,9 +2561,17 @@ impl<'a> PDFDocument<'a> {
                     #[allow(clippy::arithmetic_side_effects)]
                     (*(page.page_size.size().1/Self::pdf_point()).value()).into()
                 ],
-            });
-            page_ids.push(page_id.into());
+            };
+            if !annotations.is_empty() {
+                page_dict.set("Annots", annotations);
+            }
+            // viewers and printers turn the page clockwise by this many degrees to display it
+            if page.orientation == paper::Orientation::Landscape {
+                page_dict.set("Rotate", 90);
+            }
+            doc.set_object(*page_id, page_dict);
         }
+        let kids: Vec<Object> = page_ids.iter().map(|&page_id| page_id.into()).collect();
 
         // Again, pages is the root of the page tree. The ID was already created
         // at the top of the page, since we needed it to assign to the parent element of the page
@@ -792,7 +2587,7 @@ impl<'a> PDFDocument<'a> {
             // Type of dictionary
             "Type" => "Pages",
             // Vector of page IDs in document.
-            "Kids" => page_ids,
+            "Kids" => kids,
             // Page count
             "Count" => page_count,
             // ID of resources, defined earlier
@@ -815,20 +2610,68 @@ impl<'a> PDFDocument<'a> {
 
         // Creating document catalog.
         // There are many more entries allowed in the catalog dictionary.
-        let doc_catalog_id = doc.add_object(dictionary! {
+        let mut doc_catalog = dictionary! {
             "Type" => "Catalog",
             "Version" => pdf_version,
             "Pages" => root_id,
-        });
+        };
+        // the outline is shown in a side panel when the document is opened
+        if let Some(outline_id) = outline::write_outline(&mut doc, &self.outline, &page_ids)? {
+            doc_catalog.set("Outlines", outline_id);
+            doc_catalog.set("PageMode", "UseOutlines");
+        }
+        // every layer is listed in the layers panel, in order of name, and is shown by default
+        if !layer_ids.is_empty() {
+            let layers: Vec<Object> = layer_ids.iter().map(|&layer_id| layer_id.into()).collect();
+            doc_catalog.set(
+                "OCProperties",
+                dictionary! {
+                    "OCGs" => layers.clone(),
+                    "D" => dictionary! {"Order" => layers},
+                },
+            );
+            if self.outline.is_empty() {
+                doc_catalog.set("PageMode", "UseOC");
+            }
+        }
+        if let Some(page_labels) =
+            outline::page_labels(self.pages.iter().map(|page| page.label.as_deref()))
+        {
+            doc_catalog.set("PageLabels", page_labels);
+        }
+        if let Some(destinations) = outline::named_destinations(&self.destinations, &page_ids)? {
+            doc_catalog.set("Names", dictionary! {"Dests" => destinations});
+        }
+        let doc_catalog_id = doc.add_object(doc_catalog);
 
         // Root key in trailer is set here to ID of document catalog,
         // remainder of trailer is set during doc.save().
         doc.trailer.set("Root", doc_catalog_id);
+        // nothing is dated with the current time, so the same document is always written the
+        // same way. Reproducible builds can date documents with SOURCE_DATE_EPOCH instead.
+        let info = DocumentInfo {
+            creation_date: self.info.creation_date.or_else(PDFDate::source_date_epoch),
+            ..self.info.clone()
+        };
+        if let Some(info) = outline::info_dictionary(&info) {
+            let info_id = doc.add_object(info);
+            doc.trailer.set("Info", info_id);
+        }
 
         let file_path = out_path.join(file_name);
         if compress {
             doc.compress();
         }
+        // the ID of the document is a hash of the rest of it, so it only changes when the
+        // document does. Saving numbers the cross reference stream after the last object, so
+        // the last object ID is put back before the document is saved again.
+        let max_id = doc.max_id;
+        let mut unidentified = Vec::new();
+        doc.save_to(&mut unidentified)?;
+        doc.max_id = max_id;
+        let document_id = Object::String(document_id(&unidentified), StringFormat::Hexadecimal);
+        doc.trailer
+            .set("ID", vec![document_id.clone(), document_id]);
         doc.save(file_path)?;
         Ok(())
     }
@@ -849,6 +2692,12 @@ pub enum Error {
     SVGError(String),
     /// Errors from [`std::io`]
     IOError(String),
+    /// error in parsing a drawing scale
+    ScaleParsing(String),
+    /// error in decoding a raster image
+    ImageDecoding(String),
+    /// error in parsing a date
+    DateParsing(String),
     /// Other errors
     Other(String),
 }
@@ -864,6 +2713,9 @@ impl std::fmt::Display for Error {
             Error::PDFError(ref e) => write!(f, "PDF error: {e}"),
             Error::SVGError(ref e) => write!(f, "SVG: {e}"),
             Error::IOError(ref e) => write!(f, "IO error: {e}"),
+            Error::ScaleParsing(ref e) => write!(f, "Scale parsing: {e}"),
+            Error::ImageDecoding(ref e) => write!(f, "Image decoding: {e}"),
+            Error::DateParsing(ref e) => write!(f, "Date parsing: {e}"),
             Error::Other(ref e) => write!(f, "{e}"),
         }
     }
@@ -885,6 +2737,12 @@ impl From<USVGError> for Error {
         Error::SVGError(format!("{e}"))
     }
 }
+impl From<png::DecodingError> for Error {
+    fn from(e: png::DecodingError) -> Self {
+        Error::ImageDecoding(format!("{e}"))
+    }
+}
+
 impl From<lopdf::Error> for Error {
     fn from(e: lopdf::Error) -> Self {
         match e {
@@ -910,3 +2768,239 @@ impl From<lopdf::Error> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use paper::PaperSize;
+
+    /// `pt` returns a length of `points`
+    fn pt(points: f64) -> ucum::Meter<f64> {
+        points * PDFDocument::pdf_point()
+    }
+
+    /// `lines` returns the text shown by each text matrix on `page`, with its position and the
+    /// word spacing adjustments between its pieces
+    fn lines(page: &PDFPage) -> Vec<(String, f64, f64, Vec<f64>)> {
+        let mut lines: Vec<(String, f64, f64, Vec<f64>)> = Vec::new();
+        for operation in &page.operations {
+            match (operation.operator.as_str(), operation.operands.as_slice()) {
+                ("Tm", [.., x, y]) => lines.push((
+                    String::new(),
+                    f64::from(x.as_float().unwrap()),
+                    f64::from(y.as_float().unwrap()),
+                    Vec::new(),
+                )),
+                ("TJ", [Object::Array(pieces)]) => {
+                    let (line, _, _, adjustments) = lines.last_mut().unwrap();
+                    for piece in pieces {
+                        match piece {
+                            Object::String(bytes, _) => {
+                                line.push_str(&String::from_utf8_lossy(bytes));
+                            }
+                            _ => adjustments.push(f64::from(piece.as_float().unwrap())),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        lines
+    }
+
+    /// `set` sets `text` in 10 point Helvetica in `text_box` on a new A4 page, with a 20 point
+    /// left margin and a 30 point bottom margin, and returns the page and the layout
+    fn set(text: &str, text_box: &TextBox) -> (PDFPage, TextLayout) {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let mut page = PDFPage::new(
+            PaperSize::A4,
+            Margins {
+                top: pt(10.0),
+                bottom: pt(30.0),
+                left: pt(20.0),
+                right: pt(10.0),
+            },
+        );
+        let layout = page
+            .add_text_box(
+                text,
+                10,
+                &pdf.available_fonts[0],
+                1,
+                text_box,
+                rustybuzz::Direction::LeftToRight,
+                rustybuzz::Language::from_str("en").unwrap(),
+                &PDFTextRenderMode::Fill,
+            )
+            .unwrap();
+        (page, layout)
+    }
+
+    /// `helvetica` returns the standard Helvetica font
+    fn helvetica<'a>(pdf: &'a PDFDocument<'a>) -> &'a PDFFont<'a> {
+        &pdf.available_fonts[0]
+    }
+
+    /// `assert_close` asserts that `actual` is within rounding of `expected`
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn alignment() {
+        let text_box = TextBox {
+            x_pos: pt(10.0),
+            width: pt(200.0),
+            height: pt(100.0),
+            ..TextBox::default()
+        };
+        // Hello is 22.78 points wide
+        for (alignment, x) in [
+            (Alignment::Left, 30.0),
+            (Alignment::Center, 30.0 + (200.0 - 22.78) / 2.0),
+            (Alignment::Right, 30.0 + 200.0 - 22.78),
+            (Alignment::Justify, 30.0),
+        ] {
+            let (page, layout) = set(
+                "Hello",
+                &TextBox {
+                    alignment,
+                    ..text_box.clone()
+                },
+            );
+            assert_eq!(layout.line_count, 1);
+            let shown = lines(&page);
+            assert_eq!(shown.len(), 1);
+            assert_eq!(shown[0].0, "Hello");
+            assert_close(shown[0].1, x);
+        }
+    }
+
+    #[test]
+    fn justify() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let line_width = helvetica(&pdf).text_width("aaa bbb", 10);
+        let text_box = TextBox {
+            width: line_width + pt(5.0),
+            height: pt(100.0),
+            alignment: Alignment::Justify,
+            ..TextBox::default()
+        };
+        let (page, layout) = set("aaa bbb ccc ddd", &text_box);
+        assert_eq!(layout.line_count, 2);
+        let shown = lines(&page);
+        assert_eq!(
+            shown
+                .iter()
+                .map(|(line, _, _, _)| line.as_str())
+                .collect::<Vec<_>>(),
+            ["aaa bbb", "ccc ddd"]
+        );
+        // the 5 point slack of the first line is added to its only space, in thousandths of the
+        // font size, and the last line isn't stretched
+        assert_eq!(shown[0].3.len(), 1);
+        assert_close(shown[0].3[0], -500.0);
+        assert!(shown[1].3.is_empty());
+        assert_close(shown[1].1, 20.0);
+    }
+
+    #[test]
+    fn vertical_alignment() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let ascent = to_points(helvetica(&pdf).ascent(10));
+        let line_height = to_points(helvetica(&pdf).line_height(10));
+        let text_box = TextBox {
+            y_pos: pt(50.0),
+            width: pt(200.0),
+            height: pt(100.0),
+            ..TextBox::default()
+        };
+        // the box runs from 80 to 180 points above the bottom of the page
+        for (vertical_alignment, top) in [
+            (VerticalAlignment::Top, 180.0),
+            (
+                VerticalAlignment::Middle,
+                180.0 - (100.0 - line_height) / 2.0,
+            ),
+            (VerticalAlignment::Bottom, 80.0 + line_height),
+        ] {
+            let (page, layout) = set(
+                "Hello",
+                &TextBox {
+                    vertical_alignment,
+                    ..text_box.clone()
+                },
+            );
+            assert_close(to_points(layout.height), line_height);
+            assert!(!layout.overflowed);
+            assert_close(lines(&page)[0].2, top - ascent);
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let ascent = to_points(helvetica(&pdf).ascent(10));
+        let text_box = TextBox {
+            x_pos: pt(10.0),
+            y_pos: pt(50.0),
+            width: pt(40.0),
+            height: pt(15.0),
+            vertical_alignment: VerticalAlignment::Bottom,
+            overflow: Overflow::Clip,
+            ..TextBox::default()
+        };
+        let (page, layout) = set("one two three four", &text_box);
+        assert!(layout.overflowed);
+        assert!(layout.line_count > 1);
+        assert!(layout.height > text_box.height);
+        // text taller than the box starts at its top, whatever its vertical alignment
+        let shown = lines(&page);
+        assert_close(shown[0].2, 30.0 + 65.0 - ascent);
+        assert!(shown.last().unwrap().2 < 80.0);
+
+        // the clipping path is the box, offset by the margins
+        let clip = page
+            .operations
+            .iter()
+            .position(|operation| operation.operator == "W")
+            .unwrap();
+        let operators = page.operations[clip - 1..=clip + 1]
+            .iter()
+            .map(|operation| operation.operator.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(operators, ["re", "W", "n"]);
+        let rectangle = page.operations[clip - 1]
+            .operands
+            .iter()
+            .map(|operand| f64::from(operand.as_float().unwrap()))
+            .collect::<Vec<_>>();
+        for (actual, expected) in rectangle.into_iter().zip([30.0, 80.0, 40.0, 15.0]) {
+            assert_close(actual, expected);
+        }
+
+        // visible text isn't clipped
+        let (page, layout) = set(
+            "one two three four",
+            &TextBox {
+                overflow: Overflow::Visible,
+                ..text_box.clone()
+            },
+        );
+        assert!(layout.overflowed);
+        assert!(!page
+            .operations
+            .iter()
+            .any(|operation| operation.operator == "W"));
+    }
+
+    #[test]
+    fn empty_text() {
+        let (page, layout) = set("", &TextBox::default());
+        assert_eq!(layout.line_count, 0);
+        assert!(page.operations.is_empty());
+    }
+}
@@ -0,0 +1,512 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, StringFormat};
+
+use log::warn;
+
+use crate::Error;
+
+/// `DocumentInfo` is the metadata of a document, shown in the document properties of PDF
+/// readers. Empty fields are left out of the document.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DocumentInfo {
+    /// title of the document
+    pub title: String,
+    /// who created the content of the document
+    pub author: String,
+    /// what the document is about
+    pub subject: String,
+    /// words the document can be searched for by
+    pub keywords: Vec<String>,
+    /// program the document was created with
+    pub creator: String,
+    /// when the document was created
+    pub creation_date: Option<PDFDate>,
+}
+
+/// `PDFDate` is a date and time in a PDF document, in local time or UTC.
+///
+/// It is parsed from an ISO 8601 date such as `"2024-05-01"`, or a date and time such as
+/// `"2024-05-01T13:45"` or `"2024-05-01 13:45:30"`, which is in UTC if it ends in `Z`.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct PDFDate {
+    /// year, such as 2024
+    pub year: u16,
+    /// month of the year, from 1
+    pub month: u8,
+    /// day of the month, from 1
+    pub day: u8,
+    /// hour of the day, from 0 to 23
+    pub hour: u8,
+    /// minute of the hour
+    pub minute: u8,
+    /// second of the minute
+    pub second: u8,
+    /// whether the time is in UTC, rather than an unknown local time zone
+    pub utc: bool,
+}
+
+/// `OutlineItem` is a bookmark in the outline of a document, which jumps to a page when it is
+/// clicked. Items with children are shown collapsed.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct OutlineItem {
+    /// text of the bookmark
+    pub title: String,
+    /// zero indexed page number in the document the bookmark jumps to. This is checked when the
+    /// document is written
+    pub page: usize,
+    /// bookmarks nested under this one
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    /// `new` returns a bookmark to `page` with no children
+    #[must_use]
+    pub fn new(title: impl Into<String>, page: usize) -> Self {
+        Self {
+            title: title.into(),
+            page,
+            children: Vec::new(),
+        }
+    }
+}
+
+impl PDFDate {
+    /// `from_unix_time` returns the UTC date and time `seconds` after the start of 1970, or
+    /// `None` if it is outside the years 0 to 9999
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+    pub fn from_unix_time(seconds: i64) -> Option<Self> {
+        let days = seconds.div_euclid(86_400);
+        let time = seconds.rem_euclid(86_400);
+        // civil date from days since 1970-01-01, counted in 400 year eras of 146097 days that
+        // start on the 1st of March, so leap days are at the end of each year
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Some(Self {
+            year: u16::try_from(year).ok().filter(|year| *year <= 9999)?,
+            month: u8::try_from(month).ok()?,
+            day: u8::try_from(day).ok()?,
+            hour: u8::try_from(time / 3600).ok()?,
+            minute: u8::try_from(time % 3600 / 60).ok()?,
+            second: u8::try_from(time % 60).ok()?,
+            utc: true,
+        })
+    }
+
+    /// `source_date_epoch` returns the date and time set by the `SOURCE_DATE_EPOCH` environment
+    /// variable, in seconds since the start of 1970, which reproducible builds use instead of the
+    /// current time. Returns `None` if it is not set or is invalid.
+    #[must_use]
+    pub fn source_date_epoch() -> Option<Self> {
+        let epoch = std::env::var("SOURCE_DATE_EPOCH").ok()?;
+        let date = epoch.trim().parse().ok().and_then(Self::from_unix_time);
+        if date.is_none() {
+            warn!("SOURCE_DATE_EPOCH {epoch} is not a number of seconds since 1970, ignoring it");
+        }
+        date
+    }
+}
+
+/// `days_in_month` returns the number of days in `month` of `year`, in the Gregorian calendar
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for PDFDate {
+    /// formats the date as a PDF date string, `D:YYYYMMDDHHmmSS`, followed by `Z` if it is in
+    /// UTC
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "D:{:04}{:02}{:02}{:02}{:02}{:02}{}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            if self.utc { "Z" } else { "" }
+        )
+    }
+}
+
+impl FromStr for PDFDate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let (text, utc) = trimmed
+            .strip_suffix('Z')
+            .map_or((trimmed, false), |text| (text, true));
+        let invalid = || {
+            Error::DateParsing(format!(
+                "{trimmed} is not a date such as 2024-05-01 or a date and time such as 2024-05-01T13:45"
+            ))
+        };
+        let (date, time) = text
+            .split_once(['T', ' '])
+            .map_or((text, None), |(date, time)| (date, Some(time)));
+        let parse_fields = |fields: &str, separator: char, count: usize| {
+            let fields = fields
+                .split(separator)
+                .map(|field| {
+                    if field.is_empty() || !field.chars().all(|c| c.is_ascii_digit()) {
+                        return Err(invalid());
+                    }
+                    field.parse::<u16>().map_err(|_| invalid())
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if (count..=3).contains(&fields.len()) {
+                Ok(fields)
+            } else {
+                Err(invalid())
+            }
+        };
+        let narrow =
+            |field: Option<&u16>| u8::try_from(*field.unwrap_or(&0)).map_err(|_| invalid());
+
+        let date = parse_fields(date, '-', 3)?;
+        let time = time.map_or_else(|| Ok(Vec::new()), |time| parse_fields(time, ':', 2))?;
+        let pdf_date = Self {
+            year: date.first().copied().unwrap_or_default(),
+            month: narrow(date.get(1))?,
+            day: narrow(date.get(2))?,
+            hour: narrow(time.first())?,
+            minute: narrow(time.get(1))?,
+            second: narrow(time.get(2))?,
+            utc,
+        };
+        if pdf_date.year > 9999
+            || !(1..=12).contains(&pdf_date.month)
+            || !(1..=days_in_month(pdf_date.year, pdf_date.month)).contains(&pdf_date.day)
+            || pdf_date.hour > 23
+            || pdf_date.minute > 59
+            || pdf_date.second > 59
+        {
+            return Err(invalid());
+        }
+        Ok(pdf_date)
+    }
+}
+
+/// `text_string` returns `text` as a PDF text string. Text that isn't ASCII is encoded as
+/// UTF-16 with a byte order mark, which readers recognise instead of PDFDocEncoding.
+pub(crate) fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let bytes = [0xFE_u8, 0xFF]
+        .into_iter()
+        .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+        .collect();
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// `destination` returns an explicit destination that shows the whole of zero indexed `page`
+///
+/// # Errors
+///
+/// Will error if `page` isn't one of `page_ids`
+pub(crate) fn destination(page_ids: &[ObjectId], page: usize) -> Result<Object, Error> {
+    let page_id = page_ids
+        .get(page)
+        .ok_or_else(|| Error::Other(format!("destination page {page} does not exist")))?;
+    Ok(vec![(*page_id).into(), "Fit".into()].into())
+}
+
+/// `info_dictionary` returns the document information dictionary for `info`, or `None` if all
+/// of its fields are empty
+pub(crate) fn info_dictionary(info: &DocumentInfo) -> Option<Dictionary> {
+    let mut dictionary = Dictionary::new();
+    for (key, value) in [
+        ("Title", info.title.clone()),
+        ("Author", info.author.clone()),
+        ("Subject", info.subject.clone()),
+        ("Keywords", info.keywords.join(", ")),
+        ("Creator", info.creator.clone()),
+    ] {
+        if !value.is_empty() {
+            dictionary.set(key, text_string(&value));
+        }
+    }
+    if let Some(creation_date) = info.creation_date {
+        dictionary.set(
+            "CreationDate",
+            Object::string_literal(creation_date.to_string()),
+        );
+    }
+    (!dictionary.is_empty()).then_some(dictionary)
+}
+
+/// `write_outline` adds the outline dictionary and an item dictionary for each of `items` to
+/// `doc`, returning the ID of the outline dictionary, or `None` if there are no items
+///
+/// # Errors
+///
+/// Will error if an item jumps to a page that isn't one of `page_ids`
+pub(crate) fn write_outline(
+    doc: &mut Document,
+    items: &[OutlineItem],
+    page_ids: &[ObjectId],
+) -> Result<Option<ObjectId>, Error> {
+    if items.is_empty() {
+        return Ok(None);
+    }
+    let outline_id = doc.new_object_id();
+    let (first, last) = write_items(doc, items, outline_id, page_ids)?;
+    doc.set_object(
+        outline_id,
+        dictionary! {
+            "Type" => "Outlines",
+            "First" => first,
+            "Last" => last,
+            // only the top level items are shown until they are expanded
+            "Count" => i64::try_from(items.len()).unwrap_or(i64::MAX),
+        },
+    );
+    Ok(Some(outline_id))
+}
+
+/// `write_items` adds the item dictionaries for `items` and their children to `doc`, linked to
+/// each other and to `parent`. Returns the IDs of the first and last items.
+///
+/// # Errors
+///
+/// Will error if an item jumps to a page that isn't one of `page_ids`
+fn write_items(
+    doc: &mut Document,
+    items: &[OutlineItem],
+    parent: ObjectId,
+    page_ids: &[ObjectId],
+) -> Result<(ObjectId, ObjectId), Error> {
+    // sibling items refer to each other, so their IDs are needed before they are written
+    let item_ids = items
+        .iter()
+        .map(|_| doc.new_object_id())
+        .collect::<Vec<_>>();
+    for (index, (item, item_id)) in items.iter().zip(&item_ids).enumerate() {
+        let mut dictionary = dictionary! {
+            "Title" => text_string(&item.title),
+            "Parent" => parent,
+            "Dest" => destination(page_ids, item.page)?,
+        };
+        if let Some(previous) = index.checked_sub(1).and_then(|index| item_ids.get(index)) {
+            dictionary.set("Prev", *previous);
+        }
+        if let Some(next) = index.checked_add(1).and_then(|index| item_ids.get(index)) {
+            dictionary.set("Next", *next);
+        }
+        if !item.children.is_empty() {
+            let (first, last) = write_items(doc, &item.children, *item_id, page_ids)?;
+            dictionary.set("First", first);
+            dictionary.set("Last", last);
+            // a negative count means the item is collapsed, hiding this many children
+            dictionary.set(
+                "Count",
+                i64::try_from(item.children.len()).map_or(i64::MIN, i64::wrapping_neg),
+            );
+        }
+        doc.set_object(*item_id, dictionary);
+    }
+    match (item_ids.first(), item_ids.last()) {
+        (Some(first), Some(last)) => Ok((*first, *last)),
+        _ => Err(Error::Other("outline item has no children".to_string())),
+    }
+}
+
+/// `page_labels` returns the page labels number tree for pages with `labels`, or `None` if no
+/// page has a label. Pages without a label are labelled with their one indexed page number.
+pub(crate) fn page_labels<'l>(
+    labels: impl IntoIterator<Item = Option<&'l str>>,
+) -> Option<Dictionary> {
+    let mut nums: Vec<Object> = Vec::new();
+    let mut labelled = false;
+    let mut previous_labelled = true;
+    for (index, label) in labels.into_iter().enumerate() {
+        let page = i64::try_from(index).unwrap_or(i64::MAX);
+        // a label range with only a prefix labels its pages with exactly the prefix, and a
+        // range with a decimal style numbers its pages on from its start
+        match label {
+            Some(label) => {
+                labelled = true;
+                previous_labelled = true;
+                nums.push(page.into());
+                nums.push(dictionary! {"P" => text_string(label)}.into());
+            }
+            None if previous_labelled => {
+                previous_labelled = false;
+                nums.push(page.into());
+                nums.push(dictionary! {"S" => "D", "St" => page.saturating_add(1)}.into());
+            }
+            None => {}
+        }
+    }
+    labelled.then(|| dictionary! {"Nums" => nums})
+}
+
+/// `named_destinations` returns the name tree of `destinations`, which map names to zero indexed
+/// page numbers, or `None` if there aren't any
+///
+/// # Errors
+///
+/// Will error if a destination is a page that isn't one of `page_ids`
+pub(crate) fn named_destinations(
+    destinations: &BTreeMap<String, usize>,
+    page_ids: &[ObjectId],
+) -> Result<Option<Dictionary>, Error> {
+    if destinations.is_empty() {
+        return Ok(None);
+    }
+    // names in a name tree are in byte order, which a BTreeMap of strings already is
+    let mut names = Vec::new();
+    for (name, page) in destinations {
+        names.push(Object::string_literal(name.as_str()));
+        names.push(destination(page_ids, *page)?);
+    }
+    Ok(Some(dictionary! {"Names" => names}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date() {
+        assert_eq!(
+            "2024-05-01".parse::<PDFDate>().unwrap(),
+            PDFDate {
+                year: 2024,
+                month: 5,
+                day: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            " 2024-05-01T13:45Z ".parse::<PDFDate>().unwrap(),
+            PDFDate {
+                year: 2024,
+                month: 5,
+                day: 1,
+                hour: 13,
+                minute: 45,
+                second: 0,
+                utc: true,
+            }
+        );
+        assert_eq!(
+            "2024-05-01 13:45:30".parse::<PDFDate>().unwrap(),
+            PDFDate {
+                year: 2024,
+                month: 5,
+                day: 1,
+                hour: 13,
+                minute: 45,
+                second: 30,
+                utc: false,
+            }
+        );
+    }
+
+    #[test]
+    fn days_of_month() {
+        assert!("2024-02-29".parse::<PDFDate>().is_ok());
+        assert!("2000-02-29".parse::<PDFDate>().is_ok());
+        assert!("2024-04-30".parse::<PDFDate>().is_ok());
+        assert!("2024-12-31".parse::<PDFDate>().is_ok());
+        for text in [
+            "2024-02-30",
+            "2024-02-31",
+            "2023-02-29",
+            "1900-02-29",
+            "2024-04-31",
+            "2024-11-31",
+            "2024-01-32",
+            "2024-01-00",
+        ] {
+            assert!(
+                matches!(text.parse::<PDFDate>(), Err(Error::DateParsing(_))),
+                "{text} parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for text in [
+            "",
+            "2024",
+            "2024-13-01",
+            "2024-00-01",
+            "2024-05-01T24:00",
+            "2024-05-01T13:60",
+            "2024-05-01T13:45:60",
+            "2024-05-01T13",
+            "2024-05-01T13:45:30:00",
+            "2024-5-+1",
+            "10000-01-01",
+            "May 1st",
+        ] {
+            assert!(
+                matches!(text.parse::<PDFDate>(), Err(Error::DateParsing(_))),
+                "{text} parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn display() {
+        let date = PDFDate {
+            year: 2024,
+            month: 5,
+            day: 1,
+            hour: 9,
+            minute: 5,
+            second: 3,
+            utc: false,
+        };
+        assert_eq!(date.to_string(), "D:20240501090503");
+        assert_eq!(
+            PDFDate { utc: true, ..date }.to_string(),
+            "D:20240501090503Z"
+        );
+        assert_eq!(
+            "0999-01-02".parse::<PDFDate>().unwrap().to_string(),
+            "D:09990102000000"
+        );
+    }
+
+    #[test]
+    fn unix_time() {
+        assert_eq!(
+            PDFDate::from_unix_time(0).unwrap().to_string(),
+            "D:19700101000000Z"
+        );
+        assert_eq!(
+            PDFDate::from_unix_time(1_709_210_096).unwrap().to_string(),
+            "D:20240229123456Z"
+        );
+        assert_eq!(PDFDate::from_unix_time(253_402_300_800), None);
+    }
+}
@@ -54,6 +54,17 @@ pub enum PaperSize {
     Custom(ucum::Meter<f64>, ucum::Meter<f64>),
 }
 
+/// `Orientation` is the direction a page is laid out in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// long edge of the paper is vertical
+    #[default]
+    Portrait,
+    /// long edge of the paper is horizontal. Landscape pages are written as portrait pages
+    /// rotated a quarter turn clockwise.
+    Landscape,
+}
+
 impl PaperSize {
     /// `size` outputs the short and long side measurements of the specified
     /// paper size as a tuple in the specified order.
@@ -106,6 +117,17 @@ impl PaperSize {
             PaperSize::Custom(short, long) => (short, long),
         }
     }
+
+    /// `oriented_size` outputs the width and height of the specified paper size when laid out
+    /// in `orientation`.
+    #[must_use]
+    pub fn oriented_size(self, orientation: Orientation) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+        let (short, long) = self.size();
+        match orientation {
+            Orientation::Portrait => (short, long),
+            Orientation::Landscape => (long, short),
+        }
+    }
 }
 
 impl fmt::Display for PaperSize {
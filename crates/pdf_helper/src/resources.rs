@@ -0,0 +1,692 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::content_hash::{ContentHash, ContentHasher};
+use crate::image::PDFImage;
+use crate::Error;
+
+/// `Resources` are the named objects drawn by the operations of a page, such as forms, images,
+/// patterns, graphics states and layers, along with the glyphs drawn from each font.
+///
+/// Forms, patterns and graphics states are named after a hash of their contents, so identical
+/// resources share a name, and are written once, however many pages use them.
+#[derive(Clone, Default)]
+pub(crate) struct Resources {
+    /// Form XObjects, by name
+    pub(crate) forms: BTreeMap<String, PDFForm>,
+    /// raster images, by name
+    pub(crate) images: BTreeMap<String, PDFImage>,
+    /// patterns used to paint fills and strokes, by name
+    patterns: BTreeMap<String, PDFPattern>,
+    /// graphics state parameter dictionaries, by name
+    graphics_states: BTreeMap<String, PDFGraphicsState>,
+    /// glyph IDs drawn from each font, by font name, with the text each glyph represents
+    glyphs: BTreeMap<String, BTreeMap<u16, String>>,
+    /// names of the optional content groups that operations are marked as part of
+    layers: BTreeSet<String>,
+}
+
+/// `PDFForm` is a Form XObject, a group of operations that is written once and can be drawn
+/// any number of times on any page
+#[derive(Clone, Debug)]
+pub(crate) struct PDFForm {
+    /// operations that draw the form, in its own coordinate space
+    pub(crate) operations: Vec<Operation>,
+    /// bounding box of the form in its own coordinate space, as left, bottom, right and top
+    pub(crate) bbox: [f64; 4],
+    /// transformation from the coordinate space of the form onto the space it is drawn in, if
+    /// they aren't the same
+    pub(crate) matrix: Option<[f64; 6]>,
+    /// whether the form is a transparency group, which is composited as a whole before any
+    /// opacity or mask it is drawn with is applied
+    pub(crate) transparency_group: bool,
+}
+
+/// `PDFGraphicsState` is a set of graphics state parameters, which are set all at once with the
+/// `gs` operator
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PDFGraphicsState {
+    /// opacity of fills, from 0 to 1
+    pub(crate) fill_alpha: Option<f64>,
+    /// opacity of strokes, from 0 to 1
+    pub(crate) stroke_alpha: Option<f64>,
+    /// mask applied to everything drawn
+    pub(crate) soft_mask: Option<PDFSoftMask>,
+}
+
+/// `PDFSoftMask` is a mask that sets the opacity of everything drawn from a form
+#[derive(Clone, Debug)]
+pub(crate) struct PDFSoftMask {
+    /// whether the opacity is the luminosity of the form, rather than its own opacity
+    pub(crate) luminosity: bool,
+    /// name of the transparency group form that the mask is drawn from
+    pub(crate) form: String,
+}
+
+/// `PDFPattern` is a pattern that a fill or stroke is painted with
+#[derive(Clone, Debug)]
+pub(crate) enum PDFPattern {
+    /// a gradient, which is a shading pattern
+    Shading {
+        /// the gradient
+        shading: PDFShading,
+        /// transformation from the coordinate space of the gradient onto the content stream
+        matrix: [f64; 6],
+    },
+    /// a tile of content repeated in both directions, which is a tiling pattern
+    Tiling {
+        /// operations that draw one tile
+        operations: Vec<Operation>,
+        /// width and height of the tile
+        size: [f64; 2],
+        /// transformation from the coordinate space of the tile onto the content stream
+        matrix: [f64; 6],
+    },
+}
+
+/// `PDFShading` is a gradient, blended between colors at stops along it
+#[derive(Clone, Debug)]
+pub(crate) struct PDFShading {
+    /// whether the gradient is radial, rather than axial
+    pub(crate) radial: bool,
+    /// `x0 y0 x1 y1` of the line an axial gradient follows, or `x0 y0 r0 x1 y1 r1` of the start
+    /// and end circles of a radial gradient
+    pub(crate) coords: Vec<f64>,
+    /// whether the colors are in the DeviceGray color space, rather than DeviceRGB
+    pub(crate) gray: bool,
+    /// offset along the gradient from 0 to 1 and color components of each stop, in order
+    pub(crate) stops: Vec<(f64, Vec<f64>)>,
+}
+
+impl Resources {
+    /// `add_form` adds a form, and returns its name
+    pub(crate) fn add_form(&mut self, form: PDFForm) -> String {
+        let name = content_name("Fm", &form);
+        self.forms.entry(name.clone()).or_insert(form);
+        name
+    }
+
+    /// `add_pattern` adds a pattern, and returns its name
+    pub(crate) fn add_pattern(&mut self, pattern: PDFPattern) -> String {
+        let name = content_name("P", &pattern);
+        self.patterns.entry(name.clone()).or_insert(pattern);
+        name
+    }
+
+    /// `add_graphics_state` adds a graphics state, and returns its name
+    pub(crate) fn add_graphics_state(&mut self, graphics_state: PDFGraphicsState) -> String {
+        let name = content_name("GS", &graphics_state);
+        self.graphics_states
+            .entry(name.clone())
+            .or_insert(graphics_state);
+        name
+    }
+
+    /// `add_layer` adds the optional content group named `layer`, and returns its resource name
+    pub(crate) fn add_layer(&mut self, layer: &str) -> String {
+        self.layers.insert(layer.to_string());
+        content_name("OC", &layer)
+    }
+
+    /// `add_glyph` records that `glyph` of the font named `font` is drawn to show `text`. Glyphs
+    /// that are part of the text of an earlier glyph, such as the second glyph of a decomposed
+    /// accented letter, have no text of their own.
+    pub(crate) fn add_glyph(&mut self, font: &str, glyph: u16, text: &str) {
+        let glyph_text = self
+            .glyphs
+            .entry(font.to_string())
+            .or_default()
+            .entry(glyph)
+            .or_default();
+        if glyph_text.is_empty() {
+            text.clone_into(glyph_text);
+        }
+    }
+
+    /// `glyphs` returns the glyph IDs drawn from the font named `font`, with the text each glyph
+    /// represents, or `None` if the font isn't used
+    pub(crate) fn glyphs(&self, font: &str) -> Option<&BTreeMap<u16, String>> {
+        self.glyphs.get(font)
+    }
+
+    /// `extend` adds every resource of `other` that isn't already in these resources
+    pub(crate) fn extend(&mut self, other: &Resources) {
+        for (name, form) in &other.forms {
+            self.forms
+                .entry(name.clone())
+                .or_insert_with(|| form.clone());
+        }
+        for (name, image) in &other.images {
+            self.images
+                .entry(name.clone())
+                .or_insert_with(|| image.clone());
+        }
+        for (name, pattern) in &other.patterns {
+            self.patterns
+                .entry(name.clone())
+                .or_insert_with(|| pattern.clone());
+        }
+        for (name, graphics_state) in &other.graphics_states {
+            self.graphics_states
+                .entry(name.clone())
+                .or_insert_with(|| graphics_state.clone());
+        }
+        for (font, glyphs) in &other.glyphs {
+            for (glyph, text) in glyphs {
+                self.add_glyph(font, *glyph, text);
+            }
+        }
+        self.layers.extend(other.layers.iter().cloned());
+    }
+
+    /// `write` adds every resource to `doc`, and returns a resource dictionary that refers to
+    /// them by name. The forms and patterns use the resource dictionary `resources_id`, which
+    /// must contain the returned entries.
+    ///
+    /// # Errors
+    ///
+    /// Will error if a content stream fails to encode, or a mask refers to a missing form
+    pub(crate) fn write(
+        &self,
+        doc: &mut Document,
+        resources_id: ObjectId,
+    ) -> Result<Dictionary, Error> {
+        // Form XObjects are streams of operations like page contents, with a bounding box that
+        // clips them. Each is drawn by name with the Do operator.
+        let mut xobjects = Dictionary::new();
+        let mut form_ids = BTreeMap::new();
+        for (name, form) in &self.forms {
+            let mut dict = dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => numbers(&form.bbox),
+                "Resources" => resources_id,
+            };
+            if let Some(matrix) = form.matrix {
+                dict.set("Matrix", numbers(&matrix));
+            }
+            if form.transparency_group {
+                dict.set(
+                    "Group",
+                    dictionary! {
+                        "S" => "Transparency",
+                        "CS" => "DeviceRGB",
+                    },
+                );
+            }
+            let content = Content {
+                operations: form.operations.clone(),
+            };
+            let form_id = doc.add_object(Stream::new(dict, content.encode()?));
+            xobjects.set(name.as_str(), form_id);
+            form_ids.insert(name.as_str(), form_id);
+        }
+        // raster images are written as image XObjects, also drawn by name with Do
+        for (name, image) in &self.images {
+            xobjects.set(name.as_str(), image.write(doc)?);
+        }
+
+        let mut patterns = Dictionary::new();
+        for (name, pattern) in &self.patterns {
+            let pattern_id = match pattern {
+                PDFPattern::Shading { shading, matrix } => doc.add_object(dictionary! {
+                    "Type" => "Pattern",
+                    "PatternType" => 2,
+                    "Shading" => shading.dictionary(),
+                    "Matrix" => numbers(matrix),
+                }),
+                PDFPattern::Tiling {
+                    operations,
+                    size,
+                    matrix,
+                } => {
+                    let content = Content {
+                        operations: operations.clone(),
+                    };
+                    doc.add_object(Stream::new(
+                        dictionary! {
+                            "Type" => "Pattern",
+                            "PatternType" => 1,
+                            // colored tiles, which set their own colors
+                            "PaintType" => 1,
+                            // constant spacing between tiles
+                            "TilingType" => 1,
+                            "BBox" => numbers(&[0.0, 0.0, size[0], size[1]]),
+                            "XStep" => size[0],
+                            "YStep" => size[1],
+                            "Matrix" => numbers(matrix),
+                            "Resources" => resources_id,
+                        },
+                        content.encode()?,
+                    ))
+                }
+            };
+            patterns.set(name.as_str(), pattern_id);
+        }
+
+        let mut graphics_states = Dictionary::new();
+        for (name, graphics_state) in &self.graphics_states {
+            let mut dict = dictionary! {
+                "Type" => "ExtGState",
+            };
+            if let Some(alpha) = graphics_state.fill_alpha {
+                dict.set("ca", alpha);
+            }
+            if let Some(alpha) = graphics_state.stroke_alpha {
+                dict.set("CA", alpha);
+            }
+            if let Some(soft_mask) = &graphics_state.soft_mask {
+                let form_id = form_ids.get(soft_mask.form.as_str()).ok_or_else(|| {
+                    Error::PDFError(format!("mask form {} is missing", soft_mask.form))
+                })?;
+                dict.set(
+                    "SMask",
+                    dictionary! {
+                        "Type" => "Mask",
+                        "S" => if soft_mask.luminosity { "Luminosity" } else { "Alpha" },
+                        "G" => *form_id,
+                    },
+                );
+            }
+            graphics_states.set(name.as_str(), doc.add_object(dict));
+        }
+
+        Ok(dictionary! {
+            "XObject" => xobjects,
+            "Pattern" => patterns,
+            "ExtGState" => graphics_states,
+        })
+    }
+
+    /// `write_layers` adds an optional content group dictionary for each layer to `doc`, and
+    /// returns the property list dictionary that refers to them by resource name, along with
+    /// their IDs in the order of their names
+    pub(crate) fn write_layers(&self, doc: &mut Document) -> (Dictionary, Vec<ObjectId>) {
+        let mut properties = Dictionary::new();
+        let mut layer_ids = Vec::new();
+        for layer in &self.layers {
+            let layer_id = doc.add_object(dictionary! {
+                "Type" => "OCG",
+                "Name" => crate::outline::text_string(layer),
+            });
+            properties.set(content_name("OC", &layer.as_str()), layer_id);
+            layer_ids.push(layer_id);
+        }
+        (properties, layer_ids)
+    }
+}
+
+impl PDFShading {
+    /// `dictionary` returns the shading dictionary of the gradient. Colors before the first stop
+    /// and after the last stop are the colors of those stops.
+    fn dictionary(&self) -> Dictionary {
+        let mut stops = self.stops.clone();
+        if let Some((offset, color)) = stops.first().cloned() {
+            if offset > 0.0 {
+                stops.insert(0, (0.0, color));
+            }
+        }
+        if let Some((offset, color)) = stops.last().cloned() {
+            if offset < 1.0 {
+                stops.push((1.0, color));
+            }
+        }
+        // exponential interpolation functions with an exponent of 1 blend linearly from C0 to C1
+        let blend = |start: &[f64], end: &[f64]| -> Object {
+            dictionary! {
+                "FunctionType" => 2,
+                "Domain" => numbers(&[0.0, 1.0]),
+                "C0" => numbers(start),
+                "C1" => numbers(end),
+                "N" => 1,
+            }
+            .into()
+        };
+        let pairs = stops.windows(2).collect::<Vec<_>>();
+        let function = match pairs.as_slice() {
+            [] => {
+                let color = stops.first().map(|(_, color)| color.as_slice());
+                blend(color.unwrap_or_default(), color.unwrap_or_default())
+            }
+            [[(_, start), (_, end)]] => blend(start, end),
+            _ => {
+                // stitching functions join one function for each pair of stops, split at the
+                // offsets of the stops in between
+                let bounds = stops
+                    .get(1..stops.len().saturating_sub(1))
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|(offset, _)| *offset)
+                    .collect::<Vec<_>>();
+                dictionary! {
+                    "FunctionType" => 3,
+                    "Domain" => numbers(&[0.0, 1.0]),
+                    "Functions" => pairs
+                        .iter()
+                        .map(|pair| match pair {
+                            [(_, start), (_, end)] => blend(start, end),
+                            _ => Object::Null,
+                        })
+                        .collect::<Vec<_>>(),
+                    "Bounds" => numbers(&bounds),
+                    "Encode" => numbers(&[0.0, 1.0].repeat(pairs.len())),
+                }
+                .into()
+            }
+        };
+        dictionary! {
+            "ShadingType" => if self.radial { 3 } else { 2 },
+            "ColorSpace" => if self.gray { "DeviceGray" } else { "DeviceRGB" },
+            "Coords" => numbers(&self.coords),
+            "Function" => function,
+            "Extend" => vec![true.into(), true.into()],
+        }
+    }
+}
+
+/// `numbers` returns `values` as a PDF array
+fn numbers(values: &[f64]) -> Vec<Object> {
+    values.iter().map(|&value| value.into()).collect()
+}
+
+/// `content_name` returns a resource name starting with `prefix`, based on a hash of `resource`
+fn content_name(prefix: &str, resource: &impl ContentHash) -> String {
+    let mut hasher = ContentHasher::new();
+    resource.content_hash(&mut hasher);
+    format!("{prefix}{:016x}", hasher.finish())
+}
+
+impl ContentHash for PDFForm {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        self.operations.as_slice().content_hash(hasher);
+        self.bbox.content_hash(hasher);
+        self.matrix.content_hash(hasher);
+        hasher.write_bool(self.transparency_group);
+    }
+}
+
+impl ContentHash for PDFGraphicsState {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        self.fill_alpha.content_hash(hasher);
+        self.stroke_alpha.content_hash(hasher);
+        self.soft_mask.content_hash(hasher);
+    }
+}
+
+impl ContentHash for PDFSoftMask {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_bool(self.luminosity);
+        self.form.as_str().content_hash(hasher);
+    }
+}
+
+impl ContentHash for PDFPattern {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        match self {
+            PDFPattern::Shading { shading, matrix } => {
+                // the variant is written first, so different kinds of pattern never match
+                hasher.write_u64(0);
+                shading.content_hash(hasher);
+                matrix.content_hash(hasher);
+            }
+            PDFPattern::Tiling {
+                operations,
+                size,
+                matrix,
+            } => {
+                hasher.write_u64(1);
+                operations.as_slice().content_hash(hasher);
+                size.content_hash(hasher);
+                matrix.content_hash(hasher);
+            }
+        }
+    }
+}
+
+impl ContentHash for PDFShading {
+    fn content_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_bool(self.radial);
+        self.coords.as_slice().content_hash(hasher);
+        hasher.write_bool(self.gray);
+        hasher.write_u64(u64::try_from(self.stops.len()).unwrap_or(u64::MAX));
+        for (offset, components) in &self.stops {
+            hasher.write_f64(*offset);
+            components.as_slice().content_hash(hasher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `converted` converts the nodes of `svg`, and returns their operations and resources
+    fn converted(svg: &str) -> (Vec<Operation>, Resources) {
+        let tree = usvg::Tree::from_str(svg, &usvg::Options::default()).unwrap();
+        let mut resources = Resources::default();
+        let operations =
+            crate::loop_nodes(tree.root(), usvg::Transform::identity(), &mut resources);
+        (operations, resources)
+    }
+
+    /// `floats` returns the numbers in PDF array `object`
+    fn floats(object: &Object) -> Vec<f64> {
+        object
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|number| f64::from(number.as_float().unwrap()))
+            .collect()
+    }
+
+    /// `assert_close` asserts that each of `actual` is within rounding of `expected`
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (actual_value, expected_value) in actual.iter().zip(expected) {
+            assert!(
+                (actual_value - expected_value).abs() < 1e-3,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn two_stops() {
+        let shading = PDFShading {
+            radial: false,
+            coords: vec![0.0, 0.0, 10.0, 0.0],
+            gray: false,
+            stops: vec![(0.0, vec![1.0, 0.0, 0.0]), (1.0, vec![0.0, 0.0, 1.0])],
+        };
+        let dict = shading.dictionary();
+        assert_eq!(dict.get(b"ShadingType").unwrap().as_i64().unwrap(), 2);
+        assert_eq!(
+            dict.get(b"ColorSpace").unwrap().as_name().unwrap(),
+            b"DeviceRGB"
+        );
+        assert_close(
+            &floats(dict.get(b"Coords").unwrap()),
+            &[0.0, 0.0, 10.0, 0.0],
+        );
+        let function = dict.get(b"Function").unwrap().as_dict().unwrap();
+        assert_eq!(function.get(b"FunctionType").unwrap().as_i64().unwrap(), 2);
+        assert_close(&floats(function.get(b"C0").unwrap()), &[1.0, 0.0, 0.0]);
+        assert_close(&floats(function.get(b"C1").unwrap()), &[0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn stitched_stops() {
+        // the colors of the first and last stops continue to the ends of the gradient
+        let shading = PDFShading {
+            radial: true,
+            coords: vec![5.0, 5.0, 0.0, 5.0, 5.0, 5.0],
+            gray: true,
+            stops: vec![(0.25, vec![0.0]), (0.5, vec![1.0]), (0.75, vec![0.5])],
+        };
+        let dict = shading.dictionary();
+        assert_eq!(dict.get(b"ShadingType").unwrap().as_i64().unwrap(), 3);
+        assert_eq!(
+            dict.get(b"ColorSpace").unwrap().as_name().unwrap(),
+            b"DeviceGray"
+        );
+        let function = dict.get(b"Function").unwrap().as_dict().unwrap();
+        assert_eq!(function.get(b"FunctionType").unwrap().as_i64().unwrap(), 3);
+        assert_close(
+            &floats(function.get(b"Bounds").unwrap()),
+            &[0.25, 0.5, 0.75],
+        );
+        assert_eq!(floats(function.get(b"Encode").unwrap()).len(), 8);
+        let colors = function
+            .get(b"Functions")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|blend| {
+                let blend = blend.as_dict().unwrap();
+                [b"C0", b"C1"].map(|key| floats(blend.get(key).unwrap())[0])
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(colors, [[0.0, 0.0], [0.0, 1.0], [1.0, 0.5], [0.5, 0.5]]);
+    }
+
+    #[test]
+    fn svg_gradients() {
+        let (operations, resources) = converted(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <linearGradient id="linear" gradientUnits="userSpaceOnUse" x1="0" y1="0" x2="100" y2="0">
+                    <stop offset="0" stop-color="#ff0000"/>
+                    <stop offset="1" stop-color="#0000ff"/>
+                </linearGradient>
+                <radialGradient id="radial" gradientUnits="userSpaceOnUse" cx="50" cy="50" r="40" fx="40" fy="50">
+                    <stop offset="0" stop-color="#ffffff"/>
+                    <stop offset="1" stop-color="#000000"/>
+                </radialGradient>
+                <rect width="100" height="50" fill="url(#linear)"/>
+                <rect y="50" width="100" height="50" stroke="url(#radial)" fill="none"/>
+            </svg>"##,
+        );
+        let shadings = resources
+            .patterns
+            .values()
+            .map(|pattern| match pattern {
+                PDFPattern::Shading { shading, .. } => shading,
+                PDFPattern::Tiling { .. } => panic!("gradients are shading patterns"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(shadings.len(), 2);
+        let linear = shadings.iter().find(|shading| !shading.radial).unwrap();
+        assert_close(&linear.coords, &[0.0, 0.0, 100.0, 0.0]);
+        assert_eq!(linear.stops.len(), 2);
+        assert_close(&linear.stops[0].1, &[1.0, 0.0, 0.0]);
+        assert_close(&linear.stops[1].1, &[0.0, 0.0, 1.0]);
+        // radial gradients start at the focal point
+        let radial = shadings.iter().find(|shading| shading.radial).unwrap();
+        assert_close(&radial.coords, &[40.0, 50.0, 0.0, 50.0, 50.0, 40.0]);
+
+        // the fill is set with cs and scn, and the stroke with CS and SCN
+        let operators = operations
+            .iter()
+            .map(|operation| operation.operator.as_str())
+            .filter(|operator| ["cs", "scn", "CS", "SCN"].contains(operator))
+            .collect::<Vec<_>>();
+        assert_eq!(operators, ["cs", "scn", "CS", "SCN"]);
+        for operation in operations
+            .iter()
+            .filter(|operation| ["scn", "SCN"].contains(&operation.operator.as_str()))
+        {
+            let name = operation.operands[0].as_name_str().unwrap();
+            assert!(resources.patterns.contains_key(name));
+        }
+    }
+
+    #[test]
+    fn svg_gradient_opacity() {
+        // stops of varying opacity are masked by a gray gradient of their opacities
+        let (_, resources) = converted(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <linearGradient id="fade" gradientUnits="userSpaceOnUse" x1="0" y1="0" x2="100" y2="0">
+                    <stop offset="0" stop-color="#ff0000" stop-opacity="1"/>
+                    <stop offset="1" stop-color="#ff0000" stop-opacity="0.2"/>
+                </linearGradient>
+                <rect width="100" height="100" fill="url(#fade)"/>
+            </svg>"##,
+        );
+        let mask = resources
+            .patterns
+            .values()
+            .find_map(|pattern| match pattern {
+                PDFPattern::Shading { shading, .. } if shading.gray => Some(shading),
+                _ => None,
+            })
+            .unwrap();
+        assert_close(&mask.stops[0].1, &[1.0]);
+        assert_close(&mask.stops[1].1, &[0.2]);
+        let soft_mask = resources
+            .graphics_states
+            .values()
+            .find_map(|graphics_state| graphics_state.soft_mask.as_ref())
+            .unwrap();
+        assert!(soft_mask.luminosity);
+        assert!(resources.forms[&soft_mask.form].transparency_group);
+    }
+
+    #[test]
+    fn svg_pattern() {
+        let (_, resources) = converted(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <pattern id="dots" patternUnits="userSpaceOnUse" x="5" y="0" width="10" height="20">
+                    <circle cx="5" cy="5" r="2" fill="#000000"/>
+                </pattern>
+                <rect width="100" height="100" fill="url(#dots)"/>
+            </svg>"##,
+        );
+        let (operations, size, matrix) = resources
+            .patterns
+            .values()
+            .find_map(|pattern| match pattern {
+                PDFPattern::Tiling {
+                    operations,
+                    size,
+                    matrix,
+                } => Some((operations, size, matrix)),
+                PDFPattern::Shading { .. } => None,
+            })
+            .unwrap();
+        assert_close(size, &[10.0, 20.0]);
+        // tiles are moved to the corner of the pattern
+        assert_close(matrix, &[1.0, 0.0, 0.0, 1.0, 5.0, 0.0]);
+        assert!(operations.iter().any(|operation| operation.operator == "f"));
+
+        // tiling patterns are streams that step by the size of the tile
+        let mut doc = Document::with_version("1.5");
+        let resources_id = doc.new_object_id();
+        let dict = resources.write(&mut doc, resources_id).unwrap();
+        let (_, pattern_id) = dict
+            .get(b"Pattern")
+            .unwrap()
+            .as_dict()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap();
+        let pattern = doc
+            .get_object(pattern_id.as_reference().unwrap())
+            .unwrap()
+            .as_stream()
+            .unwrap();
+        assert_eq!(
+            pattern.dict.get(b"PatternType").unwrap().as_i64().unwrap(),
+            1
+        );
+        assert_close(
+            &floats(pattern.dict.get(b"BBox").unwrap()),
+            &[0.0, 0.0, 10.0, 20.0],
+        );
+        let steps = [b"XStep".as_slice(), b"YStep"]
+            .map(|key| f64::from(pattern.dict.get(key).unwrap().as_float().unwrap()));
+        assert_close(&steps, &[10.0, 20.0]);
+    }
+}
@@ -0,0 +1,467 @@
+use std::ops::Range;
+
+use dimensioned::ucum;
+use lopdf::content::Operation;
+
+use paragraph_breaker::{Error as ParagraphError, Line};
+
+use crate::{Error, PDFFont, PDFTextRenderMode, TextBox, TextLayout};
+
+/// `TextSpan` is a part of a paragraph of rich text, set in a single style
+pub struct TextSpan<'a> {
+    /// text of the span. Spaces at the start or end of a span separate it from the spans around
+    /// it, otherwise spans run on into each other.
+    pub text: String,
+    /// font the span is set in, if it has glyphs for the text
+    pub font: &'a PDFFont<'a>,
+    /// size of the font in points
+    pub font_size: u32,
+    /// color the text is filled and stroked with
+    pub color: usvg::Color,
+    /// whether the span is raised or lowered from the baseline
+    pub baseline_shift: BaselineShift,
+    /// how the text is rendered
+    pub render_mode: PDFTextRenderMode,
+}
+
+impl<'a> TextSpan<'a> {
+    /// `new` returns a span of black, filled text on the baseline
+    #[must_use]
+    pub fn new(text: impl Into<String>, font: &'a PDFFont<'a>, font_size: u32) -> Self {
+        Self {
+            text: text.into(),
+            font,
+            font_size,
+            color: usvg::Color::black(),
+            baseline_shift: BaselineShift::None,
+            render_mode: PDFTextRenderMode::Fill,
+        }
+    }
+}
+
+/// `BaselineShift` moves a `TextSpan` up or down from the baseline of the text around it, such
+/// as for the 2 in mm². It doesn't change the size of the text, so shifted spans are usually
+/// also set smaller.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[allow(clippy::exhaustive_enums)]
+pub enum BaselineShift {
+    /// the span is on the baseline
+    #[default]
+    None,
+    /// the span is raised by half its font size
+    Superscript,
+    /// the span is lowered by a quarter of its font size
+    Subscript,
+}
+
+impl BaselineShift {
+    /// `rise` returns how far a span at `font_size` is raised, in points
+    pub(crate) fn rise(self, font_size: u32) -> f64 {
+        match self {
+            BaselineShift::None => 0.0,
+            BaselineShift::Superscript => f64::from(font_size) * 0.5,
+            BaselineShift::Subscript => f64::from(font_size) * -0.25,
+        }
+    }
+}
+
+/// `StyledRun` is a run of a paragraph of rich text that is all in one span, and set in one font
+pub(crate) struct StyledRun<'s> {
+    /// byte range of the run in the paragraph
+    pub(crate) range: Range<usize>,
+    /// span the run is part of
+    pub(crate) span: &'s TextSpan<'s>,
+    /// font the run is set in, which is the font of the span unless it is missing glyphs
+    pub(crate) font: &'s PDFFont<'s>,
+}
+
+/// `RichParagraph` is the text of a sequence of `TextSpan`s joined together, split into runs
+/// of a single font
+pub(crate) struct RichParagraph<'s> {
+    /// text of all the spans
+    pub(crate) text: String,
+    /// runs of the text, in order
+    runs: Vec<StyledRun<'s>>,
+}
+
+impl<'s> RichParagraph<'s> {
+    /// `new` joins `spans` into a paragraph. Characters the font of their span has no glyph for
+    /// are set in the first of `fallback_fonts` that has one.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub(crate) fn new(spans: &'s [TextSpan<'s>], fallback_fonts: &'s [PDFFont<'s>]) -> Self {
+        let mut text = String::new();
+        let mut runs: Vec<StyledRun> = Vec::new();
+        for span in spans {
+            for character in span.text.chars() {
+                // spaces stay in the font of the text before them, so runs aren't split by them
+                let font = match runs.last() {
+                    Some(run) if character.is_whitespace() && std::ptr::eq(run.span, span) => {
+                        run.font
+                    }
+                    _ if span.font.has_glyph(character) => span.font,
+                    _ => fallback_fonts
+                        .iter()
+                        .find(|font| font.has_glyph(character))
+                        .unwrap_or(span.font),
+                };
+                let start = text.len();
+                text.push(character);
+                match runs.last_mut() {
+                    Some(run) if std::ptr::eq(run.span, span) && std::ptr::eq(run.font, font) => {
+                        run.range.end = text.len();
+                    }
+                    _ => runs.push(StyledRun {
+                        range: start..text.len(),
+                        span,
+                        font,
+                    }),
+                }
+            }
+        }
+        Self { text, runs }
+    }
+
+    /// `run_at` returns the run containing byte `offset`, or the last run if `offset` is past
+    /// the end of the paragraph
+    pub(crate) fn run_at(&self, offset: usize) -> Option<&StyledRun<'s>> {
+        self.runs
+            .iter()
+            .find(|run| run.range.contains(&offset))
+            .or_else(|| self.runs.last())
+    }
+
+    /// `pieces` splits `range` of the paragraph at the boundaries of its runs
+    pub(crate) fn pieces(
+        &self,
+        range: Range<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, &StyledRun<'s>)> {
+        self.runs.iter().filter_map(move |run| {
+            let start = run.range.start.max(range.start);
+            let end = run.range.end.min(range.end);
+            (start < end).then_some((start..end, run))
+        })
+    }
+
+    /// `width` returns the width of `range` of the paragraph set on a single line
+    pub(crate) fn width(&self, range: Range<usize>) -> ucum::Meter<f64> {
+        self.pieces(range)
+            .map(|(piece, run)| run.font.text_width(&self.text[piece], run.span.font_size))
+            .fold(0.0_f64 * ucum::M, |total, width| total + width)
+    }
+
+    /// `character_width` returns the width of `character` set in the run at byte `offset`
+    pub(crate) fn character_width(&self, character: &str, offset: usize) -> ucum::Meter<f64> {
+        self.run_at(offset).map_or(0.0_f64 * ucum::M, |run| {
+            run.font.text_width(character, run.span.font_size)
+        })
+    }
+
+    /// `widest_word` returns the width of the widest word in the paragraph
+    pub(crate) fn widest_word(&self) -> ucum::Meter<f64> {
+        let mut widest = 0.0_f64 * ucum::M;
+        let mut start = None;
+        for (index, character) in self.text.char_indices().chain([(self.text.len(), ' ')]) {
+            match (character.is_whitespace(), start) {
+                (true, Some(first)) => {
+                    let width = self.width(first..index);
+                    if width > widest {
+                        widest = width;
+                    }
+                    start = None;
+                }
+                (false, None) => start = Some(index),
+                _ => {}
+            }
+        }
+        widest
+    }
+
+    /// `layout` breaks the paragraph into lines that fit the width of `text_box`. Each line is
+    /// as tall as the tallest run on it, with lines `line_spacing` times their height apart.
+    ///
+    /// # Errors
+    ///
+    /// Can error if the text fails to split into lines
+    #[allow(clippy::arithmetic_side_effects)]
+    pub(crate) fn layout(
+        &self,
+        line_spacing: u32,
+        text_box: &TextBox,
+        text_language: &rustybuzz::Language,
+    ) -> Result<ParagraphLayout, Error> {
+        let text = self.text.as_str();
+        let break_lines = |width| {
+            paragraph_breaker::to_line_ranges(
+                text,
+                |range| self.width(range),
+                |offset| self.character_width(" ", offset),
+                |offset| self.character_width("-", offset.saturating_sub(1)),
+                width,
+                text_language,
+                &text_box.break_options,
+            )
+        };
+        let mut overflowed = false;
+        let lines = match break_lines(text_box.width) {
+            Ok(lines) => lines,
+            Err(ParagraphError::EmptyString) => Vec::new(),
+            Err(ParagraphError::LineLengthTooLong) => {
+                // widen the lines just enough for the words that can't be broken to fit
+                overflowed = true;
+                let widest_word = self.widest_word();
+                break_lines(if widest_word > text_box.width {
+                    widest_word
+                } else {
+                    text_box.width
+                })?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let line_ranges = lines
+            .iter()
+            .map(|line| {
+                line.words.first().map_or(0, |word| word.start)
+                    ..line.words.last().map_or(0, |word| word.end)
+            })
+            .collect::<Vec<_>>();
+        // each line is as tall as the tallest run on it
+        let line_metrics = line_ranges
+            .iter()
+            .map(|range| {
+                self.pieces(range.clone()).fold(
+                    (0.0_f64 * ucum::M, 0.0_f64 * ucum::M),
+                    |(ascent, line_height), (_, run)| {
+                        let run_ascent = run.font.ascent(run.span.font_size);
+                        let run_line_height = run.font.line_height(run.span.font_size);
+                        (
+                            if run_ascent > ascent {
+                                run_ascent
+                            } else {
+                                ascent
+                            },
+                            if run_line_height > line_height {
+                                run_line_height
+                            } else {
+                                line_height
+                            },
+                        )
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        let spacing = f64::from(line_spacing.max(1));
+        let height = line_metrics.iter().enumerate().fold(
+            0.0_f64 * ucum::M,
+            |height, (line_number, (_, line_height))| {
+                if line_number + 1 == line_metrics.len() {
+                    height + *line_height
+                } else {
+                    height + *line_height * spacing
+                }
+            },
+        );
+        let text_layout = TextLayout {
+            line_count: lines.len(),
+            height,
+            overflowed: overflowed
+                || height > text_box.height
+                || line_ranges
+                    .iter()
+                    .any(|range| self.width(range.clone()) > text_box.width),
+        };
+        Ok(ParagraphLayout {
+            lines,
+            line_metrics,
+            spacing,
+            text_layout,
+        })
+    }
+}
+
+/// `ParagraphLayout` is a `RichParagraph` broken into lines
+pub(crate) struct ParagraphLayout {
+    /// the lines of the paragraph
+    pub(crate) lines: Vec<Line>,
+    /// ascent and height of each line
+    pub(crate) line_metrics: Vec<(ucum::Meter<f64>, ucum::Meter<f64>)>,
+    /// multiple of the height of each line to the next line
+    pub(crate) spacing: f64,
+    /// size of the lines, and whether they overflow the box they were laid out in
+    pub(crate) text_layout: TextLayout,
+}
+
+/// `TextStyle` is the text state a `StyledRun` is shown with
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct TextStyle<'s> {
+    /// name of the font in the resources of the page
+    font_id: &'s str,
+    /// size of the font in points
+    font_size: u32,
+    /// fill and stroke color
+    color: usvg::Color,
+    /// shift from the baseline
+    baseline_shift: BaselineShift,
+    /// how the text is rendered
+    render_mode: PDFTextRenderMode,
+}
+
+impl<'s> TextStyle<'s> {
+    /// `of` returns the style `run` is shown with
+    pub(crate) fn of(run: &StyledRun<'s>) -> Self {
+        Self {
+            font_id: &run.font.font_id_str,
+            font_size: run.span.font_size,
+            color: run.span.color,
+            baseline_shift: run.span.baseline_shift,
+            render_mode: run.span.render_mode,
+        }
+    }
+
+    /// `operations` returns the operations that change the text state from `previous` to this
+    /// style, or set all of it if there is no previous style
+    pub(crate) fn operations(&self, previous: Option<&Self>) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        let changed = |property: &dyn Fn(&Self) -> bool| previous.is_none_or(property);
+        // Tf sets the font and font size
+        if changed(&|previous| {
+            previous.font_id != self.font_id || previous.font_size != self.font_size
+        }) {
+            operations.push(Operation::new(
+                "Tf",
+                vec![self.font_id.into(), self.font_size.into()],
+            ));
+        }
+        // rg and RG set the fill and stroke colors in the DeviceRGB color space
+        if changed(&|previous| previous.color != self.color) {
+            let components = crate::color_components(self.color);
+            operations.push(Operation::new("rg", components.clone()));
+            operations.push(Operation::new("RG", components));
+        }
+        // Ts sets the text rise, which moves text up from the baseline
+        if changed(&|previous| {
+            previous.baseline_shift != self.baseline_shift || previous.font_size != self.font_size
+        }) {
+            operations.push(Operation::new(
+                "Ts",
+                vec![self.baseline_shift.rise(self.font_size).into()],
+            ));
+        }
+        // Tr sets the text rendering mode
+        if changed(&|previous| previous.render_mode != self.render_mode) {
+            operations.push(Operation::new("Tr", vec![self.render_mode.value().into()]));
+        }
+        operations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use crate::{paper::PaperSize, PDFDocument};
+
+    /// `FONT` is a font with glyphs for characters the standard fonts can't show
+    const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+    /// `run_texts` returns the text of each run of `paragraph`, and whether it is set in `font`
+    fn run_texts<'s>(paragraph: &'s RichParagraph, font: &PDFFont) -> Vec<(&'s str, bool)> {
+        paragraph
+            .runs
+            .iter()
+            .map(|run| {
+                (
+                    &paragraph.text[run.range.clone()],
+                    std::ptr::eq(run.font, font),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fallback_runs() {
+        let standard = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let helvetica = &standard.available_fonts[0];
+        let fallback = PDFDocument::new(PaperSize::A4, vec![PathBuf::from(FONT)]).unwrap();
+        assert!(!helvetica.has_glyph('Ω'));
+        assert!(fallback.available_fonts[0].has_glyph('Ω'));
+
+        // characters missing from the font of the span are set in the fallback font, along with
+        // the spaces that follow them
+        let spans = [TextSpan::new("R 10 Ω ± 5 %", helvetica, 10)];
+        let paragraph = RichParagraph::new(&spans, &fallback.available_fonts);
+        assert_eq!(paragraph.text, "R 10 Ω ± 5 %");
+        assert_eq!(
+            run_texts(&paragraph, helvetica),
+            [("R 10 ", true), ("Ω ", false), ("± 5 %", true)]
+        );
+
+        // without a fallback font that has the glyph, the font of the span is kept
+        let paragraph = RichParagraph::new(&spans, &[]);
+        assert_eq!(run_texts(&paragraph, helvetica), [("R 10 Ω ± 5 %", true)]);
+
+        // spans in the same font stay separate runs
+        let spans = [
+            TextSpan::new("4 mm", helvetica, 10),
+            TextSpan::new("²", helvetica, 6),
+        ];
+        let paragraph = RichParagraph::new(&spans, &fallback.available_fonts);
+        assert_eq!(
+            run_texts(&paragraph, helvetica),
+            [("4 mm", true), ("²", true)]
+        );
+        assert_eq!(paragraph.run_at(4).unwrap().span.font_size, 6);
+        assert_eq!(paragraph.run_at(100).unwrap().span.font_size, 6);
+    }
+
+    #[test]
+    fn mixed_font_sizes() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let font = &pdf.available_fonts[0];
+        let spans = [
+            TextSpan::new("Heading", font, 20),
+            TextSpan::new(" a b", font, 8),
+        ];
+        let paragraph = RichParagraph::new(&spans, &[]);
+        let heading_width = font.text_width("Heading", 20);
+        assert_eq!(
+            paragraph.width(0..paragraph.text.len()),
+            heading_width + font.text_width(" a b", 8)
+        );
+        assert_eq!(paragraph.widest_word(), heading_width);
+
+        // the heading fills the first line, so the small text breaks onto the second
+        let text_box = TextBox {
+            width: heading_width + font.text_width(" ", 8),
+            height: 100.0 * PDFDocument::pdf_point(),
+            ..TextBox::default()
+        };
+        let language = rustybuzz::Language::from_str("en").unwrap();
+        let layout = paragraph.layout(1, &text_box, &language).unwrap();
+        assert_eq!(layout.text_layout.line_count, 2);
+        assert_eq!(
+            layout.line_metrics,
+            [
+                (font.ascent(20), font.line_height(20)),
+                (font.ascent(8), font.line_height(8))
+            ]
+        );
+        assert_eq!(
+            layout.text_layout.height,
+            font.line_height(20) + font.line_height(8)
+        );
+        assert!(!layout.text_layout.overflowed);
+
+        // on one line, the line is as tall as its largest text
+        let text_box = TextBox {
+            width: 200.0 * PDFDocument::pdf_point(),
+            ..text_box
+        };
+        let layout = paragraph.layout(2, &text_box, &language).unwrap();
+        assert_eq!(layout.text_layout.line_count, 1);
+        assert_eq!(layout.text_layout.height, font.line_height(20));
+    }
+}
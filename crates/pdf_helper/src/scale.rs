@@ -1,9 +1,17 @@
 use std::fmt;
+use std::str::FromStr;
+
+use dimensioned::ucum;
+
+use crate::Error;
 
 /// `ScalingFactor` is used to represent scaling when laying out PDF documents.
 /// It is a ratio or quotient represented as a:b or a/b.
 ///
 /// Default is 1:1
+///
+/// It can be parsed from a ratio such as `"1:20"`, or from a length on the page equal to a full
+/// size length such as `"1/8in=1ft"` or `"1/4\"=1'-0\""`.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct ScalingFactor {
     pub a: u16,
@@ -16,8 +24,360 @@ impl fmt::Display for ScalingFactor {
     }
 }
 
+impl ScalingFactor {
+    /// `factor` returns the size of an object on the page relative to its full size, `a / b`.
+    /// Returns `None` if either side of the ratio is 0.
+    #[must_use]
+    pub fn factor(&self) -> Option<f64> {
+        if self.a == 0 || self.b == 0 {
+            None
+        } else {
+            Some(f64::from(self.a) / f64::from(self.b))
+        }
+    }
+
+    /// `fits` returns true if an object of `size` fits within `available` at this scale
+    #[allow(clippy::arithmetic_side_effects)]
+    #[must_use]
+    pub fn fits(
+        &self,
+        size: (ucum::Meter<f64>, ucum::Meter<f64>),
+        available: (ucum::Meter<f64>, ucum::Meter<f64>),
+    ) -> bool {
+        self.factor()
+            .is_some_and(|factor| size.0 * factor < available.0 && size.1 * factor < available.1)
+    }
+}
+
 impl Default for ScalingFactor {
     fn default() -> Self {
         Self { a: 1, b: 1 }
     }
 }
+
+impl FromStr for ScalingFactor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        if let Some((page, full_size)) = text.split_once('=') {
+            return from_lengths(parse_length(page)?, parse_length(full_size)?)
+                .ok_or_else(|| Error::ScaleParsing(format!("{text} is not a usable scale")));
+        }
+        let (a, b) = text
+            .split_once(':')
+            .or_else(|| text.split_once('/'))
+            .ok_or_else(|| {
+                Error::ScaleParsing(format!(
+                    "{text} is not a ratio such as 1:20 or a length such as 1/8in=1ft"
+                ))
+            })?;
+        let parse_side = |side: &str| {
+            side.trim()
+                .parse::<u16>()
+                .map_err(|e| Error::ScaleParsing(format!("{side} in scale {text}: {e}")))
+        };
+        let scale = Self {
+            a: parse_side(a)?,
+            b: parse_side(b)?,
+        };
+        if scale.factor().is_none() {
+            return Err(Error::ScaleParsing(format!(
+                "scale {text} can not have a side of 0"
+            )));
+        }
+        Ok(scale)
+    }
+}
+
+/// `from_lengths` returns the scale at which `page` meters on the page represent `full_size`
+/// meters, with each side of the ratio as small as possible.
+/// Returns `None` if there is no such ratio with a page side up to 1000.
+#[allow(clippy::arithmetic_side_effects)]
+fn from_lengths(page: f64, full_size: f64) -> Option<ScalingFactor> {
+    let ratio = full_size / page;
+    (1..=1000_u16).find_map(|a| {
+        let b = ratio * f64::from(a);
+        let rounded = b.round();
+        let exact = (b - rounded).abs() <= 1e-6_f64 * rounded.max(1.0_f64);
+        if exact && rounded >= 1.0_f64 && rounded <= f64::from(u16::MAX) {
+            // checked to be a whole number within range above
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Some(ScalingFactor {
+                a,
+                b: rounded as u16,
+            })
+        } else {
+            None
+        }
+    })
+}
+
+/// `parse_length` parses a length with units, such as `1/8in`, `25mm`, `1ft` or `1'-6"`, into
+/// meters
+#[allow(clippy::arithmetic_side_effects)]
+fn parse_length(text: &str) -> Result<f64, Error> {
+    let text = text.trim();
+    let inch = 0.0254_f64;
+    let length = if let Some((feet, inches)) = text.split_once('\'') {
+        // architectural feet and inches, where the inches are optional
+        let inches = inches.trim().trim_start_matches('-').trim_end_matches('"');
+        let inches = if inches.trim().is_empty() {
+            0.0_f64
+        } else {
+            parse_number(inches)?
+        };
+        (parse_number(feet)? * 12.0_f64 + inches) * inch
+    } else {
+        let unit_start = text
+            .find(|c: char| c.is_alphabetic() || c == '"')
+            .ok_or_else(|| Error::ScaleParsing(format!("{text} has no unit")))?;
+        let (number, unit) = text.split_at(unit_start);
+        let unit_length = match unit.trim().to_lowercase().as_str() {
+            "\"" | "in" | "inch" | "inches" => inch,
+            "ft" | "foot" | "feet" => inch * 12.0_f64,
+            "mm" => 0.001_f64,
+            "cm" => 0.01_f64,
+            "m" => 1.0_f64,
+            _ => {
+                return Err(Error::ScaleParsing(format!(
+                    "unknown unit {unit} in length {text}"
+                )))
+            }
+        };
+        parse_number(number)? * unit_length
+    };
+    if length > 0.0_f64 && length.is_finite() {
+        Ok(length)
+    } else {
+        Err(Error::ScaleParsing(format!(
+            "length {text} must be greater than 0"
+        )))
+    }
+}
+
+/// `parse_number` parses a decimal number, a fraction such as `3/8`, or a whole number and a
+/// fraction such as `1 1/2` or `1-1/2`
+#[allow(clippy::arithmetic_side_effects)]
+fn parse_number(text: &str) -> Result<f64, Error> {
+    let text = text.trim();
+    let error = || Error::ScaleParsing(format!("{text} is not a number"));
+    let parse_part = |part: &str| part.trim().parse::<f64>().map_err(|_| error());
+    match text.split_once('/') {
+        Some((whole_and_numerator, denominator)) => {
+            let (whole, numerator) = match whole_and_numerator.trim().rsplit_once([' ', '-']) {
+                Some((whole, numerator)) => (parse_part(whole)?, numerator),
+                None => (0.0_f64, whole_and_numerator),
+            };
+            Ok(whole + parse_part(numerator)? / parse_part(denominator)?)
+        }
+        None => parse_part(text),
+    }
+}
+
+/// `ScaleSeries` is a set of standard drawing scales
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ScaleSeries {
+    /// metric scales such as 1:2, 1:5 and 1:10
+    #[default]
+    Metric,
+    /// imperial architectural scales such as 1/4"=1'-0" (1:48)
+    Architectural,
+}
+
+/// `METRIC_SCALES` are the standard metric drawing scales, largest first
+const METRIC_SCALES: [ScalingFactor; 10] = [
+    ScalingFactor { a: 1, b: 1 },
+    ScalingFactor { a: 1, b: 2 },
+    ScalingFactor { a: 1, b: 5 },
+    ScalingFactor { a: 1, b: 10 },
+    ScalingFactor { a: 1, b: 20 },
+    ScalingFactor { a: 1, b: 50 },
+    ScalingFactor { a: 1, b: 100 },
+    ScalingFactor { a: 1, b: 200 },
+    ScalingFactor { a: 1, b: 500 },
+    ScalingFactor { a: 1, b: 1000 },
+];
+
+/// `ARCHITECTURAL_SCALES` are the standard architectural drawing scales, largest first. These
+/// range from 12"=1'-0" (1:1) down to 1/16"=1'-0" (1:192).
+const ARCHITECTURAL_SCALES: [ScalingFactor; 12] = [
+    ScalingFactor { a: 1, b: 1 },
+    ScalingFactor { a: 1, b: 2 },
+    ScalingFactor { a: 1, b: 4 },
+    ScalingFactor { a: 1, b: 8 },
+    ScalingFactor { a: 1, b: 12 },
+    ScalingFactor { a: 1, b: 16 },
+    ScalingFactor { a: 1, b: 24 },
+    ScalingFactor { a: 1, b: 32 },
+    ScalingFactor { a: 1, b: 48 },
+    ScalingFactor { a: 1, b: 64 },
+    ScalingFactor { a: 1, b: 96 },
+    ScalingFactor { a: 1, b: 192 },
+];
+
+impl ScaleSeries {
+    /// `scales` returns the scales in the series, largest first
+    #[must_use]
+    pub fn scales(self) -> &'static [ScalingFactor] {
+        match self {
+            ScaleSeries::Metric => &METRIC_SCALES,
+            ScaleSeries::Architectural => &ARCHITECTURAL_SCALES,
+        }
+    }
+
+    /// `best_fit` returns the largest scale in the series at which an object of `size` fits
+    /// within `available`, or `None` if it doesn't fit at any of them
+    #[must_use]
+    pub fn best_fit(
+        self,
+        size: (ucum::Meter<f64>, ucum::Meter<f64>),
+        available: (ucum::Meter<f64>, ucum::Meter<f64>),
+    ) -> Option<ScalingFactor> {
+        self.scales()
+            .iter()
+            .find(|scale| scale.fits(size, available))
+            .copied()
+    }
+}
+
+impl fmt::Display for ScaleSeries {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScaleSeries::Metric => write!(f, "metric"),
+            ScaleSeries::Architectural => write!(f, "architectural"),
+        }
+    }
+}
+
+/// `DrawingScale` is how the scale of a drawing is chosen
+///
+/// It can be parsed from `"auto"`, `"auto metric"` or `"auto architectural"`, or from anything
+/// that parses as a `ScalingFactor`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DrawingScale {
+    /// the drawing is always at this scale
+    Fixed(ScalingFactor),
+    /// the drawing is at the largest scale in the series that fits
+    Auto(ScaleSeries),
+}
+
+impl Default for DrawingScale {
+    fn default() -> Self {
+        DrawingScale::Fixed(ScalingFactor::default())
+    }
+}
+
+impl fmt::Display for DrawingScale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawingScale::Fixed(scale) => write!(f, "{scale}"),
+            DrawingScale::Auto(series) => write!(f, "auto {series}"),
+        }
+    }
+}
+
+impl FromStr for DrawingScale {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().to_lowercase();
+        let mut words = text.split_whitespace();
+        if words.next() != Some("auto") {
+            return Ok(DrawingScale::Fixed(s.parse()?));
+        }
+        match (words.next(), words.next()) {
+            (None | Some("metric"), None) => Ok(DrawingScale::Auto(ScaleSeries::Metric)),
+            (Some("architectural"), None) => Ok(DrawingScale::Auto(ScaleSeries::Architectural)),
+            _ => Err(Error::ScaleParsing(format!(
+                "{} is not auto, auto metric or auto architectural",
+                s.trim()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratio() {
+        assert_eq!(
+            "1:20".parse::<ScalingFactor>().unwrap(),
+            ScalingFactor { a: 1, b: 20 }
+        );
+        assert_eq!(
+            " 1/50 ".parse::<ScalingFactor>().unwrap(),
+            ScalingFactor { a: 1, b: 50 }
+        );
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(
+            "1/8in=1ft".parse::<ScalingFactor>().unwrap(),
+            ScalingFactor { a: 1, b: 96 }
+        );
+        assert_eq!(
+            "1/4\"=1'-0\"".parse::<ScalingFactor>().unwrap(),
+            ScalingFactor { a: 1, b: 48 }
+        );
+        assert_eq!(
+            "1-1/2\"=1'".parse::<ScalingFactor>().unwrap(),
+            ScalingFactor { a: 1, b: 8 }
+        );
+        assert_eq!(
+            "10mm=1m".parse::<ScalingFactor>().unwrap(),
+            ScalingFactor { a: 1, b: 100 }
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        for text in [
+            "", "20", "a:b", "1:-2", "1:70000", "1in=1", "1in=1yd", "1in=x ft",
+        ] {
+            assert!(
+                matches!(text.parse::<ScalingFactor>(), Err(Error::ScaleParsing(_))),
+                "{text} parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn zero_sided() {
+        for text in ["0:20", "1:0", "0/0", "0in=1ft", "1in=0ft"] {
+            assert!(
+                matches!(text.parse::<ScalingFactor>(), Err(Error::ScaleParsing(_))),
+                "{text} parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn drawing_scale() {
+        assert_eq!(
+            "1:20".parse::<DrawingScale>().unwrap(),
+            DrawingScale::Fixed(ScalingFactor { a: 1, b: 20 })
+        );
+        assert_eq!(
+            "1/8in=1ft".parse::<DrawingScale>().unwrap(),
+            DrawingScale::Fixed(ScalingFactor { a: 1, b: 96 })
+        );
+        assert_eq!(
+            "auto".parse::<DrawingScale>().unwrap(),
+            DrawingScale::Auto(ScaleSeries::Metric)
+        );
+        assert_eq!(
+            "Auto Architectural".parse::<DrawingScale>().unwrap(),
+            DrawingScale::Auto(ScaleSeries::Architectural)
+        );
+        for text in ["auto imperial", "auto metric please", "1:0", "0in=1ft"] {
+            assert!(
+                matches!(text.parse::<DrawingScale>(), Err(Error::ScaleParsing(_))),
+                "{text} parsed"
+            );
+        }
+    }
+}
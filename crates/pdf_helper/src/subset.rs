@@ -0,0 +1,390 @@
+use std::collections::BTreeSet;
+
+use crate::Error;
+
+/// tables kept in a subset font. These are the tables a PDF reader needs to draw glyphs of an
+/// embedded TrueType font, along with `OS/2` for its metrics. Glyphs are chosen by glyph ID
+/// rather than character code, so `cmap` isn't needed.
+const KEPT_TABLES: [&[u8; 4]; 10] = [
+    b"OS/2", b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+
+/// `is_truetype` returns true if the font at `index` in `data` has TrueType outlines, which
+/// `subset` can subset, rather than CFF outlines
+pub(crate) fn is_truetype(data: &[u8], index: u32) -> bool {
+    font_tables(data, index).is_ok_and(|tables| find_table(data, &tables, b"glyf").is_some())
+}
+
+/// `subset` returns a TrueType font containing only `glyphs` of the font at `index` in `data`,
+/// along with the glyphs they are built from.
+///
+/// Glyph IDs are unchanged, so the subset can be drawn with the same glyph IDs as the full
+/// font. Unused glyphs are left empty, and glyphs after the last one used are removed.
+///
+/// # Errors
+///
+/// Will error if the font is malformed, or doesn't have TrueType outlines
+#[allow(clippy::arithmetic_side_effects)]
+pub(crate) fn subset(data: &[u8], index: u32, glyphs: &BTreeSet<u16>) -> Result<Vec<u8>, Error> {
+    let tables = font_tables(data, index)?;
+    let table = |tag: &[u8; 4]| {
+        find_table(data, &tables, tag).ok_or_else(|| {
+            Error::FontLoading(format!(
+                "font has no {} table",
+                String::from_utf8_lossy(tag)
+            ))
+        })
+    };
+    let malformed = |tag: &str| Error::FontLoading(format!("font has a malformed {tag} table"));
+
+    let head = table(b"head")?;
+    let maxp = table(b"maxp")?;
+    let hhea = table(b"hhea")?;
+    let hmtx = table(b"hmtx")?;
+    let loca = table(b"loca")?;
+    let glyf = table(b"glyf")?;
+    let long_offsets = read_u16(head, 50).ok_or_else(|| malformed("head"))? != 0;
+    let glyph_count = read_u16(maxp, 4).ok_or_else(|| malformed("maxp"))?;
+    let metric_count = read_u16(hhea, 34).ok_or_else(|| malformed("hhea"))?;
+
+    // the data of a glyph in the glyf table, which is empty for glyphs without an outline
+    let glyph_data = |glyph: u16| -> Option<&[u8]> {
+        let glyph = usize::from(glyph);
+        let (start, end) = if long_offsets {
+            (
+                usize::try_from(read_u32(loca, glyph * 4)?).ok()?,
+                usize::try_from(read_u32(loca, glyph * 4 + 4)?).ok()?,
+            )
+        } else {
+            (
+                usize::from(read_u16(loca, glyph * 2)?) * 2,
+                usize::from(read_u16(loca, glyph * 2 + 2)?) * 2,
+            )
+        };
+        glyf.get(start..end)
+    };
+
+    // glyph 0 is the .notdef glyph drawn for missing characters, which every font needs.
+    // Composite glyphs are built from other glyphs, which are needed too.
+    let mut kept = BTreeSet::new();
+    let mut pending = glyphs
+        .iter()
+        .copied()
+        .filter(|&glyph| glyph < glyph_count)
+        .chain([0])
+        .collect::<Vec<_>>();
+    while let Some(glyph) = pending.pop() {
+        if kept.insert(glyph) {
+            let data = glyph_data(glyph).ok_or_else(|| malformed("glyf"))?;
+            pending.extend(
+                components(data)
+                    .into_iter()
+                    .filter(|&component| component < glyph_count && !kept.contains(&component)),
+            );
+        }
+    }
+    let new_glyph_count = kept.last().map_or(1, |&last| last + 1);
+
+    // each kept glyph is copied, and every other glyph has no data. The new loca table always
+    // uses long offsets.
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((usize::from(new_glyph_count) + 1) * 4);
+    for glyph in 0..new_glyph_count {
+        new_loca.extend_from_slice(&offset_u32(new_glyf.len())?.to_be_bytes());
+        if kept.contains(&glyph) {
+            new_glyf.extend_from_slice(glyph_data(glyph).ok_or_else(|| malformed("glyf"))?);
+            // glyphs are aligned to 4 bytes
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+    }
+    new_loca.extend_from_slice(&offset_u32(new_glyf.len())?.to_be_bytes());
+
+    // the horizontal metrics of the first glyphs are pairs of advance width and left side
+    // bearing, and the remaining glyphs share the last advance width, so only have a left side
+    // bearing
+    let new_metric_count = metric_count.min(new_glyph_count);
+    let metrics_length =
+        usize::from(new_metric_count) * 4 + usize::from(new_glyph_count - new_metric_count) * 2;
+    let bearings_start = usize::from(metric_count) * 4;
+    let mut new_hmtx = hmtx
+        .get(..usize::from(new_metric_count) * 4)
+        .ok_or_else(|| malformed("hmtx"))?
+        .to_vec();
+    new_hmtx.extend_from_slice(
+        hmtx.get(bearings_start..bearings_start + metrics_length - new_hmtx.len())
+            .ok_or_else(|| malformed("hmtx"))?,
+    );
+
+    let mut new_head = head.to_vec();
+    // the checksum adjustment is recalculated for the subset font
+    new_head.splice(8..12, [0; 4]);
+    new_head.splice(50..52, 1_u16.to_be_bytes());
+    let mut new_maxp = maxp.to_vec();
+    new_maxp.splice(4..6, new_glyph_count.to_be_bytes());
+    let mut new_hhea = hhea.to_vec();
+    new_hhea.splice(34..36, new_metric_count.to_be_bytes());
+
+    let mut new_tables = Vec::new();
+    for tag in KEPT_TABLES {
+        let data = match tag {
+            b"glyf" => new_glyf.clone(),
+            b"head" => new_head.clone(),
+            b"hhea" => new_hhea.clone(),
+            b"hmtx" => new_hmtx.clone(),
+            b"loca" => new_loca.clone(),
+            b"maxp" => new_maxp.clone(),
+            _ => match find_table(data, &tables, tag) {
+                Some(data) => data.to_vec(),
+                None => continue,
+            },
+        };
+        new_tables.push((*tag, data));
+    }
+    write_font(new_tables)
+}
+
+/// `TableRecord` is the location of a table in a font file
+struct TableRecord {
+    /// four character table tag
+    tag: [u8; 4],
+    /// offset of the table from the start of the file
+    offset: usize,
+    /// length of the table in bytes
+    length: usize,
+}
+
+/// `font_tables` reads the table directory of the font at `index` in `data`, which is either a
+/// single font or a font collection
+#[allow(clippy::arithmetic_side_effects)]
+fn font_tables(data: &[u8], index: u32) -> Result<Vec<TableRecord>, Error> {
+    let malformed = || Error::FontLoading("font has a malformed table directory".to_string());
+    let start = if data.starts_with(b"ttcf") {
+        let index = usize::try_from(index).map_err(|_| malformed())?;
+        usize::try_from(read_u32(data, 12 + index * 4).ok_or_else(malformed)?)
+            .map_err(|_| malformed())?
+    } else {
+        0
+    };
+    let table_count = read_u16(data, start + 4).ok_or_else(malformed)?;
+    (0..usize::from(table_count))
+        .map(|table| {
+            let record = start + 12 + table * 16;
+            let tag = data.get(record..record + 4).ok_or_else(malformed)?;
+            Ok(TableRecord {
+                tag: tag.try_into().map_err(|_| malformed())?,
+                offset: usize::try_from(read_u32(data, record + 8).ok_or_else(malformed)?)
+                    .map_err(|_| malformed())?,
+                length: usize::try_from(read_u32(data, record + 12).ok_or_else(malformed)?)
+                    .map_err(|_| malformed())?,
+            })
+        })
+        .collect()
+}
+
+/// `find_table` returns the data of the table with `tag`, if the font has one
+#[allow(clippy::arithmetic_side_effects)]
+fn find_table<'a>(data: &'a [u8], tables: &[TableRecord], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let table = tables.iter().find(|table| &table.tag == tag)?;
+    data.get(table.offset..table.offset + table.length)
+}
+
+/// `components` returns the glyphs a composite glyph is built from, or nothing for a simple
+/// glyph
+#[allow(clippy::arithmetic_side_effects)]
+fn components(glyph: &[u8]) -> Vec<u16> {
+    /// the arguments of the component are 16 bit, rather than 8 bit
+    const ARGS_ARE_WORDS: u16 = 0x0001;
+    /// the component is scaled by a single value
+    const HAVE_SCALE: u16 = 0x0008;
+    /// another component follows this one
+    const MORE_COMPONENTS: u16 = 0x0020;
+    /// the component is scaled separately in x and y
+    const HAVE_XY_SCALE: u16 = 0x0040;
+    /// the component is transformed by a 2 by 2 matrix
+    const HAVE_MATRIX: u16 = 0x0080;
+
+    let mut components = Vec::new();
+    // composite glyphs have a negative number of contours
+    if read_u16(glyph, 0).is_none_or(|contours| contours & 0x8000 == 0) {
+        return components;
+    }
+    let mut position = 10;
+    while let (Some(flags), Some(component)) =
+        (read_u16(glyph, position), read_u16(glyph, position + 2))
+    {
+        components.push(component);
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+        position += 4;
+        position += if flags & ARGS_ARE_WORDS == 0 { 2 } else { 4 };
+        if flags & HAVE_SCALE != 0 {
+            position += 2;
+        } else if flags & HAVE_XY_SCALE != 0 {
+            position += 4;
+        } else if flags & HAVE_MATRIX != 0 {
+            position += 8;
+        }
+    }
+    components
+}
+
+/// `write_font` writes a TrueType font file containing `tables`, which must be in order of
+/// their tags and include a `head` table
+#[allow(clippy::arithmetic_side_effects)]
+fn write_font(tables: Vec<([u8; 4], Vec<u8>)>) -> Result<Vec<u8>, Error> {
+    let table_count = u16::try_from(tables.len())
+        .map_err(|_| Error::FontLoading("subset font has too many tables".to_string()))?;
+    // the largest power of 2 tables that fit, used to binary search the table records
+    let search_power = u16::try_from(table_count.ilog2()).unwrap_or_default();
+    let search_range = (1_u16 << search_power) * 16;
+
+    let mut font = Vec::new();
+    font.extend_from_slice(&0x0001_0000_u32.to_be_bytes());
+    font.extend_from_slice(&table_count.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&search_power.to_be_bytes());
+    font.extend_from_slice(&(table_count * 16 - search_range).to_be_bytes());
+
+    let mut offset = font.len() + tables.len() * 16;
+    let mut head_offset = None;
+    for (tag, data) in &tables {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&offset_u32(offset)?.to_be_bytes());
+        font.extend_from_slice(&offset_u32(data.len())?.to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in &tables {
+        font.extend_from_slice(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    // the checksum adjustment in the head table makes the checksum of the whole font a fixed
+    // value. The head table checksum is calculated as if the adjustment was 0.
+    let head_offset =
+        head_offset.ok_or_else(|| Error::FontLoading("font has no head table".to_string()))?;
+    let adjustment = 0xB1B0_AFBA_u32.wrapping_sub(checksum(&font));
+    font.splice(head_offset + 8..head_offset + 12, adjustment.to_be_bytes());
+    Ok(font)
+}
+
+/// `checksum` returns the sum of `data` as big endian 32 bit integers, padded with zeros
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0, |sum, chunk| {
+        let mut word = [0; 4];
+        word.iter_mut()
+            .zip(chunk)
+            .for_each(|(byte, value)| *byte = *value);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// `offset_u32` converts an offset or length in a font file to the 32 bits it is stored in
+fn offset_u32(offset: usize) -> Result<u32, Error> {
+    u32::try_from(offset).map_err(|_| Error::FontLoading("subset font is too large".to_string()))
+}
+
+/// `read_u16` reads a big endian 16 bit integer at `position` of `data`
+#[allow(clippy::arithmetic_side_effects)]
+fn read_u16(data: &[u8], position: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(position..position + 2)?.try_into().ok()?,
+    ))
+}
+
+/// `read_u32` reads a big endian 32 bit integer at `position` of `data`
+#[allow(clippy::arithmetic_side_effects)]
+fn read_u32(data: &[u8], position: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(position..position + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rustybuzz::ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+    /// `FONT` is a TrueType font with composite glyphs, installed with most Linux distributions
+    const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+    /// `Outline` records the segments of a glyph outline
+    #[derive(Debug, Default, PartialEq)]
+    struct Outline(Vec<(char, Vec<f32>)>);
+
+    impl OutlineBuilder for Outline {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push(('M', vec![x, y]));
+        }
+
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.push(('L', vec![x, y]));
+        }
+
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            self.0.push(('Q', vec![x1, y1, x, y]));
+        }
+
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            self.0.push(('C', vec![x1, y1, x2, y2, x, y]));
+        }
+
+        fn close(&mut self) {
+            self.0.push(('Z', Vec::new()));
+        }
+    }
+
+    /// `outline` returns the outline of `glyph` in `face`, if it has one
+    fn outline(face: &Face, glyph: GlyphId) -> Option<Outline> {
+        let mut outline = Outline::default();
+        face.outline_glyph(glyph, &mut outline)?;
+        Some(outline)
+    }
+
+    #[test]
+    fn subset_reparses() {
+        let data = std::fs::read(FONT).unwrap();
+        let font = Face::parse(&data, 0).unwrap();
+        // é is a composite of e and an accent, which aren't requested but have to be kept
+        let glyph_ids = ['H', 'i', 'é']
+            .map(|character| font.glyph_index(character).unwrap())
+            .to_vec();
+        assert!(is_truetype(&data, 0));
+
+        let subset_data =
+            subset(&data, 0, &glyph_ids.iter().map(|glyph| glyph.0).collect()).unwrap();
+        assert!(subset_data.len() < data.len());
+        let subset_face = Face::parse(&subset_data, 0).unwrap();
+
+        let last_glyph = glyph_ids.iter().map(|glyph| glyph.0).max().unwrap();
+        assert_eq!(subset_face.number_of_glyphs(), last_glyph + 1);
+        assert_eq!(subset_face.units_per_em(), font.units_per_em());
+        for glyph in glyph_ids {
+            assert_eq!(
+                subset_face.glyph_hor_advance(glyph),
+                font.glyph_hor_advance(glyph)
+            );
+            assert_eq!(outline(&subset_face, glyph), outline(&font, glyph));
+            assert!(outline(&subset_face, glyph).is_some());
+        }
+        // glyphs that weren't requested are left empty
+        let unused = font.glyph_index('A').unwrap();
+        assert!(unused.0 < last_glyph);
+        assert!(outline(&font, unused).is_some());
+        assert_eq!(outline(&subset_face, unused), None);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(!is_truetype(b"not a font", 0));
+        assert!(matches!(
+            subset(b"not a font", 0, &BTreeSet::from([1])),
+            Err(Error::FontLoading(_))
+        ));
+    }
+}
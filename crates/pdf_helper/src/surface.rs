@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use dimensioned::ucum;
+
+use crate::{paper, scale, Error, Margins, PDFFont, PDFPage, PDFTextRenderMode};
+
+/// `DrawingSurface` is a page that drawings are drawn on, such as a `PDFPage` written into a
+/// PDF document, or an `SVGPage` written as a standalone SVG image. Drawing code that is generic
+/// over it can write either.
+///
+/// Positions are measured from the left and bottom side of the page inside its margins.
+pub trait DrawingSurface {
+    /// `page_size` returns the paper size of the page
+    fn page_size(&self) -> paper::PaperSize;
+
+    /// `size` returns the width and height of the page in its orientation, including margins
+    fn size(&self) -> (ucum::Meter<f64>, ucum::Meter<f64>);
+
+    /// `margins` returns the margins of the page
+    fn margins(&self) -> Margins;
+
+    /// `add_line` strokes a straight line through each of `points` in turn, dashed if `dashed`
+    /// is set
+    fn add_line(
+        &mut self,
+        points: &[(ucum::Meter<f64>, ucum::Meter<f64>)],
+        line_width: ucum::Meter<f64>,
+        dashed: bool,
+    );
+
+    /// `add_rectangle` strokes the outline of a rectangle with its bottom left corner at
+    /// (`x_pos`, `y_pos`)
+    fn add_rectangle(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        line_width: ucum::Meter<f64>,
+    );
+
+    /// `add_text` writes `text` broken into lines `text_width` wide, with the baseline of the
+    /// first line starting at (`x_pos`, `y_pos`). See `PDFPage::add_text`.
+    ///
+    /// # Errors
+    ///
+    /// Can error if the text is placed off the page, or fails to shape or split into lines
+    #[allow(clippy::too_many_arguments)]
+    fn add_text(
+        &mut self,
+        text: String,
+        font_size: u32,
+        font: &PDFFont,
+        line_spacing: u32,
+        text_width: ucum::Meter<f64>,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        text_direction: rustybuzz::Direction,
+        text_language: rustybuzz::Language,
+        text_render_mode: &PDFTextRenderMode,
+    ) -> Result<(), Error>;
+
+    /// `add_svg` places an SVG image with its bottom left corner at (`x_pos`, `y_pos`), and
+    /// returns its width and height on the page. See `PDFPage::add_svg`.
+    ///
+    /// # Errors
+    ///
+    /// May error due to malformed SVGs or an invalid scale
+    fn add_svg(
+        &mut self,
+        svg_string: &str,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        scale: Option<scale::ScalingFactor>,
+        fonts: &[PDFFont],
+        resources_dir: Option<&Path>,
+    ) -> Result<(ucum::Meter<f64>, ucum::Meter<f64>), Error>;
+
+    /// `add_link` makes a rectangular area of the page a link to zero indexed
+    /// `destination_page`, without drawing anything
+    fn add_link(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        destination_page: usize,
+    );
+
+    /// `in_layer` marks everything `draw` draws on the page as part of the layer named `layer`,
+    /// which can be hidden and shown, and returns what `draw` returns
+    fn in_layer<T>(&mut self, layer: &str, draw: impl FnOnce(&mut Self) -> T) -> T
+    where
+        Self: Sized;
+}
+
+impl DrawingSurface for PDFPage {
+    fn page_size(&self) -> paper::PaperSize {
+        self.page_size
+    }
+
+    fn size(&self) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+        PDFPage::size(self)
+    }
+
+    fn margins(&self) -> Margins {
+        self.margins
+    }
+
+    fn add_line(
+        &mut self,
+        points: &[(ucum::Meter<f64>, ucum::Meter<f64>)],
+        line_width: ucum::Meter<f64>,
+        dashed: bool,
+    ) {
+        PDFPage::add_line(self, points, line_width, dashed);
+    }
+
+    fn add_rectangle(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        line_width: ucum::Meter<f64>,
+    ) {
+        PDFPage::add_rectangle(self, x_pos, y_pos, width, height, line_width);
+    }
+
+    fn add_text(
+        &mut self,
+        text: String,
+        font_size: u32,
+        font: &PDFFont,
+        line_spacing: u32,
+        text_width: ucum::Meter<f64>,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        text_direction: rustybuzz::Direction,
+        text_language: rustybuzz::Language,
+        text_render_mode: &PDFTextRenderMode,
+    ) -> Result<(), Error> {
+        PDFPage::add_text(
+            self,
+            text,
+            font_size,
+            font,
+            line_spacing,
+            text_width,
+            x_pos,
+            y_pos,
+            text_direction,
+            text_language,
+            text_render_mode,
+        )
+    }
+
+    fn add_svg(
+        &mut self,
+        svg_string: &str,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        scale: Option<scale::ScalingFactor>,
+        fonts: &[PDFFont],
+        resources_dir: Option<&Path>,
+    ) -> Result<(ucum::Meter<f64>, ucum::Meter<f64>), Error> {
+        PDFPage::add_svg(self, svg_string, x_pos, y_pos, scale, fonts, resources_dir)
+    }
+
+    fn add_link(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        destination_page: usize,
+    ) {
+        PDFPage::add_link(self, x_pos, y_pos, width, height, destination_page);
+    }
+
+    fn in_layer<T>(&mut self, layer: &str, draw: impl FnOnce(&mut Self) -> T) -> T {
+        PDFPage::in_layer(self, layer, draw)
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use dimensioned::ucum;
+
+use crate::surface::DrawingSurface;
+use crate::{
+    paper, scale, svg_options, to_points, BreakOptions, Error, FontSource, Margins, PDFFont,
+    PDFTextRenderMode,
+};
+
+/// `SVGPage` is a page drawn as a standalone SVG image rather than into a PDF document, such as
+/// for showing a drawing on a web page. It is drawn on the same way as a `PDFPage`, through
+/// `DrawingSurface`.
+///
+/// One unit of the image is one point, and its width and height are set in millimeters, so it
+/// prints at the same size as the PDF page would. Layers are written as Inkscape layers.
+pub struct SVGPage {
+    /// elements drawn on the page, in order
+    elements: Vec<String>,
+    /// links to other pages placed on the page
+    links: Vec<SVGLink>,
+    /// number of SVG images placed on the page, used to keep their IDs apart
+    embedded_count: usize,
+    /// The paper size of the page
+    pub page_size: paper::PaperSize,
+    /// The orientation the page is laid out in
+    pub orientation: paper::Orientation,
+    /// The non-printing border around the page, which positions on the page are measured from
+    pub margins: Margins,
+    /// URL each zero indexed page number that is linked to is found at, such as the file name of
+    /// the SVG image of that page. Links to pages without a URL are left out.
+    pub link_targets: BTreeMap<usize, String>,
+}
+
+/// `SVGLink` is a clickable area of an `SVGPage` that links to another page
+struct SVGLink {
+    /// clickable area in SVG user units, as left, top, width and height
+    rect: [f64; 4],
+    /// zero indexed page number of the destination page
+    destination_page: usize,
+}
+
+impl SVGPage {
+    /// `new` returns an empty `SVGPage` of the specified size
+    #[must_use]
+    pub fn new(page_size: paper::PaperSize, margins: Margins) -> Self {
+        Self {
+            elements: Vec::new(),
+            links: Vec::new(),
+            embedded_count: 0,
+            page_size,
+            orientation: paper::Orientation::Portrait,
+            margins,
+            link_targets: BTreeMap::new(),
+        }
+    }
+
+    /// `size` returns the width and height of the page in its orientation, including margins
+    #[must_use]
+    pub fn size(&self) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+        self.page_size.oriented_size(self.orientation)
+    }
+
+    /// `to_svg` returns the page as an SVG document
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn to_svg(&self) -> String {
+        let (width, height) = self.size();
+        let (width, height) = (to_points(width), to_points(height));
+        let mut svg = format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" ",
+                "xmlns:xlink=\"http://www.w3.org/1999/xlink\" ",
+                "xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" ",
+                "width=\"{}mm\" height=\"{}mm\" viewBox=\"0 0 {} {}\">\n",
+            ),
+            number(width * 25.4 / 72.0),
+            number(height * 25.4 / 72.0),
+            number(width),
+            number(height),
+        );
+        for element in &self.elements {
+            svg.push_str(element);
+            svg.push('\n');
+        }
+        // links are placed over everything else, like link annotations in a PDF
+        for link in &self.links {
+            let Some(target) = self.link_targets.get(&link.destination_page) else {
+                continue;
+            };
+            let [x, y, width, height] = link.rect;
+            let _ = writeln!(
+                svg,
+                concat!(
+                    "<a xlink:href=\"{}\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" ",
+                    "fill=\"transparent\"/></a>",
+                ),
+                escape(target),
+                number(x),
+                number(y),
+                number(width),
+                number(height),
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// `write` saves the page as an SVG image in `out_path`, named `file_name`
+    ///
+    /// # Errors
+    ///
+    /// Will error if the file can't be written
+    pub fn write(&self, out_path: &Path, file_name: &Path) -> Result<(), Error> {
+        std::fs::write(out_path.join(file_name), self.to_svg())?;
+        Ok(())
+    }
+
+    /// `page_position` converts a position inside the margins into SVG user units, which are
+    /// measured from the top left corner of the page
+    #[allow(clippy::arithmetic_side_effects)]
+    fn page_position(&self, x_pos: ucum::Meter<f64>, y_pos: ucum::Meter<f64>) -> (f64, f64) {
+        (
+            to_points(x_pos + self.margins.left),
+            to_points(self.size().1 - y_pos - self.margins.bottom),
+        )
+    }
+}
+
+impl DrawingSurface for SVGPage {
+    fn page_size(&self) -> paper::PaperSize {
+        self.page_size
+    }
+
+    fn size(&self) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+        SVGPage::size(self)
+    }
+
+    fn margins(&self) -> Margins {
+        self.margins
+    }
+
+    fn add_line(
+        &mut self,
+        points: &[(ucum::Meter<f64>, ucum::Meter<f64>)],
+        line_width: ucum::Meter<f64>,
+        dashed: bool,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        let points = points
+            .iter()
+            .map(|(x_pos, y_pos)| {
+                let (x, y) = self.page_position(*x_pos, *y_pos);
+                format!("{},{}", number(x), number(y))
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let width = to_points(line_width);
+        // the same dash pattern as PDFPage::add_line
+        let dash = if dashed {
+            let dash = number(width.max(1.0) * 3.0);
+            format!(" stroke-dasharray=\"{dash} {dash}\"")
+        } else {
+            String::new()
+        };
+        self.elements.push(format!(
+            "<polyline points=\"{points}\" fill=\"none\" stroke=\"black\" stroke-width=\"{}\"{dash}/>",
+            number(width),
+        ));
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn add_rectangle(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        line_width: ucum::Meter<f64>,
+    ) {
+        let (x, y) = self.page_position(x_pos, y_pos + height);
+        self.elements.push(format!(
+            concat!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" ",
+                "stroke=\"black\" stroke-width=\"{}\"/>",
+            ),
+            number(x),
+            number(y),
+            number(to_points(width)),
+            number(to_points(height)),
+            number(to_points(line_width)),
+        ));
+    }
+
+    /// Lines are broken the same way as `PDFPage::add_text`, and each is written as a `text`
+    /// element in the font family of `font`, which the viewer has to have installed.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn add_text(
+        &mut self,
+        text: String,
+        font_size: u32,
+        font: &PDFFont,
+        line_spacing: u32,
+        text_width: ucum::Meter<f64>,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        text_direction: rustybuzz::Direction,
+        text_language: rustybuzz::Language,
+        text_render_mode: &PDFTextRenderMode,
+    ) -> Result<(), Error> {
+        let (page_width, page_height) = self.size();
+        if x_pos > page_width
+            || x_pos < 0.0_f64 * ucum::M
+            || y_pos > page_height
+            || y_pos < 0.0_f64 * ucum::M
+        {
+            return Err(Error::Other(format!(
+                "Position of text X: {x_pos}, Y: {y_pos}, is outside page boundaries. Please fix this"
+            )));
+        }
+        let lines = font.to_lines(
+            &text,
+            font_size,
+            text_width,
+            text_direction,
+            text_language.clone(),
+            &BreakOptions::default(),
+        )?;
+        let leading = to_points(font.line_height(font_size)) * f64::from(line_spacing.max(1));
+        let paint = match text_render_mode {
+            PDFTextRenderMode::Fill | PDFTextRenderMode::FillForClipping => {
+                "fill=\"black\"".to_string()
+            }
+            PDFTextRenderMode::Stroke | PDFTextRenderMode::StrokeForClipping => {
+                "fill=\"none\" stroke=\"black\"".to_string()
+            }
+            PDFTextRenderMode::FillStroke => "fill=\"black\" stroke=\"black\"".to_string(),
+            PDFTextRenderMode::Blank => "fill=\"none\"".to_string(),
+        };
+        let (x, y) = self.page_position(x_pos, y_pos);
+        let mut line_y = y;
+        for line in lines {
+            self.elements.push(format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"{font_size}\" {} xml:lang=\"{}\" {paint} xml:space=\"preserve\">{}</text>",
+                number(x),
+                number(line_y),
+                font_attributes(font),
+                escape(text_language.as_str()),
+                escape(&line),
+            ));
+            line_y += leading;
+        }
+        Ok(())
+    }
+
+    /// The SVG is written into the page with its text drawn as paths, so it looks the same
+    /// without the fonts installed. Its IDs are prefixed so they don't clash with other SVGs on
+    /// the page.
+    fn add_svg(
+        &mut self,
+        svg_string: &str,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        scale: Option<scale::ScalingFactor>,
+        fonts: &[PDFFont],
+        resources_dir: Option<&Path>,
+    ) -> Result<(ucum::Meter<f64>, ucum::Meter<f64>), Error> {
+        let tree = usvg::Tree::from_str(svg_string, &svg_options(fonts, resources_dir))?;
+        let factor = scale
+            .unwrap_or_default()
+            .factor()
+            .ok_or_else(|| Error::Other(format!("invalid scale: {}", scale.unwrap_or_default())))?;
+        let written = tree.to_string(&usvg::WriteOptions {
+            id_prefix: Some(format!("svg{}-", self.embedded_count)),
+            ..usvg::WriteOptions::default()
+        });
+        self.embedded_count = self.embedded_count.saturating_add(1);
+        // the contents of the root element are placed in a group, which maps the CSS pixels
+        // of the image onto points
+        let contents = written
+            .find("<svg")
+            .and_then(|start| written.get(start..))
+            .and_then(|root| root.get(root.find('>')?.saturating_add(1)..))
+            .and_then(|contents| contents.get(..contents.rfind("</svg>")?))
+            .ok_or_else(|| Error::SVGError("failed to write SVG".to_string()))?;
+
+        let pixel = (1.0_f64 / 96.0_f64) * ucum::IN_US;
+        #[allow(clippy::arithmetic_side_effects)]
+        let size = (
+            f64::from(tree.size().width()) * factor * pixel,
+            f64::from(tree.size().height()) * factor * pixel,
+        );
+        #[allow(clippy::arithmetic_side_effects)]
+        let (x, top) = self.page_position(x_pos, y_pos + size.1);
+        #[allow(clippy::arithmetic_side_effects)]
+        let units = factor * 72.0 / 96.0;
+        self.elements.push(format!(
+            "<g transform=\"translate({} {}) scale({})\">{}</g>",
+            number(x),
+            number(top),
+            number(units),
+            contents.trim(),
+        ));
+        Ok(size)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn add_link(
+        &mut self,
+        x_pos: ucum::Meter<f64>,
+        y_pos: ucum::Meter<f64>,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+        destination_page: usize,
+    ) {
+        let (x, y) = self.page_position(x_pos, y_pos + height);
+        self.links.push(SVGLink {
+            rect: [x, y, to_points(width), to_points(height)],
+            destination_page,
+        });
+    }
+
+    fn in_layer<T>(&mut self, layer: &str, draw: impl FnOnce(&mut Self) -> T) -> T {
+        self.elements.push(format!(
+            "<g inkscape:groupmode=\"layer\" inkscape:label=\"{}\">",
+            escape(layer)
+        ));
+        let drawn = draw(self);
+        self.elements.push("</g>".to_string());
+        drawn
+    }
+}
+
+/// `font_attributes` returns the SVG attributes that select `font`, falling back to a generic
+/// family of the same style
+fn font_attributes(font: &PDFFont) -> String {
+    let (family, generic, bold, italic) = match &font.source {
+        FontSource::Standard(standard) => {
+            let name = standard.name();
+            let family = name.split('-').next().unwrap_or(name);
+            let generic = match family {
+                "Times" => "serif",
+                "Courier" => "monospace",
+                _ => "sans-serif",
+            };
+            (
+                family.to_string(),
+                generic,
+                name.contains("Bold"),
+                name.contains("Italic") || name.contains("Oblique"),
+            )
+        }
+        FontSource::Embedded { font_face, .. } => {
+            let family = font_face
+                .names()
+                .into_iter()
+                .find(|name| {
+                    name.name_id == rustybuzz::ttf_parser::name_id::FAMILY && name.is_unicode()
+                })
+                .and_then(|name| name.to_string())
+                .unwrap_or_else(|| font.font_name.clone());
+            (
+                family,
+                "sans-serif",
+                font_face.is_bold(),
+                font_face.is_italic() || font_face.is_oblique(),
+            )
+        }
+    };
+    format!(
+        "font-family=\"'{}', {generic}\" font-weight=\"{}\" font-style=\"{}\"",
+        escape(&family),
+        if bold { "bold" } else { "normal" },
+        if italic { "italic" } else { "normal" },
+    )
+}
+
+/// `number` formats a coordinate or length to a thousandth of a unit, without trailing zeros
+fn number(value: f64) -> String {
+    let formatted = format!("{value:.3}");
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" | "" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// `escape` escapes the characters of `text` that can't appear as is in SVG text or attributes
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::PDFDocument;
+
+    /// `points` returns `length` points
+    fn points(length: f64) -> ucum::Meter<f64> {
+        length * PDFDocument::pdf_point()
+    }
+
+    /// `draw` draws a line, a line of text and links through `DrawingSurface`, the same way
+    /// drawings are drawn on a `PDFPage`
+    fn draw(surface: &mut impl DrawingSurface, font: &PDFFont) {
+        surface.add_line(
+            &[(points(0.0), points(0.0)), (points(72.0), points(72.0))],
+            points(1.0),
+            false,
+        );
+        surface
+            .in_layer("Labels", |surface| {
+                surface.add_text(
+                    "W1 & W2".to_string(),
+                    10,
+                    font,
+                    1,
+                    points(200.0),
+                    points(10.0),
+                    points(10.0),
+                    rustybuzz::Direction::LeftToRight,
+                    rustybuzz::Language::from_str("en").unwrap(),
+                    &PDFTextRenderMode::Fill,
+                )
+            })
+            .unwrap();
+        surface.add_link(points(0.0), points(0.0), points(100.0), points(20.0), 1);
+        surface.add_link(points(0.0), points(40.0), points(100.0), points(20.0), 2);
+    }
+
+    #[test]
+    fn page() {
+        let pdf = PDFDocument::new(paper::PaperSize::Letter, Vec::new()).unwrap();
+        let mut page = SVGPage::new(
+            paper::PaperSize::Letter,
+            Margins {
+                top: points(18.0),
+                bottom: points(18.0),
+                left: points(36.0),
+                right: points(36.0),
+            },
+        );
+        page.link_targets
+            .insert(1, "page-2.svg?a=1&b=2".to_string());
+        draw(&mut page, &pdf.available_fonts[0]);
+        let svg = page.to_svg();
+
+        // one unit is one point, so a letter page is 612 by 792 units
+        assert!(svg.contains("width=\"215.9mm\" height=\"279.4mm\" viewBox=\"0 0 612 792\""));
+        // positions are measured up from the bottom margin, and down from the top of the image
+        assert!(svg.contains(
+            "<polyline points=\"36,774 108,702\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>"
+        ));
+        assert!(svg.contains("<g inkscape:groupmode=\"layer\" inkscape:label=\"Labels\">\n<text"));
+        assert!(svg.contains(
+            "<text x=\"46\" y=\"764\" font-size=\"10\" font-family=\"'Helvetica', sans-serif\""
+        ));
+        assert!(svg.contains(">W1 &amp; W2</text>\n</g>"));
+        // only links to pages with a target are written, over everything else on the page
+        assert!(svg.contains(concat!(
+            "<a xlink:href=\"page-2.svg?a=1&amp;b=2\"><rect x=\"36\" y=\"754\" width=\"100\" ",
+            "height=\"20\" fill=\"transparent\"/></a>"
+        )));
+        assert_eq!(svg.matches("<a ").count(), 1);
+        assert!(svg.ends_with("</a>\n</svg>\n"));
+    }
+}
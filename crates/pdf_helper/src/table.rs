@@ -0,0 +1,616 @@
+use dimensioned::ucum;
+use log::warn;
+use lopdf::content::Operation;
+
+use crate::rich_text::{RichParagraph, TextSpan};
+use crate::{
+    color_components, to_points, Alignment, Error, PDFFont, PDFPage, TextBox, VerticalAlignment,
+};
+
+/// `Table` is a grid of text cells laid out in columns across the width of a page inside its
+/// margins, such as a bill of materials or a cable schedule.
+///
+/// Tables longer than a page continue on new pages. Rows are never split between pages, the
+/// header rows are repeated at the top of every page the table is on, and the footer rows, such
+/// as totals, follow the last row.
+pub struct Table<'a> {
+    /// columns of the table, from left to right
+    pub columns: Vec<TableColumn>,
+    /// rows at the top of the table, repeated on every page
+    pub header: Vec<TableRow<'a>>,
+    /// rows of the body of the table
+    pub rows: Vec<TableRow<'a>>,
+    /// rows at the end of the table
+    pub footer: Vec<TableRow<'a>>,
+    /// borders and spacing of the cells
+    pub style: TableStyle,
+}
+
+/// `TableColumn` is the width and alignment of a column of a `Table`
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct TableColumn {
+    /// how the width of the column is chosen
+    pub width: ColumnWidth,
+    /// how text is aligned in the cells of the column, unless a cell overrides it
+    pub alignment: Alignment,
+}
+
+/// `ColumnWidth` is how the width of a `TableColumn` is chosen
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+#[allow(clippy::exhaustive_enums)]
+pub enum ColumnWidth {
+    /// the column is always this wide, including the padding of its cells
+    Fixed(ucum::Meter<f64>),
+    /// the column shares the width left over after the fixed and auto columns with the other
+    /// proportional columns, in proportion to its weight
+    Proportional(f64),
+    /// the column is wide enough to fit its widest cell on one line if there is room, or
+    /// narrower down to the width of its widest word if not
+    #[default]
+    Auto,
+}
+
+/// `TableRow` is a row of cells in a `Table`, one for each column
+pub struct TableRow<'a> {
+    /// cells of the row, from left to right. Missing cells are left empty.
+    pub cells: Vec<TableCell<'a>>,
+    /// background color of the row
+    pub shading: Option<usvg::Color>,
+}
+
+/// `TableCell` is the text in one cell of a `Table`
+pub struct TableCell<'a> {
+    /// text of the cell, wrapped to the width of its column
+    pub spans: Vec<TextSpan<'a>>,
+    /// background color of the cell, drawn over the shading of its row
+    pub shading: Option<usvg::Color>,
+    /// alignment of the text, if it is different to the alignment of its column
+    pub alignment: Option<Alignment>,
+}
+
+/// `TableStyle` is the borders and spacing of the cells of a `Table`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TableStyle {
+    /// space between the border of each cell and its text
+    pub padding: ucum::Meter<f64>,
+    /// width of the borders around each cell, which aren't drawn if it is 0
+    pub border_width: ucum::Meter<f64>,
+    /// color of the borders
+    pub border_color: usvg::Color,
+    /// distance between baselines of text in a cell, as a multiple of the line height
+    pub line_spacing: u32,
+    /// how text is placed in cells shorter than the tallest cell of their row
+    pub vertical_alignment: VerticalAlignment,
+}
+
+impl Default for TableStyle {
+    fn default() -> Self {
+        Self {
+            padding: 2.0_f64 * crate::PDFDocument::pdf_point(),
+            border_width: 0.5_f64 * crate::PDFDocument::pdf_point(),
+            border_color: usvg::Color::black(),
+            line_spacing: 1,
+            vertical_alignment: VerticalAlignment::Top,
+        }
+    }
+}
+
+impl<'a> TableRow<'a> {
+    /// `new` returns an unshaded row of `cells`
+    #[must_use]
+    pub fn new(cells: Vec<TableCell<'a>>) -> Self {
+        Self {
+            cells,
+            shading: None,
+        }
+    }
+}
+
+impl<'a> TableCell<'a> {
+    /// `new` returns an unshaded cell of `text` in a single style
+    #[must_use]
+    pub fn new(text: impl Into<String>, font: &'a PDFFont<'a>, font_size: u32) -> Self {
+        Self::rich(vec![TextSpan::new(text, font, font_size)])
+    }
+
+    /// `rich` returns an unshaded cell of text in several styles
+    #[must_use]
+    pub fn rich(spans: Vec<TextSpan<'a>>) -> Self {
+        Self {
+            spans,
+            shading: None,
+            alignment: None,
+        }
+    }
+}
+
+impl Table<'_> {
+    /// `column_widths` returns the width of each column of the table when it is `width` wide.
+    ///
+    /// Fixed columns get their width first, then every other column gets the width of its
+    /// widest word. Auto columns then grow towards the width of their widest cell, and whatever
+    /// is left is shared between the proportional columns, or the auto columns if there aren't
+    /// any. Columns that don't fit are left narrower than their content.
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn column_widths(
+        &self,
+        width: ucum::Meter<f64>,
+        fallback_fonts: &[PDFFont],
+    ) -> Vec<ucum::Meter<f64>> {
+        let zero = 0.0_f64 * ucum::M;
+        let padding = self.style.padding * 2.0_f64;
+        // the narrowest and widest each column can usefully be
+        let limits = (0..self.columns.len())
+            .map(|column| {
+                self.all_rows()
+                    .filter_map(|row| row.cells.get(column))
+                    .fold((zero, zero), |(narrowest, widest), cell| {
+                        let paragraph = RichParagraph::new(&cell.spans, fallback_fonts);
+                        let word = paragraph.widest_word() + padding;
+                        let line = paragraph.width(0..paragraph.text.len()) + padding;
+                        (
+                            if word > narrowest { word } else { narrowest },
+                            if line > widest { line } else { widest },
+                        )
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = self
+            .columns
+            .iter()
+            .zip(&limits)
+            .map(|(column, (narrowest, _))| match column.width {
+                ColumnWidth::Fixed(fixed) => fixed,
+                ColumnWidth::Proportional(_) | ColumnWidth::Auto => *narrowest,
+            })
+            .collect::<Vec<_>>();
+        let mut remaining = width - widths.iter().fold(zero, |total, width| total + *width);
+        if remaining <= zero {
+            return widths;
+        }
+
+        // auto columns grow towards fitting their widest cell on one line
+        let is_auto = |column: &TableColumn| column.width == ColumnWidth::Auto;
+        let growth = self
+            .columns
+            .iter()
+            .zip(&limits)
+            .filter(|(column, _)| is_auto(column))
+            .fold(zero, |total, (_, (narrowest, widest))| {
+                total + *widest - *narrowest
+            });
+        if growth > zero {
+            let fraction = if growth > remaining {
+                to_points(remaining) / to_points(growth)
+            } else {
+                1.0_f64
+            };
+            for ((width, column), (narrowest, widest)) in
+                widths.iter_mut().zip(&self.columns).zip(&limits)
+            {
+                if is_auto(column) {
+                    *width += (*widest - *narrowest) * fraction;
+                }
+            }
+            remaining -= growth * fraction;
+        }
+
+        // anything left over goes to the proportional columns, or the auto columns
+        let weights = self
+            .columns
+            .iter()
+            .map(|column| match column.width {
+                ColumnWidth::Proportional(weight) => weight.max(0.0_f64),
+                ColumnWidth::Fixed(_) | ColumnWidth::Auto => 0.0_f64,
+            })
+            .collect::<Vec<_>>();
+        let weights = if weights.iter().sum::<f64>() > 0.0_f64 {
+            weights
+        } else {
+            self.columns
+                .iter()
+                .zip(&widths)
+                .map(|(column, width)| {
+                    if is_auto(column) {
+                        to_points(*width)
+                    } else {
+                        0.0_f64
+                    }
+                })
+                .collect()
+        };
+        let total_weight = weights.iter().sum::<f64>();
+        if total_weight > 0.0_f64 {
+            for (width, weight) in widths.iter_mut().zip(weights) {
+                *width += remaining * (weight / total_weight);
+            }
+        }
+        widths
+    }
+
+    /// `draw` draws the table across the width of `first_page` inside its margins, with its top
+    /// at `top`, measured from the bottom of the page inside the margin. Rows that don't fit
+    /// continue at the top of new pages like `first_page`. Returns `first_page` followed by
+    /// any new pages.
+    ///
+    /// Characters the fonts of the cells have no glyphs for are set in the first of
+    /// `fallback_fonts` that has one.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the text of a cell fails to shape or split into lines
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn draw(
+        &self,
+        first_page: PDFPage,
+        top: ucum::Meter<f64>,
+        fallback_fonts: &[PDFFont],
+        text_direction: rustybuzz::Direction,
+        text_language: &rustybuzz::Language,
+    ) -> Result<Vec<PDFPage>, Error> {
+        let (page_width, page_height) = first_page.size();
+        let margins = first_page.margins;
+        let content_top = page_height - margins.top - margins.bottom;
+        let widths = self.column_widths(page_width - margins.left - margins.right, fallback_fonts);
+
+        let header_heights = self
+            .header
+            .iter()
+            .map(|row| self.row_height(row, &widths, fallback_fonts, text_language))
+            .collect::<Result<Vec<_>, _>>()?;
+        let header_height = header_heights
+            .iter()
+            .fold(0.0_f64 * ucum::M, |total, height| total + *height);
+
+        let mut pages = Vec::new();
+        let mut page = first_page;
+        let mut y_pos = top;
+        let mut rows_on_page = 0_usize;
+        for row in self.rows.iter().chain(&self.footer) {
+            let row_height = self.row_height(row, &widths, fallback_fonts, text_language)?;
+            let needed = if rows_on_page == 0 {
+                header_height + row_height
+            } else {
+                row_height
+            };
+            // start a new page, unless this page is already as empty as a new page
+            if y_pos - needed < 0.0_f64 * ucum::M && (rows_on_page > 0 || y_pos < content_top) {
+                let mut next_page = PDFPage::new(page.page_size, margins);
+                next_page.orientation = page.orientation;
+                pages.push(std::mem::replace(&mut page, next_page));
+                y_pos = content_top;
+                rows_on_page = 0;
+            }
+            if needed > content_top {
+                warn! {"table row is taller than a page, so it runs past the bottom margin"};
+            }
+            if rows_on_page == 0 {
+                for (header_row, height) in self.header.iter().zip(&header_heights) {
+                    self.draw_row(
+                        &mut page,
+                        header_row,
+                        &widths,
+                        (y_pos, *height),
+                        fallback_fonts,
+                        text_direction,
+                        text_language,
+                    )?;
+                    y_pos -= *height;
+                }
+            }
+            self.draw_row(
+                &mut page,
+                row,
+                &widths,
+                (y_pos, row_height),
+                fallback_fonts,
+                text_direction,
+                text_language,
+            )?;
+            y_pos -= row_height;
+            rows_on_page += 1;
+        }
+        pages.push(page);
+        Ok(pages)
+    }
+
+    /// `all_rows` returns the header, body and footer rows in order
+    fn all_rows(&self) -> impl Iterator<Item = &TableRow<'_>> {
+        self.header.iter().chain(&self.rows).chain(&self.footer)
+    }
+
+    /// `row_height` returns the height of the tallest cell of `row`, including padding, with
+    /// its columns `widths` wide
+    ///
+    /// # Errors
+    ///
+    /// Will error if the text of a cell fails to split into lines
+    #[allow(clippy::arithmetic_side_effects)]
+    fn row_height(
+        &self,
+        row: &TableRow,
+        widths: &[ucum::Meter<f64>],
+        fallback_fonts: &[PDFFont],
+        text_language: &rustybuzz::Language,
+    ) -> Result<ucum::Meter<f64>, Error> {
+        let mut tallest = 0.0_f64 * ucum::M;
+        for (cell, width) in row.cells.iter().zip(widths) {
+            let text_box = TextBox {
+                width: *width - self.style.padding * 2.0_f64,
+                height: f64::INFINITY * ucum::M,
+                ..TextBox::default()
+            };
+            let height = RichParagraph::new(&cell.spans, fallback_fonts)
+                .layout(self.style.line_spacing, &text_box, text_language)?
+                .text_layout
+                .height;
+            if height > tallest {
+                tallest = height;
+            }
+        }
+        Ok(tallest + self.style.padding * 2.0_f64)
+    }
+
+    /// `draw_row` draws the shading, text and borders of the cells of `row` onto `page`, with
+    /// its top at `y_pos` and `height` tall
+    ///
+    /// # Errors
+    ///
+    /// Will error if the text of a cell fails to shape or split into lines
+    #[allow(clippy::arithmetic_side_effects, clippy::too_many_arguments)]
+    fn draw_row(
+        &self,
+        page: &mut PDFPage,
+        row: &TableRow,
+        widths: &[ucum::Meter<f64>],
+        (y_pos, height): (ucum::Meter<f64>, ucum::Meter<f64>),
+        fallback_fonts: &[PDFFont],
+        text_direction: rustybuzz::Direction,
+        text_language: &rustybuzz::Language,
+    ) -> Result<(), Error> {
+        let bottom = y_pos - height;
+        let row_width = widths
+            .iter()
+            .fold(0.0_f64 * ucum::M, |total, width| total + *width);
+        if let Some(shading) = row.shading {
+            fill_rectangle(
+                page,
+                [0.0_f64 * ucum::M, bottom, row_width, height],
+                shading,
+            );
+        }
+        let mut left = 0.0_f64 * ucum::M;
+        for (column_number, (column, width)) in self.columns.iter().zip(widths).enumerate() {
+            let cell = row.cells.get(column_number);
+            if let Some(shading) = cell.and_then(|cell| cell.shading) {
+                fill_rectangle(page, [left, bottom, *width, height], shading);
+            }
+            if let Some(cell) = cell {
+                page.add_rich_text(
+                    &cell.spans,
+                    fallback_fonts,
+                    self.style.line_spacing,
+                    &TextBox {
+                        x_pos: left + self.style.padding,
+                        y_pos: bottom + self.style.padding,
+                        width: *width - self.style.padding * 2.0_f64,
+                        height: height - self.style.padding * 2.0_f64,
+                        alignment: cell.alignment.unwrap_or(column.alignment),
+                        vertical_alignment: self.style.vertical_alignment,
+                        ..TextBox::default()
+                    },
+                    text_direction,
+                    text_language.clone(),
+                )?;
+            }
+            if self.style.border_width > 0.0_f64 * ucum::M {
+                stroke_rectangle(
+                    page,
+                    [left, bottom, *width, height],
+                    self.style.border_width,
+                    self.style.border_color,
+                );
+            }
+            left += *width;
+        }
+        Ok(())
+    }
+}
+
+/// `rectangle_operands` returns the operands of a `re` operator for `rectangle`, given as its
+/// left, bottom, width and height inside the margins of `page`
+fn rectangle_operands(page: &PDFPage, rectangle: [ucum::Meter<f64>; 4]) -> Vec<lopdf::Object> {
+    let [x_pos, y_pos, width, height] = rectangle;
+    let (x, y) = page.page_position(x_pos, y_pos);
+    vec![
+        x.into(),
+        y.into(),
+        to_points(width).into(),
+        to_points(height).into(),
+    ]
+}
+
+/// `fill_rectangle` fills `rectangle` on `page` with `color`
+fn fill_rectangle(page: &mut PDFPage, rectangle: [ucum::Meter<f64>; 4], color: usvg::Color) {
+    let operands = rectangle_operands(page, rectangle);
+    page.operations.extend([
+        Operation::new("q", vec![]),
+        Operation::new("rg", color_components(color)),
+        Operation::new("re", operands),
+        Operation::new("f", vec![]),
+        Operation::new("Q", vec![]),
+    ]);
+}
+
+/// `stroke_rectangle` strokes the outline of `rectangle` on `page` with lines `line_width` wide
+/// in `color`
+fn stroke_rectangle(
+    page: &mut PDFPage,
+    rectangle: [ucum::Meter<f64>; 4],
+    line_width: ucum::Meter<f64>,
+    color: usvg::Color,
+) {
+    let operands = rectangle_operands(page, rectangle);
+    page.operations.extend([
+        Operation::new("q", vec![]),
+        Operation::new("RG", color_components(color)),
+        Operation::new("w", vec![to_points(line_width).into()]),
+        Operation::new("re", operands),
+        Operation::new("S", vec![]),
+        Operation::new("Q", vec![]),
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::{paper::PaperSize, Margins, PDFDocument};
+
+    /// `assert_close` checks two lengths are within a thousandth of a point of each other
+    fn assert_close(left: ucum::Meter<f64>, right: ucum::Meter<f64>) {
+        assert!(
+            (to_points(left) - to_points(right)).abs() < 0.001,
+            "{} pt != {} pt",
+            to_points(left),
+            to_points(right)
+        );
+    }
+
+    /// `column` returns a left aligned column `width` wide
+    fn column(width: ColumnWidth) -> TableColumn {
+        TableColumn {
+            width,
+            ..TableColumn::default()
+        }
+    }
+
+    /// `text_row` returns an unshaded row with a cell for each of `texts`
+    fn text_row<'a>(texts: &[&str], font: &'a PDFFont<'a>) -> TableRow<'a> {
+        TableRow::new(
+            texts
+                .iter()
+                .map(|text| TableCell::new(*text, font, 10))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn fixed_and_proportional_widths() {
+        let table = Table {
+            columns: vec![
+                column(ColumnWidth::Fixed(72.0 * PDFDocument::pdf_point())),
+                column(ColumnWidth::Proportional(1.0)),
+                column(ColumnWidth::Proportional(3.0)),
+            ],
+            header: Vec::new(),
+            rows: Vec::new(),
+            footer: Vec::new(),
+            style: TableStyle::default(),
+        };
+        let widths = table.column_widths(472.0 * PDFDocument::pdf_point(), &[]);
+        assert_eq!(widths.len(), 3);
+        assert_close(widths[0], 72.0 * PDFDocument::pdf_point());
+        assert_close(widths[1], 100.0 * PDFDocument::pdf_point());
+        assert_close(widths[2], 300.0 * PDFDocument::pdf_point());
+    }
+
+    #[test]
+    fn auto_widths() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let font = &pdf.available_fonts[0];
+        let padding = TableStyle::default().padding * 2.0;
+        let table = Table {
+            columns: vec![
+                column(ColumnWidth::Auto),
+                column(ColumnWidth::Proportional(1.0)),
+            ],
+            header: vec![text_row(&["Cable", "Description"], font)],
+            rows: vec![
+                text_row(&["W1", "short"], font),
+                text_row(&["W100 longest", "short"], font),
+            ],
+            footer: Vec::new(),
+            style: TableStyle::default(),
+        };
+        let width = 400.0 * PDFDocument::pdf_point();
+
+        // the auto column fits its widest cell, and the proportional column gets the rest
+        let widths = table.column_widths(width, &[]);
+        let auto_width = font.text_width("W100 longest", 10) + padding;
+        assert_close(widths[0], auto_width);
+        assert_close(widths[0] + widths[1], width);
+
+        // without room for the widest cell, the auto column shrinks to its widest word
+        let narrow = font.text_width("Description", 10) + font.text_width("W100", 10) + padding;
+        let widths = table.column_widths(narrow, &[]);
+        assert_close(widths[0], font.text_width("longest", 10) + padding);
+        assert!(widths[0] < auto_width);
+    }
+
+    #[test]
+    fn continues_on_new_pages() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let font = &pdf.available_fonts[0];
+        let header_color = usvg::Color::new_rgb(255, 0, 0);
+        let footer_color = usvg::Color::new_rgb(0, 0, 255);
+        let table = Table {
+            columns: vec![column(ColumnWidth::Auto), column(ColumnWidth::Auto)],
+            header: vec![TableRow {
+                shading: Some(header_color),
+                ..text_row(&["Cable", "Length"], font)
+            }],
+            rows: (1..=150)
+                .map(|number| {
+                    TableRow::new(vec![
+                        TableCell::new(format!("W{number}"), font, 10),
+                        TableCell::new("2 m", font, 10),
+                    ])
+                })
+                .collect(),
+            footer: vec![TableRow {
+                shading: Some(footer_color),
+                ..text_row(&["Total", "300 m"], font)
+            }],
+            style: TableStyle::default(),
+        };
+        let margins = Margins {
+            top: 36.0 * PDFDocument::pdf_point(),
+            bottom: 36.0 * PDFDocument::pdf_point(),
+            left: 36.0 * PDFDocument::pdf_point(),
+            right: 36.0 * PDFDocument::pdf_point(),
+        };
+        let first_page = PDFPage::new(PaperSize::A4, margins);
+        let (_, page_height) = first_page.size();
+        let pages = table
+            .draw(
+                first_page,
+                page_height - margins.top - margins.bottom,
+                &[],
+                rustybuzz::Direction::LeftToRight,
+                &rustybuzz::Language::from_str("en").unwrap(),
+            )
+            .unwrap();
+        assert!(pages.len() > 1);
+
+        // the fill colors of the shaded rows on each page, in the order they are drawn
+        let fills = |page: &PDFPage| {
+            page.operations
+                .iter()
+                .filter(|operation| operation.operator == "rg")
+                .map(|operation| operation.operands.clone())
+                .collect::<Vec<_>>()
+        };
+        let last = pages.len() - 1;
+        for (number, page) in pages.iter().enumerate() {
+            let page_fills = fills(page);
+            assert_eq!(page_fills[0], color_components(header_color));
+            let has_footer = page_fills.contains(&color_components(footer_color));
+            assert_eq!(has_footer, number == last, "footer on page {number}");
+        }
+    }
+}
@@ -0,0 +1,611 @@
+use std::str::FromStr;
+
+use dimensioned::{f64prefixes, ucum};
+
+use crate::scale::ScalingFactor;
+use crate::{
+    Alignment, Error, Margins, Overflow, PDFFont, PDFPage, PDFTextRenderMode, TextBox,
+    VerticalAlignment,
+};
+
+/// `PageTemplate` is the drawing frame applied to every page of a drawing set. It is made up of
+/// a border divided into zones, a title block in the bottom right corner, and a revision history
+/// table above the title block.
+///
+/// Zones are numbered from 1 along the width of the page, left to right, and lettered from A
+/// along the height of the page, top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageTemplate {
+    /// number of zones along the width of the page
+    pub horizontal_zones: u8,
+    /// number of zones along the height of the page, at most 26
+    pub vertical_zones: u8,
+    /// fields shown in the title block
+    pub title_block: TitleBlock,
+}
+
+/// `TitleBlock` contains the fields of the title block that are the same on every page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TitleBlock {
+    /// name of the project
+    pub project_name: String,
+    /// drawing number of the drawing set
+    pub drawing_number: String,
+    /// current revision of the drawing set
+    pub revision: String,
+    /// date of the current revision
+    pub date: String,
+    /// who drew the drawing set
+    pub drawn_by: String,
+    /// who checked the drawing set
+    pub checked_by: String,
+    /// revision history, oldest first
+    pub revisions: Vec<Revision>,
+}
+
+/// `Revision` is one row of the revision history table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Revision {
+    /// revision identifier
+    pub revision: String,
+    /// date of the revision
+    pub date: String,
+    /// description of what changed
+    pub description: String,
+    /// who made the revision
+    pub by: String,
+}
+
+/// `ZONE_BAND` is the width of the band around the border that holds the zone labels, in mm
+const ZONE_BAND: f64 = 5.0;
+/// `TITLE_BLOCK_WIDTH` is the widest the title block is drawn, in mm
+const TITLE_BLOCK_WIDTH: f64 = 180.0;
+/// `CAPTION_FONT_SIZE` is the font size of the field names in the title block, in points
+const CAPTION_FONT_SIZE: u32 = 5;
+/// `VALUE_FONT_SIZE` is the largest font size of the field values in the title block, in points
+const VALUE_FONT_SIZE: u32 = 8;
+/// `MIN_FONT_SIZE` is the smallest font size text is shrunk to so it fits in its cell, in points
+const MIN_FONT_SIZE: u32 = 3;
+/// `TITLE_BLOCK_ROWS` is the number of rows in the title block
+const TITLE_BLOCK_ROWS: u32 = 4;
+
+impl Default for PageTemplate {
+    fn default() -> Self {
+        Self {
+            horizontal_zones: 8,
+            vertical_zones: 8,
+            title_block: TitleBlock::default(),
+        }
+    }
+}
+
+impl PageTemplate {
+    /// `content_margins` returns the margins that keep page content inside the border and clear
+    /// of the title block and revision table, for a page with `margins` around the border.
+    #[allow(clippy::arithmetic_side_effects)]
+    #[must_use]
+    pub fn content_margins(&self, margins: Margins, font: &PDFFont) -> Margins {
+        let band = ZONE_BAND * ucum::M * f64prefixes::MILLI;
+        Margins {
+            top: margins.top + band,
+            bottom: margins.bottom + band + self.block_height(font) + row_gap(font),
+            left: margins.left + band,
+            right: margins.right + band,
+        }
+    }
+
+    /// `apply` draws the border, title block and revision table on `pdf_page`, inside the
+    /// margins of the page.
+    ///
+    /// # Arguments
+    ///
+    /// * `pdf_page`: page to draw the template on
+    /// * `font`: font for all text in the template
+    /// * `sheet_title`: title of this page
+    /// * `sheet_scale`: scale this page is drawn at, or `None` if it isn't drawn to scale
+    /// * `sheet_number`: one indexed number of this page in the drawing set
+    /// * `sheet_count`: total number of pages in the drawing set
+    ///
+    /// # Errors
+    ///
+    /// Will error if there are no zones, more than 26 vertical zones, or the page is too small
+    /// for the template
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn apply(
+        &self,
+        pdf_page: &mut PDFPage,
+        font: &PDFFont,
+        sheet_title: &str,
+        sheet_scale: Option<ScalingFactor>,
+        sheet_number: usize,
+        sheet_count: usize,
+    ) -> Result<(), Error> {
+        let (page_width, page_height) = pdf_page.size();
+        let width = page_width - pdf_page.margins.left - pdf_page.margins.right;
+        let height = page_height - pdf_page.margins.top - pdf_page.margins.bottom;
+        let band = ZONE_BAND * ucum::M * f64prefixes::MILLI;
+        let inner_width = width - band * 2.0;
+        if inner_width <= 0.0 * ucum::M
+            || height - band * 2.0 - self.block_height(font) <= 0.0 * ucum::M
+        {
+            return Err(Error::Other(format!(
+                "page size {} is too small for the page template",
+                pdf_page.page_size
+            )));
+        }
+        self.draw_border(pdf_page, font, width, height)?;
+
+        let max_block_width = TITLE_BLOCK_WIDTH * ucum::M * f64prefixes::MILLI;
+        let block_width = if inner_width < max_block_width {
+            inner_width
+        } else {
+            max_block_width
+        };
+        let left = band + inner_width - block_width;
+        let row_height = row_height(font);
+        let sheet = format!("{sheet_number} of {sheet_count}");
+        // NTS is the drafting abbreviation for not to scale
+        let scale = sheet_scale.map_or_else(|| "NTS".to_string(), |scale| scale.to_string());
+        let block = &self.title_block;
+        // rows from the bottom of the title block up, each row split into cells of
+        // (fraction of block width, caption, value)
+        let rows: [&[(f64, &str, &str)]; TITLE_BLOCK_ROWS as usize] = [
+            &[
+                (0.5, "Drawing No.", &block.drawing_number),
+                (0.2, "Rev", &block.revision),
+                (0.3, "Sheet", &sheet),
+            ],
+            &[(0.75, "Title", sheet_title), (0.25, "Scale", &scale)],
+            &[
+                (0.3, "Date", &block.date),
+                (0.35, "Drawn", &block.drawn_by),
+                (0.35, "Checked", &block.checked_by),
+            ],
+            &[(1.0, "Project", &block.project_name)],
+        ];
+        let mut bottom = band;
+        for row in rows {
+            draw_row(pdf_page, font, row, (left, bottom), block_width, row_height)?;
+            bottom += row_height;
+        }
+
+        // revision table, newest revision closest to the title block, with its header on top
+        let header: &[(f64, &str, &str)] = &[
+            (0.1, "", "Rev"),
+            (0.2, "", "Date"),
+            (0.55, "", "Description"),
+            (0.15, "", "By"),
+        ];
+        let table_row_height = row_gap(font);
+        for revision in block.revisions.iter().rev() {
+            let cells: &[(f64, &str, &str)] = &[
+                (0.1, "", &revision.revision),
+                (0.2, "", &revision.date),
+                (0.55, "", &revision.description),
+                (0.15, "", &revision.by),
+            ];
+            draw_row(
+                pdf_page,
+                font,
+                cells,
+                (left, bottom),
+                block_width,
+                table_row_height,
+            )?;
+            bottom += table_row_height;
+        }
+        draw_row(
+            pdf_page,
+            font,
+            header,
+            (left, bottom),
+            block_width,
+            table_row_height,
+        )
+    }
+
+    /// `block_height` returns the height of the title block and revision table together
+    #[allow(clippy::arithmetic_side_effects)]
+    fn block_height(&self, font: &PDFFont) -> ucum::Meter<f64> {
+        let table_rows = u32::try_from(self.title_block.revisions.len())
+            .unwrap_or(u32::MAX)
+            .saturating_add(1);
+        row_height(font) * f64::from(TITLE_BLOCK_ROWS) + row_gap(font) * f64::from(table_rows)
+    }
+
+    /// `draw_border` draws the outer border, the inner frame, and the zone dividers and labels
+    /// in the band between them
+    #[allow(clippy::arithmetic_side_effects)]
+    fn draw_border(
+        &self,
+        pdf_page: &mut PDFPage,
+        font: &PDFFont,
+        width: ucum::Meter<f64>,
+        height: ucum::Meter<f64>,
+    ) -> Result<(), Error> {
+        if self.horizontal_zones == 0 || self.vertical_zones == 0 || self.vertical_zones > 26 {
+            return Err(Error::Other(format!(
+                "page template needs 1 or more horizontal zones and 1 to 26 vertical zones, got {} and {}",
+                self.horizontal_zones, self.vertical_zones
+            )));
+        }
+        let band = ZONE_BAND * ucum::M * f64prefixes::MILLI;
+        let zero = 0.0 * ucum::M;
+        let thick = 1.0 * crate::PDFDocument::pdf_point();
+        let thin = 0.5 * crate::PDFDocument::pdf_point();
+        pdf_page.add_rectangle(zero, zero, width, height, thin);
+        pdf_page.add_rectangle(band, band, width - band * 2.0, height - band * 2.0, thick);
+
+        let zone_width = width / f64::from(self.horizontal_zones);
+        for zone in 0..self.horizontal_zones {
+            let left = zone_width * f64::from(zone);
+            if zone > 0 {
+                for (from, to) in [(zero, band), (height - band, height)] {
+                    pdf_page.add_line(&[(left, from), (left, to)], thin, false);
+                }
+            }
+            let label = (u32::from(zone) + 1).to_string();
+            for bottom in [zero, height - band] {
+                draw_centered(pdf_page, font, &label, (left, bottom), (zone_width, band))?;
+            }
+        }
+        let zone_height = height / f64::from(self.vertical_zones);
+        for zone in 0..self.vertical_zones {
+            // zones are lettered from the top of the page down
+            let top = height - zone_height * f64::from(zone);
+            if zone > 0 {
+                for (from, to) in [(zero, band), (width - band, width)] {
+                    pdf_page.add_line(&[(from, top), (to, top)], thin, false);
+                }
+            }
+            let label = char::from(b'A' + zone).to_string();
+            for left in [zero, width - band] {
+                draw_centered(
+                    pdf_page,
+                    font,
+                    &label,
+                    (left, top - zone_height),
+                    (band, zone_height),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `row_height` returns the height of a title block row, which holds a caption above a value
+#[allow(clippy::arithmetic_side_effects)]
+fn row_height(font: &PDFFont) -> ucum::Meter<f64> {
+    (font.line_height(CAPTION_FONT_SIZE) + font.line_height(VALUE_FONT_SIZE)) * 1.2
+}
+
+/// `row_gap` returns the height of a revision table row, which holds a single value
+#[allow(clippy::arithmetic_side_effects)]
+fn row_gap(font: &PDFFont) -> ucum::Meter<f64> {
+    font.line_height(VALUE_FONT_SIZE) * 1.4
+}
+
+/// `draw_row` draws one row of the title block or revision table, with its bottom left corner at
+/// `origin`. Each cell is drawn as a box, with its caption in the top left corner if it has one,
+/// and its value below it.
+#[allow(clippy::arithmetic_side_effects)]
+fn draw_row(
+    pdf_page: &mut PDFPage,
+    font: &PDFFont,
+    cells: &[(f64, &str, &str)],
+    origin: (ucum::Meter<f64>, ucum::Meter<f64>),
+    row_width: ucum::Meter<f64>,
+    row_height: ucum::Meter<f64>,
+) -> Result<(), Error> {
+    let line_width = 0.5 * crate::PDFDocument::pdf_point();
+    let padding = font.line_height(CAPTION_FONT_SIZE) / 3.0;
+    let (mut left, bottom) = origin;
+    for (fraction, caption, value) in cells {
+        let cell_width = row_width * *fraction;
+        pdf_page.add_rectangle(left, bottom, cell_width, row_height, line_width);
+        let text_width = cell_width - padding * 2.0;
+        let mut top = bottom + row_height - padding;
+        if !caption.is_empty() {
+            let caption_height = font.line_height(CAPTION_FONT_SIZE);
+            top -= caption_height;
+            add_line(
+                pdf_page,
+                font,
+                caption,
+                CAPTION_FONT_SIZE,
+                &TextBox {
+                    x_pos: left + padding,
+                    y_pos: top,
+                    width: text_width,
+                    height: caption_height,
+                    overflow: Overflow::Clip,
+                    ..TextBox::default()
+                },
+            )?;
+        }
+        if !value.is_empty() {
+            add_line(
+                pdf_page,
+                font,
+                value,
+                fit_font_size(font, value, text_width),
+                &TextBox {
+                    x_pos: left + padding,
+                    y_pos: bottom,
+                    width: text_width,
+                    height: top - bottom,
+                    overflow: Overflow::Clip,
+                    ..TextBox::default()
+                },
+            )?;
+        }
+        left += cell_width;
+    }
+    Ok(())
+}
+
+/// `draw_centered` draws `text` centered within the area at `origin` of `size`
+fn draw_centered(
+    pdf_page: &mut PDFPage,
+    font: &PDFFont,
+    text: &str,
+    origin: (ucum::Meter<f64>, ucum::Meter<f64>),
+    size: (ucum::Meter<f64>, ucum::Meter<f64>),
+) -> Result<(), Error> {
+    add_line(
+        pdf_page,
+        font,
+        text,
+        fit_font_size(font, text, size.0),
+        &TextBox {
+            x_pos: origin.0,
+            y_pos: origin.1,
+            width: size.0,
+            height: size.1,
+            alignment: Alignment::Center,
+            vertical_alignment: VerticalAlignment::Middle,
+            ..TextBox::default()
+        },
+    )
+}
+
+/// `fit_font_size` returns the largest font size up to `VALUE_FONT_SIZE` at which `text` fits on
+/// one line within `width`, or `MIN_FONT_SIZE` if it doesn't fit at all
+fn fit_font_size(font: &PDFFont, text: &str, width: ucum::Meter<f64>) -> u32 {
+    (MIN_FONT_SIZE..=VALUE_FONT_SIZE)
+        .rev()
+        .find(|font_size| font.text_width(text, *font_size) <= width)
+        .unwrap_or(MIN_FONT_SIZE)
+}
+
+/// `add_line` writes `text` at `font_size` into `text_box`. Text too wide for the box wraps, and
+/// is cut off if the box clips it, rather than spreading out of its cell.
+fn add_line(
+    pdf_page: &mut PDFPage,
+    font: &PDFFont,
+    text: &str,
+    font_size: u32,
+    text_box: &TextBox,
+) -> Result<(), Error> {
+    let language = rustybuzz::Language::from_str("en")
+        .map_err(|e| Error::Other(format!("invalid text language: {e}")))?;
+    pdf_page.add_text_box(
+        text,
+        font_size,
+        font,
+        1,
+        text_box,
+        rustybuzz::Direction::LeftToRight,
+        language,
+        &PDFTextRenderMode::Fill,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{paper::PaperSize, to_points, PDFDocument};
+
+    /// `mm` returns `length` millimeters
+    fn mm(length: f64) -> ucum::Meter<f64> {
+        length * ucum::M * f64prefixes::MILLI
+    }
+
+    /// `texts` returns each line of text shown on `page`, with the position of its baseline in
+    /// points
+    fn texts(page: &PDFPage) -> Vec<(String, f64, f64)> {
+        let mut texts: Vec<(String, f64, f64)> = Vec::new();
+        for operation in &page.operations {
+            match (operation.operator.as_str(), operation.operands.as_slice()) {
+                // each line starts at a new text matrix, and is shown a word at a time
+                ("Tm", [.., x, y]) => texts.push((
+                    String::new(),
+                    f64::from(x.as_float().unwrap()),
+                    f64::from(y.as_float().unwrap()),
+                )),
+                ("TJ", [lopdf::Object::Array(pieces)]) => {
+                    let (line, _, _) = texts.last_mut().unwrap();
+                    for piece in pieces {
+                        if let lopdf::Object::String(bytes, _) = piece {
+                            line.push_str(&String::from_utf8_lossy(bytes));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        texts
+    }
+
+    /// `positions` returns the position of each piece of text on `page` that reads `text`
+    fn positions(page: &PDFPage, text: &str) -> Vec<(f64, f64)> {
+        texts(page)
+            .into_iter()
+            .filter(|(shown, _, _)| shown == text)
+            .map(|(_, x, y)| (x, y))
+            .collect()
+    }
+
+    /// `test_page` returns a page of `page_size` with 10mm margins
+    fn test_page(page_size: PaperSize) -> PDFPage {
+        PDFPage::new(
+            page_size,
+            Margins {
+                top: mm(10.0),
+                bottom: mm(10.0),
+                left: mm(10.0),
+                right: mm(10.0),
+            },
+        )
+    }
+
+    #[test]
+    fn zones() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let font = &pdf.available_fonts[0];
+        let template = PageTemplate {
+            horizontal_zones: 4,
+            vertical_zones: 3,
+            ..PageTemplate::default()
+        };
+        let mut page = test_page(PaperSize::A4);
+        template.apply(&mut page, font, "", None, 1, 1).unwrap();
+
+        // each zone is labelled at both ends, centered in the zone
+        let (width, height) = page.size();
+        let zone_width = to_points(width - mm(20.0)) / 4.0;
+        let left = to_points(mm(10.0));
+        for (zone, label) in ["1", "2", "3", "4"].into_iter().enumerate() {
+            let label_positions = positions(&page, label);
+            assert_eq!(label_positions.len(), 2, "zone {label}");
+            let center = left + zone_width * (zone as f64 + 0.5);
+            let label_width = to_points(font.text_width(label, 8));
+            for (x, _) in label_positions {
+                assert!(
+                    (x + label_width / 2.0 - center).abs() < 0.01,
+                    "zone {label}"
+                );
+            }
+        }
+        // lettered down the page from the top
+        let zone_height = to_points(height - mm(20.0)) / 3.0;
+        let top = to_points(height - mm(10.0));
+        for (zone, label) in ["A", "B", "C"].into_iter().enumerate() {
+            let label_positions = positions(&page, label);
+            assert_eq!(label_positions.len(), 2, "zone {label}");
+            for (_, y) in label_positions {
+                assert!(y < top - zone_height * zone as f64, "zone {label}");
+                assert!(y > top - zone_height * (zone as f64 + 1.0), "zone {label}");
+            }
+        }
+        // a divider between each zone on both sides of the border
+        let dividers = page
+            .operations
+            .iter()
+            .filter(|operation| operation.operator == "m")
+            .count();
+        assert_eq!(dividers, (3 + 2) * 2);
+    }
+
+    #[test]
+    fn invalid_zones() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let font = &pdf.available_fonts[0];
+        for (horizontal_zones, vertical_zones) in [(0, 4), (4, 0), (4, 27)] {
+            let template = PageTemplate {
+                horizontal_zones,
+                vertical_zones,
+                ..PageTemplate::default()
+            };
+            assert!(template
+                .apply(&mut test_page(PaperSize::A4), font, "", None, 1, 1)
+                .is_err());
+        }
+
+        let mut tiny = PDFPage::new(PaperSize::A4, test_page(PaperSize::A4).margins);
+        tiny.margins.bottom = mm(280.0);
+        assert!(PageTemplate::default()
+            .apply(&mut tiny, font, "", None, 1, 1)
+            .is_err());
+    }
+
+    #[test]
+    fn title_block() {
+        let pdf = PDFDocument::new(PaperSize::A4, Vec::new()).unwrap();
+        let font = &pdf.available_fonts[0];
+        let template = PageTemplate {
+            title_block: TitleBlock {
+                project_name: "Studio fit out".to_string(),
+                drawing_number: "D-100".to_string(),
+                revision: "B".to_string(),
+                date: "2024-03-01".to_string(),
+                drawn_by: "JS".to_string(),
+                checked_by: "AK".to_string(),
+                revisions: vec![
+                    Revision {
+                        revision: "A".to_string(),
+                        description: "First issue".to_string(),
+                        ..Revision::default()
+                    },
+                    Revision {
+                        revision: "B".to_string(),
+                        description: "Moved rack 2".to_string(),
+                        ..Revision::default()
+                    },
+                ],
+            },
+            ..PageTemplate::default()
+        };
+        // A3 is wide enough for the title block to be narrower than the border
+        let mut page = test_page(PaperSize::A3);
+        let scale = Some(ScalingFactor { a: 1, b: 50 });
+        template
+            .apply(&mut page, font, "Rack elevations", scale, 2, 5)
+            .unwrap();
+
+        for value in [
+            "Studio fit out",
+            "D-100",
+            "2024-03-01",
+            "JS",
+            "AK",
+            "Rack elevations",
+            "1:50",
+            "2 of 5",
+        ] {
+            assert_eq!(positions(&page, value).len(), 1, "{value}");
+        }
+        let y = |text: &str| positions(&page, text)[0].1;
+        // rows from the bottom of the title block up
+        assert!(y("Drawing No.") < y("Title"));
+        assert!(y("Title") < y("Date"));
+        assert!(y("Date") < y("Project"));
+        // the revision table is above the title block, newest revision first
+        assert!(y("Project") < y("Moved rack 2"));
+        assert!(y("Moved rack 2") < y("First issue"));
+        assert!(y("First issue") < y("Description"));
+
+        // the title block is in the bottom right corner, at most 180mm wide
+        let (width, _) = page.size();
+        let block_left = to_points(width - mm(10.0 + ZONE_BAND + TITLE_BLOCK_WIDTH));
+        let padding = to_points(font.line_height(CAPTION_FONT_SIZE)) / 3.0;
+        for text in ["Drawing No.", "Title", "Date", "Project"] {
+            let x = positions(&page, text)[0].0;
+            assert!((x - (block_left + padding)).abs() < 0.01, "{text}");
+        }
+
+        // content is kept above the revision table
+        let content = template.content_margins(page.margins, font);
+        assert!(to_points(content.bottom) > y("Description"));
+        assert!(content.top > page.margins.top);
+
+        // pages that aren't drawn to scale say so
+        let mut unscaled = test_page(PaperSize::A3);
+        template
+            .apply(&mut unscaled, font, "Overview", None, 1, 5)
+            .unwrap();
+        assert_eq!(positions(&unscaled, "NTS").len(), 1);
+    }
+}
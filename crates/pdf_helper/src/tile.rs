@@ -0,0 +1,397 @@
+use std::str::FromStr;
+
+use dimensioned::{f64prefixes, ucum};
+use lopdf::content::Operation;
+
+use crate::{to_points, Error, PDFDocument, PDFFont, PDFLink, PDFPage, PDFTextRenderMode};
+
+/// `Tiling` splits a drawing that is larger than a page across several pages, so it can be
+/// printed on smaller paper and assembled.
+///
+/// Neighbouring tiles overlap by `overlap`, and alignment marks are drawn in the middle of each
+/// overlap so the same marks appear on both tiles. Tiles are lettered from A down the drawing and
+/// numbered from 1 across it, and each is labelled with its position, such as "A2 of 3x4" for
+/// the second tile of the first row in a drawing 3 tiles wide and 4 tiles high.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tiling {
+    /// distance neighbouring tiles overlap by
+    pub overlap: ucum::Meter<f64>,
+}
+
+/// `TileGrid` is the number of tiles a drawing is split into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileGrid {
+    /// number of tiles across the drawing
+    pub columns: usize,
+    /// number of tiles down the drawing
+    pub rows: usize,
+}
+
+/// `LABEL_FONT_SIZE` is the font size of the tile label, in points
+const LABEL_FONT_SIZE: u32 = 8;
+/// `MARK_RADIUS` is the radius of the circle of an alignment mark, in mm
+const MARK_RADIUS: f64 = 2.0;
+
+impl Default for Tiling {
+    fn default() -> Self {
+        Self {
+            overlap: 10.0_f64 * ucum::M * f64prefixes::MILLI,
+        }
+    }
+}
+
+impl TileGrid {
+    /// `count` returns the total number of tiles
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.columns.saturating_mul(self.rows)
+    }
+
+    /// `label` returns the label of the tile in zero indexed `column` and `row`, such as
+    /// "A2 of 3x4"
+    #[must_use]
+    pub fn label(&self, column: usize, row: usize) -> String {
+        format!(
+            "{}{} of {}x{}",
+            row_letters(row),
+            column.saturating_add(1),
+            self.columns,
+            self.rows
+        )
+    }
+}
+
+/// `row_letters` returns the letters of zero indexed `row`, continuing A to Z with AA, AB and so
+/// on
+#[allow(clippy::arithmetic_side_effects)]
+fn row_letters(row: usize) -> String {
+    let mut letters = Vec::new();
+    let mut remaining = row + 1;
+    while remaining > 0 {
+        remaining -= 1;
+        // remainder is always below 26
+        #[allow(clippy::cast_possible_truncation)]
+        letters.push(char::from(b'A' + (remaining % 26) as u8));
+        remaining /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+impl Tiling {
+    /// `grid` returns the grid of tiles needed to split a drawing of `drawing_size` across pages
+    /// like `page`, which sets the paper size, orientation and margins of each tile.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the area of a tile inside the margins is not larger than the overlap
+    pub fn grid(
+        &self,
+        drawing_size: (ucum::Meter<f64>, ucum::Meter<f64>),
+        page: &PDFPage,
+        font: &PDFFont,
+    ) -> Result<TileGrid, Error> {
+        let (tile_width, tile_height) = tile_area(page, font);
+        Ok(TileGrid {
+            columns: self.tile_count(drawing_size.0, tile_width)?,
+            rows: self.tile_count(drawing_size.1, tile_height)?,
+        })
+    }
+
+    /// `tile_count` returns the number of tiles of `tile` length needed to cover `length`
+    #[allow(clippy::arithmetic_side_effects)]
+    fn tile_count(&self, length: ucum::Meter<f64>, tile: ucum::Meter<f64>) -> Result<usize, Error> {
+        let step = to_points(tile - self.overlap);
+        if step <= 0.0_f64 {
+            return Err(Error::Other(format!(
+                "tiles are not larger than their overlap of {}",
+                self.overlap
+            )));
+        }
+        let count = (to_points(length - self.overlap) / step)
+            .ceil()
+            .max(1.0_f64);
+        // count is a small positive whole number
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Ok(count as usize)
+    }
+
+    /// `split` splits `drawing` into tiles, returning one page per tile like `page`, from left to
+    /// right along the top row, then each row below it.
+    ///
+    /// The drawing is everything inside the margins of `drawing`. Links on the drawing are kept
+    /// on the tiles they appear on.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the tiles are not larger than the overlap, or the labels fail to layout
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn split(
+        &self,
+        drawing: &PDFPage,
+        page: &PDFPage,
+        font: &PDFFont,
+    ) -> Result<Vec<PDFPage>, Error> {
+        let (drawing_width, drawing_height) = drawing.size();
+        let drawing_size = (
+            drawing_width - drawing.margins.left - drawing.margins.right,
+            drawing_height - drawing.margins.top - drawing.margins.bottom,
+        );
+        let grid = self.grid(drawing_size, page, font)?;
+        let (tile_width, tile_height) = tile_area(page, font);
+        let step = (tile_width - self.overlap, tile_height - self.overlap);
+        let marks = self.alignment_marks(grid, drawing_size, (tile_width, tile_height));
+
+        let mut tiles = Vec::new();
+        for row in 0..grid.rows {
+            for column in 0..grid.columns {
+                // position of the bottom left corner of the tile within the drawing, with the
+                // top row lined up with the top of the drawing
+                let left = step.0 * column as f64;
+                let bottom = drawing_size.1 - tile_height - step.1 * row as f64;
+                let mut tile = PDFPage::new(page.page_size, page.margins);
+                tile.orientation = page.orientation;
+                let (clip_left, clip_bottom) =
+                    tile.page_position(0.0_f64 * ucum::M, 0.0_f64 * ucum::M);
+                let clip = [
+                    clip_left,
+                    clip_bottom,
+                    clip_left + to_points(tile_width),
+                    clip_bottom + to_points(tile_height),
+                ];
+                // moves the drawing so the corner of the tile is at the corner of the clip
+                let offset = (
+                    clip_left - to_points(drawing.margins.left + left),
+                    clip_bottom - to_points(drawing.margins.bottom + bottom),
+                );
+
+                tile.operations.extend([
+                    Operation::new("q", vec![]),
+                    // re W n clips everything after it to the rectangle, without drawing it
+                    Operation::new(
+                        "re",
+                        vec![
+                            clip[0].into(),
+                            clip[1].into(),
+                            (clip[2] - clip[0]).into(),
+                            (clip[3] - clip[1]).into(),
+                        ],
+                    ),
+                    Operation::new("W", vec![]),
+                    Operation::new("n", vec![]),
+                    Operation::new(
+                        "cm",
+                        vec![
+                            1.into(),
+                            0.into(),
+                            0.into(),
+                            1.into(),
+                            offset.0.into(),
+                            offset.1.into(),
+                        ],
+                    ),
+                ]);
+                tile.operations.extend(drawing.operations.iter().cloned());
+                tile.resources.clone_from(&drawing.resources);
+                for &(x, y) in &marks {
+                    tile.operations.extend(mark_operations(
+                        to_points(drawing.margins.left + x),
+                        to_points(drawing.margins.bottom + y),
+                    ));
+                }
+                tile.operations.push(Operation::new("Q", vec![]));
+
+                for link in &drawing.links {
+                    let rect = [
+                        (link.rect[0] + offset.0).max(clip[0]),
+                        (link.rect[1] + offset.1).max(clip[1]),
+                        (link.rect[2] + offset.0).min(clip[2]),
+                        (link.rect[3] + offset.1).min(clip[3]),
+                    ];
+                    if rect[0] < rect[2] && rect[1] < rect[3] {
+                        tile.links.push(PDFLink {
+                            rect,
+                            destination_page: link.destination_page,
+                        });
+                    }
+                }
+
+                let line_width = 0.5_f64 * PDFDocument::pdf_point();
+                let zero = 0.0_f64 * ucum::M;
+                tile.add_rectangle(zero, zero, tile_width, tile_height, line_width);
+                add_label(&mut tile, font, &grid.label(column, row), tile_height)?;
+                tiles.push(tile);
+            }
+        }
+        Ok(tiles)
+    }
+
+    /// `alignment_marks` returns the positions of the alignment marks within a drawing of
+    /// `drawing_size` split into `grid` tiles of `tile_size`. Two marks are placed in the middle
+    /// of the overlap along each edge shared by two tiles.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn alignment_marks(
+        &self,
+        grid: TileGrid,
+        drawing_size: (ucum::Meter<f64>, ucum::Meter<f64>),
+        tile_size: (ucum::Meter<f64>, ucum::Meter<f64>),
+    ) -> Vec<(ucum::Meter<f64>, ucum::Meter<f64>)> {
+        let step = (tile_size.0 - self.overlap, tile_size.1 - self.overlap);
+        let top = drawing_size.1;
+        let mut marks = Vec::new();
+        for row in 0..grid.rows {
+            let bottom = top - tile_size.1 - step.1 * row as f64;
+            for column in 0..grid.columns {
+                let left = step.0 * column as f64;
+                if column + 1 < grid.columns {
+                    // overlap with the tile to the right
+                    let x = left + step.0 + self.overlap / 2.0_f64;
+                    for fraction in [1.0_f64 / 3.0_f64, 2.0_f64 / 3.0_f64] {
+                        marks.push((x, bottom + tile_size.1 * fraction));
+                    }
+                }
+                if row + 1 < grid.rows {
+                    // overlap with the tile below
+                    let y = bottom + self.overlap / 2.0_f64;
+                    for fraction in [1.0_f64 / 3.0_f64, 2.0_f64 / 3.0_f64] {
+                        marks.push((left + tile_size.0 * fraction, y));
+                    }
+                }
+            }
+        }
+        marks
+    }
+}
+
+/// `tile_area` returns the width and height of the drawing shown on each tile, which is the area
+/// inside the margins of `page` less a band at the top for the tile label
+#[allow(clippy::arithmetic_side_effects)]
+fn tile_area(page: &PDFPage, font: &PDFFont) -> (ucum::Meter<f64>, ucum::Meter<f64>) {
+    let (width, height) = page.size();
+    (
+        width - page.margins.left - page.margins.right,
+        height
+            - page.margins.top
+            - page.margins.bottom
+            - font.line_height(LABEL_FONT_SIZE) * 1.5_f64,
+    )
+}
+
+/// `add_label` writes the tile label in the band above the tile area of `tile_height`
+#[allow(clippy::arithmetic_side_effects)]
+fn add_label(
+    tile: &mut PDFPage,
+    font: &PDFFont,
+    label: &str,
+    tile_height: ucum::Meter<f64>,
+) -> Result<(), Error> {
+    let language = rustybuzz::Language::from_str("en")
+        .map_err(|e| Error::Other(format!("invalid text language: {e}")))?;
+    let text = format!("Tile {label}");
+    let text_width = font.text_width(&text, LABEL_FONT_SIZE) + PDFDocument::pdf_point();
+    tile.add_text(
+        text,
+        LABEL_FONT_SIZE,
+        font,
+        1,
+        text_width,
+        0.0_f64 * ucum::M,
+        tile_height + font.line_height(LABEL_FONT_SIZE) * 0.5_f64,
+        rustybuzz::Direction::LeftToRight,
+        language,
+        &PDFTextRenderMode::Fill,
+    )
+}
+
+/// `mark_operations` returns the PDF operations that draw an alignment mark, a circle with a
+/// cross through it, centered on (`x`, `y`) in PDF user space units
+#[allow(clippy::arithmetic_side_effects)]
+fn mark_operations(x: f64, y: f64) -> Vec<Operation> {
+    let radius = to_points(MARK_RADIUS * ucum::M * f64prefixes::MILLI);
+    // distance of the bezier control points from the ends of a quarter circle
+    let control = radius * 0.552_284_8_f64;
+    let point = |dx: f64, dy: f64| -> Vec<lopdf::Object> { vec![(x + dx).into(), (y + dy).into()] };
+    let curve = |points: [(f64, f64); 3]| {
+        Operation::new(
+            "c",
+            points.iter().flat_map(|&(dx, dy)| point(dx, dy)).collect(),
+        )
+    };
+    vec![
+        Operation::new("q", vec![]),
+        Operation::new("w", vec![0.5_f64.into()]),
+        Operation::new("m", point(radius, 0.0_f64)),
+        curve([(radius, control), (control, radius), (0.0_f64, radius)]),
+        curve([(-control, radius), (-radius, control), (-radius, 0.0_f64)]),
+        curve([(-radius, -control), (-control, -radius), (0.0_f64, -radius)]),
+        curve([(control, -radius), (radius, -control), (radius, 0.0_f64)]),
+        Operation::new("m", point(-radius * 1.5_f64, 0.0_f64)),
+        Operation::new("l", point(radius * 1.5_f64, 0.0_f64)),
+        Operation::new("m", point(0.0_f64, -radius * 1.5_f64)),
+        Operation::new("l", point(0.0_f64, radius * 1.5_f64)),
+        Operation::new("S", vec![]),
+        Operation::new("Q", vec![]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `mm` returns `length` millimeters
+    fn mm(length: f64) -> ucum::Meter<f64> {
+        length * ucum::M * f64prefixes::MILLI
+    }
+
+    #[test]
+    fn tile_count() {
+        // 100mm tiles overlapping by 10mm cover 100mm, then 90mm more for each extra tile
+        let tiling = Tiling::default();
+        assert_eq!(tiling.tile_count(mm(5.0), mm(100.0)).unwrap(), 1);
+        assert_eq!(tiling.tile_count(mm(95.0), mm(100.0)).unwrap(), 1);
+        assert_eq!(tiling.tile_count(mm(105.0), mm(100.0)).unwrap(), 2);
+        assert_eq!(tiling.tile_count(mm(185.0), mm(100.0)).unwrap(), 2);
+        assert_eq!(tiling.tile_count(mm(195.0), mm(100.0)).unwrap(), 3);
+        assert_eq!(tiling.tile_count(mm(1000.0), mm(100.0)).unwrap(), 11);
+
+        let no_overlap = Tiling { overlap: mm(0.0) };
+        assert_eq!(no_overlap.tile_count(mm(250.0), mm(100.0)).unwrap(), 3);
+    }
+
+    #[test]
+    fn tile_count_overlap_too_large() {
+        let tiling = Tiling { overlap: mm(20.0) };
+        assert!(matches!(
+            tiling.tile_count(mm(100.0), mm(20.0)),
+            Err(Error::Other(_))
+        ));
+        assert!(matches!(
+            tiling.tile_count(mm(100.0), mm(10.0)),
+            Err(Error::Other(_))
+        ));
+    }
+
+    #[test]
+    fn letters() {
+        assert_eq!(row_letters(0), "A");
+        assert_eq!(row_letters(1), "B");
+        assert_eq!(row_letters(25), "Z");
+        assert_eq!(row_letters(26), "AA");
+        assert_eq!(row_letters(27), "AB");
+        assert_eq!(row_letters(51), "AZ");
+        assert_eq!(row_letters(52), "BA");
+        assert_eq!(row_letters(701), "ZZ");
+        assert_eq!(row_letters(702), "AAA");
+    }
+
+    #[test]
+    fn grid_label() {
+        let grid = TileGrid {
+            columns: 3,
+            rows: 4,
+        };
+        assert_eq!(grid.count(), 12);
+        assert_eq!(grid.label(0, 0), "A1 of 3x4");
+        assert_eq!(grid.label(1, 0), "A2 of 3x4");
+        assert_eq!(grid.label(2, 3), "D3 of 3x4");
+    }
+}